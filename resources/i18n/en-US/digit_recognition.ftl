@@ -0,0 +1,373 @@
+# Messages of the command line and the GUI, in Fluent syntax.
+# Translations fall back to these for messages they do not have.
+
+program-description = A simple AI-driven program for classifying pictures based on what digit is written on them
+error-message = Error [{ $code }]: { $details }
+explain-hint = Run with --explain { $code } for help
+
+## GUI
+
+gui-window-title = Digit recognition
+gui-result = Result
+gui-unrecognized = Unrecognized
+gui-draw = Draw a digit
+gui-chance = chance:
+gui-resubmit = Resubmit
+gui-threshold = threshold: { $percent }%
+gui-history = before: { $answers }
+gui-profile = profile:
+gui-correct-answer = correct answer
+gui-teach = Teach
+gui-taught = taught { $profile } that it is { $answer }
+gui-unknown-class = `{ $answer }` is not a class of the model
+gui-no-drawing = submit a drawing first
+gui-no-answer = type the correct answer first
+gui-collect = Save drawings
+gui-save-drawing = Save
+gui-collected = saved as { $answer }, { $count } so far
+gui-about = About the model
+gui-about-title = Model
+
+## Command line options
+
+help-train = Start training using provided dataset
+help-evaluate = Report the accuracy and loss of the model on --images and --labels, or on a CSV with the label in the first column given as --images, reading --batch-size samples at a time
+help-info = Show the layers of the model with their shapes, parameter counts and activations
+help-benchmark-suite = Evaluate the model on MNIST, Fashion-MNIST and EMNIST letters found in DIR
+help-quantize = Save an int8-quantized copy of the model to OUTPUT for faster inference
+help-convert-model = Save a copy of the model to OUTPUT, as JSON if it ends in .json and in the compact binary format otherwise
+help-export-bundle = Save the model with its training history, best model and checkpoint to BUNDLE
+help-import-bundle = Restore the files saved by --export-bundle next to the model
+help-detect = Find the digits in a wide IMAGE, e.g. a handwritten phone number, and print them left to right with their bounding boxes (binary PGM)
+help-min-confidence = Ignore windows classified with less than this confidence with --detect
+help-export-tensors = Write the network inputs and labels of --images as training computes them to FILE, for reproducing them in other frameworks
+help-export-grid = Save the first 100 images of the dataset (or as many as --limit, picked at random with --random-subset) to a PNG contact sheet labeled with their classes, after --preprocess and --augment if given
+help-grid-columns = Images per row of the --export-grid contact sheet
+help-convert-dataset = Convert the dataset to another format and save it here: a CSV file if it ends in .csv, an image directory with a subdirectory per class if it is a directory or ends in /, or IDX files otherwise, such as MNIST's train-images-idx3-ubyte, gzipped if they end in .gz
+help-convert-labels = IDX labels file written by --convert-dataset
+help-find-duplicates = Report pairs of identical or nearly identical images in the dataset, and images of --validation-images (or of the MNIST test set with --mnist) that are also in it, which inflate evaluation results
+help-duplicate-distance = How many of the 64 bits of their perceptual hashes nearly identical images for --find-duplicates may differ in; 0 finds only images that look the same
+help-tensor-format = Format of --export-tensors: npz, or parquet in builds with the parquet feature
+help-fgsm = Perturb the first correctly classified sample of --images along the gradient sign and save it before and after as PNGs in DIR
+help-epsilon = How far --fgsm moves each pixel, as a fraction of the brightness range
+help-sample-index = Position in --images from which --fgsm looks for a correctly classified sample
+help-prune = Zero the weights of the model smaller than THRESHOLD in magnitude and save it
+help-quantized = Show the GUI with a model saved by --quantize
+help-grid-cells = Split the GUI canvas into N cells for entering an N-digit number, one digit per cell
+help-profiles = Keep per-user profiles in DIR, each with its own copy of --model fine-tuned on the corrections taught in the GUI and a dataset of those drawings
+help-profile = Profile selected when the GUI starts with --profiles, created on the first correction
+help-collect = Let the GUI save drawings with their confirmed answers to this dataset, a CSV file, an image directory or an IDX images file, to fine-tune on later
+help-collect-labels = IDX labels file the drawings saved with --collect are labeled in
+help-multiscale = Classify crops of the drawing at several scales in the GUI and show the most confident one, for digits drawn much smaller than the canvas
+help-mc-dropout = Estimate the uncertainty of predictions in the GUI from N forward passes with dropout, shown as confidence ± uncertainty
+help-mc-dropout-rate = Probability of dropping a hidden unit on each --mc-dropout pass
+help-bench-history = Append the benchmark results to the JSON Lines FILE and show how they changed since the previous entry
+help-compare-predictions = Compare the model with class probabilities of another tool for the samples of --images and --labels, one CSV row per sample, and with the average of both
+help-from-scratch = Benchmark freshly trained networks instead of the saved model
+help-model = File the neural network is loaded from and saved to, as JSON if it ends in .json and in the compact binary format otherwise; repeat to classify with an ensemble of models in the GUI
+help-runs-dir = Save each training run to its own directory in DIR; the GUI shows the best model of the latest run unless --model is given
+help-ensemble-voting = How the models of an ensemble are combined (average, majority)
+help-images = File or http(s) URL containing images used for training (- reads from the standard input), or a directory with a subdirectory of PNG or JPEG images per label
+help-labels = File or http(s) URL containing labels used for training (- reads from the standard input)
+help-skip = Leave out the first N samples of the dataset
+help-limit = Use at most N samples of the dataset, e.g. for a quick experiment
+help-random-subset = Pick the samples of --limit at random and shuffle them (seeded by --seed) instead of taking the first ones in order; without --limit, shuffle the whole dataset
+help-skip-invalid = Leave out samples that cannot be read, logging the position and the reason of each, instead of stopping at the first one
+help-fetch-mnist = Download the MNIST training and test sets into this directory, checking their checksums and resuming interrupted downloads
+help-mnist-mirror = URL the MNIST files are downloaded from by --fetch-mnist
+help-synthesize-mnist = Draw digits in the installed fonts, at random sizes, rotations and stroke widths, into this directory as a stand-in for MNIST when it cannot be downloaded; use it with --mnist
+help-fonts = Comma-separated directories searched for the .ttf and .otf fonts of --synthesize-mnist instead of the system's font directories
+help-synthetic-samples = Number of training digits drawn by --synthesize-mnist; a sixth as many are drawn for the test set
+help-mnist = Train on the MNIST training set, or evaluate on its test set, as downloaded into this directory by --fetch-mnist, instead of --images and --labels
+help-epochs = Number of passes over the training dataset
+help-learning-rate = Step size of gradient descent
+help-layer-lr-multipliers = Comma-separated learning rate multipliers for each layer, e.g. 0.1,1.0
+help-reduce-lr-on-plateau = Reduce the learning rate when the validation loss (training loss without a validation set) has not improved for PATIENCE epochs
+help-lr-reduction-factor = Factor the learning rate is multiplied by on a loss plateau
+help-min-learning-rate = Learning rate below which plateaus no longer reduce it, and the bottom of --lr-cycle
+help-freeze-layers = Comma-separated indices of layers, input side first from 0, whose parameters are not trained, e.g. 0 to fine-tune only the output layer
+help-batch-size = Number of training examples averaged into one gradient descent step
+help-seed = Seed of the random numbers that initialize new networks and pick search and sweep candidates, for reproducible runs
+help-threads = Threads computing the gradients of a batch (0 uses all cores)
+help-checkpoint-every = Save a training checkpoint after every N training examples (0 disables checkpoints)
+help-warmup-batches = Raise the learning rate linearly from almost zero over the first N batches
+help-lr-cycle = Cycle the learning rate between --min-learning-rate and --learning-rate after the warmup, rising and falling linearly (triangular) or falling along a cosine and restarting (cosine)
+help-lr-cycle-length = Batches in one --lr-cycle
+help-shuffle = Train on the samples in a new random order every epoch, reproducible with --seed
+help-preprocess = Deskew and recenter every image like MNIST before training; the model keeps doing so for the images it classifies, including drawings
+help-normalize = Standardize the pixel inputs with the mean and standard deviation of the training set; the model keeps them and applies them to every image it classifies
+help-augment = Randomly rotate and shift every training image each time it is trained on
+help-augment-rotation = Largest rotation of --augment either way, in degrees
+help-augment-shift = Largest shift of --augment along each axis, in pixels
+help-augment-elastic = Also distort --augment images elastically, moving pixels by up to about ALPHA times a smoothed random field (34 in the MNIST literature)
+help-augment-elastic-sigma = Smoothness of the --augment-elastic displacements, the standard deviation of their Gaussian in pixels
+help-augment-blur = Also blur --augment images by a Gaussian of up to SIGMA pixels, like the antialiased strokes of the drawing canvas
+help-augment-noise = Also add Gaussian noise to --augment images, with this standard deviation as a fraction of the brightness range
+help-augment-salt-and-pepper = Also turn pixels of --augment images black or white, each with this probability
+help-robust-training = Also train on a perturbed copy of every batch, made adversarial along the gradient sign (fgsm) or noisy (noise), for robustness to drawings unlike the dataset
+help-robust-strength = FGSM epsilon or noise standard deviation of --robust-training, as a fraction of the brightness range
+help-teacher = Train the network to reproduce the softened outputs of this trained model, e.g. to compress a large model into a smaller one
+help-distillation-temperature = Softmax temperature of the --teacher outputs; above 1 reveals which wrong classes the teacher finds similar
+help-distillation-weight = Weight of the dataset labels against the --teacher outputs, from 0 to 1
+help-lr-find = Instead of training, sweep the learning rate exponentially over --lr-find-range, write the loss after each batch to CSV and suggest a learning rate
+help-lr-find-range = Smallest and largest learning rate of --lr-find
+help-lr-find-batches = Batches trained during --lr-find
+help-max-duration = Stop training after DURATION (e.g. 90s, 10m, 1.5h), saving a checkpoint to resume from
+help-class-weights = Weigh the loss of each class, inversely to its frequency in the training set (balanced) or by a comma-separated list with one weight per class
+help-sampling = Draw the samples of every epoch so that each class is equally likely (balanced), or with at most this many samples of each class
+help-sample-weights = Weigh the loss of each training sample by the corresponding line of FILE, e.g. to emphasize recently collected corrections; also used by --search and --sweep
+help-extra-images = Images of a second training set, e.g. drawings collected with the GUI, trained on together with --images and spread evenly among them
+help-extra-labels = Labels of the images of --extra-images
+help-extra-weight = Weight of the samples of --extra-images in the loss
+help-ema-decay = Save an exponential moving average of the weights, updated with DECAY (e.g. 0.999) after every batch, instead of the final weights
+help-resume = Continue training from a checkpoint file
+help-input-features = Inputs of a newly created model: raw pixels, or pixels followed by histograms of stroke directions (directions)
+help-init-from = Start a new model from the layers of a saved network whose shapes match, e.g. to retrain only a different output layer
+help-architecture = Create a new model with the hidden layers, activation and learning rate of a TOML config written by --search
+help-search = Search hidden layer sizes, activations and learning rates on --images and --labels by successive halving and save the best as a TOML config for --architecture
+help-search-candidates = Number of random candidates --search starts with; every round keeps the better half
+help-search-epochs = Training epochs of the first --search round, doubled in every following round
+help-sweep = Train a model for every combination of the hidden sizes, learning rates and batch sizes in a TOML config, rank them on the validation set and save the best as --model
+help-sweep-dir = Directory keeping the models, configs, logs and ranked results of --sweep
+help-sweep-jobs = Number of --sweep training processes running in parallel
+help-validation-images = File containing images used to pick the best model during training
+help-validation-labels = File containing labels for the validation images
+help-validation-split = Without --validation-images, hold out this fraction of the training samples as the validation set, picked at random but the same for the same --seed
+help-verify-sha256 = Abort unless the dataset files match the SHA-256 digests listed in MANIFEST (the output format of sha256sum)
+help-alphabet = Set of classes the model is trained on: digits, EMNIST letters, or the EMNIST split emnist-byclass, emnist-bymerge, emnist-balanced, emnist-digits or emnist-mnist, whose images are flipped upright
+help-label-map = Remap or merge dataset labels into the classes listed in FILE, one "label class-name" per line; unlisted labels are skipped
+help-classes = Number of classes in a dataset that is neither digits nor letters
+help-class-names = Comma-separated names of the dataset classes, shown instead of class numbers; by default they are read from a class-names.json array beside the labels
+help-detect-nan = Report layers producing NaN or infinite values during training
+help-rollback-on-divergence = When the loss becomes NaN or infinite, save the network of the last checkpoint as the model before stopping
+help-calibrate = Fit the softmax temperature on the validation set after training, so that displayed confidences match the actual accuracy
+help-rejection-threshold = Confidence (0-1) below which the model reports a drawing as unrecognized
+help-max-latency-ms = Switch the GUI to a quantized model when predictions take longer than MS on average
+help-compute-backend = Implementation of the tensor math (nalgebra; matrixmultiply and wgpu when built with the blas and gpu features)
+help-script = Digit script the model is trained on, used to display its results (latin, arabic-indic, eastern-arabic-indic, devanagari, bengali, thai)
+help-lang = Language of the help text and messages (en, ru); defaults to $DIGIT_RECOGNITION_LANG, then the system locale
+help-explain = Describe the error with CODE, shown in brackets before error messages, and how to fix it
+help-log-level = Verbosity of diagnostic messages (off, error, warn, info, debug, trace)
+
+## Error codes, described by --explain
+
+E0001 = a dataset file does not start with the IDX magic number
+    .remedy = Check that --images points to an IDX3 image file and --labels to an IDX1 label file, and that they are not swapped.
+E0002 = the image and label files have different sample counts
+    .remedy = Use the label file that belongs to the image file, e.g. train-labels with train-images and t10k-labels with t10k-images. A truncated download also causes this; download it again.
+E0003 = a dataset file cannot be read
+    .remedy = Check that the file exists and is readable. An unexpected end of file means the download was truncated.
+E0004 = both --images and --labels read from the standard input
+    .remedy = Only one of --images and --labels can be `-`; pass the other one as a file.
+E0005 = a sample weights file has an invalid weight
+    .remedy = Write one non-negative number per line, without a header.
+E0006 = the sample weights file does not have one weight per sample
+    .remedy = Write one weight for each sample of --images, in the order of the file.
+E0007 = a row of a CSV dataset is not a label followed by the pixels of a square image
+    .remedy = Write one sample per row: the label, then the pixels row by row as numbers 0-255, all separated by commas. Every image must have the same size.
+E0008 = an image of an image directory dataset cannot be decoded
+    .remedy = Make sure every .png, .jpg and .jpeg file in the directory is a valid image, or remove it.
+E0009 = a directory of an image dataset is not named after a label
+    .remedy = Put the images of each label in a directory named after the label number, e.g. data/3/, and move other directories elsewhere.
+E0010 = the fraction of a dataset to hold out for validation is not between 0 and 1
+    .remedy = Pass --validation-split a fraction such as 0.1 to hold out a tenth of the training samples.
+E0011 = a dataset could not be downloaded from its URL
+    .remedy = Check the URL and your network connection, or download the files and pass their paths instead.
+E0012 = a dataset's class names file is invalid
+    .remedy = Write class-names.json as a JSON array of strings, one name per label in label order, or remove it.
+E0013 = a sample past the end of a dataset was asked for
+    .remedy = Ask for samples below the dataset's length.
+E0014 = an IDX file has an unexpected number of dimensions
+    .remedy = Image files need the count, rows and columns, and label files only the count; convert color or multi-channel datasets to grayscale first.
+E0101 = a model file is not valid JSON or does not describe a network
+    .remedy = Check that --model points to a model saved by this program and not, for example, to a training history or checkpoint. Files with a .json extension are parsed as JSON, any other extension as the binary format.
+E0102 = a model file cannot be read
+    .remedy = Check the path passed to --model and its permissions.
+E0103 = a model cannot be saved
+    .remedy = Check that the directory of the output file exists and is writable and that the disk is not full.
+E0104 = a binary model file is corrupt
+    .remedy = The file was truncated or is not a binary model. Restore it from a backup or a bundle, or retrain it. Saving with a .json extension writes the human-readable format instead.
+E0105 = a model cannot be encoded in the binary format
+    .remedy = Save the model with a .json extension instead and report the problem.
+E0106 = a model was saved by a newer version of the program
+    .remedy = Update the program to the version that saved the model, or retrain the model with this version.
+E0107 = a model file is JSON, but not a network
+    .remedy = Check that --model points to a model and not to another JSON file such as a training history.
+E0108 = a binary model file has an unsupported format version
+    .remedy = Update the program to the version that saved the model, or convert the model to JSON with --convert-model using that version.
+E0109 = the input does not have the size the model was trained on
+    .remedy = Use images with the resolution of the training set; a model trained on 28x28 images only classifies 28x28 images.
+E0110 = a label is larger than the number of output classes
+    .remedy = The dataset has more classes than the model. Train a new model with the matching --alphabet or --classes instead of continuing to train the existing one.
+E0111 = unknown activation
+    .remedy = Use sigmoid, tanh or relu.
+E0112 = invalid dropout rate
+    .remedy = Pass a --mc-dropout-rate of at least 0 and below 1.
+E0201 = the training history cannot be saved
+    .remedy = Check that the directory of --model is writable and that the disk is not full.
+E0202 = the learning rate curve of --lr-find cannot be saved
+    .remedy = Check that the CSV path passed to --lr-find is writable.
+E0203 = a training checkpoint cannot be read
+    .remedy = Check the path passed to --resume. Checkpoints are saved next to the model as *.checkpoint.json.
+E0204 = a training checkpoint cannot be saved
+    .remedy = Check that the directory of --model is writable and that the disk is not full.
+E0205 = the training threads cannot be started
+    .remedy = Lower --threads, or pass 0 to use one thread per core.
+E0206 = --freeze-layers names a layer the network does not have
+    .remedy = Layers are numbered from 0 on the input side; the default network has layers 0 and 1.
+E0207 = a training label is larger than the number of output classes
+    .remedy = The dataset has more classes than the model. Train a new model with the matching --alphabet or --classes.
+E0208 = --class-weights has a different number of weights than the model has classes
+    .remedy = Pass one weight per class, or `balanced` to derive them from the training set.
+E0209 = unknown learning rate cycle
+    .remedy = Use triangular or cosine for --lr-cycle.
+E0210 = a training checkpoint or history is corrupt
+    .remedy = Resume from an older checkpoint, or restart training without --resume.
+E0211 = training diverged, the loss or gradients became NaN or infinite
+    .remedy = Lower --learning-rate or add --warmup-batches; --detect-nan shows the layer where it started. With --rollback-on-divergence the model of the last checkpoint is kept, and training can continue from it with --resume.
+E0212 = unknown perturbation
+    .remedy = Pass fgsm or noise to --robust-training.
+E0213 = the teacher network does not fit the trained network
+    .remedy = Pass a --teacher model trained on images of the same size and with the same classes.
+E0214 = invalid distillation temperature
+    .remedy = Pass a positive --distillation-temperature.
+E0215 = the teacher network prepares images differently from the trained network
+    .remedy = Pass a --teacher model trained with --preprocess if and only if this training uses --preprocess too.
+E0301 = an option has an invalid value
+    .remedy = Run with --help to see the expected format of the option.
+E0302 = several --model files were given to a command that uses one
+    .remedy = Only the GUI combines several models into an ensemble; pass a single --model to other commands.
+E0303 = the existing model has a different number of classes than the selected alphabet
+    .remedy = Train into a new --model file, or select the alphabet the model was trained with.
+E0304 = --calibrate without a validation set
+    .remedy = Pass --validation-images and --validation-labels, or hold out part of the training set with --validation-split; calibrating on the training set would make the model overconfident.
+E0305 = --sweep without a validation set
+    .remedy = Pass --validation-images and --validation-labels to rank the trained models.
+E0306 = the command line cannot be parsed
+    .remedy = Run with --help to see the available options.
+E0307 = --explain got an unknown error code
+    .remedy = Error codes have the form E0001 and are printed in brackets before error messages.
+E0308 = an image could not be saved
+    .remedy = Check that the directory of the file exists and that you can write to it.
+E0309 = options that cannot work together were given
+    .remedy = Leave out one of the two options named in the message.
+E0401 = unknown digit script
+    .remedy = Use latin, arabic-indic, eastern-arabic-indic, devanagari, bengali or thai for --script.
+E0402 = unknown alphabet
+    .remedy = Use digits, letters or an EMNIST split such as emnist-balanced for --alphabet, or --classes for another number of classes.
+E0403 = unknown or unavailable compute backend
+    .remedy = Use nalgebra; matrixmultiply and wgpu are only available when built with the blas and gpu features.
+E0404 = unknown input features
+    .remedy = Use pixels or directions for --input-features.
+E0405 = unknown ensemble voting
+    .remedy = Use average or majority for --ensemble-voting.
+E0406 = an ensemble without models
+    .remedy = Pass at least one --model.
+E0407 = ensemble members do not match
+    .remedy = All models of an ensemble must have the input size and classes of the first one; train them on the same dataset.
+E0501 = a file cannot be read for checksum verification
+    .remedy = Check the paths in the manifest passed to --verify-sha256 and the dataset paths.
+E0502 = a line of the checksum manifest is invalid
+    .remedy = Manifest lines have the format of sha256sum: a hex digest, two spaces and a file name.
+E0503 = a dataset file is not listed in the checksum manifest
+    .remedy = Add the file to the manifest, or check that the dataset file names match the manifest.
+E0504 = a dataset file does not match its checksum
+    .remedy = The file is corrupt or was modified. Download it again.
+E0505 = the standard input cannot be verified against a checksum
+    .remedy = Pass the dataset as a file when using --verify-sha256.
+E0601 = a bundle or one of its files cannot be read
+    .remedy = Check the path passed to --import-bundle, or the model and its history for --export-bundle.
+E0602 = a bundle or one of its files cannot be written
+    .remedy = Check that the target directory is writable and that the disk is not full.
+E0603 = a bundle file is corrupt
+    .remedy = Export the bundle again.
+E0604 = a bundle does not contain a model
+    .remedy = Export the bundle again from a trained model.
+E0701 = a run directory cannot be created
+    .remedy = Check that the directory passed to --runs-dir is writable.
+E0702 = the runs directory cannot be listed
+    .remedy = Check the path passed to --runs-dir and its permissions.
+E0703 = no run has a trained model
+    .remedy = Train a model with --train --runs-dir first, or pass --model.
+E0801 = the benchmark history cannot be read
+    .remedy = Check the path passed to --bench-history and its permissions.
+E0802 = the benchmark history cannot be written
+    .remedy = Check that the path passed to --bench-history is writable.
+E0803 = the benchmark history is corrupt
+    .remedy = Every line of the history must be a JSON record; remove or repair the broken line.
+E0901 = the GUI cannot be shown
+    .remedy = Check that a display is available; the GUI cannot run over plain SSH or in a container without a display server.
+E0902 = the drawing cannot be copied from the canvas
+    .remedy = Restart the GUI and report the problem.
+E0911 = the profiles directory cannot be accessed
+    .remedy = Check that the directory passed to --profiles is writable.
+E0912 = invalid profile name
+    .remedy = Profile names may only contain letters, digits, `-` and `_`.
+E0913 = a sample cannot be added to a profile's dataset
+    .remedy = Check that the profile directory is writable. The dataset must have the resolution of the model.
+E1001 = the image passed to --detect cannot be read
+    .remedy = Check the path and permissions of the image.
+E1002 = the image passed to --detect has an unsupported format
+    .remedy = Convert it to a binary 8-bit grayscale PGM (P5), e.g. with `convert image.png image.pgm`.
+E1101 = the predictions CSV cannot be read
+    .remedy = Check the path passed to --compare-predictions and its permissions.
+E1102 = the predictions CSV has a value that is not a probability
+    .remedy = Export probabilities, e.g. predict_proba in scikit-learn or the softmax of a PyTorch model, not logits or class labels.
+E1103 = a row of the predictions CSV does not have one column per class
+    .remedy = Write one column per class of the model and no index column.
+E1104 = the predictions CSV has a different number of rows than the test set
+    .remedy = Predict every sample of --images, in the order of the file, without shuffling.
+E1201 = a training config cannot be read
+    .remedy = Check the path passed to --architecture or --sweep.
+E1202 = a training config cannot be written
+    .remedy = Check that the path passed to --search is writable.
+E1203 = a training config is not valid TOML or misses values
+    .remedy = Compare it with a config written by --search, or with the example in the documentation of --sweep.
+E1204 = a training config cannot be serialized
+    .remedy = Report the problem.
+E1205 = the search space has no architecture small enough
+    .remedy = Use smaller hidden sizes, or images of a lower resolution.
+E1206 = the output of --sweep cannot be written
+    .remedy = Check that the directory passed to --sweep-dir is writable.
+E1207 = a training process of --sweep cannot be started
+    .remedy = Lower --sweep-jobs if the system ran out of processes or memory.
+E1208 = a training process of --sweep failed
+    .remedy = The error is at the end of the log file named in the message.
+E1301 = the label map cannot be read
+    .remedy = Check the path passed to --label-map.
+E1302 = a line of the label map is invalid
+    .remedy = Write each line as a dataset label from 0 to 255, a space and a class name.
+E1303 = a dataset label is mapped twice
+    .remedy = Map each label to one class; give labels the same class name to merge them.
+E1304 = the label map is empty
+    .remedy = List at least one dataset label and its class.
+E1401 = invalid --epsilon
+    .remedy = Pass an --epsilon above 0 and at most 1, e.g. 0.1.
+E1402 = no sample is classified correctly
+    .remedy = Check that the model was trained on the alphabet of the dataset, or lower --sample-index.
+E1403 = an image cannot be saved
+    .remedy = Check that the directory passed to --fgsm is writable.
+E1501 = unknown tensor format
+    .remedy = Pass npz or parquet to --tensor-format.
+E1502 = the tensors cannot be written
+    .remedy = Check that the file passed to --export-tensors is writable.
+E1503 = parquet files are not supported by this build
+    .remedy = Rebuild with `cargo build --features parquet`, or export npz.
+E1601 = cannot download MNIST
+    .remedy = Check the network connection, or pass another --mnist-mirror.
+E1602 = cannot write a downloaded file
+    .remedy = Check that the --fetch-mnist directory is writable and the disk is not full.
+E1603 = a downloaded MNIST file does not match its checksum
+    .remedy = Run --fetch-mnist again; if it keeps failing, pass another --mnist-mirror.
+E1701 = cannot write a dataset
+    .remedy = Check that the output directory exists and is writable, and that the disk is not full.
+E1702 = a dataset's images have different sizes
+    .remedy = IDX files hold images of one size; resize the images before writing them.
+E1703 = too many samples for an IDX dataset
+    .remedy = Split the dataset into several files of at most 4294967295 samples.
+E1704 = an IDX file is shorter or longer than its header says
+    .remedy = The file was cut off or is not an IDX file; write the dataset to new files instead of adding to it.
+E1801 = cannot read a font
+    .remedy = Check that the font files of the --fonts directories are readable.
+E1802 = no fonts found to draw digits with
+    .remedy = Install TrueType or OpenType fonts, or pass the directories they are in with --fonts.
@@ -0,0 +1,373 @@
+# Русский перевод сообщений командной строки и GUI.
+# Сообщения без перевода берутся из en-US.
+
+program-description = Простая программа на основе нейронной сети, распознающая цифры на изображениях
+error-message = Ошибка [{ $code }]: { $summary } ({ $details })
+explain-hint = Подробности: --explain { $code }
+
+## GUI
+
+gui-window-title = Распознавание цифр
+gui-result = Результат
+gui-unrecognized = Не распознано
+gui-draw = Нарисуйте цифру
+gui-chance = вероятность:
+gui-resubmit = Распознать
+gui-threshold = порог: { $percent }%
+gui-history = ранее: { $answers }
+gui-profile = профиль:
+gui-correct-answer = правильный ответ
+gui-teach = Обучить
+gui-taught = { $profile }: запомнено, что это { $answer }
+gui-unknown-class = «{ $answer }» не является классом модели
+gui-no-drawing = сначала нарисуйте и отправьте цифру
+gui-no-answer = сначала введите правильный ответ
+gui-collect = Сохранять рисунки
+gui-save-drawing = Сохранить
+gui-collected = сохранено как { $answer }, всего { $count }
+gui-about = О модели
+gui-about-title = Модель
+
+## Command line options
+
+help-train = Обучить модель на заданном наборе данных
+help-evaluate = Вывести точность и ошибку модели на --images и --labels или на CSV с меткой в первом столбце, переданном в --images, читая по --batch-size примеров за раз
+help-info = Показать слои модели с их размерами, числом параметров и функциями активации
+help-benchmark-suite = Оценить модель на MNIST, Fashion-MNIST и буквах EMNIST из каталога DIR
+help-quantize = Сохранить в OUTPUT копию модели с весами int8 для более быстрого распознавания
+help-convert-model = Сохранить копию модели в OUTPUT: в JSON, если имя оканчивается на .json, иначе в компактном двоичном формате
+help-export-bundle = Сохранить модель вместе с историей обучения, лучшей моделью и контрольной точкой в архив BUNDLE
+help-import-bundle = Восстановить рядом с моделью файлы, сохранённые --export-bundle
+help-detect = Найти цифры на широком изображении IMAGE (двоичный PGM), например рукописном номере телефона, и вывести их слева направо с ограничивающими рамками
+help-min-confidence = Игнорировать окна, распознанные --detect с меньшей уверенностью
+help-export-tensors = Записать в FILE входы сети и метки из --images в том виде, в котором их вычисляет обучение, чтобы воспроизвести их в других фреймворках
+help-export-grid = Сохранить первые 100 изображений набора данных (или столько, сколько задано --limit, выбранных случайно с --random-subset) в PNG-лист с подписанными классами, после --preprocess и --augment, если они заданы
+help-grid-columns = Число изображений в строке листа --export-grid
+help-convert-dataset = Преобразовать набор данных в другой формат и сохранить его сюда: в CSV-файл, если путь оканчивается на .csv, в каталог изображений с подкаталогом для каждого класса, если это каталог или путь оканчивается на /, или в файлы IDX в остальных случаях, как train-images-idx3-ubyte из MNIST, сжатые gzip, если они оканчиваются на .gz
+help-convert-labels = Файл меток IDX, записываемый --convert-dataset
+help-find-duplicates = Найти пары одинаковых или почти одинаковых изображений в наборе данных, а также изображения из --validation-images (или из тестового набора MNIST при --mnist), которые есть в нём и завышают результаты оценки
+help-duplicate-distance = Во скольких из 64 битов перцептивных хешей могут различаться почти одинаковые изображения для --find-duplicates; 0 находит только неотличимые изображения
+help-tensor-format = Формат --export-tensors: npz или parquet в сборках с функцией parquet
+help-fgsm = Исказить первый правильно распознанный пример из --images по знаку градиента и сохранить его до и после в DIR в формате PNG
+help-epsilon = Насколько --fgsm сдвигает каждый пиксель, в долях диапазона яркости
+help-sample-index = Позиция в --images, с которой --fgsm ищет правильно распознанный пример
+help-prune = Обнулить веса модели, меньшие THRESHOLD по модулю, и сохранить её
+help-quantized = Показать GUI с моделью, сохранённой --quantize
+help-grid-cells = Разделить холст GUI на N ячеек для ввода N-значного числа, по одной цифре в ячейке
+help-profiles = Хранить в DIR профили пользователей, у каждого своя копия --model, дообученная на исправлениях из GUI, и набор этих рисунков
+help-profile = Профиль, выбранный при запуске GUI с --profiles; создаётся при первом исправлении
+help-collect = Разрешить GUI сохранять рисунки с подтверждёнными ответами в этот набор данных — CSV-файл, каталог изображений или файл изображений IDX, — чтобы потом дообучить на них модель
+help-collect-labels = Файл меток IDX для рисунков, сохраняемых с --collect
+help-multiscale = Распознавать фрагменты рисунка в нескольких масштабах и показывать самый уверенный результат, для цифр, нарисованных намного меньше холста
+help-mc-dropout = Оценивать неопределённость предсказаний в GUI по N прямым проходам с dropout и показывать её как вероятность ± неопределённость
+help-mc-dropout-rate = Вероятность отключения скрытого нейрона на каждом проходе --mc-dropout
+help-bench-history = Дописать результаты оценки в файл JSON Lines FILE и показать изменения с предыдущей записи
+help-compare-predictions = Сравнить модель с вероятностями классов другой программы для --images и --labels (по строке CSV на пример) и с их средним
+help-from-scratch = Оценивать заново обученные сети вместо сохранённой модели
+help-model = Файл, из которого загружается и в который сохраняется сеть, в формате JSON, если он оканчивается на .json, и в компактном двоичном формате иначе; повторите, чтобы распознавать в GUI ансамблем моделей
+help-runs-dir = Сохранять каждый запуск обучения в отдельный каталог в DIR; без --model GUI показывает лучшую модель последнего запуска
+help-ensemble-voting = Способ объединения моделей ансамбля (average, majority)
+help-images = Файл или http(s) URL с изображениями для обучения (- читает из стандартного ввода) или каталог с подкаталогом изображений PNG или JPEG для каждой метки
+help-labels = Файл или http(s) URL с метками для обучения (- читает из стандартного ввода)
+help-skip = Пропустить первые N образцов набора данных
+help-limit = Использовать не более N образцов набора данных, например для быстрого эксперимента
+help-random-subset = Выбирать образцы для --limit случайно и перемешивать их (с зерном --seed), а не брать первые по порядку; без --limit перемешать весь набор данных
+help-skip-invalid = Пропускать образцы, которые не удаётся прочитать, записывая в журнал позицию и причину каждого, вместо остановки на первом
+help-fetch-mnist = Скачать обучающий и тестовый наборы MNIST в этот каталог, проверив контрольные суммы и продолжив прерванные загрузки
+help-mnist-mirror = URL, с которого --fetch-mnist скачивает файлы MNIST
+help-synthesize-mnist = Нарисовать цифры установленными шрифтами, со случайными размером, поворотом и толщиной линий, в этот каталог вместо MNIST, если его нельзя скачать; используется с --mnist
+help-fonts = Каталоги через запятую, в которых --synthesize-mnist ищет шрифты .ttf и .otf вместо системных каталогов шрифтов
+help-synthetic-samples = Число обучающих цифр, рисуемых --synthesize-mnist; для тестового набора рисуется вшестеро меньше
+help-mnist = Обучать на обучающем наборе MNIST или оценивать на его тестовом наборе, скачанных в этот каталог с помощью --fetch-mnist, вместо --images и --labels
+help-epochs = Число проходов по обучающему набору
+help-learning-rate = Шаг градиентного спуска
+help-layer-lr-multipliers = Множители скорости обучения для каждого слоя через запятую, например 0.1,1.0
+help-reduce-lr-on-plateau = Снижать скорость обучения, если ошибка на валидации (без неё — на обучающем наборе) не уменьшалась PATIENCE эпох
+help-lr-reduction-factor = Множитель скорости обучения при остановке улучшений
+help-min-learning-rate = Скорость обучения, ниже которой она не снижается, и нижняя граница --lr-cycle
+help-freeze-layers = Номера слоёв через запятую, начиная с 0 со стороны входа, параметры которых не обучаются, например 0, чтобы дообучить только выходной слой
+help-batch-size = Число примеров, градиенты которых усредняются в один шаг градиентного спуска
+help-seed = Начальное значение случайных чисел для весов новых сетей и выбора кандидатов --search и --sweep, чтобы запуски повторялись
+help-threads = Число потоков, вычисляющих градиенты пакета (0 — все ядра)
+help-checkpoint-every = Сохранять контрольную точку каждые N обучающих примеров (0 отключает)
+help-warmup-batches = Линейно увеличивать скорость обучения почти от нуля в течение первых N пакетов
+help-lr-cycle = После разогрева циклически менять скорость обучения между --min-learning-rate и --learning-rate: линейно вверх и вниз (triangular) или по косинусу с перезапуском (cosine)
+help-lr-cycle-length = Число пакетов в одном цикле --lr-cycle
+help-shuffle = Обучать на образцах в новом случайном порядке каждую эпоху, воспроизводимом с --seed
+help-preprocess = Выравнивать наклон и центрировать каждое изображение, как в MNIST, перед обучением; модель продолжает делать это для классифицируемых изображений, включая рисунки
+help-normalize = Стандартизовать входные пиксели по среднему и стандартному отклонению обучающего набора; модель сохраняет их и применяет к каждому классифицируемому изображению
+help-augment = Случайно поворачивать и сдвигать каждое обучающее изображение при каждом обучении на нём
+help-augment-rotation = Наибольший поворот --augment в каждую сторону, в градусах
+help-augment-shift = Наибольший сдвиг --augment по каждой оси, в пикселях
+help-augment-elastic = Дополнительно упруго искажать изображения --augment, сдвигая пиксели на сглаженное случайное поле, умноженное на ALPHA (в литературе по MNIST — 34)
+help-augment-elastic-sigma = Гладкость смещений --augment-elastic: стандартное отклонение их гауссианы в пикселях
+help-augment-blur = Дополнительно размывать изображения --augment гауссианой до SIGMA пикселей, как сглаженные штрихи холста для рисования
+help-augment-noise = Дополнительно добавлять к изображениям --augment гауссов шум с этим стандартным отклонением в долях диапазона яркости
+help-augment-salt-and-pepper = Дополнительно делать пиксели изображений --augment чёрными или белыми, каждый с этой вероятностью
+help-robust-training = Дополнительно обучать на искажённой копии каждого пакета: состязательной по знаку градиента (fgsm) или зашумлённой (noise), для устойчивости к рисункам, непохожим на набор данных
+help-robust-strength = Epsilon FGSM или стандартное отклонение шума для --robust-training, в долях диапазона яркости
+help-teacher = Обучать сеть воспроизводить сглаженные выходы этой обученной модели, например чтобы сжать большую модель в меньшую
+help-distillation-temperature = Температура softmax для выходов --teacher; больше 1 показывает, какие неверные классы учитель считает похожими
+help-distillation-weight = Вес меток набора данных относительно выходов --teacher, от 0 до 1
+help-lr-find = Вместо обучения перебрать скорости обучения по экспоненте в диапазоне --lr-find-range, записать ошибку после каждого пакета в CSV и предложить скорость обучения
+help-lr-find-range = Наименьшая и наибольшая скорость обучения для --lr-find
+help-lr-find-batches = Число пакетов, обучаемых при --lr-find
+help-max-duration = Остановить обучение через DURATION (например 90s, 10m, 1.5h), сохранив контрольную точку для продолжения
+help-class-weights = Взвешивать ошибку каждого класса обратно его частоте в обучающем наборе (balanced) или списком весов через запятую, по одному на класс
+help-sampling = Выбирать образцы каждой эпохи так, чтобы все классы были равновероятны (balanced), или не более этого числа образцов каждого класса
+help-sample-weights = Взвешивать ошибку каждого обучающего примера соответствующей строкой FILE, например чтобы усилить недавно собранные исправления; также используется в --search и --sweep
+help-extra-images = Изображения второго обучающего набора, например рисунки, собранные в интерфейсе; обучение идёт на них вместе с --images, равномерно вперемешку
+help-extra-labels = Метки изображений из --extra-images
+help-extra-weight = Вес образцов из --extra-images в функции потерь
+help-ema-decay = Сохранять вместо итоговых весов их экспоненциальное скользящее среднее, обновляемое с коэффициентом DECAY (например 0.999) после каждого пакета
+help-resume = Продолжить обучение с контрольной точки
+help-input-features = Входы новой модели: пиксели (pixels) или пиксели с гистограммами направлений штрихов (directions)
+help-init-from = Начать новую модель со слоёв сохранённой сети совпадающих размеров, например чтобы переобучить только другой выходной слой
+help-architecture = Создать новую модель со скрытыми слоями, функцией активации и скоростью обучения из конфигурации TOML, записанной --search
+help-search = Подобрать размеры скрытых слоёв, функции активации и скорости обучения на --images и --labels последовательным отсевом и сохранить лучший вариант в конфигурацию TOML для --architecture
+help-search-candidates = Число случайных кандидатов в начале --search; каждый раунд оставляет лучшую половину
+help-search-epochs = Число эпох первого раунда --search, удваивается в каждом следующем
+help-sweep = Обучить модель для каждого сочетания размеров скрытого слоя, скоростей обучения и размеров пакета из конфигурации TOML, упорядочить их по точности на валидации и сохранить лучшую в --model
+help-sweep-dir = Каталог с моделями, конфигурациями, журналами и результатами --sweep
+help-sweep-jobs = Число процессов обучения --sweep, работающих параллельно
+help-validation-images = Файл с изображениями для выбора лучшей модели во время обучения
+help-validation-labels = Файл с метками изображений для валидации
+help-validation-split = Без --validation-images откладывать эту долю обучающих образцов как набор для валидации, выбранную случайно, но одинаковую при одном --seed
+help-verify-sha256 = Прервать работу, если файлы набора данных не совпадают с дайджестами SHA-256 из MANIFEST (формат вывода sha256sum)
+help-alphabet = Набор классов, на котором обучается модель: digits — цифры, letters — буквы EMNIST или часть EMNIST emnist-byclass, emnist-bymerge, emnist-balanced, emnist-digits либо emnist-mnist, изображения которой переворачиваются
+help-label-map = Переназначить или объединить метки набора данных в классы из FILE, по одной строке «метка имя-класса»; метки, которых нет в файле, пропускаются
+help-classes = Число классов в наборе данных, не являющемся ни цифрами, ни буквами
+help-class-names = Названия классов набора данных через запятую, показываемые вместо номеров; по умолчанию читаются из массива class-names.json рядом с метками
+help-detect-nan = Сообщать о слоях, выдающих NaN или бесконечные значения во время обучения
+help-rollback-on-divergence = Если ошибка становится NaN или бесконечной, перед остановкой сохранить в модель сеть из последней контрольной точки
+help-calibrate = После обучения подобрать температуру softmax на валидационном наборе, чтобы показываемая уверенность соответствовала реальной точности
+help-rejection-threshold = Уверенность (0-1), ниже которой модель считает рисунок нераспознанным
+help-max-latency-ms = Переключать GUI на квантованную модель, если распознавание в среднем занимает больше MS миллисекунд
+help-compute-backend = Реализация матричных вычислений (nalgebra; matrixmultiply и wgpu при сборке с возможностями blas и gpu)
+help-script = Система записи цифр, на которой обучена модель, для отображения результатов (latin, arabic-indic, eastern-arabic-indic, devanagari, bengali, thai)
+help-lang = Язык справки и сообщений (en, ru); по умолчанию $DIGIT_RECOGNITION_LANG, затем язык системы
+help-explain = Описать ошибку с кодом CODE, указанным в скобках перед сообщением об ошибке, и способ её исправить
+help-log-level = Подробность диагностических сообщений (off, error, warn, info, debug, trace)
+
+## Error codes, described by --explain
+
+E0001 = файл набора данных не начинается с магического числа IDX
+    .remedy = Проверьте, что --images указывает на файл изображений IDX3, а --labels на файл меток IDX1 и что они не перепутаны.
+E0002 = число примеров в файлах изображений и меток различается
+    .remedy = Используйте файл меток, соответствующий файлу изображений, например train-labels с train-images и t10k-labels с t10k-images. Причиной может быть и недокачанный файл; скачайте его заново.
+E0003 = не удаётся прочитать файл набора данных
+    .remedy = Проверьте, что файл существует и доступен для чтения. Неожиданный конец файла означает, что он скачан не полностью.
+E0004 = и --images, и --labels читаются из стандартного ввода
+    .remedy = Только один из параметров --images и --labels может быть `-`; второй передайте файлом.
+E0005 = в файле весов примеров есть недопустимый вес
+    .remedy = Запишите по одному неотрицательному числу в строке, без заголовка.
+E0006 = в файле весов примеров не по одному весу на пример
+    .remedy = Запишите по одному весу на каждый пример из --images в порядке файла.
+E0007 = строка набора данных CSV не состоит из метки и пикселей квадратного изображения
+    .remedy = Запишите по примеру в строке: метку, затем пиксели построчно числами 0-255 через запятую. Все изображения должны быть одного размера.
+E0008 = не удаётся декодировать изображение из каталога набора данных
+    .remedy = Убедитесь, что все файлы .png, .jpg и .jpeg в каталоге являются корректными изображениями, или удалите их.
+E0009 = каталог набора изображений не назван по метке
+    .remedy = Поместите изображения каждой метки в каталог с номером метки, например data/3/, а остальные каталоги перенесите в другое место.
+E0010 = доля набора данных, откладываемая для валидации, не лежит между 0 и 1
+    .remedy = Передайте --validation-split долю, например 0.1, чтобы отложить десятую часть обучающих образцов.
+E0011 = не удалось скачать набор данных по URL
+    .remedy = Проверьте URL и сетевое подключение или скачайте файлы и передайте пути к ним.
+E0012 = неверный файл имён классов набора данных
+    .remedy = Запишите class-names.json как JSON-массив строк, по одному имени на метку в порядке меток, или удалите его.
+E0013 = запрошен образец за концом набора данных
+    .remedy = Запрашивайте образцы с номером меньше длины набора данных.
+E0014 = у файла IDX неожиданное число измерений
+    .remedy = В файле изображений должны быть число, строки и столбцы, а в файле меток только число; сначала переведите цветные или многоканальные наборы в оттенки серого.
+E0101 = файл модели не является корректным JSON или не описывает сеть
+    .remedy = Проверьте, что --model указывает на модель, сохранённую этой программой, а не, например, на историю обучения или контрольную точку. Файлы с расширением .json читаются как JSON, остальные как двоичный формат.
+E0102 = не удаётся прочитать файл модели
+    .remedy = Проверьте путь, переданный в --model, и права доступа к нему.
+E0103 = не удаётся сохранить модель
+    .remedy = Проверьте, что каталог выходного файла существует и доступен для записи и что на диске есть место.
+E0104 = двоичный файл модели повреждён
+    .remedy = Файл обрезан или не является двоичной моделью. Восстановите его из резервной копии или архива либо обучите модель заново. При сохранении с расширением .json записывается читаемый формат.
+E0105 = не удаётся закодировать модель в двоичный формат
+    .remedy = Сохраните модель с расширением .json и сообщите о проблеме.
+E0106 = модель сохранена более новой версией программы
+    .remedy = Обновите программу до версии, сохранившей модель, или обучите модель заново этой версией.
+E0107 = файл модели является JSON, но не описывает сеть
+    .remedy = Проверьте, что --model указывает на модель, а не на другой файл JSON, например историю обучения.
+E0108 = двоичный файл модели имеет неподдерживаемую версию формата
+    .remedy = Обновите программу до версии, сохранившей модель, или преобразуйте модель в JSON командой --convert-model этой версии.
+E0109 = размер входа не совпадает с размером, на котором обучена модель
+    .remedy = Используйте изображения с разрешением обучающего набора; модель, обученная на изображениях 28x28, распознаёт только изображения 28x28.
+E0110 = метка больше числа выходных классов
+    .remedy = В наборе данных больше классов, чем у модели. Обучите новую модель с подходящим --alphabet или --classes вместо продолжения обучения существующей.
+E0111 = неизвестная функция активации
+    .remedy = Используйте sigmoid, tanh или relu.
+E0112 = некорректная доля отключаемых нейронов
+    .remedy = Передайте --mc-dropout-rate не меньше 0 и меньше 1.
+E0201 = не удаётся сохранить историю обучения
+    .remedy = Проверьте, что каталог --model доступен для записи и что на диске есть место.
+E0202 = не удаётся сохранить кривую скорости обучения --lr-find
+    .remedy = Проверьте, что путь к CSV, переданный в --lr-find, доступен для записи.
+E0203 = не удаётся прочитать контрольную точку обучения
+    .remedy = Проверьте путь, переданный в --resume. Контрольные точки сохраняются рядом с моделью как *.checkpoint.json.
+E0204 = не удаётся сохранить контрольную точку обучения
+    .remedy = Проверьте, что каталог --model доступен для записи и что на диске есть место.
+E0205 = не удаётся запустить потоки обучения
+    .remedy = Уменьшите --threads или передайте 0, чтобы использовать по потоку на ядро.
+E0206 = --freeze-layers указывает слой, которого нет в сети
+    .remedy = Слои нумеруются с 0 со стороны входа; в сети по умолчанию есть слои 0 и 1.
+E0207 = метка обучающего примера больше числа выходных классов
+    .remedy = В наборе данных больше классов, чем у модели. Обучите новую модель с подходящим --alphabet или --classes.
+E0208 = число весов --class-weights не совпадает с числом классов модели
+    .remedy = Передайте по одному весу на класс или balanced, чтобы вычислить их по обучающему набору.
+E0209 = неизвестный цикл скорости обучения
+    .remedy = Используйте triangular или cosine для --lr-cycle.
+E0210 = контрольная точка или история обучения повреждена
+    .remedy = Продолжите с более старой контрольной точки или начните обучение заново без --resume.
+E0211 = обучение разошлось: ошибка или градиенты стали NaN или бесконечными
+    .remedy = Уменьшите --learning-rate или добавьте --warmup-batches; --detect-nan покажет слой, где это началось. С --rollback-on-divergence сохраняется модель из последней контрольной точки, и обучение можно продолжить с неё через --resume.
+E0212 = неизвестный вид искажения
+    .remedy = Передайте fgsm или noise в --robust-training.
+E0213 = сеть-учитель не подходит к обучаемой сети
+    .remedy = Передайте в --teacher модель, обученную на изображениях того же размера и с теми же классами.
+E0214 = недопустимая температура дистилляции
+    .remedy = Передайте положительное значение --distillation-temperature.
+E0215 = сеть-учитель подготавливает изображения иначе, чем обучаемая сеть
+    .remedy = Передайте в --teacher модель, обученную с --preprocess тогда и только тогда, когда с ним обучается и эта сеть.
+E0301 = недопустимое значение параметра
+    .remedy = Запустите с --help, чтобы увидеть ожидаемый формат параметра.
+E0302 = команде, использующей одну модель, передано несколько --model
+    .remedy = Объединять модели в ансамбль умеет только GUI; другим командам передайте одну --model.
+E0303 = число классов существующей модели не совпадает с выбранным алфавитом
+    .remedy = Обучайте в новый файл --model или выберите алфавит, на котором обучена модель.
+E0304 = --calibrate без валидационного набора
+    .remedy = Передайте --validation-images и --validation-labels или отложите часть обучающего набора с --validation-split; калибровка на обучающем наборе сделала бы модель самоуверенной.
+E0305 = --sweep без валидационного набора
+    .remedy = Передайте --validation-images и --validation-labels, чтобы упорядочить обученные модели.
+E0306 = не удаётся разобрать командную строку
+    .remedy = Запустите с --help, чтобы увидеть доступные параметры.
+E0307 = --explain получил неизвестный код ошибки
+    .remedy = Коды ошибок имеют вид E0001 и выводятся в скобках перед сообщениями об ошибках.
+E0308 = не удалось сохранить изображение
+    .remedy = Проверьте, что каталог файла существует и доступен для записи.
+E0309 = переданы несовместимые параметры
+    .remedy = Уберите один из двух параметров, названных в сообщении.
+E0401 = неизвестная система записи цифр
+    .remedy = Используйте latin, arabic-indic, eastern-arabic-indic, devanagari, bengali или thai для --script.
+E0402 = неизвестный алфавит
+    .remedy = Используйте digits, letters или часть EMNIST, например emnist-balanced, для --alphabet либо --classes для другого числа классов.
+E0403 = неизвестная или недоступная реализация вычислений
+    .remedy = Используйте nalgebra; matrixmultiply и wgpu доступны только при сборке с возможностями blas и gpu.
+E0404 = неизвестные входные признаки
+    .remedy = Используйте pixels или directions для --input-features.
+E0405 = неизвестный способ голосования ансамбля
+    .remedy = Используйте average или majority для --ensemble-voting.
+E0406 = ансамбль без моделей
+    .remedy = Передайте хотя бы одну --model.
+E0407 = модели ансамбля не совпадают
+    .remedy = У всех моделей ансамбля должны быть размер входа и классы первой; обучайте их на одном наборе данных.
+E0501 = не удаётся прочитать файл для проверки контрольной суммы
+    .remedy = Проверьте пути в манифесте, переданном в --verify-sha256, и пути к набору данных.
+E0502 = строка манифеста контрольных сумм некорректна
+    .remedy = Строки манифеста имеют формат sha256sum: шестнадцатеричный дайджест, два пробела и имя файла.
+E0503 = файла набора данных нет в манифесте контрольных сумм
+    .remedy = Добавьте файл в манифест или проверьте, что имена файлов набора данных совпадают с манифестом.
+E0504 = файл набора данных не совпадает со своей контрольной суммой
+    .remedy = Файл повреждён или изменён. Скачайте его заново.
+E0505 = стандартный ввод нельзя проверить по контрольной сумме
+    .remedy = При использовании --verify-sha256 передайте набор данных файлом.
+E0601 = не удаётся прочитать архив или один из его файлов
+    .remedy = Проверьте путь, переданный в --import-bundle, или модель и её историю для --export-bundle.
+E0602 = не удаётся записать архив или один из его файлов
+    .remedy = Проверьте, что целевой каталог доступен для записи и что на диске есть место.
+E0603 = файл архива повреждён
+    .remedy = Экспортируйте архив заново.
+E0604 = в архиве нет модели
+    .remedy = Экспортируйте архив заново из обученной модели.
+E0701 = не удаётся создать каталог запуска
+    .remedy = Проверьте, что каталог, переданный в --runs-dir, доступен для записи.
+E0702 = не удаётся прочитать каталог запусков
+    .remedy = Проверьте путь, переданный в --runs-dir, и права доступа к нему.
+E0703 = ни в одном запуске нет обученной модели
+    .remedy = Сначала обучите модель с --train --runs-dir или передайте --model.
+E0801 = не удаётся прочитать историю оценок
+    .remedy = Проверьте путь, переданный в --bench-history, и права доступа к нему.
+E0802 = не удаётся записать историю оценок
+    .remedy = Проверьте, что путь, переданный в --bench-history, доступен для записи.
+E0803 = история оценок повреждена
+    .remedy = Каждая строка истории должна быть записью JSON; удалите или исправьте повреждённую строку.
+E0901 = не удаётся показать GUI
+    .remedy = Проверьте, что доступен дисплей; GUI не работает через обычный SSH или в контейнере без графического сервера.
+E0902 = не удаётся скопировать рисунок с холста
+    .remedy = Перезапустите GUI и сообщите о проблеме.
+E0911 = нет доступа к каталогу профилей
+    .remedy = Проверьте, что каталог, переданный в --profiles, доступен для записи.
+E0912 = недопустимое имя профиля
+    .remedy = Имена профилей могут содержать только буквы, цифры, `-` и `_`.
+E0913 = не удаётся добавить пример в набор данных профиля
+    .remedy = Проверьте, что каталог профиля доступен для записи. Разрешение набора данных должно совпадать с разрешением модели.
+E1001 = не удаётся прочитать изображение, переданное в --detect
+    .remedy = Проверьте путь к изображению и права доступа к нему.
+E1002 = изображение, переданное в --detect, имеет неподдерживаемый формат
+    .remedy = Преобразуйте его в двоичный 8-битный PGM в оттенках серого (P5), например командой `convert image.png image.pgm`.
+E1101 = не удаётся прочитать CSV с предсказаниями
+    .remedy = Проверьте путь, переданный в --compare-predictions, и права доступа к нему.
+E1102 = в CSV с предсказаниями есть значение, не являющееся вероятностью
+    .remedy = Экспортируйте вероятности, например predict_proba в scikit-learn или softmax модели PyTorch, а не логиты или метки классов.
+E1103 = в строке CSV с предсказаниями не по одному столбцу на класс
+    .remedy = Запишите по одному столбцу на каждый класс модели, без столбца индекса.
+E1104 = число строк CSV с предсказаниями не совпадает с размером тестового набора
+    .remedy = Предскажите каждый пример из --images в порядке файла, без перемешивания.
+E1201 = не удаётся прочитать конфигурацию обучения
+    .remedy = Проверьте путь, переданный в --architecture или --sweep.
+E1202 = не удаётся записать конфигурацию обучения
+    .remedy = Проверьте, что путь, переданный в --search, доступен для записи.
+E1203 = конфигурация обучения не является корректным TOML или в ней не хватает значений
+    .remedy = Сравните её с конфигурацией, записанной --search, или с примером в документации --sweep.
+E1204 = не удаётся сериализовать конфигурацию обучения
+    .remedy = Сообщите о проблеме.
+E1205 = в пространстве поиска нет достаточно маленькой архитектуры
+    .remedy = Используйте меньшие размеры скрытых слоёв или изображения меньшего разрешения.
+E1206 = не удаётся записать результаты --sweep
+    .remedy = Проверьте, что каталог, переданный в --sweep-dir, доступен для записи.
+E1207 = не удаётся запустить процесс обучения --sweep
+    .remedy = Уменьшите --sweep-jobs, если в системе закончились процессы или память.
+E1208 = процесс обучения --sweep завершился с ошибкой
+    .remedy = Ошибка записана в конце журнала, указанного в сообщении.
+E1301 = не удаётся прочитать таблицу меток
+    .remedy = Проверьте путь, переданный в --label-map.
+E1302 = строка таблицы меток некорректна
+    .remedy = Запишите каждую строку как метку набора данных от 0 до 255, пробел и имя класса.
+E1303 = метка набора данных назначена дважды
+    .remedy = Назначьте каждой метке один класс; чтобы объединить метки, дайте им одинаковое имя класса.
+E1304 = таблица меток пуста
+    .remedy = Укажите хотя бы одну метку набора данных и её класс.
+E1401 = некорректный --epsilon
+    .remedy = Передайте --epsilon больше 0 и не больше 1, например 0.1.
+E1402 = ни один пример не распознан правильно
+    .remedy = Проверьте, что модель обучена на алфавите набора данных, или уменьшите --sample-index.
+E1403 = не удаётся сохранить изображение
+    .remedy = Проверьте, что каталог, переданный в --fgsm, доступен для записи.
+E1501 = неизвестный формат тензоров
+    .remedy = Передайте npz или parquet в --tensor-format.
+E1502 = не удаётся записать тензоры
+    .remedy = Проверьте, что файл, переданный в --export-tensors, доступен для записи.
+E1503 = эта сборка не поддерживает файлы parquet
+    .remedy = Пересоберите программу с `cargo build --features parquet` или экспортируйте npz.
+E1601 = не удаётся скачать MNIST
+    .remedy = Проверьте подключение к сети или передайте другой --mnist-mirror.
+E1602 = не удаётся записать скачанный файл
+    .remedy = Проверьте, что каталог --fetch-mnist доступен для записи и диск не заполнен.
+E1603 = скачанный файл MNIST не совпадает с контрольной суммой
+    .remedy = Запустите --fetch-mnist ещё раз; если ошибка повторяется, передайте другой --mnist-mirror.
+E1701 = не удаётся записать набор данных
+    .remedy = Проверьте, что выходной каталог существует и доступен для записи, а диск не заполнен.
+E1702 = изображения набора данных разного размера
+    .remedy = Файлы IDX содержат изображения одного размера; измените размер изображений перед записью.
+E1703 = слишком много образцов для набора IDX
+    .remedy = Разделите набор данных на несколько файлов, не более 4294967295 образцов в каждом.
+E1704 = длина файла IDX не совпадает с его заголовком
+    .remedy = Файл обрезан или не является файлом IDX; запишите набор данных в новые файлы, а не дополняйте этот.
+E1801 = не удаётся прочитать шрифт
+    .remedy = Проверьте, что файлы шрифтов в каталогах --fonts доступны для чтения.
+E1802 = не найдены шрифты, которыми можно нарисовать цифры
+    .remedy = Установите шрифты TrueType или OpenType или укажите каталоги с ними в --fonts.
@@ -0,0 +1,116 @@
+use std::cmp::Ordering;
+use std::io;
+use thiserror::Error;
+use crate::data::Image;
+use crate::network::{self, NeuralNetwork, Prediction};
+use crate::training::input_vector;
+use crate::training_data::{Label, LabeledTrainingData};
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error(transparent)]
+    NeuralNetworkError(#[from] network::ErrorKind),
+
+    #[error("epsilon {0} is not in (0, 1]")]
+    InvalidEpsilon(f64),
+
+    #[error("no sample from position {0} on is classified correctly")]
+    NoCorrectlyClassifiedSample(usize),
+
+    #[error("cannot save {file} ({source})")]
+    CannotSaveImage {
+        file: String,
+        source: io::Error
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::NeuralNetworkError(err) => err.code(),
+            ErrorKind::InvalidEpsilon(_) => "E1401",
+            ErrorKind::NoCorrectlyClassifiedSample(_) => "E1402",
+            ErrorKind::CannotSaveImage { .. } => "E1403"
+        }
+    }
+}
+
+/// A correctly classified sample and its perturbed copy.
+pub struct AdversarialExample {
+    /// Position of the sample in the dataset.
+    pub position: usize,
+    pub label: Label,
+    pub original: Prediction,
+    pub perturbed: Image,
+    pub adversarial: Prediction
+}
+
+impl AdversarialExample {
+    /// Whether the perturbation changed the predicted class.
+    pub fn flipped(&self) -> bool {
+        self.adversarial.class != self.original.class
+    }
+}
+
+/// Fast gradient sign method: moves every pixel of `image` by `epsilon` of
+/// the full brightness range in the direction that increases the loss for
+/// `label`.
+pub fn fgsm(network: &NeuralNetwork, image: &Image, label: Label, epsilon: f64) -> Result<Image> {
+    if !(epsilon > 0.0 && epsilon <= 1.0) {
+        return Err(ErrorKind::InvalidEpsilon(epsilon))
+    }
+
+    let class_count = network.output_size();
+    let target = label
+        .to_one_hot(class_count)
+        .ok_or(network::ErrorKind::LabelOutOfRange { class: label.class(), class_count })?;
+    let gradient = network.input_gradient(input_vector(image), &target)?;
+
+    let step = epsilon * 255.0;
+    let pixels: Vec<u8> = image
+        .pixels()
+        .iter()
+        .zip(gradient.iter())
+        .map(|(pixel, gradient)| {
+            let direction = match gradient.partial_cmp(&0.0) {
+                Some(Ordering::Greater) => 1.0,
+                Some(Ordering::Less) => -1.0,
+                _ => 0.0
+            };
+            (*pixel as f64 + direction * step).round().clamp(0.0, 255.0) as u8
+        })
+        .collect();
+
+    Ok(Image::builder()
+        .with_size(image.size())
+        .with_pixels_row_major(pixels)
+        .build())
+}
+
+/// Perturbs the first sample from `start` on that `network` classifies
+/// correctly.
+pub fn find_adversarial_example(network: &NeuralNetwork,
+                                samples: &[LabeledTrainingData],
+                                start: usize,
+                                epsilon: f64) -> Result<AdversarialExample> {
+    for (position, sample) in samples.iter().enumerate().skip(start) {
+        let original = network.predict(input_vector(sample.image()))?;
+        if original.class != sample.label().class() as usize {
+            continue
+        }
+
+        let perturbed = fgsm(network, sample.image(), *sample.label(), epsilon)?;
+        let adversarial = network.predict(input_vector(&perturbed))?;
+
+        return Ok(AdversarialExample { position, label: *sample.label(), original, perturbed, adversarial })
+    }
+
+    Err(ErrorKind::NoCorrectlyClassifiedSample(start))
+}
+
+/// Saves an image as a PNG, naming the file in the error.
+pub fn save_image(image: &Image, file: &str) -> Result<()> {
+    image.save_png(file).map_err(|source| ErrorKind::CannotSaveImage { file: file.to_string(), source })
+}
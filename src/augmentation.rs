@@ -0,0 +1,272 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand_distr::{Distribution, Normal};
+use rayon::ThreadPool;
+use rayon::prelude::*;
+use crate::data::Image;
+use crate::training_data::LabeledTrainingData;
+use crate::transform::Transform;
+
+/// Random transformations of the training images, drawn anew every time a
+/// sample is trained on, so the network doesn't see the same image twice;
+/// a [`Transform`] applying [`RotateAndShift`] and then the others in the
+/// order of the fields.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Augmentation {
+    /// Images are rotated by up to this many degrees either way.
+    pub max_rotation: f64,
+    /// Images are moved by up to this many pixels along each axis.
+    pub max_shift: f64,
+    /// Applied after the rotation and shift.
+    pub elastic: Option<ElasticDistortion>,
+    /// Applied after the distortions, in this order.
+    pub blur: Option<Blur>,
+    pub gaussian_noise: Option<GaussianNoise>,
+    pub salt_and_pepper: Option<SaltAndPepper>
+}
+
+impl Default for Augmentation {
+    fn default() -> Self {
+        Augmentation {
+            max_rotation: 15.0,
+            max_shift: 2.0,
+            elastic: None,
+            blur: None,
+            gaussian_noise: None,
+            salt_and_pepper: None
+        }
+    }
+}
+
+impl Transform for Augmentation {
+    fn apply(&self, image: &mut Image, rng: &mut impl Rng) {
+        RotateAndShift { max_rotation: self.max_rotation, max_shift: self.max_shift }
+            .then(self.elastic)
+            .then(self.blur)
+            .then(self.gaussian_noise)
+            .then(self.salt_and_pepper)
+            .apply(image, rng);
+    }
+}
+
+impl Augmentation {
+    /// Augmented copies of `batch`; each copy is seeded by `seed` and its
+    /// position in the batch, so runs don't depend on the thread count.
+    pub(crate) fn augment_batch(&self,
+                                batch: &[LabeledTrainingData],
+                                seed: u64,
+                                thread_pool: &ThreadPool) -> Vec<LabeledTrainingData> {
+        thread_pool.install(|| batch
+            .par_iter()
+            .enumerate()
+            .map(|(index, example)| {
+                let mut image = example.image().clone();
+                self.apply(&mut image, &mut StdRng::seed_from_u64(seed.wrapping_add(index as u64)));
+                LabeledTrainingData::new(image, *example.label()).with_weight(example.weight())
+            })
+            .collect())
+    }
+}
+
+/// Uniformly distributed in `[-max, max]`.
+fn symmetric(rng: &mut impl Rng, max: f64) -> f64 {
+    return if max > 0.0 {
+        rng.gen_range(-max..=max)
+    } else {
+        0.0
+    }
+}
+
+/// Rotation by up to `max_rotation` degrees and a shift by up to `max_shift`
+/// pixels along each axis, either way.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RotateAndShift {
+    pub max_rotation: f64,
+    pub max_shift: f64
+}
+
+impl Transform for RotateAndShift {
+    fn apply(&self, image: &mut Image, rng: &mut impl Rng) {
+        let angle = symmetric(rng, self.max_rotation).to_radians();
+        let shift = (symmetric(rng, self.max_shift), symmetric(rng, self.max_shift));
+        *image = rotate_and_shift(image, angle, shift);
+    }
+}
+
+/// `image` rotated by `angle` radians around its center and moved by
+/// `shift` pixels, with bilinear interpolation. Pixels moved in from outside
+/// the image are black, the background of MNIST.
+pub fn rotate_and_shift(image: &Image, angle: f64, shift: (f64, f64)) -> Image {
+    let size = image.size();
+    let (width, height) = (size.width as usize, size.height as usize);
+    let center = ((width as f64 - 1.0) / 2.0, (height as f64 - 1.0) / 2.0);
+    let (sin, cos) = angle.sin_cos();
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            // the inverse transformation finds where each output pixel comes from
+            let dx = x as f64 - center.0 - shift.0;
+            let dy = y as f64 - center.1 - shift.1;
+            let source_x = cos * dx + sin * dy + center.0;
+            let source_y = -sin * dx + cos * dy + center.1;
+            pixels.push(bilinear(image, source_x, source_y));
+        }
+    }
+
+    Image::builder()
+        .with_size(size)
+        .with_pixels_row_major(pixels)
+        .build()
+}
+
+/// Elastic deformation of Simard et al., "Best Practices for Convolutional
+/// Neural Networks Applied to Visual Document Analysis": every pixel is moved
+/// by a random displacement, smoothed with a Gaussian of standard deviation
+/// `sigma` so that nearby pixels move together, and scaled by `alpha`.
+/// The paper uses `alpha` 34 and `sigma` 4 for 28x28 images.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ElasticDistortion {
+    pub alpha: f64,
+    pub sigma: f64
+}
+
+impl Default for ElasticDistortion {
+    fn default() -> Self {
+        ElasticDistortion {
+            alpha: 34.0,
+            sigma: 4.0
+        }
+    }
+}
+
+impl Transform for ElasticDistortion {
+    fn apply(&self, image: &mut Image, rng: &mut impl Rng) {
+        let size = image.size();
+        let (width, height) = (size.width as usize, size.height as usize);
+        let mut field = || {
+            let noise: Vec<f64> = (0..width * height).map(|_| rng.gen_range(-1.0..=1.0)).collect();
+            gaussian_blur(&noise, width, height, self.sigma)
+        };
+        let (dx, dy) = (field(), field());
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * width + x;
+                pixels.push(bilinear(image, x as f64 + self.alpha * dx[i], y as f64 + self.alpha * dy[i]));
+            }
+        }
+
+        image.pixels_mut().copy_from_slice(&pixels);
+    }
+}
+
+/// Gaussian blur of a random strength, like the antialiasing of strokes
+/// drawn on the GUI canvas.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Blur {
+    /// The standard deviation of the Gaussian, in pixels, is drawn from
+    /// `[0, max_sigma]`.
+    pub max_sigma: f64
+}
+
+impl Transform for Blur {
+    fn apply(&self, image: &mut Image, rng: &mut impl Rng) {
+        let sigma = symmetric(rng, self.max_sigma).abs();
+        let size = image.size();
+        let values: Vec<f64> = image.pixels().iter().map(|pixel| *pixel as f64).collect();
+        let blurred = gaussian_blur(&values, size.width as usize, size.height as usize, sigma);
+
+        for (pixel, value) in image.pixels_mut().iter_mut().zip(blurred) {
+            *pixel = value.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// Normally distributed noise added to every pixel; `std_dev` is a fraction
+/// of the brightness range.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GaussianNoise {
+    pub std_dev: f64
+}
+
+impl Transform for GaussianNoise {
+    fn apply(&self, image: &mut Image, rng: &mut impl Rng) {
+        let noise = Normal::new(0.0, self.std_dev.abs() * 255.0).expect("standard deviation is not negative");
+        for pixel in image.pixels_mut() {
+            *pixel = (*pixel as f64 + noise.sample(rng)).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// Turns pixels black or white, each with `probability`, like dust on a scan.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SaltAndPepper {
+    pub probability: f64
+}
+
+impl Transform for SaltAndPepper {
+    fn apply(&self, image: &mut Image, rng: &mut impl Rng) {
+        let probability = self.probability.clamp(0.0, 1.0);
+        for pixel in image.pixels_mut() {
+            if rng.gen_bool(probability) {
+                *pixel = if rng.gen_bool(0.5) { u8::MAX } else { 0 };
+            }
+        }
+    }
+}
+
+/// Row-major `values` convolved with a Gaussian of standard deviation
+/// `sigma`, treating values beyond the edges as 0.
+fn gaussian_blur(values: &[f64], width: usize, height: usize, sigma: f64) -> Vec<f64> {
+    if sigma <= 0.0 {
+        return values.to_vec()
+    }
+
+    let radius = (3.0 * sigma).ceil() as isize;
+    let kernel: Vec<f64> = (-radius..=radius).map(|d| (-((d * d) as f64) / (2.0 * sigma * sigma)).exp()).collect();
+    let total: f64 = kernel.iter().sum();
+    let kernel: Vec<f64> = kernel.iter().map(|weight| weight / total).collect();
+
+    // the kernel is separable, so the rows and the columns are blurred in turn
+    let convolve = |values: &[f64], step: (isize, isize)| -> Vec<f64> {
+        (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as isize, (i / width) as isize);
+                kernel
+                    .iter()
+                    .enumerate()
+                    .map(|(k, weight)| {
+                        let d = k as isize - radius;
+                        let (x, y) = (x + d * step.0, y + d * step.1);
+                        return if x < 0 || y < 0 || x >= width as isize || y >= height as isize {
+                            0.0
+                        } else {
+                            weight * values[y as usize * width + x as usize]
+                        }
+                    })
+                    .sum()
+            })
+            .collect()
+    };
+
+    convolve(&convolve(values, (1, 0)), (0, 1))
+}
+
+pub(crate) fn bilinear(image: &Image, x: f64, y: f64) -> u8 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let pixel = |x: f64, y: f64| -> f64 {
+        let size = image.size();
+        return if x < 0.0 || y < 0.0 || x >= size.width as f64 || y >= size.height as f64 {
+            0.0
+        } else {
+            image.pixels()[y as usize * size.width as usize + x as usize] as f64
+        }
+    };
+
+    let top = pixel(x0, y0) * (1.0 - fx) + pixel(x0 + 1.0, y0) * fx;
+    let bottom = pixel(x0, y0 + 1.0) * (1.0 - fx) + pixel(x0 + 1.0, y0 + 1.0) * fx;
+    (top * (1.0 - fy) + bottom * fy).round().clamp(0.0, 255.0) as u8
+}
@@ -0,0 +1,254 @@
+use nalgebra::{DMatrix, DVector};
+use crate::backend::{Backend, ComputeBackend};
+use crate::network::Scalar;
+use crate::sparse::CsrMatrix;
+
+pub type NodeId = usize;
+
+enum Operation {
+    Input,
+    MatMul {
+        layer: usize,
+        input: NodeId
+    },
+    AddBias {
+        layer: usize,
+        input: NodeId
+    },
+    Scale {
+        input: NodeId,
+        factor: Scalar
+    },
+    Mask {
+        input: NodeId,
+        mask: DVector<Scalar>
+    },
+    Activation {
+        input: NodeId,
+        derivative: fn(Scalar) -> Scalar
+    },
+    Softmax {
+        input: NodeId
+    }
+}
+
+pub fn softmax(vec: &mut DVector<Scalar>) {
+    let mx = vec.max();
+    vec.apply(|x| *x = (*x-mx).exp());
+
+    let exp_sum = vec.sum();
+    vec.apply(|x| {
+        *x /= exp_sum
+    });
+}
+
+/// Parameter and node gradients produced by [`Tape::backward`]. Layers that
+/// took no part in the recorded computation have no gradients.
+pub struct Gradients {
+    weights: Vec<Option<DMatrix<Scalar>>>,
+    biases: Vec<Option<DVector<Scalar>>>,
+    nodes: Vec<Option<DVector<Scalar>>>
+}
+
+impl Gradients {
+    pub fn weights(&self, layer: usize) -> Option<&DMatrix<Scalar>> {
+        self.weights.get(layer)?.as_ref()
+    }
+
+    pub fn biases(&self, layer: usize) -> Option<&DVector<Scalar>> {
+        self.biases.get(layer)?.as_ref()
+    }
+
+    pub fn node(&self, node: NodeId) -> Option<&DVector<Scalar>> {
+        self.nodes.get(node)?.as_ref()
+    }
+
+    /// Adds the parameter gradients of `other`, e.g. of another example in
+    /// the same batch. Node gradients are not combined.
+    pub fn merge(&mut self, other: &Gradients) {
+        for (gradient, other) in self.weights.iter_mut().zip(&other.weights) {
+            if let Some(other) = other {
+                match gradient {
+                    Some(gradient) => *gradient += other,
+                    None => *gradient = Some(other.clone_owned())
+                }
+            }
+        }
+
+        for (gradient, other) in self.biases.iter_mut().zip(&other.biases) {
+            if let Some(other) = other {
+                match gradient {
+                    Some(gradient) => *gradient += other,
+                    None => *gradient = Some(other.clone_owned())
+                }
+            }
+        }
+    }
+
+    /// Whether no parameter gradient is NaN or infinite.
+    pub fn is_finite(&self) -> bool {
+        self.weights.iter().flatten().all(|gradient| gradient.iter().all(|x| x.is_finite()))
+            && self.biases.iter().flatten().all(|gradient| gradient.iter().all(|x| x.is_finite()))
+    }
+
+    pub fn scale(&mut self, factor: Scalar) {
+        self.weights.iter_mut().flatten().for_each(|gradient| *gradient *= factor);
+        self.biases.iter_mut().flatten().for_each(|gradient| *gradient *= factor);
+    }
+}
+
+fn accumulate(backend: &dyn Backend, gradient: &mut Option<DVector<Scalar>>, value: DVector<Scalar>) {
+    match gradient {
+        Some(gradient) => backend.axpy(1.0, &value, gradient),
+        None => *gradient = Some(value)
+    }
+}
+
+/// Records the operations of a forward pass together with their results, so
+/// the backward pass can be derived from the recorded operations instead of
+/// being written by hand for every architecture.
+pub struct Tape {
+    backend: &'static dyn Backend,
+    operations: Vec<Operation>,
+    values: Vec<DVector<Scalar>>
+}
+
+impl Default for Tape {
+    fn default() -> Self {
+        Tape::new()
+    }
+}
+
+impl Tape {
+    pub fn new() -> Tape {
+        Tape::with_backend(ComputeBackend::default().backend())
+    }
+
+    pub fn with_backend(backend: &'static dyn Backend) -> Tape {
+        Tape {
+            backend,
+            operations: Vec::new(),
+            values: Vec::new()
+        }
+    }
+
+    fn push(&mut self, operation: Operation, value: DVector<Scalar>) -> NodeId {
+        self.operations.push(operation);
+        self.values.push(value);
+        self.values.len() - 1
+    }
+
+    pub fn value(&self, node: NodeId) -> &DVector<Scalar> {
+        &self.values[node]
+    }
+
+    pub fn into_value(mut self, node: NodeId) -> DVector<Scalar> {
+        self.values.swap_remove(node)
+    }
+
+    pub fn input(&mut self, value: DVector<Scalar>) -> NodeId {
+        self.push(Operation::Input, value)
+    }
+
+    pub fn mat_mul(&mut self, layer: usize, weights: &DMatrix<Scalar>, input: NodeId) -> NodeId {
+        let value = self.backend.mat_vec(weights, &self.values[input]);
+        self.push(Operation::MatMul { layer, input }, value)
+    }
+
+    /// Same as [`Tape::mat_mul`] for a sparse copy of the layer's weights;
+    /// the backward pass still takes the dense weights.
+    pub fn sparse_mat_mul(&mut self, layer: usize, weights: &CsrMatrix, input: NodeId) -> NodeId {
+        let value = weights.mul_vec(&self.values[input]);
+        self.push(Operation::MatMul { layer, input }, value)
+    }
+
+    pub fn add_bias(&mut self, layer: usize, biases: &DVector<Scalar>, input: NodeId) -> NodeId {
+        let mut value = self.values[input].clone_owned();
+        self.backend.axpy(1.0, biases, &mut value);
+        self.push(Operation::AddBias { layer, input }, value)
+    }
+
+    pub fn scale(&mut self, factor: Scalar, input: NodeId) -> NodeId {
+        let value = self.backend.scale(factor, &self.values[input]);
+        self.push(Operation::Scale { input, factor }, value)
+    }
+
+    /// Multiplies `input` elementwise by `mask`, e.g. to drop units out.
+    pub fn mask(&mut self, mask: DVector<Scalar>, input: NodeId) -> NodeId {
+        let value = self.backend.component_mul(&self.values[input], &mask);
+        self.push(Operation::Mask { input, mask }, value)
+    }
+
+    pub fn activation(&mut self,
+                      function: fn(Scalar) -> Scalar,
+                      derivative: fn(Scalar) -> Scalar,
+                      input: NodeId) -> NodeId {
+        let value = self.backend.map(&self.values[input], function);
+        self.push(Operation::Activation { input, derivative }, value)
+    }
+
+    pub fn softmax(&mut self, input: NodeId) -> NodeId {
+        let mut value = self.values[input].clone_owned();
+        softmax(&mut value);
+        self.push(Operation::Softmax { input }, value)
+    }
+
+    /// Propagates `output_gradient` (the gradient of the loss w.r.t. `output`)
+    /// back through the tape. `weights` are the matrices the tape was recorded with.
+    pub fn backward(&self,
+                    output: NodeId,
+                    output_gradient: DVector<Scalar>,
+                    weights: &[&DMatrix<Scalar>]) -> Gradients {
+        let backend = self.backend;
+        let mut gradients = Gradients {
+            weights: vec![None; weights.len()],
+            biases: vec![None; weights.len()],
+            nodes: vec![None; self.values.len()]
+        };
+
+        gradients.nodes[output] = Some(output_gradient);
+
+        for node in (0..=output).rev() {
+            let gradient = match &gradients.nodes[node] {
+                Some(gradient) => gradient.clone_owned(),
+                None => continue
+            };
+
+            match &self.operations[node] {
+                Operation::Input => {},
+                Operation::MatMul { layer, input } => {
+                    let weight_gradient = backend.outer(&gradient, &self.values[*input]);
+                    match &mut gradients.weights[*layer] {
+                        Some(accumulated) => backend.matrix_axpy(1.0, &weight_gradient, accumulated),
+                        None => gradients.weights[*layer] = Some(weight_gradient)
+                    }
+
+                    accumulate(backend, &mut gradients.nodes[*input],
+                               backend.tr_mat_vec(weights[*layer], &gradient));
+                },
+                Operation::AddBias { layer, input } => {
+                    accumulate(backend, &mut gradients.biases[*layer], gradient.clone_owned());
+                    accumulate(backend, &mut gradients.nodes[*input], gradient);
+                },
+                Operation::Scale { input, factor } => {
+                    accumulate(backend, &mut gradients.nodes[*input], backend.scale(*factor, &gradient));
+                },
+                Operation::Mask { input, mask } => {
+                    accumulate(backend, &mut gradients.nodes[*input], backend.component_mul(&gradient, mask));
+                },
+                Operation::Activation { input, derivative } => {
+                    let local = backend.map(&self.values[*input], *derivative);
+                    accumulate(backend, &mut gradients.nodes[*input], backend.component_mul(&gradient, &local));
+                },
+                Operation::Softmax { input } => {
+                    let value = &self.values[node];
+                    let dot = backend.dot(&gradient, value);
+                    let local = backend.component_mul(value, &gradient.add_scalar(-dot));
+                    accumulate(backend, &mut gradients.nodes[*input], local);
+                }
+            }
+        }
+
+        gradients
+    }
+}
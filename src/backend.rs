@@ -0,0 +1,260 @@
+use std::str::FromStr;
+#[cfg(feature = "gpu")]
+use std::sync::Once;
+#[cfg(feature = "gpu")]
+use log::warn;
+use nalgebra::{DMatrix, DVector};
+use crate::network::Scalar;
+use thiserror::Error;
+#[cfg(feature = "gpu")]
+use crate::wgpu_backend::WgpuBackend;
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error("unknown compute backend `{0}` (expected one of: {})", backend_names())]
+    UnknownComputeBackend(String)
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::UnknownComputeBackend(_) => "E0403"
+        }
+    }
+}
+
+/// Tensor math the network is computed with.
+pub trait Backend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// `matrix * vector`
+    fn mat_vec(&self, matrix: &DMatrix<Scalar>, vector: &DVector<Scalar>) -> DVector<Scalar>;
+
+    /// `matrix^T * vector`
+    fn tr_mat_vec(&self, matrix: &DMatrix<Scalar>, vector: &DVector<Scalar>) -> DVector<Scalar>;
+
+    /// `a * b^T`
+    fn outer(&self, a: &DVector<Scalar>, b: &DVector<Scalar>) -> DMatrix<Scalar>;
+
+    /// `y += alpha * x`
+    fn axpy(&self, alpha: Scalar, x: &DVector<Scalar>, y: &mut DVector<Scalar>);
+
+    /// `y += alpha * x` for matrices
+    fn matrix_axpy(&self, alpha: Scalar, x: &DMatrix<Scalar>, y: &mut DMatrix<Scalar>);
+
+    fn scale(&self, alpha: Scalar, x: &DVector<Scalar>) -> DVector<Scalar>;
+
+    fn map(&self, x: &DVector<Scalar>, function: fn(Scalar) -> Scalar) -> DVector<Scalar>;
+
+    fn component_mul(&self, a: &DVector<Scalar>, b: &DVector<Scalar>) -> DVector<Scalar>;
+
+    fn dot(&self, a: &DVector<Scalar>, b: &DVector<Scalar>) -> Scalar;
+}
+
+pub struct NalgebraBackend;
+
+impl Backend for NalgebraBackend {
+    fn name(&self) -> &'static str {
+        "nalgebra"
+    }
+
+    fn mat_vec(&self, matrix: &DMatrix<Scalar>, vector: &DVector<Scalar>) -> DVector<Scalar> {
+        let mut result = DVector::zeros(matrix.nrows());
+        matrix.mul_to(vector, &mut result);
+        result
+    }
+
+    fn tr_mat_vec(&self, matrix: &DMatrix<Scalar>, vector: &DVector<Scalar>) -> DVector<Scalar> {
+        matrix.tr_mul(vector)
+    }
+
+    fn outer(&self, a: &DVector<Scalar>, b: &DVector<Scalar>) -> DMatrix<Scalar> {
+        a * b.transpose()
+    }
+
+    fn axpy(&self, alpha: Scalar, x: &DVector<Scalar>, y: &mut DVector<Scalar>) {
+        y.axpy(alpha, x, 1.0);
+    }
+
+    fn matrix_axpy(&self, alpha: Scalar, x: &DMatrix<Scalar>, y: &mut DMatrix<Scalar>) {
+        y.zip_apply(x, |y, x| *y += alpha * x);
+    }
+
+    fn scale(&self, alpha: Scalar, x: &DVector<Scalar>) -> DVector<Scalar> {
+        x * alpha
+    }
+
+    fn map(&self, x: &DVector<Scalar>, function: fn(Scalar) -> Scalar) -> DVector<Scalar> {
+        x.map(function)
+    }
+
+    fn component_mul(&self, a: &DVector<Scalar>, b: &DVector<Scalar>) -> DVector<Scalar> {
+        a.component_mul(b)
+    }
+
+    fn dot(&self, a: &DVector<Scalar>, b: &DVector<Scalar>) -> Scalar {
+        a.dot(b)
+    }
+}
+
+#[cfg(all(feature = "blas", feature = "f32"))]
+use matrixmultiply::sgemm as gemm;
+#[cfg(all(feature = "blas", not(feature = "f32")))]
+use matrixmultiply::dgemm as gemm;
+
+/// Matrix products computed by the cache-blocked, SIMD kernels of the
+/// `matrixmultiply` crate; element-wise operations are left to nalgebra.
+#[cfg(feature = "blas")]
+pub struct MatrixMultiplyBackend;
+
+#[cfg(feature = "blas")]
+impl Backend for MatrixMultiplyBackend {
+    fn name(&self) -> &'static str {
+        "matrixmultiply"
+    }
+
+    fn mat_vec(&self, matrix: &DMatrix<Scalar>, vector: &DVector<Scalar>) -> DVector<Scalar> {
+        let (rows, columns) = matrix.shape();
+        let mut result = DVector::zeros(rows);
+
+        // nalgebra stores matrices column-major
+        unsafe {
+            gemm(rows, columns, 1,
+                 1.0,
+                 matrix.as_ptr(), 1, rows as isize,
+                 vector.as_ptr(), 1, columns as isize,
+                 0.0,
+                 result.as_mut_ptr(), 1, rows as isize);
+        }
+
+        result
+    }
+
+    fn tr_mat_vec(&self, matrix: &DMatrix<Scalar>, vector: &DVector<Scalar>) -> DVector<Scalar> {
+        let (rows, columns) = matrix.shape();
+        let mut result = DVector::zeros(columns);
+
+        unsafe {
+            gemm(columns, rows, 1,
+                 1.0,
+                 matrix.as_ptr(), rows as isize, 1,
+                 vector.as_ptr(), 1, rows as isize,
+                 0.0,
+                 result.as_mut_ptr(), 1, columns as isize);
+        }
+
+        result
+    }
+
+    fn outer(&self, a: &DVector<Scalar>, b: &DVector<Scalar>) -> DMatrix<Scalar> {
+        let (rows, columns) = (a.len(), b.len());
+        let mut result = DMatrix::zeros(rows, columns);
+
+        unsafe {
+            gemm(rows, 1, columns,
+                 1.0,
+                 a.as_ptr(), 1, rows as isize,
+                 b.as_ptr(), columns as isize, 1,
+                 0.0,
+                 result.as_mut_ptr(), 1, rows as isize);
+        }
+
+        result
+    }
+
+    fn axpy(&self, alpha: Scalar, x: &DVector<Scalar>, y: &mut DVector<Scalar>) {
+        NalgebraBackend.axpy(alpha, x, y)
+    }
+
+    fn matrix_axpy(&self, alpha: Scalar, x: &DMatrix<Scalar>, y: &mut DMatrix<Scalar>) {
+        NalgebraBackend.matrix_axpy(alpha, x, y)
+    }
+
+    fn scale(&self, alpha: Scalar, x: &DVector<Scalar>) -> DVector<Scalar> {
+        NalgebraBackend.scale(alpha, x)
+    }
+
+    fn map(&self, x: &DVector<Scalar>, function: fn(Scalar) -> Scalar) -> DVector<Scalar> {
+        NalgebraBackend.map(x, function)
+    }
+
+    fn component_mul(&self, a: &DVector<Scalar>, b: &DVector<Scalar>) -> DVector<Scalar> {
+        NalgebraBackend.component_mul(a, b)
+    }
+
+    fn dot(&self, a: &DVector<Scalar>, b: &DVector<Scalar>) -> Scalar {
+        NalgebraBackend.dot(a, b)
+    }
+}
+
+/// Backends selectable at runtime with `--compute-backend`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum ComputeBackend {
+    #[default]
+    Nalgebra,
+    #[cfg(feature = "blas")]
+    MatrixMultiply,
+    #[cfg(feature = "gpu")]
+    Wgpu
+}
+
+impl ComputeBackend {
+    pub const ALL: &'static [ComputeBackend] = &[
+        ComputeBackend::Nalgebra,
+        #[cfg(feature = "blas")]
+        ComputeBackend::MatrixMultiply,
+        #[cfg(feature = "gpu")]
+        ComputeBackend::Wgpu
+    ];
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            ComputeBackend::Nalgebra => "nalgebra",
+            #[cfg(feature = "blas")]
+            ComputeBackend::MatrixMultiply => "matrixmultiply",
+            #[cfg(feature = "gpu")]
+            ComputeBackend::Wgpu => "wgpu"
+        }
+    }
+
+    /// Implementation of the backend. Falls back to nalgebra when the
+    /// wgpu backend is selected but no GPU is available.
+    pub fn backend(&self) -> &'static dyn Backend {
+        return match self {
+            ComputeBackend::Nalgebra => &NalgebraBackend,
+            #[cfg(feature = "blas")]
+            ComputeBackend::MatrixMultiply => &MatrixMultiplyBackend,
+            #[cfg(feature = "gpu")]
+            ComputeBackend::Wgpu => match WgpuBackend::shared() {
+                Some(backend) => backend,
+                None => {
+                    static WARNING: Once = Once::new();
+                    WARNING.call_once(|| warn!("no GPU available, computing on the CPU with nalgebra"));
+                    &NalgebraBackend
+                }
+            }
+        }
+    }
+}
+
+fn backend_names() -> String {
+    ComputeBackend::ALL
+        .iter()
+        .map(|backend| backend.name())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl FromStr for ComputeBackend {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self> {
+        ComputeBackend::ALL
+            .iter()
+            .copied()
+            .find(|backend| backend.name() == s.to_lowercase())
+            .ok_or_else(|| ErrorKind::UnknownComputeBackend(s.to_string()))
+    }
+}
@@ -0,0 +1,397 @@
+use std::fmt::{Display, Formatter};
+use std::fs::{OpenOptions, read_to_string};
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use rand::RngCore;
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+use crate::backend::ComputeBackend;
+use crate::classes::Alphabet;
+use crate::features::InputFeatures;
+use crate::network;
+use crate::network::{Architecture, DEFAULT_INPUT_SIZE, NeuralNetwork};
+use crate::training::{input_vector, Trainer};
+use crate::training_data::{LabeledTrainingData, load_samples};
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error("cannot read benchmark history {file} ({source})")]
+    CannotReadHistory {
+        file: String,
+        source: io::Error
+    },
+
+    #[error("cannot write benchmark history {file} ({source})")]
+    CannotWriteHistory {
+        file: String,
+        source: io::Error
+    },
+
+    #[error("cannot parse or serialize benchmark history ({0})")]
+    InvalidHistory(#[from] serde_json::Error)
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::CannotReadHistory { .. } => "E0801",
+            ErrorKind::CannotWriteHistory { .. } => "E0802",
+            ErrorKind::InvalidHistory(_) => "E0803"
+        }
+    }
+}
+
+const MNIST_FILES: [&str; 4] = [
+    "train-images-idx3-ubyte",
+    "train-labels-idx1-ubyte",
+    "t10k-images-idx3-ubyte",
+    "t10k-labels-idx1-ubyte"
+];
+
+const EMNIST_LETTERS_FILES: [&str; 4] = [
+    "emnist-letters-train-images-idx3-ubyte",
+    "emnist-letters-train-labels-idx1-ubyte",
+    "emnist-letters-test-images-idx3-ubyte",
+    "emnist-letters-test-labels-idx1-ubyte"
+];
+
+pub struct BenchmarkDataset {
+    name: &'static str,
+    alphabet: Alphabet,
+    train_images: PathBuf,
+    train_labels: PathBuf,
+    test_images: PathBuf,
+    test_labels: PathBuf
+}
+
+impl BenchmarkDataset {
+    fn new(name: &'static str, alphabet: Alphabet, dir: PathBuf, files: [&str; 4]) -> Self {
+        BenchmarkDataset {
+            name,
+            alphabet,
+            train_images: dir.join(files[0]),
+            train_labels: dir.join(files[1]),
+            test_images: dir.join(files[2]),
+            test_labels: dir.join(files[3])
+        }
+    }
+
+    pub fn files(&self) -> [&Path; 4] {
+        [&self.train_images, &self.train_labels, &self.test_images, &self.test_labels]
+    }
+}
+
+/// MNIST, Fashion-MNIST and EMNIST letters, expected in `mnist/`,
+/// `fashion-mnist/` and `emnist/` under `data_dir` with their original file names.
+pub fn standard_suite<P: AsRef<Path>>(data_dir: P) -> Vec<BenchmarkDataset> {
+    let data_dir = data_dir.as_ref();
+
+    vec![
+        BenchmarkDataset::new("mnist", Alphabet::default(),
+                              data_dir.join("mnist"), MNIST_FILES),
+        BenchmarkDataset::new("fashion-mnist", Alphabet::default(),
+                              data_dir.join("fashion-mnist"), MNIST_FILES),
+        BenchmarkDataset::new("emnist-letters", Alphabet::Letters,
+                              data_dir.join("emnist"), EMNIST_LETTERS_FILES)
+    ]
+}
+
+pub enum ModelSource<'a> {
+    Existing(&'a NeuralNetwork),
+    FromScratch {
+        epochs: u32,
+        backend: ComputeBackend
+    }
+}
+
+/// Per-sample inference time over a test set.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LatencyStats {
+    pub throughput: f64,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration
+}
+
+impl LatencyStats {
+    fn from_latencies(mut latencies: Vec<Duration>) -> LatencyStats {
+        latencies.sort();
+        let total: Duration = latencies.iter().sum();
+        // nearest-rank percentile
+        let percentile = |p: f64| {
+            let rank = ((p * latencies.len() as f64).ceil() as usize).max(1);
+            latencies.get(rank - 1).copied().unwrap_or_default()
+        };
+
+        LatencyStats {
+            throughput: (latencies.len() as f64) / total.as_secs_f64().max(f64::MIN_POSITIVE),
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99)
+        }
+    }
+}
+
+pub enum BenchmarkOutcome {
+    Evaluated {
+        test_samples: usize,
+        accuracy: f64,
+        latency: LatencyStats,
+        elapsed: Duration
+    },
+    Skipped(String)
+}
+
+pub struct BenchmarkRow {
+    pub dataset: &'static str,
+    pub outcome: BenchmarkOutcome
+}
+
+pub struct BenchmarkReport {
+    rows: Vec<BenchmarkRow>
+}
+
+impl BenchmarkReport {
+    pub fn rows(&self) -> &[BenchmarkRow] {
+        &self.rows
+    }
+}
+
+impl Display for BenchmarkReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:<16} {:>12} {:>10} {:>10} {:>10} {:>10} {:>10}",
+                 "dataset", "test samples", "accuracy", "samples/s", "p50 ms", "p99 ms", "time")?;
+
+        for row in &self.rows {
+            match &row.outcome {
+                BenchmarkOutcome::Evaluated { test_samples, accuracy, latency, elapsed } =>
+                    writeln!(f, "{:<16} {:>12} {:>9.2}% {:>10.0} {:>10.3} {:>10.3} {:>9.1}s",
+                             row.dataset, test_samples, accuracy*100.0, latency.throughput,
+                             milliseconds(latency.p50), milliseconds(latency.p99), elapsed.as_secs_f64())?,
+                BenchmarkOutcome::Skipped(reason) =>
+                    writeln!(f, "{:<16} {:>12} {:>10} {:>10} {:>10} {:>10} {:>10}  skipped: {}",
+                             row.dataset, "-", "-", "-", "-", "-", "-", reason)?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// `rng` initializes the networks trained from scratch.
+pub fn run(datasets: &[BenchmarkDataset], model: &ModelSource, rng: &mut dyn RngCore) -> BenchmarkReport {
+    let rows = datasets
+        .iter()
+        .map(|dataset| BenchmarkRow {
+            dataset: dataset.name,
+            outcome: benchmark(dataset, model, rng)
+        })
+        .collect();
+
+    BenchmarkReport { rows }
+}
+
+fn benchmark(dataset: &BenchmarkDataset, model: &ModelSource, rng: &mut dyn RngCore) -> BenchmarkOutcome {
+    let class_count = dataset.alphabet.class_count();
+    if let ModelSource::Existing(network) = model {
+        if network.output_size() != class_count {
+            return BenchmarkOutcome::Skipped(
+                format!("model has {} classes, dataset has {}", network.output_size(), class_count));
+        }
+    }
+
+    let test_samples =
+        match load_samples(&dataset.test_images, &dataset.test_labels, dataset.alphabet) {
+            Ok(samples) => samples,
+            Err(err) => return BenchmarkOutcome::Skipped(err.to_string())
+        };
+
+    let started = Instant::now();
+    let evaluation = match model {
+        ModelSource::Existing(network) => evaluate_timed(network, &test_samples),
+        ModelSource::FromScratch { epochs, backend } => {
+            let train_samples =
+                match load_samples(&dataset.train_images, &dataset.train_labels, dataset.alphabet) {
+                    Ok(samples) => samples,
+                    Err(err) => return BenchmarkOutcome::Skipped(err.to_string())
+                };
+
+            let input_size = train_samples
+                .first()
+                .map_or(DEFAULT_INPUT_SIZE, |sample| sample.image().size());
+
+            let mut network = NeuralNetwork::new_untrained_with_rng(rng,
+                                                                    input_size,
+                                                                    class_count,
+                                                                    InputFeatures::default(),
+                                                                    &Architecture::default());
+            network.set_compute_backend(*backend);
+            let mut trainer = Trainer::builder()
+                .with_epochs(*epochs)
+                .build();
+
+            if let Err(err) = trainer.train(&mut network, &train_samples) {
+                return BenchmarkOutcome::Skipped(err.to_string());
+            }
+
+            evaluate_timed(&network, &test_samples)
+        }
+    };
+
+    let (accuracy, latencies) = match evaluation {
+        Ok(evaluation) => evaluation,
+        Err(err) => return BenchmarkOutcome::Skipped(err.to_string())
+    };
+
+    BenchmarkOutcome::Evaluated {
+        test_samples: test_samples.len(),
+        accuracy,
+        latency: LatencyStats::from_latencies(latencies),
+        elapsed: started.elapsed()
+    }
+}
+
+/// Accuracy of `network` on `samples` and the time each prediction took.
+fn evaluate_timed(network: &NeuralNetwork,
+                  samples: &[LabeledTrainingData]) -> network::Result<(f64, Vec<Duration>)> {
+    let mut correct_answers = 0;
+    let mut latencies = Vec::with_capacity(samples.len());
+    for example in samples {
+        let input = input_vector(example.image());
+
+        let started = Instant::now();
+        let output = network.compute(input)?;
+        latencies.push(started.elapsed());
+
+        if output.argmax().0 == example.label().class() as usize {
+            correct_answers += 1;
+        }
+    }
+
+    Ok(((correct_answers as f64) / (samples.len().max(1) as f64), latencies))
+}
+
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// One line of the benchmark history file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BenchmarkRecord {
+    pub timestamp: u64,
+    pub git_hash: Option<String>,
+    pub model_fingerprint: Option<String>,
+    pub results: Vec<DatasetRecord>
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DatasetRecord {
+    pub dataset: String,
+    pub accuracy: f64,
+    pub throughput: f64,
+    pub latency_p50_ms: f64,
+    pub latency_p90_ms: f64,
+    pub latency_p99_ms: f64
+}
+
+impl BenchmarkReport {
+    /// Results of the evaluated datasets, stamped with the current time and
+    /// the git commit checked out in the working directory, if any.
+    pub fn to_record(&self, model_fingerprint: Option<String>) -> BenchmarkRecord {
+        let results = self.rows
+            .iter()
+            .filter_map(|row| match &row.outcome {
+                BenchmarkOutcome::Evaluated { accuracy, latency, .. } => Some(DatasetRecord {
+                    dataset: row.dataset.to_string(),
+                    accuracy: *accuracy,
+                    throughput: latency.throughput,
+                    latency_p50_ms: milliseconds(latency.p50),
+                    latency_p90_ms: milliseconds(latency.p90),
+                    latency_p99_ms: milliseconds(latency.p99)
+                }),
+                BenchmarkOutcome::Skipped(_) => None
+            })
+            .collect();
+
+        BenchmarkRecord {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs()),
+            git_hash: current_git_hash(),
+            model_fingerprint,
+            results
+        }
+    }
+}
+
+fn current_git_hash() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Appends `record` to the JSON Lines history in `file` and returns the
+/// record it previously ended with.
+pub fn append_to_history<P: AsRef<Path>>(file: P, record: &BenchmarkRecord) -> Result<Option<BenchmarkRecord>> {
+    let path = file.as_ref();
+
+    let previous = match read_to_string(path) {
+        Ok(history) => match history.lines().rev().find(|line| !line.trim().is_empty()) {
+            Some(line) => Some(serde_json::from_str(line)?),
+            None => None
+        },
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(source) => return Err(ErrorKind::CannotReadHistory { file: path.display().to_string(), source })
+    };
+
+    let line = serde_json::to_string(record)?;
+    OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .and_then(|mut history| writeln!(history, "{}", line))
+        .map_err(|source| ErrorKind::CannotWriteHistory { file: path.display().to_string(), source })?;
+
+    Ok(previous)
+}
+
+/// Changes of the datasets benchmarked both in `previous` and `current`.
+pub struct RecordComparison<'a> {
+    pub previous: &'a BenchmarkRecord,
+    pub current: &'a BenchmarkRecord
+}
+
+impl Display for RecordComparison<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "compared to {}{}:",
+                 self.previous.git_hash.as_deref().unwrap_or("the previous run"),
+                 if self.previous.model_fingerprint != self.current.model_fingerprint { " (different model)" } else { "" })?;
+
+        for current in &self.current.results {
+            let previous = match self.previous.results.iter().find(|previous| previous.dataset == current.dataset) {
+                Some(previous) => previous,
+                None => continue
+            };
+
+            writeln!(f, "{:<16} accuracy {:+.2}%, throughput {:+.1}%, p50 {:+.1}%, p99 {:+.1}%",
+                     current.dataset,
+                     (current.accuracy - previous.accuracy) * 100.0,
+                     relative_change(previous.throughput, current.throughput),
+                     relative_change(previous.latency_p50_ms, current.latency_p50_ms),
+                     relative_change(previous.latency_p99_ms, current.latency_p99_ms))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn relative_change(previous: f64, current: f64) -> f64 {
+    (current - previous) / previous.max(f64::MIN_POSITIVE) * 100.0
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions, read_to_string, write};
+use std::io;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+use crate::training::{best_model_path, Checkpoint, TrainingHistory};
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error("cannot read {file} ({source})")]
+    CannotReadFile {
+        file: String,
+        source: io::Error
+    },
+
+    #[error("cannot write {file} ({source})")]
+    CannotWriteFile {
+        file: String,
+        source: io::Error
+    },
+
+    #[error("cannot parse bundle ({0})")]
+    CannotParseBundle(#[from] serde_json::Error),
+
+    #[error("bundle does not contain a model")]
+    MissingModel
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::CannotReadFile { .. } => "E0601",
+            ErrorKind::CannotWriteFile { .. } => "E0602",
+            ErrorKind::CannotParseBundle(_) => "E0603",
+            ErrorKind::MissingModel => "E0604"
+        }
+    }
+}
+
+const MODEL: &str = "model";
+const HISTORY: &str = "history";
+const BEST_MODEL: &str = "best";
+const CHECKPOINT: &str = "checkpoint";
+
+/// Files that belong to a model, keyed by their role in the bundle.
+fn model_files<P: AsRef<Path>>(model_file: P) -> [(&'static str, PathBuf); 4] {
+    let model_file = model_file.as_ref();
+
+    [
+        (MODEL, model_file.to_path_buf()),
+        (HISTORY, TrainingHistory::path_for_model(model_file)),
+        (BEST_MODEL, best_model_path(model_file)),
+        (CHECKPOINT, Checkpoint::path_for_model(model_file))
+    ]
+}
+
+/// A model together with its training history, best-so-far model and
+/// checkpoint, stored in one file so the whole state can be moved to
+/// another machine.
+#[derive(Serialize, Deserialize)]
+pub struct Bundle {
+    files: BTreeMap<String, String>
+}
+
+impl Bundle {
+    /// Collects `model_file` and whichever of its companion files exist.
+    pub fn collect<P: AsRef<Path>>(model_file: P) -> Result<Bundle> {
+        let mut files = BTreeMap::new();
+
+        for (role, path) in model_files(model_file) {
+            match read_to_string(&path) {
+                Ok(contents) => {
+                    files.insert(role.to_string(), contents);
+                },
+                Err(err) if role != MODEL && err.kind() == io::ErrorKind::NotFound => {},
+                Err(source) => return Err(ErrorKind::CannotReadFile {
+                    file: path.display().to_string(),
+                    source
+                })
+            }
+        }
+
+        Ok(Bundle { files })
+    }
+
+    pub fn load<P: AsRef<Path>>(file: P) -> Result<Bundle> {
+        let path = file.as_ref();
+        let file = File::open(path)
+            .map_err(|source| ErrorKind::CannotReadFile { file: path.display().to_string(), source })?;
+
+        let bundle: Bundle = serde_json::from_reader(BufReader::new(file))?;
+        if !bundle.files.contains_key(MODEL) {
+            return Err(ErrorKind::MissingModel)
+        }
+
+        Ok(bundle)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, file: P) -> Result<()> {
+        let path = file.as_ref();
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .map_err(|source| ErrorKind::CannotWriteFile { file: path.display().to_string(), source })?;
+
+        serde_json::to_writer(BufWriter::new(file), self)?;
+
+        Ok(())
+    }
+
+    /// Writes the bundled files next to `model_file`, replacing existing ones.
+    /// Returns the paths of the restored files.
+    pub fn restore<P: AsRef<Path>>(&self, model_file: P) -> Result<Vec<PathBuf>> {
+        let mut restored = Vec::new();
+
+        for (role, path) in model_files(model_file) {
+            if let Some(contents) = self.files.get(role) {
+                write(&path, contents)
+                    .map_err(|source| ErrorKind::CannotWriteFile { file: path.display().to_string(), source })?;
+                restored.push(path);
+            }
+        }
+
+        Ok(restored)
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::fs::{File, read_to_string};
+use std::io;
+use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use crate::training_data::is_stdin;
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error("cannot read {file} ({source})")]
+    CannotReadFile {
+        file: String,
+        source: io::Error
+    },
+
+    #[error("line {line} of the checksum manifest is not `<sha256>  <file>`")]
+    InvalidManifestLine {
+        line: usize
+    },
+
+    #[error("{file} is not listed in the checksum manifest")]
+    NotInManifest {
+        file: String
+    },
+
+    #[error("{file} has SHA-256 {actual}, but the manifest expects {expected}; the file may be truncated or corrupted")]
+    ChecksumMismatch {
+        file: String,
+        expected: String,
+        actual: String
+    },
+
+    #[error("the standard input cannot be verified against a checksum manifest")]
+    CannotVerifyStdin
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::CannotReadFile { .. } => "E0501",
+            ErrorKind::InvalidManifestLine { .. } => "E0502",
+            ErrorKind::NotInManifest { .. } => "E0503",
+            ErrorKind::ChecksumMismatch { .. } => "E0504",
+            ErrorKind::CannotVerifyStdin => "E0505"
+        }
+    }
+}
+
+/// Expected SHA-256 digests of dataset files, in the format written by
+/// `sha256sum`. Relative paths are resolved against the manifest's directory.
+pub struct ChecksumManifest {
+    digests: HashMap<PathBuf, String>
+}
+
+impl ChecksumManifest {
+    pub fn load<P: AsRef<Path>>(file: P) -> Result<ChecksumManifest> {
+        let path = file.as_ref();
+        let contents = read_to_string(path)
+            .map_err(|source| ErrorKind::CannotReadFile { file: path.display().to_string(), source })?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        let mut digests = HashMap::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+
+            let (digest, file) = line
+                .split_once(char::is_whitespace)
+                .filter(|(digest, _)| digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()))
+                .ok_or(ErrorKind::InvalidManifestLine { line: index + 1 })?;
+
+            // `sha256sum` marks files hashed in binary mode with a leading `*`
+            let file = file.trim_start();
+            let file = file.strip_prefix('*').unwrap_or(file);
+
+            digests.insert(normalize(&base_dir.join(file)), digest.to_lowercase());
+        }
+
+        Ok(ChecksumManifest { digests })
+    }
+
+    /// Hashes `file` and fails unless the digest matches its manifest entry.
+    pub fn verify<P: AsRef<Path>>(&self, file: P) -> Result<()> {
+        let path = file.as_ref();
+        if is_stdin(path) {
+            return Err(ErrorKind::CannotVerifyStdin)
+        }
+
+        let expected = self.digests
+            .get(&normalize(path))
+            .ok_or_else(|| ErrorKind::NotInManifest { file: path.display().to_string() })?;
+
+        let actual = sha256(path)?;
+        if &actual != expected {
+            return Err(ErrorKind::ChecksumMismatch {
+                file: path.display().to_string(),
+                expected: expected.clone(),
+                actual
+            })
+        }
+
+        Ok(())
+    }
+}
+
+pub fn sha256<P: AsRef<Path>>(file: P) -> Result<String> {
+    let path = file.as_ref();
+    let cannot_read = |source| ErrorKind::CannotReadFile { file: path.display().to_string(), source };
+
+    let mut file = File::open(path).map_err(cannot_read)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).map_err(cannot_read)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
@@ -0,0 +1,178 @@
+use std::str::FromStr;
+use thiserror::Error;
+use crate::training_data::{LabeledTrainingData, Label};
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error("unknown digit script `{0}` (expected one of: latin, arabic-indic, eastern-arabic-indic, devanagari, bengali, thai)")]
+    UnknownDigitScript(String),
+
+    #[error("unknown alphabet `{0}` (expected one of: digits, letters, emnist-byclass, emnist-bymerge, emnist-balanced, \
+             emnist-digits, emnist-mnist)")]
+    UnknownAlphabet(String)
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::UnknownDigitScript(_) => "E0401",
+            ErrorKind::UnknownAlphabet(_) => "E0402"
+        }
+    }
+}
+
+const DIGIT_COUNT: u32 = 10;
+const LETTER_COUNT: u32 = 26;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DigitScript {
+    Latin,
+    ArabicIndic,
+    EasternArabicIndic,
+    Devanagari,
+    Bengali,
+    Thai
+}
+
+impl DigitScript {
+    const fn zero(&self) -> char {
+        match self {
+            DigitScript::Latin => '\u{0030}',
+            DigitScript::ArabicIndic => '\u{0660}',
+            DigitScript::EasternArabicIndic => '\u{06F0}',
+            DigitScript::Devanagari => '\u{0966}',
+            DigitScript::Bengali => '\u{09E6}',
+            DigitScript::Thai => '\u{0E50}'
+        }
+    }
+
+    /// Glyphs of the digits 0-9 in this script, indexed by class.
+    pub fn class_names(&self) -> Vec<String> {
+        (0..DIGIT_COUNT)
+            .filter_map(|digit| char::from_u32(self.zero() as u32 + digit))
+            .map(String::from)
+            .collect()
+    }
+}
+
+impl FromStr for DigitScript {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self> {
+        return match s.to_lowercase().as_str() {
+            "latin" => Ok(DigitScript::Latin),
+            "arabic-indic" => Ok(DigitScript::ArabicIndic),
+            "eastern-arabic-indic" | "persian" => Ok(DigitScript::EasternArabicIndic),
+            "devanagari" => Ok(DigitScript::Devanagari),
+            "bengali" => Ok(DigitScript::Bengali),
+            "thai" => Ok(DigitScript::Thai),
+            _ => Err(ErrorKind::UnknownDigitScript(s.to_string()))
+        }
+    }
+}
+
+/// Splits of EMNIST besides its letters, which are [`Alphabet::Letters`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EmnistSplit {
+    /// Digits, upper and lower case letters: 62 classes.
+    ByClass,
+    /// Like [`EmnistSplit::ByClass`], with the 15 lower case letters that look
+    /// like their upper case merged into them: 47 classes.
+    ByMerge,
+    /// The classes of [`EmnistSplit::ByMerge`] with as many samples each.
+    Balanced,
+    /// Digits only, more of them than in MNIST.
+    Digits,
+    /// Digits, as many as in MNIST.
+    Mnist
+}
+
+/// Lower case letters kept apart from their upper case in the merged splits.
+const UNMERGED_LETTERS: [char; 11] = ['a', 'b', 'd', 'e', 'f', 'g', 'h', 'n', 'q', 'r', 't'];
+
+impl EmnistSplit {
+    /// Class names indexed by the EMNIST label.
+    pub fn class_names(&self) -> Vec<String> {
+        let digits = DigitScript::Latin.class_names();
+        let upper_case = ('A'..='Z').map(String::from);
+
+        return match self {
+            EmnistSplit::ByClass =>
+                digits.into_iter().chain(upper_case).chain(('a'..='z').map(String::from)).collect(),
+            EmnistSplit::ByMerge | EmnistSplit::Balanced =>
+                digits.into_iter().chain(upper_case).chain(UNMERGED_LETTERS.iter().map(|c| c.to_string())).collect(),
+            EmnistSplit::Digits | EmnistSplit::Mnist => digits
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Alphabet {
+    Digits(DigitScript),
+    Letters,
+    Emnist(EmnistSplit),
+    Custom(usize)
+}
+
+impl Alphabet {
+    pub fn class_count(&self) -> usize {
+        return match self {
+            Alphabet::Digits(_) => DIGIT_COUNT as usize,
+            Alphabet::Letters => LETTER_COUNT as usize,
+            Alphabet::Emnist(split) => split.class_names().len(),
+            Alphabet::Custom(class_count) => *class_count
+        }
+    }
+
+    pub fn class_names(&self) -> Vec<String> {
+        return match self {
+            Alphabet::Digits(script) => script.class_names(),
+            Alphabet::Letters => ('A'..='Z').map(String::from).collect(),
+            Alphabet::Emnist(split) => split.class_names(),
+            Alphabet::Custom(class_count) => (0..*class_count).map(|class| class.to_string()).collect()
+        }
+    }
+
+    /// Brings a dataset sample into the layout the network is trained on.
+    ///
+    /// EMNIST images are stored transposed, so they are flipped upright
+    /// (matching what the GUI canvas produces). EMNIST letters are labeled
+    /// 1-26, so their labels are also shifted to start from 0.
+    pub fn normalize_sample(&self, sample: LabeledTrainingData) -> LabeledTrainingData {
+        let label_offset = match self {
+            Alphabet::Digits(_) | Alphabet::Custom(_) => return sample,
+            Alphabet::Letters => 1,
+            Alphabet::Emnist(_) => 0
+        };
+
+        let weight = sample.weight();
+        let (image, label) = sample.into_parts();
+        LabeledTrainingData::new(image.transposed(), Label::new(label.class().saturating_sub(label_offset)))
+            .with_weight(weight)
+    }
+}
+
+impl Default for Alphabet {
+    fn default() -> Self {
+        Alphabet::Digits(DigitScript::Latin)
+    }
+}
+
+impl FromStr for Alphabet {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self> {
+        return match s.to_lowercase().as_str() {
+            "digits" => Ok(Alphabet::default()),
+            "letters" | "emnist-letters" => Ok(Alphabet::Letters),
+            "emnist-byclass" => Ok(Alphabet::Emnist(EmnistSplit::ByClass)),
+            "emnist-bymerge" => Ok(Alphabet::Emnist(EmnistSplit::ByMerge)),
+            "emnist-balanced" => Ok(Alphabet::Emnist(EmnistSplit::Balanced)),
+            "emnist-digits" => Ok(Alphabet::Emnist(EmnistSplit::Digits)),
+            "emnist-mnist" => Ok(Alphabet::Emnist(EmnistSplit::Mnist)),
+            _ => Err(ErrorKind::UnknownAlphabet(s.to_string()))
+        }
+    }
+}
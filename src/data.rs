@@ -1,6 +1,9 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use serde::{Serialize, Deserialize};
 
-
-#[derive(Copy, Clone, Debug, PartialEq, Default)]
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Default)]
 pub struct ImageSize {
     pub width: u32,
     pub height: u32
@@ -19,7 +22,7 @@ impl ImageSize {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Image {
     size: ImageSize,
     pixels: Vec<u8>
@@ -36,6 +39,103 @@ impl Image {
     pub fn pixels(&self) -> &[u8] {
         &self.pixels
     }
+
+    pub fn pixels_mut(&mut self) -> &mut [u8] {
+        &mut self.pixels
+    }
+
+    pub fn size(&self) -> ImageSize {
+        self.size
+    }
+
+    /// Swaps rows and columns, e.g. to undo the column-major storage of EMNIST images.
+    pub fn transposed(&self) -> Image {
+        let (width, height) = (self.size.width as usize, self.size.height as usize);
+        let mut pixels = vec![0; self.pixels.len()];
+
+        for row in 0..height {
+            for col in 0..width {
+                pixels[col*height + row] = self.pixels[row*width + col];
+            }
+        }
+
+        Image::builder()
+            .with_size(ImageSize { width: self.size.height, height: self.size.width })
+            .with_pixels_row_major(pixels)
+            .build()
+    }
+
+    /// Scaled to `target`, averaging the pixels under each target pixel along
+    /// axes that shrink and interpolating bilinearly along axes that grow.
+    pub fn resize(&self, target: ImageSize) -> Image {
+        if target == self.size {
+            return self.clone()
+        }
+        if self.pixels.is_empty() {
+            return Image { size: target, pixels: vec![0; target.area()] }
+        }
+
+        let (width, height) = (self.size.width as usize, self.size.height as usize);
+        let (target_width, target_height) = (target.width as usize, target.height as usize);
+        let values: Vec<f64> = self.pixels.iter().map(|pixel| *pixel as f64).collect();
+
+        // the rows are resized first, then the columns of the result
+        let mut rows = Vec::with_capacity(target_width * height);
+        for row in values.chunks(width) {
+            rows.extend(resample(row, target_width));
+        }
+
+        let mut pixels = vec![0; target.area()];
+        for col in 0..target_width {
+            let column: Vec<f64> = (0..height).map(|row| rows[row * target_width + col]).collect();
+            for (row, value) in resample(&column, target_height).into_iter().enumerate() {
+                pixels[row * target_width + col] = value.round().clamp(0.0, 255.0) as u8;
+            }
+        }
+
+        Image::builder()
+            .with_size(target)
+            .with_pixels_row_major(pixels)
+            .build()
+    }
+
+    /// Writes the image as an 8-bit grayscale PNG.
+    pub fn save_png<P: AsRef<Path>>(&self, file: P) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(file)?), self.size.width, self.size.height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        Ok(writer.finish()?)
+    }
+}
+
+/// `values`, of which there is at least one, stretched or shrunk to `len` values.
+fn resample(values: &[f64], len: usize) -> Vec<f64> {
+    let source_len = values.len();
+    let ratio = source_len as f64 / len as f64;
+    return if len <= source_len {
+        // each target value covers `ratio` source values, some of them in part
+        (0..len)
+            .map(|i| {
+                let (start, end) = (i as f64 * ratio, (i + 1) as f64 * ratio);
+                let covered = start.floor() as usize..(end.ceil() as usize).min(source_len);
+                covered
+                    .map(|j| (end.min(j as f64 + 1.0) - start.max(j as f64)) * values[j])
+                    .sum::<f64>() / ratio
+            })
+            .collect()
+    } else {
+        (0..len)
+            .map(|i| {
+                let position = ((i as f64 + 0.5) * ratio - 0.5).clamp(0.0, (source_len - 1) as f64);
+                let (left, fraction) = (position.floor() as usize, position.fract());
+                let right = (left + 1).min(source_len - 1);
+                values[left] * (1.0 - fraction) + values[right] * fraction
+            })
+            .collect()
+    }
 }
 
 pub struct ImageBuilder {
@@ -0,0 +1,7 @@
+//! Building, checking and saving datasets, and pictures of them, as opposed
+//! to reading them, which is done by [`crate::training_data`].
+
+pub mod contact_sheet;
+pub mod duplicates;
+pub mod synthetic;
+pub mod write;
@@ -0,0 +1,82 @@
+use crate::data::{Image, ImageSize};
+use crate::training_data::LabeledTrainingData;
+
+/// Brightness of the lines between the images and behind the labels, apart
+/// from both the black background and the white strokes of the images.
+const GRID_BRIGHTNESS: u8 = 96;
+
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+
+/// 3x5 bitmaps of the digits, row by row from the most significant bit.
+const DIGIT_GLYPHS: [u16; 10] = [
+    0b111_101_101_101_111,
+    0b010_110_010_010_111,
+    0b111_001_111_100_111,
+    0b111_001_111_001_111,
+    0b101_101_111_001_001,
+    0b111_100_111_001_111,
+    0b111_100_111_101_111,
+    0b111_001_001_001_001,
+    0b111_101_111_101_111,
+    0b111_101_111_001_111
+];
+
+/// The images of `samples` in rows of `columns`, separated by gray lines and
+/// labeled with their class number in the top left corner, to look over a
+/// dataset or an augmentation at a glance. Images are scaled to the size of
+/// the first one.
+pub fn contact_sheet(samples: &[LabeledTrainingData], columns: u32) -> Image {
+    let cell = samples.first().map_or(ImageSize::default(), |sample| sample.image().size());
+    let columns = columns.min(samples.len() as u32).max(1);
+    let rows = (samples.len() as u32).div_ceil(columns);
+    let size = ImageSize {
+        width: columns * (cell.width + 1) + 1,
+        height: rows * (cell.height + 1) + 1
+    };
+
+    let mut pixels = vec![GRID_BRIGHTNESS; size.area()];
+    for (i, sample) in samples.iter().enumerate() {
+        let (left, top) = (1 + (i as u32 % columns) * (cell.width + 1), 1 + (i as u32 / columns) * (cell.height + 1));
+        let image = sample.image().resize(cell);
+        for (y, row) in image.pixels().chunks(cell.width as usize).enumerate() {
+            let start = ((top + y as u32) * size.width + left) as usize;
+            pixels[start..start + row.len()].copy_from_slice(row);
+        }
+
+        draw_label(&mut pixels, size.width, (left, top), cell, sample.label().class());
+    }
+
+    Image::builder()
+        .with_size(size)
+        .with_pixels_row_major(pixels)
+        .build()
+}
+
+/// Draws `class` in white on a gray box at `corner` of a cell, cut off at the
+/// edges of cells too small for it.
+fn draw_label(pixels: &mut [u8], width: u32, corner: (u32, u32), cell: ImageSize, class: u8) {
+    let digits: Vec<u16> = class
+        .to_string()
+        .bytes()
+        .map(|digit| DIGIT_GLYPHS[(digit - b'0') as usize])
+        .collect();
+
+    let box_width = (digits.len() as u32 * (GLYPH_WIDTH + 1) + 1).min(cell.width);
+    let box_height = (GLYPH_HEIGHT + 2).min(cell.height);
+    for y in 0..box_height {
+        for x in 0..box_width {
+            // one pixel of the box on every side of the digits
+            let glyph = (x > 0 && y > 0).then(|| ((x - 1) / (GLYPH_WIDTH + 1), (x - 1) % (GLYPH_WIDTH + 1), y - 1));
+            let lit = match glyph {
+                Some((digit, column, row)) if column < GLYPH_WIDTH && row < GLYPH_HEIGHT => {
+                    let bit = GLYPH_WIDTH * GLYPH_HEIGHT - 1 - (row * GLYPH_WIDTH + column);
+                    digits[digit as usize] >> bit & 1 == 1
+                },
+                _ => false
+            };
+
+            pixels[((corner.1 + y) * width + corner.0 + x) as usize] = if lit { u8::MAX } else { GRID_BRIGHTNESS };
+        }
+    }
+}
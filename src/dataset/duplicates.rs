@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use rayon::prelude::*;
+use crate::data::{Image, ImageSize};
+use crate::training_data::LabeledTrainingData;
+
+/// Side of the thumbnail a perceptual hash is computed from, one bit per pixel.
+const HASH_SIDE: u32 = 8;
+
+/// Hash of the pixels and the size of an image, equal only for identical images.
+pub fn exact_hash(image: &Image) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (image.size().width, image.size().height, image.pixels()).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Average hash: a bit per pixel of an 8x8 thumbnail, set where the
+/// thumbnail is brighter than its mean. Images that differ by slight noise,
+/// blur or rescaling get hashes that differ in few bits.
+pub fn perceptual_hash(image: &Image) -> u64 {
+    let thumbnail = image.resize(ImageSize::square(HASH_SIDE));
+    let mean = thumbnail.pixels().iter().map(|pixel| *pixel as u32).sum::<u32>() / (HASH_SIDE * HASH_SIDE);
+    thumbnail
+        .pixels()
+        .iter()
+        .fold(0, |hash, pixel| hash << 1 | (*pixel as u32 > mean) as u64)
+}
+
+/// Two samples with the same or nearly the same image: `first` and `second`
+/// are their positions, in the same dataset or in the first and the second
+/// of two.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Duplicate {
+    pub first: usize,
+    pub second: usize,
+    /// Bits in which the perceptual hashes differ.
+    pub distance: u32,
+    /// Whether the images are identical, not just alike.
+    pub exact: bool
+}
+
+struct Hashes {
+    exact: u64,
+    perceptual: u64
+}
+
+fn hashes(samples: &[LabeledTrainingData]) -> Vec<Hashes> {
+    samples
+        .par_iter()
+        .map(|sample| Hashes { exact: exact_hash(sample.image()), perceptual: perceptual_hash(sample.image()) })
+        .collect()
+}
+
+/// Pairs of samples in `samples` whose perceptual hashes differ in at most
+/// `max_distance` bits, each pair once with the earlier sample first.
+pub fn find_duplicates(samples: &[LabeledTrainingData], max_distance: u32) -> Vec<Duplicate> {
+    let hashes = hashes(samples);
+    return if max_distance == 0 {
+        // only equal hashes match, which are found without comparing every pair
+        let mut groups: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, hash) in hashes.iter().enumerate() {
+            groups.entry(hash.perceptual).or_default().push(i);
+        }
+
+        let mut duplicates: Vec<Duplicate> = groups
+            .values()
+            .flat_map(|group| group
+                .iter()
+                .enumerate()
+                .flat_map(move |(k, &first)| group[k + 1..].iter().map(move |&second| (first, second))))
+            .map(|(first, second)| duplicate(&hashes, &hashes, first, second))
+            .collect();
+        duplicates.sort_by_key(|duplicate| (duplicate.first, duplicate.second));
+        duplicates
+    } else {
+        (0..hashes.len())
+            .into_par_iter()
+            .flat_map_iter(|first| (first + 1..hashes.len()).map(move |second| (first, second)))
+            .map(|(first, second)| duplicate(&hashes, &hashes, first, second))
+            .filter(|duplicate| duplicate.distance <= max_distance)
+            .collect()
+    }
+}
+
+/// Pairs of a sample of `first` and one of `second`, e.g. of a training and a
+/// test set, whose perceptual hashes differ in at most `max_distance` bits.
+pub fn find_duplicates_between(first: &[LabeledTrainingData],
+                               second: &[LabeledTrainingData],
+                               max_distance: u32) -> Vec<Duplicate> {
+    let (first_hashes, second_hashes) = (hashes(first), hashes(second));
+    (0..first_hashes.len())
+        .into_par_iter()
+        .flat_map_iter(|i| (0..second_hashes.len()).map(move |j| (i, j)))
+        .map(|(i, j)| duplicate(&first_hashes, &second_hashes, i, j))
+        .filter(|duplicate| duplicate.distance <= max_distance)
+        .collect()
+}
+
+fn duplicate(first_hashes: &[Hashes], second_hashes: &[Hashes], first: usize, second: usize) -> Duplicate {
+    let (a, b) = (&first_hashes[first], &second_hashes[second]);
+    Duplicate {
+        first,
+        second,
+        distance: (a.perceptual ^ b.perceptual).count_ones(),
+        exact: a.exact == b.exact
+    }
+}
@@ -0,0 +1,200 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use ab_glyph::{Font, FontVec, point};
+use log::warn;
+use rand::Rng;
+use thiserror::Error;
+use crate::augmentation::rotate_and_shift;
+use crate::data::{Image, ImageSize};
+use crate::training_data::{Label, LabeledTrainingData};
+use crate::transform::{Recenter, Transform};
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error("cannot read font {file} ({source})")]
+    CannotReadFont {
+        file: String,
+        source: io::Error
+    },
+
+    #[error("no TrueType or OpenType fonts with digits found in {dirs}")]
+    NoFonts {
+        dirs: String
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::CannotReadFont { .. } => "E1801",
+            ErrorKind::NoFonts { .. } => "E1802"
+        }
+    }
+}
+
+/// Where the fonts of the system are installed on Linux, macOS and Windows.
+pub const SYSTEM_FONT_DIRS: &[&str] = &[
+    "/usr/share/fonts",
+    "/usr/local/share/fonts",
+    "/Library/Fonts",
+    "/System/Library/Fonts",
+    "C:\\Windows\\Fonts"
+];
+
+/// Size of the glyphs that outlines are measured at to find the size that
+/// draws a digit as tall as asked.
+const REFERENCE_SCALE: f32 = 100.0;
+
+/// Loads the `.ttf` and `.otf` fonts in `dirs` and their subdirectories that
+/// have all ten digits; directories that don't exist are passed over, and
+/// files that aren't fonts are left out with a warning.
+pub fn load_fonts<P: AsRef<Path>>(dirs: &[P]) -> Result<Vec<FontVec>> {
+    let mut files = Vec::new();
+    for dir in dirs {
+        font_files(dir.as_ref(), &mut files);
+    }
+    files.sort();
+
+    let mut fonts = Vec::new();
+    for file in files {
+        let data = fs::read(&file)
+            .map_err(|source| ErrorKind::CannotReadFont { file: file.display().to_string(), source })?;
+        match FontVec::try_from_vec(data) {
+            Ok(font) if ('0'..='9').all(|digit| font.glyph_id(digit).0 != 0) => fonts.push(font),
+            Ok(_) => {},
+            Err(err) => warn!("left out {} ({})", file.display(), err)
+        }
+    }
+
+    return if fonts.is_empty() {
+        let dirs: Vec<String> = dirs.iter().map(|dir| dir.as_ref().display().to_string()).collect();
+        Err(ErrorKind::NoFonts { dirs: dirs.join(", ") })
+    } else {
+        Ok(fonts)
+    }
+}
+
+fn font_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return
+    };
+
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        let extension = path.extension().and_then(|extension| extension.to_str()).map(str::to_lowercase);
+        if path.is_dir() {
+            font_files(&path, files);
+        } else if matches!(extension.as_deref(), Some("ttf" | "otf")) {
+            files.push(path);
+        }
+    }
+}
+
+/// Digits drawn from fonts as a stand-in for MNIST: white on black, in a
+/// random font, as tall as `min_height` to `max_height` pixels, with strokes
+/// thickened or thinned by up to `max_thickness` pixels, rotated by up to
+/// `max_rotation` degrees either way and centered by their center of mass.
+/// The defaults are those of MNIST, whose digits fit in a 20x20 box.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SyntheticDigits {
+    pub size: ImageSize,
+    pub min_height: f64,
+    pub max_height: f64,
+    pub max_thickness: u32,
+    pub max_rotation: f64
+}
+
+impl Default for SyntheticDigits {
+    fn default() -> Self {
+        SyntheticDigits {
+            size: ImageSize::square(28),
+            min_height: 14.0,
+            max_height: 20.0,
+            max_thickness: 1,
+            max_rotation: 10.0
+        }
+    }
+}
+
+impl SyntheticDigits {
+    /// `count` samples with the digits in turn, so each is as frequent.
+    pub fn generate(&self, fonts: &[FontVec], count: usize, rng: &mut impl Rng) -> Vec<LabeledTrainingData> {
+        (0..count)
+            .map(|i| {
+                let digit = (i % 10) as u8;
+                let font = &fonts[rng.gen_range(0..fonts.len())];
+                LabeledTrainingData::new(self.draw(font, digit, rng), Label::new(digit))
+            })
+            .collect()
+    }
+
+    pub fn draw(&self, font: &impl Font, digit: u8, rng: &mut impl Rng) -> Image {
+        let mut image = Image::builder()
+            .with_size(self.size)
+            .with_pixels_row_major(vec![0; self.size.area()])
+            .build();
+
+        let glyph_id = font.glyph_id(char::from(b'0' + digit));
+        let height = rng.gen_range(self.min_height..=self.max_height.max(self.min_height)) as f32;
+        let reference_height = font
+            .outline_glyph(glyph_id.with_scale(REFERENCE_SCALE))
+            .map_or(0.0, |outline| outline.px_bounds().height());
+        if reference_height <= 0.0 {
+            return image
+        }
+
+        let scale = REFERENCE_SCALE * height / reference_height;
+        if let Some(outline) = font.outline_glyph(glyph_id.with_scale_and_position(scale, point(0.0, 0.0))) {
+            let bounds = outline.px_bounds();
+            let left = (self.size.width as f32 - bounds.width()) / 2.0;
+            let top = (self.size.height as f32 - bounds.height()) / 2.0;
+            let (width, rows) = (self.size.width as usize, self.size.height as usize);
+            let pixels = image.pixels_mut();
+            outline.draw(|x, y, coverage| {
+                let (x, y) = ((left + x as f32).round(), (top + y as f32).round());
+                if x >= 0.0 && y >= 0.0 && (x as usize) < width && (y as usize) < rows {
+                    let pixel = &mut pixels[y as usize * width + x as usize];
+                    *pixel = (*pixel).max((coverage.clamp(0.0, 1.0) * 255.0).round() as u8);
+                }
+            });
+        }
+
+        let thickness = rng.gen_range(-(self.max_thickness as i32)..=self.max_thickness as i32);
+        for _ in 0..thickness.unsigned_abs() {
+            image = morph(&image, thickness > 0);
+        }
+
+        let angle = rng.gen_range(-self.max_rotation.abs()..=self.max_rotation.abs());
+        image = rotate_and_shift(&image, angle.to_radians(), (0.0, 0.0));
+        Recenter.apply(&mut image, rng);
+        image
+    }
+}
+
+/// Dilation, which thickens white strokes by a pixel, or erosion, which
+/// thins them, with a 3x3 square.
+fn morph(image: &Image, dilate: bool) -> Image {
+    let size = image.size();
+    let (width, height) = (size.width as i64, size.height as i64);
+    let pixels: Vec<u8> = (0..width * height)
+        .map(|i| {
+            let neighbours = (-1..=1)
+                .flat_map(|dy| (-1..=1).map(move |dx| (i % width + dx, i / width + dy)))
+                .filter(|(x, y)| (0..width).contains(x) && (0..height).contains(y))
+                .map(|(x, y)| image.pixels()[(y * width + x) as usize]);
+            return if dilate {
+                neighbours.max().unwrap_or(0)
+            } else {
+                neighbours.min().unwrap_or(0)
+            }
+        })
+        .collect();
+
+    Image::builder()
+        .with_size(size)
+        .with_pixels_row_major(pixels)
+        .build()
+}
@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Cursor, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use thiserror::Error;
+use crate::data::ImageSize;
+use crate::training_data::{self, IMAGES_MAGIC, LABELS_MAGIC, LabeledTrainingData, TrainingDataset};
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error("cannot write dataset ({0})")]
+    CannotWriteDataset(#[from] io::Error),
+
+    #[error("sample {position} is {found_width}x{found_height}, but the dataset's images are {width}x{height}")]
+    ImageSizeMismatch {
+        position: u32,
+        width: u32,
+        height: u32,
+        found_width: u32,
+        found_height: u32
+    },
+
+    #[error("an IDX dataset cannot hold more than {} samples", u32::MAX)]
+    TooManySamples,
+
+    #[error("{file} is {found} bytes long, but its header counts {count} samples, which take {expected}")]
+    IncompleteDataset {
+        file: String,
+        count: u32,
+        expected: u64,
+        found: u64
+    },
+
+    #[error(transparent)]
+    CannotReadDataset(#[from] training_data::ErrorKind)
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::CannotWriteDataset(_) => "E1701",
+            ErrorKind::ImageSizeMismatch { .. } => "E1702",
+            ErrorKind::TooManySamples => "E1703",
+            ErrorKind::IncompleteDataset { .. } => "E1704",
+            ErrorKind::CannotReadDataset(err) => err.code()
+        }
+    }
+}
+
+/// Offset of the item count in both IDX headers, after the magic number.
+const COUNT_OFFSET: u64 = 4;
+
+/// Lengths of the headers [`IdxWriter`] writes.
+const IMAGES_HEADER_LEN: u64 = 16;
+const LABELS_HEADER_LEN: u64 = 8;
+
+/// Writes samples into a pair of IDX files that
+/// [`TrainingDataset`](crate::training_data::TrainingDataset) reads, e.g. to
+/// save an augmented or collected dataset. All images must have the size of
+/// the first one. The item counts in the headers are filled in by
+/// [`IdxWriter::finish`], without which the files are left incomplete.
+pub struct IdxWriter<I: Write + Seek, L: Write + Seek> {
+    images: I,
+    labels: L,
+    size: Option<ImageSize>,
+    count: u32
+}
+
+impl IdxWriter<BufWriter<File>, BufWriter<File>> {
+    /// Creates the image and label files, replacing existing ones.
+    pub fn create<P: AsRef<Path>>(images: P, labels: P) -> Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(images)?), BufWriter::new(File::create(labels)?)))
+    }
+}
+
+impl IdxWriter<File, File> {
+    /// Opens the image and label files to add samples after the ones in
+    /// them, creating them if they don't exist yet. The headers of existing
+    /// files are checked first, so that samples are only added to an IDX
+    /// pair of the same number of samples, with images of the same size as
+    /// the ones that are added.
+    pub fn append<P: AsRef<Path>>(images: P, labels: P) -> Result<Self> {
+        let open = |path: &Path| OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path);
+        let (mut images_file, mut labels_file) = (open(images.as_ref())?, open(labels.as_ref())?);
+        if images_file.metadata()?.len() == 0 && labels_file.metadata()?.len() == 0 {
+            return Ok(Self::new(images_file, labels_file))
+        }
+
+        let dataset = TrainingDataset::from_readers(&mut images_file, &mut labels_file)?;
+        let (count, size) = (dataset.size(), dataset.image_size());
+        for (path, file, header_len, record_len) in [(images.as_ref(), &images_file, IMAGES_HEADER_LEN, size.area() as u64),
+                                                     (labels.as_ref(), &labels_file, LABELS_HEADER_LEN, 1)] {
+            let (expected, found) = (header_len + count as u64 * record_len, file.metadata()?.len());
+            if found != expected {
+                return Err(ErrorKind::IncompleteDataset { file: path.display().to_string(), count, expected, found })
+            }
+        }
+
+        images_file.seek(SeekFrom::End(0))?;
+        labels_file.seek(SeekFrom::End(0))?;
+        Ok(Self {
+            images: images_file,
+            labels: labels_file,
+            size: Some(size),
+            count
+        })
+    }
+}
+
+impl<I: Write + Seek, L: Write + Seek> IdxWriter<I, L> {
+    pub fn new(images: I, labels: L) -> Self {
+        Self {
+            images,
+            labels,
+            size: None,
+            count: 0
+        }
+    }
+
+    pub fn write(&mut self, sample: &LabeledTrainingData) -> Result<()> {
+        let found = sample.image().size();
+        let size = match self.size {
+            Some(size) => size,
+            None => {
+                self.write_headers(found)?;
+                self.size = Some(found);
+                found
+            }
+        };
+
+        if found != size {
+            return Err(ErrorKind::ImageSizeMismatch {
+                position: self.count,
+                width: size.width,
+                height: size.height,
+                found_width: found.width,
+                found_height: found.height
+            })
+        }
+
+        self.count = self.count.checked_add(1).ok_or(ErrorKind::TooManySamples)?;
+        self.images.write_all(sample.image().pixels())?;
+        self.labels.write_all(&[sample.label().class()])?;
+
+        Ok(())
+    }
+
+    pub fn write_all<'a>(&mut self, samples: impl IntoIterator<Item = &'a LabeledTrainingData>) -> Result<()> {
+        for sample in samples {
+            self.write(sample)?;
+        }
+
+        Ok(())
+    }
+
+    /// Fills in the item counts and flushes both files, returning the
+    /// number of samples written and the writers.
+    pub fn finish(mut self) -> Result<(u32, I, L)> {
+        if self.size.is_none() {
+            self.write_headers(ImageSize::default())?;
+        }
+
+        for output in [&mut self.images as &mut dyn WriteSeek, &mut self.labels] {
+            output.seek(SeekFrom::Start(COUNT_OFFSET))?;
+            output.write_all(&self.count.to_be_bytes())?;
+            output.seek(SeekFrom::End(0))?;
+            output.flush()?;
+        }
+
+        Ok((self.count, self.images, self.labels))
+    }
+
+    fn write_headers(&mut self, size: ImageSize) -> Result<()> {
+        self.images.write_all(&IMAGES_MAGIC.to_be_bytes())?;
+        self.images.write_all(&0u32.to_be_bytes())?;
+        self.images.write_all(&size.height.to_be_bytes())?;
+        self.images.write_all(&size.width.to_be_bytes())?;
+
+        self.labels.write_all(&LABELS_MAGIC.to_be_bytes())?;
+        self.labels.write_all(&0u32.to_be_bytes())?;
+
+        Ok(())
+    }
+}
+
+/// Writes `samples` into gzipped IDX image and label files, the way MNIST
+/// is distributed, and returns their number. The files are put together in
+/// memory first, since the item counts cannot be filled in afterwards in a
+/// gzip stream.
+pub fn write_gzipped<P: AsRef<Path>>(images: P, labels: P, samples: &[LabeledTrainingData]) -> Result<u32> {
+    let mut writer = IdxWriter::new(Cursor::new(Vec::new()), Cursor::new(Vec::new()));
+    writer.write_all(samples)?;
+    let (count, images_data, labels_data) = writer.finish()?;
+
+    for (path, data) in [(images, images_data), (labels, labels_data)] {
+        let mut output = GzEncoder::new(BufWriter::new(File::create(path)?), Compression::default());
+        output.write_all(data.get_ref())?;
+        output.finish()?.flush()?;
+    }
+
+    Ok(count)
+}
+
+trait WriteSeek: Write + Seek {}
+
+impl<T: Write + Seek> WriteSeek for T {}
+
+/// A dataset on disk that samples are added to one at a time, such as
+/// drawings confirmed in the GUI, so it grows into a personal dataset to
+/// fine-tune on. Implemented for the formats
+/// [`stream_samples`](crate::training_data::stream_samples) reads.
+pub trait SampleSink {
+    fn append(&mut self, sample: &LabeledTrainingData) -> Result<()>;
+}
+
+/// The formats samples are written in, told apart by the path of the images
+/// the way they are when read.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Format {
+    Csv,
+    ImageDir,
+    Idx
+}
+
+impl Format {
+    /// Image directories are existing directories or paths ending in a
+    /// separator; any other file but a `.csv` one is IDX, including MNIST's
+    /// extensionless `train-images-idx3-ubyte`.
+    fn of(images: &Path) -> Format {
+        let directory = images.as_os_str().to_string_lossy().ends_with(['/', std::path::MAIN_SEPARATOR]);
+        return match images.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => Format::Csv,
+            _ if directory || images.is_dir() => Format::ImageDir,
+            _ => Format::Idx
+        }
+    }
+}
+
+/// Whether a dataset written to `images` goes into IDX files, the only
+/// format with a separate labels file.
+pub fn has_labels_file<P: AsRef<Path>>(images: P) -> bool {
+    Format::of(images.as_ref()) == Format::Idx
+}
+
+/// The sink for `images` and `labels` in the format they are read in: a CSV
+/// file, an image directory (an existing directory, or a path ending in a
+/// separator) or a pair of IDX files, such as an existing MNIST file.
+pub fn open_sink<P: AsRef<Path>>(images: P, labels: P) -> Box<dyn SampleSink> {
+    let images = images.as_ref().to_path_buf();
+    return match Format::of(&images) {
+        Format::Csv => Box::new(CsvSink { file: images }),
+        Format::ImageDir => Box::new(ImageDirSink { dir: images }),
+        Format::Idx => Box::new(IdxSink { images, labels: labels.as_ref().to_path_buf() })
+    }
+}
+
+/// Writes `samples` into a new dataset at `images` and `labels`, in the
+/// format [`open_sink`] picks for them, and returns their number. IDX files
+/// ending in `.gz` are gzipped like MNIST. Unlike a sink, which opens the
+/// dataset for every sample, the output is written in one go, and existing
+/// files are replaced rather than added to.
+pub fn convert<P, I>(samples: I, images: P, labels: P) -> Result<usize>
+    where P: AsRef<Path>,
+          I: IntoIterator<Item = training_data::Result<LabeledTrainingData>> {
+    let (images, labels) = (images.as_ref(), labels.as_ref());
+    let gzipped = images.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("gz"));
+    let mut samples = samples.into_iter();
+
+    return match Format::of(images) {
+        Format::Idx if gzipped => {
+            let samples = samples.collect::<training_data::Result<Vec<_>>>()?;
+            Ok(write_gzipped(images, labels, &samples)? as usize)
+        },
+        Format::Idx => {
+            let mut writer = IdxWriter::create(images, labels)?;
+            samples.try_for_each(|sample| writer.write(&sample?))?;
+            Ok(writer.finish()?.0 as usize)
+        },
+        Format::Csv => {
+            let mut output = BufWriter::new(File::create(images)?);
+            let mut count = 0;
+            for sample in samples {
+                output.write_all(csv_row(&sample?).as_bytes())?;
+                count += 1;
+            }
+            output.flush()?;
+            Ok(count)
+        },
+        Format::ImageDir => {
+            let mut counts: HashMap<u8, usize> = HashMap::new();
+            for sample in samples {
+                let sample = sample?;
+                let number = counts.entry(sample.label().class()).or_default();
+                save_numbered(images, &sample, *number)?;
+                *number += 1;
+            }
+            Ok(counts.values().sum())
+        }
+    }
+}
+
+/// Appends to a pair of IDX files, creating them if they don't exist yet.
+pub struct IdxSink {
+    pub images: PathBuf,
+    pub labels: PathBuf
+}
+
+impl SampleSink for IdxSink {
+    fn append(&mut self, sample: &LabeledTrainingData) -> Result<()> {
+        append_sample(&self.images, &self.labels, sample)
+    }
+}
+
+/// Appends `sample` to a pair of IDX files, creating them if they don't
+/// exist yet; see [`IdxWriter::append`].
+pub fn append_sample<P: AsRef<Path>>(images: P, labels: P, sample: &LabeledTrainingData) -> Result<()> {
+    let mut writer = IdxWriter::append(images, labels)?;
+    writer.write(sample)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Appends rows of the label and the pixels to a CSV file.
+pub struct CsvSink {
+    pub file: PathBuf
+}
+
+impl SampleSink for CsvSink {
+    fn append(&mut self, sample: &LabeledTrainingData) -> Result<()> {
+        OpenOptions::new().create(true).append(true).open(&self.file)?.write_all(csv_row(sample).as_bytes())?;
+        Ok(())
+    }
+}
+
+fn csv_row(sample: &LabeledTrainingData) -> String {
+    let mut row = sample.label().class().to_string();
+    for pixel in sample.image().pixels() {
+        row.push(',');
+        row.push_str(&pixel.to_string());
+    }
+    row.push('\n');
+    row
+}
+
+/// Saves every sample as a PNG in the subdirectory of its label, numbered
+/// after the images already there.
+pub struct ImageDirSink {
+    pub dir: PathBuf
+}
+
+impl SampleSink for ImageDirSink {
+    fn append(&mut self, sample: &LabeledTrainingData) -> Result<()> {
+        let label_dir = self.dir.join(sample.label().class().to_string());
+        fs::create_dir_all(&label_dir)?;
+
+        let mut number = fs::read_dir(&label_dir)?.count();
+        while label_dir.join(format!("{}.png", number)).exists() {
+            number += 1;
+        }
+        save_numbered(&self.dir, sample, number)
+    }
+}
+
+/// Saves `sample` as `<label>/<number>.png` in `dir`.
+fn save_numbered(dir: &Path, sample: &LabeledTrainingData, number: usize) -> Result<()> {
+    let label_dir = dir.join(sample.label().class().to_string());
+    fs::create_dir_all(&label_dir)?;
+    sample.image().save_png(label_dir.join(format!("{}.png", number)))?;
+    Ok(())
+}
@@ -0,0 +1,166 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use thiserror::Error;
+use crate::data::{Image, ImageSize};
+use crate::multiscale::{crop_input, crop_offsets};
+use crate::network;
+use crate::network::Classifier;
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error("cannot read image {file} ({source})")]
+    CannotReadImage {
+        file: String,
+        source: io::Error
+    },
+
+    #[error("{0} is not a binary 8-bit PGM image")]
+    UnsupportedImageFormat(String),
+
+    #[error(transparent)]
+    NeuralNetworkError(#[from] network::ErrorKind)
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::CannotReadImage { .. } => "E1001",
+            ErrorKind::UnsupportedImageFormat(_) => "E1002",
+            ErrorKind::NeuralNetworkError(err) => err.code()
+        }
+    }
+}
+
+/// Window sides tried, as fractions of the shorter side of the image.
+const WINDOW_SCALES: &[f64] = &[1.0, 0.8, 0.6];
+
+/// Windows whose horizontal extent overlaps a more confident detection by
+/// more than this intersection over union are dropped.
+const MAX_OVERLAP: f64 = 0.3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    pub class: usize,
+    pub class_name: String,
+    pub confidence: f64,
+    pub x: u32,
+    pub y: u32,
+    pub side: u32
+}
+
+impl Detection {
+    /// Intersection over union of the columns covered by both detections;
+    /// the digits are expected to be written on a single line.
+    fn overlap(&self, other: &Detection) -> f64 {
+        let intersection = (self.x + self.side).min(other.x + other.side).saturating_sub(self.x.max(other.x));
+        let union = (self.x + self.side).max(other.x + other.side) - self.x.min(other.x);
+
+        (intersection as f64) / (union.max(1) as f64)
+    }
+}
+
+/// Slides the classifier over `image`, e.g. a row of handwritten digits,
+/// and returns the digits found left to right. Windows less confident than
+/// `min_confidence` or the classifier's rejection threshold are ignored, and
+/// of overlapping windows only the most confident one is kept.
+pub fn detect(classifier: &dyn Classifier, image: &Image, min_confidence: f64) -> Result<Vec<Detection>> {
+    let size = image.size();
+    let shorter_side = size.width.min(size.height);
+
+    let mut windows = Vec::new();
+    for scale in WINDOW_SCALES {
+        let side = ((shorter_side as f64) * scale).round() as u32;
+        if side == 0 {
+            continue
+        }
+
+        for y in crop_offsets(size.height, side, (side / 2).max(1)) {
+            for x in crop_offsets(size.width, side, (side / 4).max(1)) {
+                windows.push((x, y, side));
+            }
+        }
+    }
+
+    let (input_size, preprocessing) = (classifier.input_size(), classifier.preprocessing());
+    let inputs = windows
+        .iter()
+        .map(|&(x, y, side)| crop_input(image, x, y, side, input_size, preprocessing))
+        .collect();
+
+    let min_confidence = min_confidence.max(classifier.rejection_threshold().unwrap_or(0.0));
+    let mut candidates: Vec<Detection> = classifier
+        .predict_batch(inputs)?
+        .into_iter()
+        .zip(windows)
+        .filter(|(prediction, _)| prediction.confidence >= min_confidence)
+        .map(|(prediction, (x, y, side))| Detection {
+            class: prediction.class,
+            class_name: classifier.class_name(prediction.class),
+            confidence: prediction.confidence,
+            x,
+            y,
+            side
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+    let mut detections: Vec<Detection> = Vec::new();
+    for candidate in candidates {
+        if detections.iter().all(|detection| detection.overlap(&candidate) <= MAX_OVERLAP) {
+            detections.push(candidate);
+        }
+    }
+    detections.sort_by_key(|detection| detection.x);
+
+    Ok(detections)
+}
+
+/// Loads a binary (P5) PGM image with 8-bit samples, as written by most image
+/// tools, converted to white digits on black like the training datasets.
+pub fn load_pgm<P: AsRef<Path>>(file: P) -> Result<Image> {
+    let path = file.as_ref();
+    let cannot_read = |source| ErrorKind::CannotReadImage { file: path.display().to_string(), source };
+    let unsupported = || ErrorKind::UnsupportedImageFormat(path.display().to_string());
+
+    let mut reader = BufReader::new(File::open(path).map_err(cannot_read)?);
+
+    let mut header = Vec::new();
+    while header.len() < 4 {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(cannot_read)? == 0 {
+            return Err(unsupported())
+        }
+
+        let line = line.split('#').next().unwrap_or_default();
+        header.extend(line.split_whitespace().map(String::from));
+    }
+
+    let (width, height, max_value) = match header.as_slice() {
+        [magic, width, height, max_value] if magic == "P5" =>
+            (width.parse::<u32>(), height.parse::<u32>(), max_value.parse::<u32>()),
+        _ => return Err(unsupported())
+    };
+
+    let size = match (width, height, max_value) {
+        (Ok(width), Ok(height), Ok(255)) => ImageSize { width, height },
+        _ => return Err(unsupported())
+    };
+
+    let mut pixels = vec![0; size.area()];
+    reader.read_exact(&mut pixels).map_err(cannot_read)?;
+
+    // photos and scans have dark ink on light paper
+    let mean = pixels.iter().map(|x| *x as usize).sum::<usize>() / pixels.len().max(1);
+    if mean > 127 {
+        pixels.iter_mut().for_each(|x| *x = 255 - *x);
+    }
+
+    Ok(Image::builder()
+        .with_size(size)
+        .with_pixels_row_major(pixels)
+        .build())
+}
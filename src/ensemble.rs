@@ -0,0 +1,164 @@
+use std::path::Path;
+use std::str::FromStr;
+use nalgebra::DVector;
+use thiserror::Error;
+use crate::backend::ComputeBackend;
+use crate::data::ImageSize;
+use crate::network;
+use crate::network::{to_f64, Classifier, NeuralNetwork, Prediction, Scalar};
+use crate::transform::Preprocessing;
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error(transparent)]
+    NeuralNetworkError(#[from] network::ErrorKind),
+
+    #[error("an ensemble needs at least one model")]
+    NoMembers,
+
+    #[error("ensemble member {member} does not match the first model's input size or class count")]
+    MemberMismatch {
+        member: usize
+    },
+
+    #[error("unknown voting strategy `{0}` (expected one of: average, majority)")]
+    UnknownVoting(String)
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::NeuralNetworkError(err) => err.code(),
+            ErrorKind::NoMembers => "E0406",
+            ErrorKind::MemberMismatch { .. } => "E0407",
+            ErrorKind::UnknownVoting(_) => "E0405"
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum Voting {
+    /// Averages the softmax outputs of all members.
+    #[default]
+    Average,
+    /// Every member votes for its most probable class.
+    Majority
+}
+
+impl FromStr for Voting {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self> {
+        return match s.to_lowercase().as_str() {
+            "average" => Ok(Voting::Average),
+            "majority" => Ok(Voting::Majority),
+            _ => Err(ErrorKind::UnknownVoting(s.to_string()))
+        }
+    }
+}
+
+/// Independently trained networks classifying the same inputs together.
+/// Class names and the rejection threshold are taken from the first member.
+pub struct Ensemble {
+    members: Vec<NeuralNetwork>,
+    voting: Voting
+}
+
+impl Ensemble {
+    pub fn new(members: Vec<NeuralNetwork>, voting: Voting) -> Result<Ensemble> {
+        let first = members.first().ok_or(ErrorKind::NoMembers)?;
+
+        if let Some(member) = members.iter().position(|member|
+            member.input_size() != first.input_size() || member.output_size() != first.output_size()) {
+            return Err(ErrorKind::MemberMismatch { member })
+        }
+
+        Ok(Ensemble { members, voting })
+    }
+
+    pub fn load<P: AsRef<Path>>(files: &[P], voting: Voting) -> Result<Ensemble> {
+        let members = files
+            .iter()
+            .map(NeuralNetwork::load)
+            .collect::<network::Result<Vec<_>>>()?;
+
+        Ensemble::new(members, voting)
+    }
+
+    pub fn set_compute_backend(&mut self, backend: ComputeBackend) {
+        for member in &mut self.members {
+            member.set_compute_backend(backend);
+        }
+    }
+
+    pub fn members(&self) -> &[NeuralNetwork] {
+        &self.members
+    }
+
+    pub fn input_size(&self) -> ImageSize {
+        self.members[0].input_size()
+    }
+
+    pub fn output_size(&self) -> usize {
+        self.members[0].output_size()
+    }
+
+    pub fn class_name(&self, class: usize) -> String {
+        self.members[0].class_name(class)
+    }
+
+    pub fn rejection_threshold(&self) -> Option<f64> {
+        self.members[0].rejection_threshold()
+    }
+
+    /// Averaged class probabilities, or the share of votes each class got
+    /// with [`Voting::Majority`].
+    pub fn compute(&self, input: DVector<Scalar>) -> network::Result<DVector<Scalar>> {
+        let mut result = DVector::zeros(self.output_size());
+
+        for member in &self.members {
+            let output = member.compute(input.clone_owned())?;
+            match self.voting {
+                Voting::Average => result += output,
+                Voting::Majority => result[output.argmax().0] += 1.0
+            }
+        }
+
+        Ok(result / (self.members.len() as Scalar))
+    }
+
+    pub fn predict(&self, input: DVector<Scalar>) -> network::Result<Prediction> {
+        let output = self.compute(input)?;
+        let (class, confidence) = output.argmax();
+
+        Ok(Prediction { class, confidence: to_f64(confidence) })
+    }
+}
+
+impl Classifier for Ensemble {
+    fn input_size(&self) -> ImageSize {
+        Ensemble::input_size(self)
+    }
+
+    fn output_size(&self) -> usize {
+        Ensemble::output_size(self)
+    }
+
+    fn class_name(&self, class: usize) -> String {
+        Ensemble::class_name(self, class)
+    }
+
+    fn rejection_threshold(&self) -> Option<f64> {
+        Ensemble::rejection_threshold(self)
+    }
+
+    fn preprocessing(&self) -> Preprocessing {
+        self.members[0].preprocessing()
+    }
+
+    fn predict(&self, input: DVector<Scalar>) -> network::Result<Prediction> {
+        Ensemble::predict(self, input)
+    }
+}
@@ -0,0 +1,45 @@
+use crate::i18n::catalog;
+
+/// A stable identifier of an error, shown with its message and looked up by
+/// `--explain` so that bug reports and documentation can refer to it.
+pub struct ErrorCode {
+    pub code: &'static str,
+    pub summary: String,
+    pub remedy: String
+}
+
+/// Codes are grouped by module: E00xx datasets, E01xx models, E02xx training,
+/// E03xx command line, E04xx model options, E05xx checksums, E06xx bundles,
+/// E07xx runs, E08xx benchmarks, E09xx GUI and profiles, E10xx detection,
+/// E11xx external predictions, E12xx search and sweeps, E13xx label maps,
+/// E14xx adversarial examples, E15xx tensor exports, E16xx MNIST downloads,
+/// E17xx dataset writing, E18xx synthetic datasets.
+/// Codes are never reused for a different error. Their summaries and remedies
+/// are in the message catalogs, keyed by the code.
+pub const ERROR_CODES: &[&str] = &[
+    "E0001", "E0002", "E0003", "E0004", "E0005", "E0006", "E0007", "E0008",
+    "E0009", "E0010", "E0011", "E0012", "E0013", "E0014", "E0101", "E0102",
+    "E0103", "E0104", "E0105", "E0106", "E0107", "E0108", "E0109", "E0110",
+    "E0111", "E0112", "E0201", "E0202", "E0203", "E0204", "E0205", "E0206",
+    "E0207", "E0208", "E0209", "E0210", "E0211", "E0212", "E0213", "E0214",
+    "E0215", "E0301", "E0302", "E0303", "E0304", "E0305", "E0306", "E0307",
+    "E0308", "E0309", "E0401", "E0402", "E0403", "E0404", "E0405", "E0406",
+    "E0407", "E0501", "E0502", "E0503", "E0504", "E0505", "E0601", "E0602",
+    "E0603", "E0604", "E0701", "E0702", "E0703", "E0801", "E0802", "E0803",
+    "E0901", "E0902", "E0911", "E0912", "E0913", "E1001", "E1002", "E1101",
+    "E1102", "E1103", "E1104", "E1201", "E1202", "E1203", "E1204", "E1205",
+    "E1206", "E1207", "E1208", "E1301", "E1302", "E1303", "E1304", "E1401",
+    "E1402", "E1403", "E1501", "E1502", "E1503", "E1601", "E1602", "E1603",
+    "E1701", "E1702", "E1703", "E1704", "E1801", "E1802"
+];
+
+pub fn explain(code: &str) -> Option<ErrorCode> {
+    let code = ERROR_CODES.iter().find(|known| known.eq_ignore_ascii_case(code))?;
+    let catalog = catalog();
+
+    Some(ErrorCode {
+        code,
+        summary: catalog.message(code),
+        remedy: catalog.attribute(code, "remedy").unwrap_or_default()
+    })
+}
@@ -0,0 +1,162 @@
+use std::fmt::{Display, Formatter};
+use std::fs::read_to_string;
+use std::io;
+use std::path::Path;
+use nalgebra::DVector;
+use thiserror::Error;
+use crate::network;
+use crate::network::{NeuralNetwork, Scalar};
+use crate::training::input_vector;
+use crate::training_data::LabeledTrainingData;
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error("cannot read predictions {file} ({source})")]
+    CannotReadPredictions {
+        file: String,
+        source: io::Error
+    },
+
+    #[error("invalid probability `{value}` on line {line} of the predictions")]
+    InvalidProbability {
+        line: usize,
+        value: String
+    },
+
+    #[error("line {line} of the predictions has {found} columns, but the model has {expected} classes")]
+    ColumnCountMismatch {
+        line: usize,
+        expected: usize,
+        found: usize
+    },
+
+    #[error("there are {predictions} external predictions for {samples} test samples")]
+    SampleCountMismatch {
+        predictions: usize,
+        samples: usize
+    },
+
+    #[error(transparent)]
+    NeuralNetworkError(#[from] network::ErrorKind)
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::CannotReadPredictions { .. } => "E1101",
+            ErrorKind::InvalidProbability { .. } => "E1102",
+            ErrorKind::ColumnCountMismatch { .. } => "E1103",
+            ErrorKind::SampleCountMismatch { .. } => "E1104",
+            ErrorKind::NeuralNetworkError(err) => err.code()
+        }
+    }
+}
+
+/// Class probabilities of another model, e.g. `predict_proba` of scikit-learn
+/// or the softmax of a PyTorch model, as a CSV with one row per test sample in
+/// the order of the IDX files and one column per class. A header row is
+/// skipped and rows are normalized to sum to one.
+pub struct ExternalPredictions {
+    rows: Vec<DVector<Scalar>>
+}
+
+impl ExternalPredictions {
+    pub fn load<P: AsRef<Path>>(path: P, class_count: usize) -> Result<ExternalPredictions> {
+        let path = path.as_ref();
+        let content = read_to_string(path)
+            .map_err(|source| ErrorKind::CannotReadPredictions { file: path.display().to_string(), source })?;
+
+        let mut rows = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if line.trim().is_empty() || (index == 0 && fields[0].parse::<Scalar>().is_err()) {
+                continue;
+            }
+
+            if fields.len() != class_count {
+                return Err(ErrorKind::ColumnCountMismatch { line: index + 1, expected: class_count, found: fields.len() })
+            }
+
+            let probabilities = fields
+                .iter()
+                .map(|field| field
+                    .parse::<Scalar>()
+                    .ok()
+                    .filter(|value| value.is_finite() && *value >= 0.0)
+                    .ok_or_else(|| ErrorKind::InvalidProbability { line: index + 1, value: field.to_string() }))
+                .collect::<Result<Vec<Scalar>>>()?;
+
+            let row = DVector::from_vec(probabilities);
+            let sum = row.sum();
+            rows.push(if sum > 0.0 { row / sum } else { row });
+        }
+
+        Ok(ExternalPredictions { rows })
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn probabilities(&self, sample: usize) -> &DVector<Scalar> {
+        &self.rows[sample]
+    }
+}
+
+/// Accuracy of a model, the external predictions and their average on the
+/// same test samples.
+pub struct PredictionComparison {
+    pub samples: usize,
+    pub native_accuracy: f64,
+    pub external_accuracy: f64,
+    pub ensemble_accuracy: f64,
+    /// Samples on which the model and the external predictions disagree.
+    pub disagreements: usize
+}
+
+impl Display for PredictionComparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:<10} {:>10}", "model", "accuracy")?;
+        writeln!(f, "{:<10} {:>9.2}%", "native", self.native_accuracy*100.0)?;
+        writeln!(f, "{:<10} {:>9.2}%", "external", self.external_accuracy*100.0)?;
+        writeln!(f, "{:<10} {:>9.2}%", "ensemble", self.ensemble_accuracy*100.0)?;
+        writeln!(f, "{} of {} samples classified differently", self.disagreements, self.samples)
+    }
+}
+
+pub fn compare(network: &NeuralNetwork,
+               samples: &[LabeledTrainingData],
+               external: &ExternalPredictions) -> Result<PredictionComparison> {
+    if external.len() != samples.len() {
+        return Err(ErrorKind::SampleCountMismatch { predictions: external.len(), samples: samples.len() })
+    }
+
+    let (mut native_correct, mut external_correct, mut ensemble_correct, mut disagreements) = (0, 0, 0, 0);
+    for (index, sample) in samples.iter().enumerate() {
+        let class = sample.label().class() as usize;
+        let native = network.compute(input_vector(sample.image()))?;
+        let external = external.probabilities(index);
+        let ensemble = (&native + external) / 2.0;
+
+        let (native_class, external_class) = (native.argmax().0, external.argmax().0);
+        native_correct += (native_class == class) as usize;
+        external_correct += (external_class == class) as usize;
+        ensemble_correct += (ensemble.argmax().0 == class) as usize;
+        disagreements += (native_class != external_class) as usize;
+    }
+
+    let accuracy = |correct: usize| (correct as f64) / (samples.len().max(1) as f64);
+    Ok(PredictionComparison {
+        samples: samples.len(),
+        native_accuracy: accuracy(native_correct),
+        external_accuracy: accuracy(external_correct),
+        ensemble_accuracy: accuracy(ensemble_correct),
+        disagreements
+    })
+}
@@ -0,0 +1,158 @@
+use std::f64::consts::PI;
+use std::str::FromStr;
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+use crate::data::ImageSize;
+use crate::network::{to_f64, Scalar};
+use crate::training::input_vector;
+use crate::training_data::LabeledTrainingData;
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error("unknown input features `{0}` (expected pixels or directions)")]
+    UnknownInputFeatures(String)
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::UnknownInputFeatures(_) => "E0404"
+        }
+    }
+}
+
+/// Zones per side of the image with their own direction histogram.
+const DIRECTION_ZONES: usize = 4;
+
+/// Stroke orientations told apart: horizontal, both diagonals and vertical.
+const DIRECTION_BINS: usize = 4;
+
+/// What a network sees of an image.
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq)]
+pub enum InputFeatures {
+    #[default]
+    Pixels,
+    /// The pixels followed by a histogram of edge orientations for each zone
+    /// of a grid over the image, weighted by edge strength. Describes how the
+    /// digit was drawn rather than exactly where, which is less sensitive to
+    /// sloppy strokes.
+    PixelsAndDirections
+}
+
+impl FromStr for InputFeatures {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self> {
+        return match s.to_lowercase().as_str() {
+            "pixels" => Ok(InputFeatures::Pixels),
+            "directions" => Ok(InputFeatures::PixelsAndDirections),
+            _ => Err(ErrorKind::UnknownInputFeatures(s.to_string()))
+        }
+    }
+}
+
+impl InputFeatures {
+    /// Name accepted by `--input-features`.
+    pub fn name(&self) -> &'static str {
+        return match self {
+            InputFeatures::Pixels => "pixels",
+            InputFeatures::PixelsAndDirections => "directions"
+        }
+    }
+
+    /// Length of the input vector for images of `size`.
+    pub fn input_len(&self, size: ImageSize) -> usize {
+        return match self {
+            InputFeatures::Pixels => size.area(),
+            InputFeatures::PixelsAndDirections => size.area() + DIRECTION_ZONES * DIRECTION_ZONES * DIRECTION_BINS
+        }
+    }
+
+    /// Appends the features to `pixels`, an image of `size` as returned by
+    /// [`crate::training::input_vector`].
+    pub fn extend(&self, pixels: DVector<Scalar>, size: ImageSize) -> DVector<Scalar> {
+        return match self {
+            InputFeatures::Pixels => pixels,
+            InputFeatures::PixelsAndDirections => {
+                let directions = direction_histograms(&pixels, size);
+                let len = pixels.len();
+                let mut input = pixels.resize_vertically(len + directions.len(), 0.0);
+                input.rows_mut(len, directions.len()).copy_from_slice(&directions);
+                input
+            }
+        }
+    }
+}
+
+fn direction_histograms(pixels: &DVector<Scalar>, size: ImageSize) -> Vec<Scalar> {
+    let (width, height) = (size.width as usize, size.height as usize);
+    let pixel = |x: usize, y: usize| to_f64(pixels[y * width + x]);
+
+    let mut histograms = vec![0.0; DIRECTION_ZONES * DIRECTION_ZONES * DIRECTION_BINS];
+    for y in 1..height.saturating_sub(1) {
+        for x in 1..width.saturating_sub(1) {
+            let dx = pixel(x + 1, y) - pixel(x - 1, y);
+            let dy = pixel(x, y + 1) - pixel(x, y - 1);
+            let magnitude = dx.hypot(dy);
+            if magnitude == 0.0 {
+                continue
+            }
+
+            // edges are undirected, so orientations repeat every half turn
+            let orientation = dy.atan2(dx).rem_euclid(PI);
+            let bin = ((orientation / PI * DIRECTION_BINS as f64).round() as usize) % DIRECTION_BINS;
+            let zone = (y * DIRECTION_ZONES / height) * DIRECTION_ZONES + x * DIRECTION_ZONES / width;
+
+            histograms[zone * DIRECTION_BINS + bin] += magnitude;
+        }
+    }
+
+    // scaled into the range of the pixel inputs
+    let max = histograms.iter().cloned().fold(0.0, f64::max);
+    histograms
+        .iter()
+        .map(|x| (if max > 0.0 { x / max } else { 0.0 } - 0.5) as Scalar)
+        .collect()
+}
+
+/// Mean and standard deviation of the pixel inputs over a training set,
+/// stored with the model so that every image it sees is standardized alike.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct Normalization {
+    pub mean: Scalar,
+    pub std_dev: Scalar
+}
+
+impl Normalization {
+    /// Statistics of the [`input_vector`]s of `samples`; `None` without
+    /// samples or if all their pixels are alike.
+    pub fn of(samples: &[LabeledTrainingData]) -> Option<Normalization> {
+        let (mut count, mut sum, mut sum_of_squares) = (0usize, 0.0f64, 0.0f64);
+        for sample in samples {
+            for value in input_vector(sample.image()).iter() {
+                count += 1;
+                sum += to_f64(*value);
+                sum_of_squares += to_f64(*value).powi(2);
+            }
+        }
+
+        if count == 0 {
+            return None
+        }
+
+        let mean = sum / count as f64;
+        let std_dev = (sum_of_squares / count as f64 - mean * mean).max(0.0).sqrt();
+        return if std_dev > 0.0 {
+            Some(Normalization { mean: mean as Scalar, std_dev: std_dev as Scalar })
+        } else {
+            None
+        }
+    }
+
+    pub fn apply(&self, pixels: DVector<Scalar>) -> DVector<Scalar> {
+        pixels.map(|value| (value - self.mean) / self.std_dev)
+    }
+}
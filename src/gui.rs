@@ -1,8 +1,10 @@
-use druid::{Data, Lens, AppLauncher, Color, Insets, LocalizedString, MenuDesc, PlatformError, Size, TextAlignment, Widget, WidgetExt, WindowDesc, piet};
-use druid::widget::{Button, Flex, FlexParams, Label, SizedBox};
+use druid::{Data, Lens, AppLauncher, Color, FontDescriptor, FontFamily, Insets, LocalizedString, MenuDesc, PlatformError, Size, TextAlignment, Widget, WidgetExt, WindowDesc, lens, piet};
+use druid::widget::{Button, Checkbox, Either, Flex, FlexParams, Label, RadioGroup, SizedBox, Slider, TextBox};
 use thiserror::Error;
 use crate::data::{Image, ImageSize};
+use crate::i18n::{tr, tr_args};
 use crate::interactive_canvas_widget::{InteractiveCanvas, InteractiveCanvasState};
+use crate::view_model::{Answer, ViewModel};
 
 #[derive(Error, Debug)]
 pub enum ErrorKind {
@@ -15,90 +17,194 @@ pub enum ErrorKind {
 
 pub type Result<T> = std::result::Result<T, ErrorKind>;
 
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::DruidPlatformError(_) => "E0901",
+            ErrorKind::CannotCopyPixels(_) => "E0902"
+        }
+    }
+}
+
 #[derive(Data, Lens, Clone)]
 struct AppState {
-    digit: u8,
-    accuracy: f64,
+    #[data(same_fn = "PartialEq::eq")]
+    view_model: ViewModel,
     canvas_state: InteractiveCanvasState
 }
 
-impl Default for AppState {
-    fn default() -> Self {
-        AppState {
-            digit: 9,
-            accuracy: 0.99,
-            canvas_state: InteractiveCanvasState::builder()
-                .with_background(Color::WHITE)
-                .with_stroke_brush(Color::BLACK)
-                .with_stroke_width(0.036)
-                .build()
-        }
-    }
+/// Selector of the user profile and controls teaching the profile's model
+/// the correct answer for the last drawing.
+pub struct ProfileControls {
+    pub names: Vec<String>,
+    pub selected: String,
+    /// Called with the selected profile and the answer typed by the user;
+    /// returns a message to show.
+    pub on_teach: Box<dyn Fn(&str, &str) -> String>
+}
+
+/// A toggle for saving drawings to a dataset, and a button saving the last
+/// one with the answer the user confirmed or typed.
+pub struct CollectControls {
+    /// Called with the confirmed answer; returns a message to show.
+    pub on_collect: Box<dyn Fn(&str) -> String>
 }
 
 pub struct ImageLoader<'a> {
-    canvas: &'a mut InteractiveCanvasState
+    canvas: &'a mut InteractiveCanvasState,
+    profile: &'a str
 }
 
 impl ImageLoader<'_> {
-    pub fn load_image(&self, size_dimension: u32) -> Result<Image> {
-        let pixels = self.canvas.copy_pixels_grayscale(size_dimension)?;
+    /// The selected user profile, empty without [`ProfileControls`].
+    pub fn profile(&self) -> &str {
+        self.profile
+    }
+
+    pub fn load_image(&self, size: ImageSize) -> Result<Image> {
+        let pixels = self.canvas.copy_pixels_grayscale(size.width, size.height)?;
         Ok(Image::builder()
-            .with_size(ImageSize::square(size_dimension))
+            .with_size(size)
             .with_pixels_row_major(pixels)
             .build())
     }
+
+    /// One image of `cell_size` per column of a canvas split into `cells`.
+    pub fn load_cells(&self, cell_size: ImageSize, cells: u32) -> Result<Vec<Image>> {
+        let width = cell_size.width * cells;
+        let pixels = self.canvas.copy_pixels_grayscale(width, cell_size.height)?;
+
+        Ok((0..cells)
+            .map(|cell| {
+                let cell_pixels: Vec<u8> = pixels
+                    .chunks(width as usize)
+                    .flat_map(|row| row.chunks(cell_size.width as usize).nth(cell as usize).unwrap_or_default())
+                    .copied()
+                    .collect();
+
+                Image::builder()
+                    .with_size(cell_size)
+                    .with_pixels_row_major(cell_pixels)
+                    .build()
+            })
+            .collect())
+    }
 }
 
-pub fn launch<F>(on_submit: F) -> Result<()>
-    where F: Fn(ImageLoader) -> (u8, f64) + 'static
+/// Shows the drawing window; with more than one `grid_cells` the canvas is
+/// split into columns for entering one digit each. `collect` lets the user
+/// save drawings to a dataset, and `about` describes the model in a dialog
+/// of its own.
+pub fn launch<F>(rejection_threshold: f64,
+                 grid_cells: u32,
+                 profiles: Option<ProfileControls>,
+                 collect: Option<CollectControls>,
+                 about: Option<String>,
+                 on_submit: F) -> Result<()>
+    where F: Fn(ImageLoader) -> Answer + 'static
 {
-    open_window(move |state| {
-        let image_loader = ImageLoader { canvas: &mut state.canvas_state };
-        (state.digit, state.accuracy) = on_submit(image_loader);
+    let profile = profiles.as_ref().map_or_else(String::new, |profiles| profiles.selected.clone());
+    let initial_state = AppState {
+        view_model: ViewModel::new(rejection_threshold, profile),
+        canvas_state: InteractiveCanvasState::builder()
+            .with_background(Color::WHITE)
+            .with_stroke_brush(Color::BLACK)
+            .with_stroke_width(0.036)
+            .with_grid_cells(grid_cells)
+            .build()
+    };
+
+    let window_width = 200.0 + 600.0_f64.max(300.0 * (grid_cells as f64));
+    open_window(initial_state, window_width, profiles, collect, about, move |state| {
+        let image_loader = ImageLoader { canvas: &mut state.canvas_state, profile: state.view_model.profile() };
+        let answer = on_submit(image_loader);
+        state.view_model.submit(answer);
         state.canvas_state.clear();
     })
 }
 
-fn open_window<F>(on_submit: F) -> Result<()>
+fn open_window<F>(initial_state: AppState,
+                  width: f64,
+                  profiles: Option<ProfileControls>,
+                  collect: Option<CollectControls>,
+                  about: Option<String>,
+                  on_submit: F) -> Result<()>
     where F: Fn(&mut AppState) + 'static
 {
     let window_menu = MenuDesc::new(LocalizedString::new("window_title"));
-    let window = WindowDesc::new(move || build_ui(on_submit))
-        .window_size(Size::new(800.0, 600.0))
+    let window = WindowDesc::new(move || build_ui(on_submit, profiles, collect, about))
+        .title(tr("gui-window-title"))
+        .window_size(Size::new(width, 600.0))
         .resizable(true)
         .menu(window_menu);
 
     AppLauncher::with_window(window)
         .use_simple_logger()
-        .launch(AppState::default())?;
+        .launch(initial_state)?;
 
     Ok(())
 }
 
-fn build_ui<F>(on_submit: F) -> impl Widget<AppState>
+fn build_ui<F>(on_submit: F,
+               profiles: Option<ProfileControls>,
+               collect: Option<CollectControls>,
+               about: Option<String>) -> impl Widget<AppState>
     where F: Fn(&mut AppState) + 'static
 {
     let canvas = InteractiveCanvas::default()
         .with_state(
             |state: &AppState| state.canvas_state.clone());
 
-    let recognized_digit_label = Label::dynamic(|digit, _| format!("{}", digit))
+    let recognized_digit_label = Label::dynamic(|state: &AppState, _| state.view_model.answer_text().to_string())
         .with_text_size(60.0)
         .with_text_alignment(TextAlignment::Center)
         .padding(Insets::uniform_xy(10.0, 0.0))
         .background(Color::BLUE)
-        .rounded(60.0)
-        .lens(AppState::digit);
+        .rounded(60.0);
+
+    let unrecognized_label = Label::new("?")
+        .with_text_size(60.0)
+        .with_text_alignment(TextAlignment::Center)
+        .padding(Insets::uniform_xy(10.0, 0.0))
+        .background(Color::rgb8(0x80, 0x80, 0x80))
+        .rounded(60.0);
+
+    let result_label = Either::new(|state: &AppState, _| state.view_model.is_recognized(),
+                                   recognized_digit_label,
+                                   unrecognized_label);
+
+    let result_caption = Either::new(|state: &AppState, _| state.view_model.is_recognized(),
+                                     Label::new(tr("gui-result")).with_text_size(50.0),
+                                     Label::dynamic(|state: &AppState, _| tr(state.view_model.caption_key()))
+                                         .with_text_size(36.0));
+
+    let rejection_threshold = lens::Map::new(
+        |state: &AppState| state.view_model.rejection_threshold(),
+        |state: &mut AppState, threshold: f64| state.view_model.set_rejection_threshold(threshold));
+
+    let rejection_threshold_label =
+        Label::dynamic(|threshold: &f64, _| tr_args("gui-threshold", &[("percent", format!("{:.0}", threshold*100.0))]))
+            .with_text_size(20.0)
+            .lens(rejection_threshold);
+
+    let rejection_threshold_slider = Slider::new()
+        .lens(rejection_threshold);
 
     let recognized_digit_accuracy_text_label =
-        Label::dynamic(|accuracy, _| format!("{:.0}%", accuracy*100.0))
+        Label::dynamic(|state: &AppState, _| state.view_model.confidence_text())
             .with_text_size(33.0)
-            .with_text_alignment(TextAlignment::Center)
-            .lens(AppState::accuracy);
+            .with_text_alignment(TextAlignment::Center);
+
+    let history_label = Label::dynamic(|state: &AppState, _| {
+        let answers: Vec<&str> = state.view_model.history().iter().skip(1).map(|answer| answer.text.as_str()).collect();
+        match answers.is_empty() {
+            true => String::new(),
+            false => tr_args("gui-history", &[("answers", answers.join(" "))])
+        }
+    });
 
     let submit_button_label: Label<AppState> =
-        Label::new("Resubmit")
+        Label::new(tr("gui-resubmit"))
             .with_text_size(30.0);
 
     let submit_button =
@@ -106,19 +212,117 @@ fn build_ui<F>(on_submit: F) -> impl Widget<AppState>
             .on_click(move |_, state, _|
                 on_submit(state));
 
-    let controls =
+    let mut controls =
         Flex::column()
-            .with_child(Label::new("Result").with_text_size(50.0))
+            .with_child(result_caption)
             .with_spacer(20.0)
-            .with_child(recognized_digit_label)
+            .with_child(result_label)
             .with_spacer(20.0)
-            .with_child(Label::new("chance:").with_text_size(33.0))
+            .with_child(Label::new(tr("gui-chance")).with_text_size(33.0))
             .with_spacer(10.0)
             .with_child(recognized_digit_accuracy_text_label)
             .with_spacer(50.0)
-            .with_child(submit_button);
+            .with_child(submit_button)
+            .with_spacer(30.0)
+            .with_child(rejection_threshold_label)
+            .with_child(rejection_threshold_slider)
+            .with_spacer(10.0)
+            .with_child(history_label);
+
+    let has_profiles = profiles.is_some();
+    if let Some(profiles) = profiles {
+        let on_teach = profiles.on_teach;
+        let profile_selector = RadioGroup::new(profiles.names
+            .into_iter()
+            .map(|name| (name.clone(), name)))
+            .lens(lens::Map::new(
+                |state: &AppState| state.view_model.profile().to_string(),
+                |state: &mut AppState, profile: String| state.view_model.select_profile(profile)));
+
+        let teach_button = Button::new(tr("gui-teach"))
+            .on_click(move |_, state: &mut AppState, _| {
+                if let Some(correction) = state.view_model.take_correction_to_teach() {
+                    let status = on_teach(state.view_model.profile(), &correction);
+                    state.view_model.set_teaching_status(status);
+                }
+            });
+
+        let teaching_status_label = Label::dynamic(|state: &AppState, _| state.view_model.teaching_status().to_string());
+
+        controls.add_spacer(30.0);
+        controls.add_child(Label::new(tr("gui-profile")).with_text_size(20.0));
+        controls.add_child(profile_selector);
+        controls.add_spacer(10.0);
+        controls.add_child(correction_box());
+        controls.add_child(teach_button);
+        controls.add_child(teaching_status_label);
+    }
+
+    if let Some(collect) = collect {
+        let on_collect = collect.on_collect;
+        let collect_toggle = Checkbox::new(tr("gui-collect"))
+            .lens(lens::Map::new(
+                |state: &AppState| state.view_model.is_collecting(),
+                |state: &mut AppState, collecting: bool| state.view_model.set_collecting(collecting)));
+
+        let save_button = Button::new(tr("gui-save-drawing"))
+            .on_click(move |_, state: &mut AppState, _| {
+                let status = match state.view_model.take_confirmed_answer() {
+                    Some(answer) => on_collect(&answer),
+                    None => tr("gui-no-answer")
+                };
+                state.view_model.set_teaching_status(status);
+            });
+
+        // the correction box of the profiles is shared, since both take the right answer;
+        // the view model keeps it for whichever of Teach and Save comes second
+        let mut save_controls = Flex::column();
+        if !has_profiles {
+            save_controls.add_child(correction_box());
+        }
+        save_controls.add_child(save_button);
+        if !has_profiles {
+            save_controls.add_child(Label::dynamic(|state: &AppState, _| state.view_model.teaching_status().to_string()));
+        }
+
+        controls.add_spacer(30.0);
+        controls.add_child(collect_toggle);
+        controls.add_child(Either::new(|state: &AppState, _| state.view_model.is_collecting(),
+                                       save_controls,
+                                       SizedBox::empty()));
+    }
+
+    if let Some(about) = about {
+        let about_button = Button::new(tr("gui-about"))
+            .on_click(move |ctx, _, _| {
+                let about = about.clone();
+                let dialog = WindowDesc::new(move || about_view(about))
+                    .title(tr("gui-about-title"))
+                    .window_size(Size::new(560.0, 320.0));
+                ctx.new_window(dialog);
+            });
+
+        controls.add_spacer(30.0);
+        controls.add_child(about_button);
+    }
 
     Flex::row()
         .with_flex_child(canvas, FlexParams::from(1.0))
         .with_child(SizedBox::new(controls).width(200.0))
-}
\ No newline at end of file
+}
+
+/// Where the user types the right answer for the last drawing.
+fn correction_box() -> impl Widget<AppState> {
+    TextBox::new()
+        .with_placeholder(tr("gui-correct-answer"))
+        .lens(lens::Map::new(
+            |state: &AppState| state.view_model.correction().to_string(),
+            |state: &mut AppState, correction: String| state.view_model.set_correction(correction)))
+}
+
+fn about_view(about: String) -> impl Widget<AppState> {
+    Label::new(about)
+        .with_font(FontDescriptor::new(FontFamily::MONOSPACE))
+        .with_text_size(14.0)
+        .padding(20.0)
+}
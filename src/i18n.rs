@@ -0,0 +1,134 @@
+use std::env;
+use std::sync::OnceLock;
+use fluent_bundle::{FluentArgs, FluentResource};
+use fluent_bundle::concurrent::FluentBundle;
+use unic_langid::LanguageIdentifier;
+
+/// Overrides the language of the system locale, e.g. `ru` or `en`.
+pub const LANG_ENV: &str = "DIGIT_RECOGNITION_LANG";
+
+const ENGLISH_MESSAGES: &str = include_str!("../resources/i18n/en-US/digit_recognition.ftl");
+const RUSSIAN_MESSAGES: &str = include_str!("../resources/i18n/ru-RU/digit_recognition.ftl");
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Locale {
+    English,
+    Russian
+}
+
+impl Locale {
+    /// Locale of a language tag such as `ru`, `en-US` or `ru_RU.UTF-8`;
+    /// English for languages without a translation.
+    pub fn from_tag(tag: &str) -> Locale {
+        let language = tag
+            .split(['-', '_', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        return match language.as_str() {
+            "ru" => Locale::Russian,
+            _ => Locale::English
+        }
+    }
+
+    /// From `--lang`, which has to be known before the help text is built,
+    /// then [`LANG_ENV`] and the usual locale variables.
+    pub fn detect() -> Locale {
+        let args: Vec<String> = env::args().collect();
+        let lang_arg = args
+            .iter()
+            .position(|arg| arg == "--lang")
+            .and_then(|position| args.get(position + 1).cloned())
+            .or_else(|| args.iter().find_map(|arg| arg.strip_prefix("--lang=").map(String::from)));
+
+        let tag = lang_arg.or_else(|| [LANG_ENV, "LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|key| env::var(key).ok())
+            .find(|value| !value.is_empty()));
+
+        tag.map_or(Locale::English, |tag| Locale::from_tag(&tag))
+    }
+
+    fn messages(&self) -> (&'static str, &'static str) {
+        return match self {
+            Locale::English => ("en-US", ENGLISH_MESSAGES),
+            Locale::Russian => ("ru-RU", RUSSIAN_MESSAGES)
+        }
+    }
+}
+
+/// Fluent messages of the command line and the GUI in one locale, falling
+/// back to English for messages without a translation.
+pub struct Catalog {
+    bundles: Vec<FluentBundle<FluentResource>>
+}
+
+impl Catalog {
+    pub fn new(locale: Locale) -> Catalog {
+        let mut locales = vec![locale];
+        if locale != Locale::English {
+            locales.push(Locale::English);
+        }
+
+        Catalog {
+            bundles: locales.iter().map(|locale| bundle(*locale)).collect()
+        }
+    }
+
+    /// The message `key`, or the key itself if no locale has it.
+    pub fn message(&self, key: &str) -> String {
+        self.format(key, &[])
+    }
+
+    pub fn format(&self, key: &str, args: &[(&str, String)]) -> String {
+        let mut fluent_args = FluentArgs::new();
+        for (name, value) in args {
+            fluent_args.set(*name, value.clone());
+        }
+
+        self.bundles
+            .iter()
+            .find_map(|bundle| {
+                let pattern = bundle.get_message(key)?.value()?;
+                let mut errors = Vec::new();
+                Some(bundle.format_pattern(pattern, Some(&fluent_args), &mut errors).into_owned())
+            })
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    pub fn attribute(&self, key: &str, attribute: &str) -> Option<String> {
+        self.bundles.iter().find_map(|bundle| {
+            let pattern = bundle.get_message(key)?.get_attribute(attribute)?.value();
+            let mut errors = Vec::new();
+            Some(bundle.format_pattern(pattern, None, &mut errors).into_owned())
+        })
+    }
+}
+
+fn bundle(locale: Locale) -> FluentBundle<FluentResource> {
+    let (tag, messages) = locale.messages();
+    let language: LanguageIdentifier = tag.parse().expect("invalid language tag");
+    let resource = FluentResource::try_new(messages.to_string()).expect("invalid message catalog");
+
+    let mut bundle = FluentBundle::new_concurrent(vec![language]);
+    // isolation marks around arguments show up as garbage in terminals
+    bundle.set_use_isolating(false);
+    bundle.add_resource(resource).expect("duplicate messages in catalog");
+    bundle
+}
+
+/// Catalog of the detected [`Locale`].
+pub fn catalog() -> &'static Catalog {
+    CATALOG.get_or_init(|| Catalog::new(Locale::detect()))
+}
+
+pub fn tr(key: &str) -> String {
+    catalog().message(key)
+}
+
+pub fn tr_args(key: &str, args: &[(&str, String)]) -> String {
+    catalog().format(key, args)
+}
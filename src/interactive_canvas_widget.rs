@@ -2,10 +2,11 @@ use std::sync::{Arc, Mutex};
 use druid::{Affine, BoxConstraints, Color, Data, Env, Event,
             EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx,
             piet, Point, RenderContext, Size, UpdateCtx, Widget};
-use druid::kurbo::{BezPath, PathEl};
+use druid::kurbo::{BezPath, Line, PathEl};
 use druid::piet::{Device, ImageFormat, LineCap, LineJoin, PaintBrush, StrokeStyle};
 
 const TRANSPARENT: Color = Color::rgba8(0, 0, 0, 0xff);
+const GRID_COLOR: Color = Color::rgb8(0xc0, 0xc0, 0xc0);
 
 struct CanvasForeground {
     path: BezPath,
@@ -38,18 +39,16 @@ impl CanvasForeground {
         self.path.push(PathEl::MoveTo(starting_point))
     }
 
+    // the path is scaled rather than the context, so strokes keep their
+    // width, relative to the canvas height, on canvases that are not square
     fn draw_stroke(&self, size: Size, ctx: &mut impl RenderContext) {
-        ctx.with_save(|ctx| {
-            ctx.transform(Affine::scale_non_uniform(size.width, size.height));
-            ctx.stroke_styled(&self.path,
-                              &self.stroke_brush,
-                              self.stroke_width,
-                              &StrokeStyle::new()
-                                  .line_cap(LineCap::Round)
-                                  .line_join(LineJoin::Round));
-
-            Ok(())
-        }).unwrap();
+        let path = Affine::scale_non_uniform(size.width, size.height) * &self.path;
+        ctx.stroke_styled(&path,
+                          &self.stroke_brush,
+                          self.stroke_width * size.height,
+                          &StrokeStyle::new()
+                              .line_cap(LineCap::Round)
+                              .line_join(LineJoin::Round));
     }
 
     fn clear(&mut self) {
@@ -60,6 +59,7 @@ impl CanvasForeground {
 struct CanvasContent {
     background: PaintBrush,
     foreground: CanvasForeground,
+    grid_cells: u32
 }
 
 impl Default for CanvasContent {
@@ -67,6 +67,7 @@ impl Default for CanvasContent {
         CanvasContent {
             background: TRANSPARENT.into(),
             foreground: CanvasForeground::default(),
+            grid_cells: 1
         }
     }
 }
@@ -77,6 +78,15 @@ impl CanvasContent {
         self.foreground.draw_stroke(size, ctx);
     }
 
+    /// Lines between the cells, only shown on screen.
+    fn draw_grid(&self, size: Size, ctx: &mut impl RenderContext) {
+        let cell_width = size.width / (self.grid_cells as f64);
+        for cell in 1..self.grid_cells {
+            let x = cell_width * (cell as f64);
+            ctx.stroke(Line::new((x, 0.0), (x, size.height)), &GRID_COLOR, 1.0);
+        }
+    }
+
     fn clear(&mut self) {
         self.foreground.clear();
     }
@@ -132,6 +142,12 @@ impl InteractiveCanvasStateBuilder {
         self
     }
 
+    /// Splits the canvas into this many columns, each holding one digit.
+    pub fn with_grid_cells(mut self, grid_cells: u32) -> Self {
+        self.content.grid_cells = grid_cells.max(1);
+        self
+    }
+
     pub fn build(self) -> InteractiveCanvasState {
         InteractiveCanvasState {
             content: Arc::new(self.content.into()),
@@ -156,24 +172,24 @@ impl InteractiveCanvasState {
         self.invalidate();
     }
 
-    pub fn copy_pixels_grayscale(&self, size_dimension: u32) -> Result<Vec<u8>, piet::Error> {
+    pub fn copy_pixels_grayscale(&self, width: u32, height: u32) -> Result<Vec<u8>, piet::Error> {
         let content = self.content.lock().unwrap();
         let mut device = Device::new()?;
         let mut target =
             device
-                .bitmap_target(size_dimension as usize,
-                               size_dimension as usize,
+                .bitmap_target(width as usize,
+                               height as usize,
                                1.0)?;
 
-        let size = Size::new(size_dimension as f64,
-                             size_dimension as f64);
+        let size = Size::new(width as f64,
+                             height as f64);
 
         let mut context = target.render_context();
         content.draw(size,&mut context);
 
         context.finish()?;
 
-        let mut buf = vec![0u8; (size_dimension*size_dimension * 4) as usize];
+        let mut buf = vec![0u8; (width*height * 4) as usize];
         target.copy_raw_pixels(ImageFormat::RgbaPremul, &mut buf)?;
 
         let mut pixels = Vec::with_capacity(size.area() as usize);
@@ -273,7 +289,11 @@ impl<T: Data> Widget<T> for InteractiveCanvas<T> {
     }
 
     fn paint(&mut self, ctx: &mut PaintCtx, data: &T, _env: &Env) {
-        (self.state_provider)(data).content.lock().unwrap()
-            .draw(ctx.region().bounding_box().size(), ctx.render_ctx)
+        let size = ctx.region().bounding_box().size();
+        let state = (self.state_provider)(data);
+        let content = state.content.lock().unwrap();
+
+        content.draw(size, ctx.render_ctx);
+        content.draw_grid(size, ctx.render_ctx);
     }
 }
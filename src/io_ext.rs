@@ -1,6 +1,5 @@
 use std::io;
 use std::io::Read;
-use std::marker::PhantomData;
 
 pub trait ReadFromBytes {
     type Error: From<io::Error>;
@@ -31,6 +30,19 @@ pub struct DataIter<T: ReadFromBytes, R: Read, F>
     config: T::Config
 }
 
+impl<T: ReadFromBytes, R: Read, F> DataIter<T, R, F>
+    where F: Fn(&Result<T, T::Error>) -> bool
+{
+    /// The input, e.g. to seek to another item.
+    pub fn input_mut(&mut self) -> &mut R {
+        &mut self.input
+    }
+
+    pub fn config(&self) -> &T::Config {
+        &self.config
+    }
+}
+
 impl<T: ReadFromBytes, R: Read, F> Iterator for DataIter<T, R, F>
     where F: Fn(&Result<T, T::Error>) -> bool
 {
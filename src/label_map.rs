@@ -0,0 +1,128 @@
+use std::fs::read_to_string;
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+use crate::training_data::{Label, LabeledTrainingData};
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error("cannot read label map {file} ({source})")]
+    CannotReadLabelMap {
+        file: String,
+        source: io::Error
+    },
+
+    #[error("line {line} of the label map is not a dataset label followed by a class name: `{content}`")]
+    InvalidLine {
+        line: usize,
+        content: String
+    },
+
+    #[error("label {label} is mapped again on line {line} of the label map")]
+    DuplicateLabel {
+        label: u8,
+        line: usize
+    },
+
+    #[error("the label map does not map any label")]
+    EmptyLabelMap
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::CannotReadLabelMap { .. } => "E1301",
+            ErrorKind::InvalidLine { .. } => "E1302",
+            ErrorKind::DuplicateLabel { .. } => "E1303",
+            ErrorKind::EmptyLabelMap => "E1304"
+        }
+    }
+}
+
+/// Classes a model is trained on, made of dataset labels. Each line of a
+/// label map file holds a dataset label (as the alphabet numbers it) and the
+/// name of its class; labels sharing a name are merged into one class, e.g.
+///
+/// ```text
+/// # EMNIST byclass: C and c look the same
+/// 12 c
+/// 38 c
+/// ```
+///
+/// Classes are numbered in the order their names first appear. Samples with
+/// a label that isn't listed are left out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelMap {
+    /// Class of every dataset label.
+    classes: Vec<Option<u8>>,
+    class_names: Vec<String>
+}
+
+impl LabelMap {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<LabelMap> {
+        let path = path.as_ref();
+        let content = read_to_string(path)
+            .map_err(|source| ErrorKind::CannotReadLabelMap { file: path.display().to_string(), source })?;
+
+        LabelMap::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<LabelMap> {
+        let mut classes = vec![None; u8::MAX as usize + 1];
+        let mut class_names: Vec<String> = Vec::new();
+
+        for (index, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let invalid_line = || ErrorKind::InvalidLine { line: index + 1, content: line.to_string() };
+            let (label, name) = line.split_once(char::is_whitespace).ok_or_else(invalid_line)?;
+            let label: u8 = label.parse().map_err(|_| invalid_line())?;
+            let name = name.trim();
+
+            if classes[label as usize].is_some() {
+                return Err(ErrorKind::DuplicateLabel { label, line: index + 1 })
+            }
+
+            let class = match class_names.iter().position(|class_name| class_name == name) {
+                Some(class) => class,
+                None => {
+                    class_names.push(name.to_string());
+                    class_names.len() - 1
+                }
+            };
+            classes[label as usize] = Some(class as u8);
+        }
+
+        if class_names.is_empty() {
+            return Err(ErrorKind::EmptyLabelMap)
+        }
+
+        Ok(LabelMap { classes, class_names })
+    }
+
+    pub fn class_count(&self) -> usize {
+        self.class_names.len()
+    }
+
+    pub fn class_names(&self) -> &[String] {
+        &self.class_names
+    }
+
+    pub fn map(&self, label: Label) -> Option<Label> {
+        self.classes[label.class() as usize].map(Label::new)
+    }
+
+    /// `sample` with its label mapped, or `None` if the label isn't listed.
+    pub fn apply(&self, sample: LabeledTrainingData) -> Option<LabeledTrainingData> {
+        let label = self.map(*sample.label())?;
+        let weight = sample.weight();
+        let (image, _) = sample.into_parts();
+
+        Some(LabeledTrainingData::new(image, label).with_weight(weight))
+    }
+}
@@ -0,0 +1,94 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+use log::warn;
+use nalgebra::DVector;
+use crate::data::ImageSize;
+use crate::network::{Classifier, Prediction, Result, Scalar};
+use crate::transform::Preprocessing;
+
+/// Weight of the latest measurement in the running latency average.
+const LATENCY_SMOOTHING: f64 = 0.5;
+
+/// Classifies with `primary` until its average per-inference latency
+/// exceeds the budget, then switches to the cheaper `fallback` for good.
+pub struct LatencyBudget {
+    primary: Box<dyn Classifier>,
+    fallback: Option<Box<dyn Classifier>>,
+    budget: Duration,
+    average_latency: Cell<Option<f64>>,
+    degraded: Cell<bool>
+}
+
+impl LatencyBudget {
+    pub fn new(primary: Box<dyn Classifier>,
+               fallback: Option<Box<dyn Classifier>>,
+               budget: Duration) -> LatencyBudget {
+        LatencyBudget {
+            primary,
+            fallback,
+            budget,
+            average_latency: Cell::new(None),
+            degraded: Cell::new(false)
+        }
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.get()
+    }
+
+    fn active(&self) -> &dyn Classifier {
+        return match &self.fallback {
+            Some(fallback) if self.degraded.get() => fallback.as_ref(),
+            _ => self.primary.as_ref()
+        }
+    }
+
+    fn record(&self, latency: Duration) {
+        let latency = latency.as_secs_f64();
+        let average = match self.average_latency.get() {
+            Some(average) => average + LATENCY_SMOOTHING * (latency - average),
+            None => latency
+        };
+        self.average_latency.set(Some(average));
+
+        if average > self.budget.as_secs_f64() && self.fallback.is_some() && !self.degraded.get() {
+            warn!("inference takes {:.1}ms on average, over the {}ms budget; switching to the faster model",
+                  average * 1000.0, self.budget.as_millis());
+            self.degraded.set(true);
+        }
+    }
+}
+
+impl Classifier for LatencyBudget {
+    fn input_size(&self) -> ImageSize {
+        self.active().input_size()
+    }
+
+    fn output_size(&self) -> usize {
+        self.active().output_size()
+    }
+
+    fn class_name(&self, class: usize) -> String {
+        self.active().class_name(class)
+    }
+
+    fn rejection_threshold(&self) -> Option<f64> {
+        self.primary.rejection_threshold()
+    }
+
+    fn preprocessing(&self) -> Preprocessing {
+        self.active().preprocessing()
+    }
+
+    fn predict(&self, input: DVector<Scalar>) -> Result<Prediction> {
+        if self.degraded.get() {
+            return self.active().predict(input)
+        }
+
+        let started = Instant::now();
+        let prediction = self.primary.predict(input)?;
+        self.record(started.elapsed());
+
+        Ok(prediction)
+    }
+}
@@ -1,34 +1,114 @@
+// early returns in `match` expressions are the house style
+#![allow(clippy::needless_return)]
+
 extern crate core;
 
-use std::{env, io};
-use std::fs::File;
-use std::ops::Deref;
+use std::{env, fs, io};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+use std::str::FromStr;
 use args::Args;
 use getopts::Occur;
+use log::{info, LevelFilter, warn};
 use nalgebra::DVector;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rayon::prelude::*;
 use thiserror::Error;
-use crate::network::{INPUT_LAYER_SIZE, NeuralNetwork};
-use crate::training_data::TrainingDataset;
+use crate::augmentation::{Augmentation, Blur, ElasticDistortion, GaussianNoise, SaltAndPepper};
+use crate::backend::ComputeBackend;
+use crate::benchmark::{ModelSource, RecordComparison};
+use crate::bundle::Bundle;
+use crate::checksum::{ChecksumManifest, sha256};
+use crate::classes::{Alphabet, DigitScript};
+use crate::data::{Image, ImageSize};
+use crate::dataset::contact_sheet::contact_sheet;
+use crate::dataset::duplicates::{Duplicate, find_duplicates, find_duplicates_between};
+use crate::dataset::synthetic::{self, load_fonts, SyntheticDigits, SYSTEM_FONT_DIRS};
+use crate::dataset::write::{self, open_sink, SampleSink};
+use crate::gui::{CollectControls, ImageLoader, ProfileControls};
+use crate::i18n::{tr, tr_args};
+use crate::features::{InputFeatures, Normalization};
+use crate::ensemble::{Ensemble, Voting};
+use crate::external::ExternalPredictions;
+use crate::label_map::LabelMap;
+use crate::latency::LatencyBudget;
+use crate::mnist::MnistSplit;
+use crate::multiscale::{DEFAULT_SCALES, predict_multiscale};
+use crate::profiles::ProfileStore;
+use crate::network::{Classifier, DEFAULT_INPUT_SIZE, LayerEvent, NeuralNetwork, Scalar};
+use crate::quantization::QuantizedNetwork;
+use crate::tensors::{TensorFormat, Tensors};
+use crate::search::{ArchitectureSearch, SearchSpace, Sweep, SweepConfig, TrainingConfig};
+use crate::runs::{create_run_dir, latest_best_model, run_model_path};
+use crate::training::{DEFAULT_LEARNING_RATE, best_model_path, calibrate_temperature, evaluate_stream, input_vector, CallbackAction, class_counts, ClassWeights, Checkpoint, CyclePolicy, CyclicalSchedule, Distillation, EpochMetrics, LearningRateSweep, Perturbation, PerturbationKind, PlateauReduction, Sampling, Trainer, TrainingCallback, TrainingHistory};
+use crate::view_model::Answer;
+use crate::training_data::{InMemoryDataset, Label, LabeledTrainingData, load_class_names, load_sample_weights, load_samples, merge_samples, SampleIterExt, split_samples, stream_samples, Subset};
+use crate::transform::{Invert, Preprocessing, Transform};
 
-mod training_data;
+pub mod adversarial;
+pub mod augmentation;
+pub mod benchmark;
+pub mod bundle;
+pub mod checksum;
+pub mod classes;
+pub mod dataset;
+pub mod ensemble;
+pub mod error_codes;
+pub mod external;
+pub mod training;
+pub mod training_data;
+pub mod transform;
 mod interactive_canvas_widget;
 mod gui;
-mod data;
+pub mod data;
+pub mod features;
+pub mod i18n;
+pub mod label_map;
+pub mod detection;
 mod io_ext;
-mod network;
+pub mod autograd;
+pub mod backend;
+pub mod latency;
+pub mod mnist;
+pub mod multiscale;
+pub mod network;
+pub mod profiles;
+pub mod quantization;
+pub mod runs;
+pub mod search;
+pub mod sparse;
+pub mod view_model;
+pub mod tensors;
+#[cfg(feature = "gpu")]
+pub mod wgpu_backend;
 
 static PROGRAM_NAME: &str = "digit_recognition";
-static PROGRAM_DESCRIPTION: &str =
-    "A simple AI-driven program for classifying pictures based on what digit is written on them";
 
 static KEY_IMAGES_FILE: &str = "IMAGES";
 static KEY_LABELS_FILE: &str = "LABELS";
+static KEY_VALIDATION_IMAGES_FILE: &str = "VALIDATION_IMAGES";
+static KEY_VALIDATION_LABELS_FILE: &str = "VALIDATION_LABELS";
+
+static DEFAULT_MODEL_FILE: &str = "neural_network_4.json";
+
+/// Resolution of the canvas for `--multiscale`, relative to the model's input size.
+const MULTISCALE_CANVAS_FACTOR: u32 = 4;
+
+/// Images on an `--export-grid` contact sheet without `--limit`.
+const DEFAULT_GRID_IMAGES: usize = 100;
 
 #[derive(Error, Debug)]
 pub enum ErrorKind {
     #[error(transparent)]
     GuiError(#[from] gui::ErrorKind),
 
+    #[error(transparent)]
+    AdversarialError(#[from] adversarial::ErrorKind),
+
     #[error(transparent)]
     NeuralNetworkError(#[from] network::ErrorKind),
 
@@ -38,139 +118,2777 @@ pub enum ErrorKind {
     #[error("cannot read training dataset ({0})")]
     CannotReadTrainingDataset(#[from] io::Error),
 
+    #[error(transparent)]
+    BackendError(#[from] backend::ErrorKind),
+
+    #[error(transparent)]
+    BenchmarkError(#[from] benchmark::ErrorKind),
+
+    #[error(transparent)]
+    BundleError(#[from] bundle::ErrorKind),
+
+    #[error("dataset verification failed ({0})")]
+    ChecksumError(#[from] checksum::ErrorKind),
+
+    #[error(transparent)]
+    DetectionError(#[from] detection::ErrorKind),
+
+    #[error(transparent)]
+    FeaturesError(#[from] features::ErrorKind),
+
+    #[error(transparent)]
+    EnsembleError(#[from] ensemble::ErrorKind),
+
+    #[error(transparent)]
+    ExternalPredictionsError(#[from] external::ErrorKind),
+
+    #[error(transparent)]
+    TrainingError(#[from] training::ErrorKind),
+
+    #[error(transparent)]
+    ProfilesError(#[from] profiles::ErrorKind),
+
+    #[error(transparent)]
+    SearchError(#[from] search::ErrorKind),
+
+    #[error(transparent)]
+    RunsError(#[from] runs::ErrorKind),
+
+    #[error(transparent)]
+    ClassesError(#[from] classes::ErrorKind),
+
+    #[error(transparent)]
+    TensorsError(#[from] tensors::ErrorKind),
+
+    #[error(transparent)]
+    LabelMapError(#[from] label_map::ErrorKind),
+
+    #[error(transparent)]
+    MnistError(#[from] mnist::ErrorKind),
+
+    #[error(transparent)]
+    DatasetWriteError(#[from] write::ErrorKind),
+
+    #[error(transparent)]
+    SyntheticDatasetError(#[from] synthetic::ErrorKind),
+
+    #[error("model has {model_classes} output classes, but the selected alphabet has {alphabet_classes}; train it into a different --model file")]
+    ClassCountMismatch {
+        model_classes: usize,
+        alphabet_classes: usize
+    },
+
+    #[error("more than one --model is only supported when showing the GUI")]
+    MultipleModels,
+
+    #[error("--sweep requires a validation set (--validation-images and --validation-labels) to rank the models")]
+    SweepRequiresValidationSet,
+
+    #[error("--calibrate requires a validation set (--validation-images and --validation-labels, or --validation-split)")]
+    CalibrationRequiresValidationSet,
+
+    #[error("unknown error code `{0}`")]
+    UnknownErrorCode(String),
+
+    #[error("cannot save image {file} ({source})")]
+    CannotSaveImage {
+        file: String,
+        source: io::Error
+    },
+
+    #[error("invalid value `{value}` for --{option}")]
+    InvalidOptionValue {
+        option: &'static str,
+        value: String
+    },
+
+    #[error("--{option} cannot be combined with --{other}")]
+    ConflictingOptions {
+        option: &'static str,
+        other: &'static str
+    },
+
     #[error(transparent)]
     CliError(#[from] args::ArgsError)
 }
 
 pub type Result<T> = std::result::Result<T, ErrorKind>;
 
+impl ErrorKind {
+    /// Stable code of the error, described in [`error_codes::ERROR_CODES`].
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::GuiError(err) => err.code(),
+            ErrorKind::AdversarialError(err) => err.code(),
+            ErrorKind::NeuralNetworkError(err) => err.code(),
+            ErrorKind::FailedToReadTrainingDataset(err) => err.code(),
+            ErrorKind::CannotReadTrainingDataset(_) => "E0003",
+            ErrorKind::BackendError(err) => err.code(),
+            ErrorKind::BenchmarkError(err) => err.code(),
+            ErrorKind::BundleError(err) => err.code(),
+            ErrorKind::ChecksumError(err) => err.code(),
+            ErrorKind::DetectionError(err) => err.code(),
+            ErrorKind::FeaturesError(err) => err.code(),
+            ErrorKind::EnsembleError(err) => err.code(),
+            ErrorKind::ExternalPredictionsError(err) => err.code(),
+            ErrorKind::TrainingError(err) => err.code(),
+            ErrorKind::ProfilesError(err) => err.code(),
+            ErrorKind::SearchError(err) => err.code(),
+            ErrorKind::RunsError(err) => err.code(),
+            ErrorKind::ClassesError(err) => err.code(),
+            ErrorKind::TensorsError(err) => err.code(),
+            ErrorKind::LabelMapError(err) => err.code(),
+            ErrorKind::MnistError(err) => err.code(),
+            ErrorKind::DatasetWriteError(err) => err.code(),
+            ErrorKind::SyntheticDatasetError(err) => err.code(),
+            ErrorKind::ClassCountMismatch { .. } => "E0303",
+            ErrorKind::MultipleModels => "E0302",
+            ErrorKind::SweepRequiresValidationSet => "E0305",
+            ErrorKind::CalibrationRequiresValidationSet => "E0304",
+            ErrorKind::UnknownErrorCode(_) => "E0307",
+            ErrorKind::CannotSaveImage { .. } => "E0308",
+            ErrorKind::InvalidOptionValue { .. } => "E0301",
+            ErrorKind::ConflictingOptions { .. } => "E0309",
+            ErrorKind::CliError(_) => "E0306"
+        }
+    }
+}
+
+struct GuiOption {
+    model_files: Vec<String>,
+    latest_run_of: Option<String>,
+    voting: Voting,
+    backend: ComputeBackend,
+    quantized: bool,
+    multiscale: bool,
+    grid_cells: u32,
+    max_latency: Option<Duration>,
+    profiles_dir: Option<String>,
+    profile: Option<String>,
+    /// Images and labels file of `--collect`.
+    collect_files: Option<(String, String)>,
+    /// Forward passes and dropout rate of `--mc-dropout`.
+    mc_dropout: Option<(usize, f64)>
+}
+
 struct TrainingOption {
+    model_file: String,
+    runs_dir: Option<String>,
+    images_file: String,
+    labels_file: String,
+    epochs: u32,
+    learning_rate: f64,
+    layer_multipliers: Vec<f64>,
+    frozen_layers: Vec<usize>,
+    plateau_reduction: Option<PlateauReduction>,
+    warmup_batches: usize,
+    cyclical_schedule: Option<CyclicalSchedule>,
+    augmentation: Option<Augmentation>,
+    perturbation: Option<Perturbation>,
+    shuffle: bool,
+    preprocess: bool,
+    normalize: bool,
+    /// Teacher model file, temperature and weight of the hard targets.
+    distillation: Option<(String, f64, f64)>,
+    learning_rate_sweep: Option<(String, LearningRateSweep)>,
+    max_duration: Option<Duration>,
+    ema_decay: Option<f64>,
+    class_weights: Option<ClassWeights>,
+    sampling: Option<Sampling>,
+    sample_weights_file: Option<String>,
+    subset: Subset,
+    skip_invalid: bool,
+    /// Images and labels file of a second training set, and the weight of its samples.
+    extra_source: Option<(String, String, f64)>,
+    batch_size: usize,
+    threads: usize,
+    checkpoint_interval: usize,
+    resume_file: Option<String>,
+    init_file: Option<String>,
+    architecture_file: Option<String>,
+    validation_files: Option<(String, String)>,
+    validation_split: Option<f64>,
+    alphabet: Alphabet,
+    label_map: Option<LabelMap>,
+    input_features: InputFeatures,
+    class_names: Option<Vec<String>>,
+    rejection_threshold: Option<f64>,
+    detect_nan: bool,
+    rollback_on_divergence: bool,
+    seed: Option<u64>,
+    calibrate: bool,
+    manifest_file: Option<String>,
+    backend: ComputeBackend
+}
+
+struct BenchmarkOption {
+    model_file: String,
+    data_dir: String,
+    from_scratch: bool,
+    epochs: u32,
+    manifest_file: Option<String>,
+    history_file: Option<String>,
+    seed: Option<u64>,
+    backend: ComputeBackend
+}
+
+struct SearchOption {
+    output_file: String,
+    images_file: String,
+    labels_file: String,
+    validation_files: Option<(String, String)>,
+    alphabet: Alphabet,
+    label_map: Option<LabelMap>,
+    sample_weights_file: Option<String>,
+    subset: Subset,
+    skip_invalid: bool,
+    input_features: InputFeatures,
+    search: ArchitectureSearch,
+    seed: Option<u64>
+}
+
+struct SweepOption {
+    model_file: String,
+    validation_files: (String, String),
+    alphabet: Alphabet,
+    label_map: Option<LabelMap>,
+    /// Options every training process of the sweep gets.
+    training_args: Vec<String>,
+    sweep: Sweep,
+    seed: Option<u64>
+}
+
+struct ComparisonOption {
+    model_file: String,
+    images_file: String,
+    labels_file: String,
+    predictions_file: String,
+    alphabet: Alphabet,
+    backend: ComputeBackend
+}
+
+struct EvaluationOption {
+    model_file: String,
+    images_file: String,
+    labels_file: String,
+    alphabet: Alphabet,
+    label_map: Option<LabelMap>,
+    subset: Subset,
+    skip_invalid: bool,
+    batch_size: usize,
+    threads: usize,
+    backend: ComputeBackend
+}
+
+struct ExportTensorsOption {
+    output_file: String,
+    format: TensorFormat,
+    images_file: String,
+    labels_file: String,
+    alphabet: Alphabet,
+    label_map: Option<LabelMap>,
+    sample_weights_file: Option<String>,
+    subset: Subset,
+    skip_invalid: bool,
+    input_features: InputFeatures
+}
+
+struct ExportGridOption {
+    output_file: String,
+    images_file: String,
+    labels_file: String,
+    alphabet: Alphabet,
+    subset: Subset,
+    columns: u32,
+    augmentation: Option<Augmentation>,
+    preprocess: bool,
+    seed: Option<u64>
+}
+
+struct FindDuplicatesOption {
+    images_file: String,
+    labels_file: String,
+    /// Checked for images of the dataset, e.g. a test set against a training set.
+    other_files: Option<(String, String)>,
+    alphabet: Alphabet,
+    max_distance: u32
+}
+
+struct ConvertDatasetOption {
     images_file: String,
-    labels_file: String
+    labels_file: String,
+    alphabet: Alphabet,
+    subset: Subset,
+    skip_invalid: bool,
+    output_files: (String, String)
+}
+
+struct QuantizeOption {
+    model_file: String,
+    output_file: String
+}
+
+struct ConvertOption {
+    model_file: String,
+    output_file: String
+}
+
+struct DetectOption {
+    model_file: String,
+    image_file: String,
+    min_confidence: f64,
+    backend: ComputeBackend
+}
+
+struct FetchMnistOption {
+    dir: String,
+    mirror: String
+}
+
+struct SynthesizeMnistOption {
+    dir: String,
+    font_dirs: Vec<String>,
+    samples: usize,
+    seed: Option<u64>
+}
+
+struct AdversarialOption {
+    model_file: String,
+    images_file: String,
+    labels_file: String,
+    alphabet: Alphabet,
+    output_dir: String,
+    epsilon: f64,
+    start: usize,
+    backend: ComputeBackend
+}
+
+struct PruneOption {
+    model_file: String,
+    threshold: f64
+}
+
+struct BundleOption {
+    model_file: String,
+    bundle_file: String
+}
+
+struct ConsoleProgress {
+    checkpoint_file: PathBuf
+}
+
+impl TrainingCallback for ConsoleProgress {
+    fn on_epoch_end(&mut self, metrics: &EpochMetrics) -> CallbackAction {
+        print!("epoch {}: loss {:.4}, accuracy {:.2}%",
+               metrics.epoch, metrics.loss, metrics.accuracy*100.0);
+
+        match (metrics.validation_accuracy, metrics.validation_loss) {
+            (Some(accuracy), Some(loss)) =>
+                println!(", validation loss {:.4}, validation accuracy {:.2}%", loss, accuracy*100.0),
+            (Some(accuracy), None) => println!(", validation accuracy {:.2}%", accuracy*100.0),
+            _ => println!()
+        }
+
+        CallbackAction::Continue
+    }
+
+    fn on_learning_rate_reduced(&mut self, epoch: u32, learning_rate: f64) {
+        println!("epoch {}: loss stopped improving, reducing learning rate to {}", epoch, learning_rate);
+    }
+
+    fn on_time_limit_reached(&mut self, completed_epochs: u32, position: usize) {
+        println!("time limit reached after {} epochs and {} examples of epoch {}; continue with --resume {}",
+                 completed_epochs, position, completed_epochs + 1, self.checkpoint_file.display());
+    }
 }
 
 enum Action {
-    ShowGui,
-    Train(TrainingOption)
+    Explain(String),
+    ShowGui(GuiOption),
+    Train(Box<TrainingOption>),
+    BenchmarkSuite(BenchmarkOption),
+    SearchArchitecture(SearchOption),
+    Sweep(SweepOption),
+    ComparePredictions(ComparisonOption),
+    Info(String),
+    Evaluate(EvaluationOption),
+    ExportTensors(ExportTensorsOption),
+    ExportGrid(ExportGridOption),
+    FindDuplicates(FindDuplicatesOption),
+    ConvertDataset(ConvertDatasetOption),
+    FetchMnist(FetchMnistOption),
+    SynthesizeMnist(SynthesizeMnistOption),
+    Quantize(QuantizeOption),
+    ConvertModel(ConvertOption),
+    Detect(DetectOption),
+    Adversarial(AdversarialOption),
+    Prune(PruneOption),
+    ExportBundle(BundleOption),
+    ImportBundle(BundleOption)
 }
 
 pub fn launch() -> Result<()> {
-    let action = parse_args()?;
-    let mut neural_network =
-        NeuralNetwork::load("neural_network_4.json")
-            .unwrap_or(NeuralNetwork::new_untrained());
+    let (action, log_level) = parse_args()?;
 
-    let mut correct_answers = 0;
-    let mut incorrect_answers = 0;
+    // RUST_LOG takes precedence, e.g. to enable tracing for a single module
+    env_logger::Builder::new()
+        .filter_level(log_level)
+        .parse_default_env()
+        .init();
 
     match action {
-        Action::ShowGui => gui::launch(move |img_loader| {
-            let image = img_loader.load_image(28).unwrap();
-            let image_pixels = image.pixels();
-            let input =
-                DVector::from_iterator(28*28, image_pixels.iter()
-                    .map(|x| (1.0 - ((*x as f64) / 255.0)) - 0.5));
-
-            let output = neural_network.compute(input);
-            let (digit, chance) = output
-                .as_slice()
-                .iter()
-                .enumerate()
-                .fold((0u8, f64::NEG_INFINITY), |(acc_i, acc_v), (i, x)| {
-                    let (i, x) = (i as u8, *x);
-                    return if x > acc_v {
-                        (i, x)
-                    } else {
-                        (acc_i, acc_v)
+        Action::Explain(code) => {
+            let entry = error_codes::explain(&code).ok_or(ErrorKind::UnknownErrorCode(code))?;
+            println!("{}: {}\n\n{}", entry.code, entry.summary, entry.remedy);
+        },
+        Action::ShowGui(opts) => {
+            let model_files = match &opts.latest_run_of {
+                Some(runs_dir) => vec![latest_best_model(runs_dir)?.display().to_string()],
+                None => opts.model_files
+            };
+
+            // a canvas of several cells isn't one sample
+            if opts.grid_cells > 1 && opts.collect_files.is_some() {
+                warn!("--collect saves single digits, so it is ignored with --grid-cells");
+            }
+            let collect_files = opts.collect_files.as_ref().filter(|_| opts.grid_cells <= 1);
+
+            if let Some((passes, rate)) = opts.mc_dropout {
+                let mut network = NeuralNetwork::load(single_model_file(&model_files)?)?;
+                network.set_compute_backend(opts.backend);
+                let rejection_threshold = network.rejection_threshold().unwrap_or(0.0);
+                let about = network.summary().to_string();
+
+                let collector = collect_files.map(|files| DrawingCollector::open(files, &network));
+                let collect_controls = collector.as_ref().map(DrawingCollector::controls);
+
+                let rng = RefCell::new(StdRng::from_entropy());
+                let grid_cells = opts.grid_cells;
+                gui::launch(rejection_threshold, grid_cells, None, collect_controls, Some(about), move |img_loader| {
+                    if let Some(collector) = &collector {
+                        collector.borrow_mut().remember_drawing(&img_loader);
                     }
-                });
+                    classify_canvas_mc_dropout(&network, &img_loader, grid_cells, passes, rate, &mut rng.borrow_mut())
+                })?;
 
-            (digit, chance)
-        })?,
-        Action::Train(opts) => {
-            let images = File::open(opts.images_file)?;
-            let labels = File::open(opts.labels_file)?;
+                return Ok(())
+            }
+
+            if let Some(profiles_dir) = &opts.profiles_dir {
+                let store = ProfileStore::open(profiles_dir, single_model_file(&model_files)?)?;
+                let mut names = store.names()?;
+                let selected = match (&opts.profile, names.first()) {
+                    (Some(profile), _) => profile.clone(),
+                    (None, Some(name)) => name.clone(),
+                    (None, None) => String::from(profiles::DEFAULT_PROFILE)
+                };
+                if !names.contains(&selected) {
+                    names.push(selected.clone());
+                }
+
+                let mut network = store.load_model(&selected)?;
+                network.set_compute_backend(opts.backend);
+                let rejection_threshold = network.rejection_threshold().unwrap_or(0.0);
+                let about = network.summary().to_string();
+                // profiles are tuned copies of one model, so they share its classes
+                let collector = collect_files.map(|files| DrawingCollector::open(files, &network));
+                let collect_controls = collector.as_ref().map(DrawingCollector::controls);
+
+                let session = Rc::new(RefCell::new(ProfileSession {
+                    store,
+                    profile: selected.clone(),
+                    network,
+                    backend: opts.backend,
+                    last_drawing: None
+                }));
+
+                let teaching_session = session.clone();
+                let controls = ProfileControls {
+                    names,
+                    selected,
+                    on_teach: Box::new(move |profile, answer| {
+                        teaching_session.borrow_mut().teach(profile, answer)
+                            .unwrap_or_else(|err| err.to_string())
+                    })
+                };
+
+                let (multiscale, grid_cells) = (opts.multiscale, opts.grid_cells);
+                gui::launch(rejection_threshold, grid_cells, Some(controls), collect_controls, Some(about), move |img_loader| {
+                    if let Some(collector) = &collector {
+                        collector.borrow_mut().remember_drawing(&img_loader);
+                    }
+                    let mut session = session.borrow_mut();
+                    // the drawing is still classified, by the profile in use so far;
+                    // teaching the new one reports the error in the window
+                    if let Err(err) = session.switch_to(img_loader.profile()) {
+                        warn!("cannot switch to profile {} ({})", img_loader.profile(), err);
+                    }
+                    session.remember_drawing(&img_loader);
+                    classify_canvas(&session.network, &img_loader, grid_cells, multiscale)
+                })?;
+
+                return Ok(())
+            }
+
+            // the fallback is only used when inference is over the latency budget
+            let (classifier, fallback, about): (Box<dyn Classifier>, Option<Box<dyn Classifier>>, _) =
+                match model_files.as_slice() {
+                    [model_file] if opts.quantized => (Box::new(QuantizedNetwork::load(model_file)?), None, None),
+                    [model_file] => {
+                        let mut network = load_or_untrained(model_file, || {
+                            NeuralNetwork::new_untrained(DEFAULT_INPUT_SIZE, Alphabet::default().class_count())
+                        })?;
+                        network.set_compute_backend(opts.backend);
 
-            let dataset =
-                TrainingDataset::from_readers(images, labels)?;
+                        let fallback = QuantizedNetwork::from_network(&network);
+                        let about = network.summary().to_string();
+                        (Box::new(network), Some(Box::new(fallback)), Some(about))
+                    },
+                    model_files => {
+                        let mut ensemble = Ensemble::load(model_files, opts.voting)?;
+                        ensemble.set_compute_backend(opts.backend);
 
-            let dataset_size = dataset.size();
+                        let fallback = QuantizedNetwork::from_network(&ensemble.members()[0]);
+                        let about = model_files.iter().zip(ensemble.members())
+                            .map(|(model_file, member)| format!("{}\n{}", model_file, member.summary()))
+                            .collect::<Vec<String>>()
+                            .join("\n");
+                        (Box::new(ensemble), Some(Box::new(fallback)), Some(about))
+                    }
+                };
 
-            let mut count = vec![0; 10];
+            let classifier: Box<dyn Classifier> = match opts.max_latency {
+                Some(budget) => Box::new(LatencyBudget::new(classifier, fallback, budget)),
+                None => classifier
+            };
 
-            for (i, example) in dataset.take(60_000).enumerate() {
-                match example {
-                    Ok(example) => {
-                        let pixels =
-                            DVector::from_iterator(28*28, example
-                                .image()
-                                .pixels()
-                                .iter()
-                                .map(|px| ((*px as f64) / 255.0) - 0.5));
+            let collector = collect_files.map(|files| DrawingCollector::open(files, classifier.as_ref()));
+            let collect_controls = collector.as_ref().map(DrawingCollector::controls);
 
-                        println!("input: {}", pixels.mean());
+            let rejection_threshold = classifier.rejection_threshold().unwrap_or(0.0);
+            let multiscale = opts.multiscale;
+            let grid_cells = opts.grid_cells;
+            gui::launch(rejection_threshold, grid_cells, None, collect_controls, about, move |img_loader| {
+                if let Some(collector) = &collector {
+                    collector.borrow_mut().remember_drawing(&img_loader);
+                }
+                classify_canvas(classifier.as_ref(), &img_loader, grid_cells, multiscale)
+            })?
+        },
+        Action::Train(opts) => {
+            if let Some(manifest_file) = &opts.manifest_file {
+                let manifest = ChecksumManifest::load(manifest_file)?;
+                manifest.verify(&opts.images_file)?;
+                manifest.verify(&opts.labels_file)?;
+                if let Some((images_file, labels_file)) = &opts.validation_files {
+                    manifest.verify(images_file)?;
+                    manifest.verify(labels_file)?;
+                }
+                if let Some((images_file, labels_file, _)) = &opts.extra_source {
+                    manifest.verify(images_file)?;
+                    manifest.verify(labels_file)?;
+                }
+            }
 
-                        let mut expected_output = DVector::zeros(10);
-                        expected_output[example.label().digit() as usize] = 1.0;
+            let alphabet = opts.alphabet;
+            let samples = load_training_samples(&opts.images_file,
+                                                &opts.labels_file,
+                                                alphabet,
+                                                opts.sample_weights_file.as_deref(),
+                                                opts.subset,
+                                                opts.skip_invalid)?;
+            let samples = match &opts.extra_source {
+                Some((images_file, labels_file, weight)) => {
+                    let extra = stream_samples(images_file, labels_file, alphabet)?
+                        .weighted(*weight)
+                        .collect::<training_data::Result<Vec<_>>>()?;
+                    println!("training on {} more samples from {} with weight {}", extra.len(), images_file, weight);
+                    merge_samples(vec![samples, extra])
+                },
+                None => samples
+            };
+            let samples = remap_samples(samples, opts.label_map.as_ref());
+            // held out before anything else sees the samples, and the same on every run
+            let (samples, held_out) = match (opts.validation_split, &opts.validation_files) {
+                (Some(fraction), None) => split_samples(samples, fraction, opts.seed.unwrap_or_default())?,
+                _ => (samples, Vec::new())
+            };
+            let class_count = opts.label_map.as_ref().map_or(alphabet.class_count(), LabelMap::class_count);
 
-                        //neural_network.train(pixels, &expected_output);
-                        let res = neural_network.compute(pixels);
+            let input_size = samples
+                .first()
+                .map_or(DEFAULT_INPUT_SIZE, |sample| sample.image().size());
 
-                        println!("expected: {}, found: {}.", example.label().digit(), res.argmax().0);
+            let config = match &opts.architecture_file {
+                Some(architecture_file) => Some(TrainingConfig::load(architecture_file)?),
+                None => None
+            };
+            let architecture = config.as_ref().map(|config| config.architecture.clone()).unwrap_or_default();
+            let learning_rate = config.as_ref().map_or(opts.learning_rate, |config| config.learning_rate);
 
-                        if example.label().digit() == (res.argmax().0 as u8) {
-                            correct_answers += 1;
-                        } else {
-                            incorrect_answers += 1;
-                        }
+            let (checkpoint_network, resume_point) = match &opts.resume_file {
+                Some(resume_file) => {
+                    let (network, resume_point) = Checkpoint::load(resume_file)?.into_parts();
+                    (Some(network), Some(resume_point))
+                },
+                None => (None, None)
+            };
 
-                        let completion = ((i+1) as f64) / (dataset_size as f64);
-                        println!("Finished {} training examples ({:.2}%)", i+1, completion*100.0);
+            let mut rng = seeded_rng(opts.seed);
+            let mut neural_network = match (checkpoint_network, &opts.init_file) {
+                // a resumed run goes on with the weights of the checkpoint,
+                // configured for this run below like a loaded model
+                (Some(network), _) => network,
+                (None, Some(init_file)) => {
+                    let mut network = NeuralNetwork::new_untrained_with_rng(&mut rng,
+                                                                            input_size,
+                                                                            class_count,
+                                                                            opts.input_features,
+                                                                            &architecture);
+                    let copied = network.load_partial(init_file)?;
+                    println!("initialized {} of {} layers from {}", copied, network.layer_count(), init_file);
+                    network
+                },
+                (None, None) => load_or_untrained(&opts.model_file, || {
+                    NeuralNetwork::new_untrained_with_rng(&mut rng,
+                                                          input_size,
+                                                          class_count,
+                                                          opts.input_features,
+                                                          &architecture)
+                })?
+            };
+            if neural_network.output_size() != class_count {
+                return Err(ErrorKind::ClassCountMismatch {
+                    model_classes: neural_network.output_size(),
+                    alphabet_classes: class_count
+                })
+            }
 
-                        count[example.label().digit() as usize] += 1;
+            neural_network.set_compute_backend(opts.backend);
+            let class_names = match (opts.class_names, &opts.label_map) {
+                (Some(names), _) => names,
+                (None, Some(label_map)) => label_map.class_names().to_vec(),
+                // names of the dataset's sidecar file are used if they fit its classes
+                (None, None) => match load_class_names(&opts.images_file, &opts.labels_file)? {
+                    Some(names) if names.len() == class_count => names,
+                    Some(names) => {
+                        warn!("ignored the {} class names of the dataset, which has {} classes", names.len(), class_count);
+                        alphabet.class_names()
                     },
-                    Err(_) => break
+                    None => alphabet.class_names()
+                }
+            };
+            neural_network.set_class_names(class_names);
+            if opts.rejection_threshold.is_some() {
+                neural_network.set_rejection_threshold(opts.rejection_threshold);
+            }
+
+            // a model trained further keeps the preprocessing it was trained with
+            if opts.preprocess {
+                neural_network.set_preprocessing(Preprocessing::MNIST);
+            }
+            let preprocessing = neural_network.preprocessing();
+            // images of another size than the network's input are resized to it
+            let input_size = neural_network.input_size();
+            let samples = prepare_samples(samples, preprocessing, input_size);
+            let held_out = prepare_samples(held_out, preprocessing, input_size);
+
+            // likewise the normalization, since the weights were fitted to it
+            if opts.normalize && neural_network.normalization().is_none() {
+                if let Some(normalization) = Normalization::of(&samples) {
+                    println!("normalizing inputs with mean {:.3} and standard deviation {:.3}",
+                             normalization.mean, normalization.std_dev);
+                    neural_network.set_normalization(Some(normalization));
+                }
+            }
+
+            if opts.detect_nan {
+                neural_network.add_hook(report_non_finite_values);
+            }
+
+            if let Some((csv_file, sweep)) = &opts.learning_rate_sweep {
+                let curve = sweep.run(&mut neural_network, &samples)?;
+                curve.save_csv(csv_file)?;
+
+                println!("recorded the loss at {} learning rates in {}", curve.points().len(), csv_file);
+                if let Some(learning_rate) = curve.suggested_learning_rate() {
+                    println!("suggested learning rate: {:.3e}", learning_rate);
+                }
+                return Ok(())
+            }
+
+            if opts.calibrate && opts.validation_files.is_none() && held_out.is_empty() {
+                return Err(ErrorKind::CalibrationRequiresValidationSet)
+            }
+
+            // a run directory keeps the outputs of each training run apart,
+            // while the network still starts from --model
+            let output_file = match &opts.runs_dir {
+                Some(runs_dir) => {
+                    let run_dir = create_run_dir(runs_dir)?;
+                    println!("saving this run to {}", run_dir.display());
+                    run_model_path(run_dir).display().to_string()
+                },
+                None => opts.model_file.clone()
+            };
+
+            let validation_samples = match &opts.validation_files {
+                Some((images_file, labels_file)) => prepare_samples(
+                    remap_samples(load_samples(images_file, labels_file, alphabet)?, opts.label_map.as_ref()),
+                    preprocessing,
+                    input_size),
+                None => held_out
+            };
+
+            let mut trainer_builder = Trainer::builder()
+                .with_epochs(opts.epochs)
+                .with_learning_rate(learning_rate)
+                .with_layer_learning_rate_multipliers(opts.layer_multipliers)
+                .with_frozen_layers(opts.frozen_layers)
+                .with_warmup(opts.warmup_batches)
+                .with_batch_size(opts.batch_size)
+                .with_threads(opts.threads)
+                .with_checkpoints(Checkpoint::path_for_model(&output_file),
+                                  opts.checkpoint_interval)
+                .with_validation_set(validation_samples)
+                .with_best_model(best_model_path(&output_file))
+                .with_callback(ConsoleProgress { checkpoint_file: Checkpoint::path_for_model(&output_file) });
+
+            if let Some(plateau_reduction) = opts.plateau_reduction {
+                trainer_builder = trainer_builder.with_plateau_reduction(plateau_reduction);
+            }
+
+            if let Some(schedule) = opts.cyclical_schedule {
+                trainer_builder = trainer_builder.with_cyclical_schedule(schedule);
+            }
+
+            if let Some(augmentation) = opts.augmentation {
+                trainer_builder = trainer_builder.with_augmentation(augmentation);
+            }
+
+            if opts.shuffle {
+                trainer_builder = trainer_builder.with_shuffling(opts.seed.unwrap_or_else(rand::random));
+            }
+
+            if let Some(sampling) = opts.sampling {
+                trainer_builder = trainer_builder.with_sampling(sampling, opts.seed.unwrap_or_else(rand::random));
+            }
+
+            if let Some(perturbation) = opts.perturbation {
+                trainer_builder = trainer_builder.with_perturbation(perturbation);
+            }
+
+            if let Some((teacher_file, temperature, hard_target_weight)) = &opts.distillation {
+                let distillation = Distillation::new(NeuralNetwork::load(teacher_file)?, *temperature)?
+                    .with_hard_target_weight(*hard_target_weight);
+                trainer_builder = trainer_builder.with_distillation(distillation);
+            }
+
+            if let Some(max_duration) = opts.max_duration {
+                trainer_builder = trainer_builder.with_max_duration(max_duration);
+            }
+
+            if let Some(decay) = opts.ema_decay {
+                trainer_builder = trainer_builder.with_ema(decay);
+            }
+
+            if let Some(class_weights) = &opts.class_weights {
+                let weights = class_weights.resolve(&samples, neural_network.output_size())?;
+                println!("class counts {:?}, loss weights {:.3?}",
+                         class_counts(&samples, neural_network.output_size()), weights);
+                trainer_builder = trainer_builder.with_class_weights(ClassWeights::Explicit(weights));
+            }
+
+            let mut trainer = trainer_builder.build();
+            let mut dataset = InMemoryDataset::from(samples);
+
+            let result = match resume_point {
+                Some(resume_point) => trainer.resume_from(&mut neural_network, resume_point, &mut dataset),
+                None => trainer.train_dataset(&mut neural_network, &mut dataset)
+            };
+
+            let history = match result {
+                Err(err @ training::ErrorKind::TrainingDiverged { .. }) if opts.rollback_on_divergence => {
+                    let checkpoint_file = Checkpoint::path_for_model(&output_file);
+                    if checkpoint_file.is_file() {
+                        let mut checkpoint = Checkpoint::load(&checkpoint_file)?;
+                        checkpoint.network_mut().save(&output_file)?;
+                        println!("rolled back to the last checkpoint, saved to {}", output_file);
+                    } else {
+                        println!("no checkpoint to roll back to, enable them with --checkpoint-every");
+                    }
+                    return Err(err.into())
+                },
+                result => result?
+            };
+
+            if opts.calibrate {
+                let temperature = calibrate_temperature(&mut neural_network, trainer.validation_set())?;
+                println!("calibrated softmax temperature: {:.3}", temperature);
+            }
+
+            neural_network.save(&output_file)?;
+            history.save(TrainingHistory::path_for_model(&output_file))?;
+        },
+        Action::BenchmarkSuite(opts) => {
+            let datasets = benchmark::standard_suite(&opts.data_dir);
+
+            if let Some(manifest_file) = &opts.manifest_file {
+                let manifest = ChecksumManifest::load(manifest_file)?;
+                // missing datasets are reported as skipped by the benchmark itself
+                for file in datasets.iter().flat_map(|dataset| dataset.files()) {
+                    if file.exists() {
+                        manifest.verify(file)?;
+                    }
+                }
+            }
+
+            let mut rng = seeded_rng(opts.seed);
+            let report = if opts.from_scratch {
+                benchmark::run(&datasets, &ModelSource::FromScratch {
+                    epochs: opts.epochs,
+                    backend: opts.backend
+                }, &mut rng)
+            } else {
+                let mut neural_network = NeuralNetwork::load(&opts.model_file)?;
+                neural_network.set_compute_backend(opts.backend);
+                benchmark::run(&datasets, &ModelSource::Existing(&neural_network), &mut rng)
+            };
+
+            print!("{}", report);
+
+            if let Some(history_file) = &opts.history_file {
+                let model_fingerprint = match opts.from_scratch {
+                    true => None,
+                    false => Some(sha256(&opts.model_file)?)
+                };
+
+                let record = report.to_record(model_fingerprint);
+                if let Some(previous) = benchmark::append_to_history(history_file, &record)? {
+                    print!("{}", RecordComparison { previous: &previous, current: &record });
+                }
+            }
+        },
+        Action::SearchArchitecture(opts) => {
+            let label_map = opts.label_map.as_ref();
+            let samples = load_training_samples(&opts.images_file,
+                                                &opts.labels_file,
+                                                opts.alphabet,
+                                                opts.sample_weights_file.as_deref(),
+                                                opts.subset,
+                                                opts.skip_invalid)?;
+            let mut samples = remap_samples(samples, label_map);
+            let validation_samples = match &opts.validation_files {
+                Some((images_file, labels_file)) =>
+                    remap_samples(load_samples(images_file, labels_file, opts.alphabet)?, label_map),
+                // without a validation set the last sixth of the training set is held out
+                None => samples.split_off(samples.len() - samples.len() / 6)
+            };
+
+            let input_size = samples
+                .first()
+                .map_or(DEFAULT_INPUT_SIZE, |sample| sample.image().size());
+
+            let results = opts.search.run(&mut seeded_rng(opts.seed),
+                                          input_size,
+                                          label_map.map_or(opts.alphabet.class_count(), LabelMap::class_count),
+                                          opts.input_features,
+                                          &samples,
+                                          &validation_samples)?;
+
+            println!("{:>10} {:>7}  candidate", "accuracy", "epochs");
+            for result in &results {
+                println!("{:>9.2}% {:>7}  {}", result.accuracy*100.0, result.epochs, result.config);
+            }
+
+            results[0].config.save(&opts.output_file)?;
+            println!("saved the best candidate to {}; train it with --train --architecture {}",
+                     opts.output_file, opts.output_file);
+        },
+        Action::Sweep(opts) => {
+            let (images_file, labels_file) = &opts.validation_files;
+            let validation_samples = remap_samples(load_samples(images_file, labels_file, opts.alphabet)?,
+                                                   opts.label_map.as_ref());
+
+            let program = env::current_exe().map_err(search::ErrorKind::CannotStartTraining)?;
+            let results = opts.sweep.run(&mut seeded_rng(opts.seed), program, &opts.training_args, &validation_samples)?;
+
+            println!("{:>4} {:>8} {:>13} {:>10} {:>10}", "rank", "hidden", "learning rate", "batch size", "accuracy");
+            for (rank, result) in results.iter().enumerate() {
+                println!("{:>4} {:>8} {:>13.3e} {:>10} {:>9.2}%",
+                         rank + 1, result.point.hidden_size, result.point.learning_rate,
+                         result.point.batch_size, result.accuracy*100.0);
+            }
+
+            let results_file = opts.sweep.dir.join("results.csv");
+            opts.sweep.save_results(&results, &results_file)?;
+            println!("saved the results to {}", results_file.display());
+
+            if let Some(best) = results.first() {
+                NeuralNetwork::load(&best.model_file)?.save(&opts.model_file)?;
+                println!("saved the best model to {}", opts.model_file);
+            }
+        },
+        Action::ComparePredictions(opts) => {
+            let mut neural_network = NeuralNetwork::load(&opts.model_file)?;
+            neural_network.set_compute_backend(opts.backend);
+
+            let samples = load_samples(&opts.images_file, &opts.labels_file, opts.alphabet)?;
+            let predictions = ExternalPredictions::load(&opts.predictions_file, neural_network.output_size())?;
+            print!("{}", external::compare(&neural_network, &samples, &predictions)?);
+        },
+        Action::Info(model_file) => {
+            print!("{}", NeuralNetwork::load(&model_file)?.summary());
+        },
+        Action::Evaluate(opts) => {
+            let mut neural_network = NeuralNetwork::load(&opts.model_file)?;
+            neural_network.set_compute_backend(opts.backend);
+
+            // the next batch is read and prepared while the current one is evaluated
+            let (preprocessing, input_size) = (neural_network.preprocessing(), neural_network.input_size());
+            let samples = stream_samples(&opts.images_file, &opts.labels_file, opts.alphabet)?;
+            let samples = skipping_invalid(samples, opts.skip_invalid)
+                .subset(opts.subset)
+                .map(move |sample| sample.map(|sample| preprocessing.prepare_sample(sample.resized(input_size))))
+                .prefetch(opts.batch_size.max(1));
+            let samples: Box<dyn Iterator<Item = _>> = match opts.label_map {
+                Some(label_map) => Box::new(samples.filter_map(move |sample| match sample {
+                    Ok(sample) => label_map.apply(sample).map(Ok),
+                    Err(err) => Some(Err(err))
+                })),
+                None => Box::new(samples)
+            };
+            let evaluation = evaluate_stream(&neural_network, samples, opts.batch_size, opts.threads)?;
+            println!("{}", evaluation);
+
+            if let Some(peak) = peak_resident_memory() {
+                info!("peak resident memory {:.1} MiB", peak as f64 / (1024.0 * 1024.0));
+            }
+        },
+        Action::ExportTensors(opts) => {
+            let samples = load_training_samples(&opts.images_file,
+                                                &opts.labels_file,
+                                                opts.alphabet,
+                                                opts.sample_weights_file.as_deref(),
+                                                opts.subset,
+                                                opts.skip_invalid)?;
+            let samples = remap_samples(samples, opts.label_map.as_ref());
+
+            let tensors = Tensors::from_samples(&samples, opts.input_features);
+            tensors.save(&opts.output_file, opts.format)?;
+            println!("saved {} samples of {} values to {}", tensors.sample_count(), tensors.input_len(), opts.output_file);
+        },
+        Action::ExportGrid(opts) => {
+            let samples = stream_samples(&opts.images_file, &opts.labels_file, opts.alphabet)?
+                .subset(opts.subset)
+                .collect::<training_data::Result<Vec<_>>>()?;
+
+            // prepared and augmented as for training
+            let transform = opts.preprocess.then_some(Preprocessing::MNIST).then(opts.augmentation);
+            let mut rng = seeded_rng(opts.seed);
+            let samples: Vec<LabeledTrainingData> = samples
+                .into_iter()
+                .map(|sample| {
+                    let mut image = sample.image().clone();
+                    transform.apply(&mut image, &mut rng);
+                    LabeledTrainingData::new(image, *sample.label())
+                })
+                .collect();
+
+            contact_sheet(&samples, opts.columns)
+                .save_png(&opts.output_file)
+                .map_err(|source| ErrorKind::CannotSaveImage { file: opts.output_file.clone(), source })?;
+            println!("saved {} images to {}", samples.len(), opts.output_file);
+        },
+        Action::FindDuplicates(opts) => {
+            let load = |images_file: &str, labels_file: &str| stream_samples(images_file, labels_file, opts.alphabet)?
+                .collect::<training_data::Result<Vec<_>>>();
+            let samples = load(&opts.images_file, &opts.labels_file)?;
+
+            let duplicates = find_duplicates(&samples, opts.max_distance);
+            for duplicate in &duplicates {
+                print_duplicate(duplicate, (&opts.images_file, &samples), (&opts.images_file, &samples));
+            }
+            println!("found {} pairs of duplicates among {} samples of {}", duplicates.len(), samples.len(), opts.images_file);
+
+            if let Some((images_file, labels_file)) = &opts.other_files {
+                let others = load(images_file, labels_file)?;
+                let duplicates = find_duplicates_between(&samples, &others, opts.max_distance);
+                for duplicate in &duplicates {
+                    print_duplicate(duplicate, (&opts.images_file, &samples), (images_file, &others));
                 }
+                println!("found {} samples of {} that are also in {}",
+                         duplicates.iter().map(|duplicate| duplicate.second).collect::<HashSet<_>>().len(),
+                         images_file,
+                         opts.images_file);
+            }
+        },
+        Action::ConvertDataset(opts) => {
+            let samples = stream_samples(&opts.images_file, &opts.labels_file, opts.alphabet)?;
+            let samples = skipping_invalid(samples, opts.skip_invalid).subset(opts.subset);
+            let (images_file, labels_file) = &opts.output_files;
+            let count = write::convert(samples, images_file, labels_file)?;
+            println!("converted {} samples of {} to {}", count, opts.images_file, images_file);
+        },
+        Action::FetchMnist(opts) => {
+            mnist::fetch(&opts.dir, &opts.mirror)?;
+            println!("downloaded MNIST to {}; train with --train --mnist {0} and evaluate with --evaluate --mnist {0}",
+                     opts.dir);
+        },
+        Action::SynthesizeMnist(opts) => {
+            let fonts = load_fonts(&opts.font_dirs)?;
+            let generator = SyntheticDigits::default();
+            let mut rng = seeded_rng(opts.seed);
+            fs::create_dir_all(&opts.dir).map_err(write::ErrorKind::from)?;
+
+            // as many test samples per training sample as in MNIST
+            for (split, count) in [(MnistSplit::Train, opts.samples), (MnistSplit::Test, opts.samples / 6)] {
+                let (images_file, labels_file) = mnist::files(&opts.dir, split);
+                write::write_gzipped(images_file, labels_file, &generator.generate(&fonts, count, &mut rng))?;
+            }
+            println!("drew {} training and {} test digits in {} fonts into {}; train with --train --mnist {3}",
+                     opts.samples, opts.samples / 6, fonts.len(), opts.dir);
+        },
+        Action::Quantize(opts) => {
+            let neural_network = NeuralNetwork::load(&opts.model_file)?;
+            QuantizedNetwork::from_network(&neural_network).save(&opts.output_file)?;
+        },
+        Action::ConvertModel(opts) => {
+            NeuralNetwork::load(&opts.model_file)?.save(&opts.output_file)?;
+        },
+        Action::Adversarial(opts) => {
+            let mut neural_network = NeuralNetwork::load(&opts.model_file)?;
+            neural_network.set_compute_backend(opts.backend);
+
+            let samples = load_samples(&opts.images_file, &opts.labels_file, opts.alphabet)?;
+            let example = adversarial::find_adversarial_example(&neural_network, &samples, opts.start, opts.epsilon)?;
+
+            fs::create_dir_all(&opts.output_dir)
+                .map_err(|source| adversarial::ErrorKind::CannotSaveImage { file: opts.output_dir.clone(), source })?;
+            let original_file = Path::new(&opts.output_dir).join("original.png").display().to_string();
+            let adversarial_file = Path::new(&opts.output_dir).join("adversarial.png").display().to_string();
+            adversarial::save_image(samples[example.position].image(), &original_file)?;
+            adversarial::save_image(&example.perturbed, &adversarial_file)?;
+
+            println!("sample {} labeled {}", example.position, neural_network.class_name(example.label.class() as usize));
+            println!("before: {} ({:.1}%), saved to {}",
+                     neural_network.class_name(example.original.class), example.original.confidence*100.0, original_file);
+            println!("after:  {} ({:.1}%), saved to {}",
+                     neural_network.class_name(example.adversarial.class), example.adversarial.confidence*100.0,
+                     adversarial_file);
+            if !example.flipped() {
+                println!("the prediction did not flip; try a larger --epsilon");
             }
+        },
+        Action::Detect(opts) => {
+            let mut neural_network = NeuralNetwork::load(&opts.model_file)?;
+            neural_network.set_compute_backend(opts.backend);
+
+            let image = detection::load_pgm(&opts.image_file)?;
+            let detections = detection::detect(&neural_network, &image, opts.min_confidence)?;
 
-            println!("error rate: {}",
-                     (incorrect_answers as f64) / ((correct_answers + incorrect_answers) as f64));
+            let digits: String = detections.iter().map(|detection| detection.class_name.as_str()).collect();
+            println!("{}", digits);
+            for detection in &detections {
+                println!("{} at x {}, y {}, {}x{} ({:.1}%)",
+                         detection.class_name, detection.x, detection.y, detection.side, detection.side,
+                         detection.confidence*100.0);
+            }
+        },
+        Action::Prune(opts) => {
+            let mut neural_network = NeuralNetwork::load(&opts.model_file)?;
+            let sparsity = neural_network.prune(opts.threshold);
+            neural_network.save(&opts.model_file)?;
 
-            println!("cc: {}", DVector::from_column_slice(&count));
-            //neural_network.save("neural_network_4.json");
+            println!("{} of {} weights are zero ({:.1}% sparsity)",
+                     sparsity.zero_weights, sparsity.total_weights, sparsity.ratio()*100.0);
+        },
+        Action::ExportBundle(opts) => {
+            Bundle::collect(&opts.model_file)?.save(&opts.bundle_file)?;
+        },
+        Action::ImportBundle(opts) => {
+            for file in Bundle::load(&opts.bundle_file)?.restore(&opts.model_file)? {
+                println!("restored {}", file.display());
+            }
         }
     }
 
     Ok(())
 }
 
-fn parse_args() -> Result<Action> {
-    let mut args = Args::new(PROGRAM_NAME, PROGRAM_DESCRIPTION);
+/// Learning rate of the corrections taught in the GUI, low enough that a
+/// single drawing does not undo the training on the full dataset.
+const PROFILE_LEARNING_RATE: f64 = 0.01;
 
-    args.flag("t", "train", "Start training using provided dataset");
+/// Model of the user profile selected in the GUI and the last drawing
+/// submitted, which a correction teaches the model.
+struct ProfileSession {
+    store: ProfileStore,
+    profile: String,
+    network: NeuralNetwork,
+    backend: ComputeBackend,
+    last_drawing: Option<Image>
+}
 
-    args.option("i",
-                "images",
-                "File containing images used for training",
-                "IMAGES",
-                Occur::Optional,
-                env::var(KEY_IMAGES_FILE).ok());
+impl ProfileSession {
+    fn switch_to(&mut self, profile: &str) -> Result<()> {
+        if profile != self.profile {
+            self.network = self.store.load_model(profile)?;
+            self.network.set_compute_backend(self.backend);
+            self.profile = profile.to_string();
+            self.last_drawing = None;
+        }
 
-    args.option("l",
-                "labels",
-                "File containing labels used for training",
-                "LABELS",
-                Occur::Optional,
-                env::var(KEY_LABELS_FILE).ok());
+        Ok(())
+    }
 
-    args.parse_from_cli()?;
+    fn remember_drawing(&mut self, img_loader: &ImageLoader) {
+        self.last_drawing = canvas_sample(img_loader, self.network.input_size());
+    }
 
-    return if args.value_of::<bool>("train")? {
-        let images_file: String = args.value_of("images")?;
-        let labels_file: String = args.value_of("labels")?;
+    fn teach(&mut self, profile: &str, answer: &str) -> Result<String> {
+        self.switch_to(profile)?;
 
-        Ok(Action::Train(TrainingOption { images_file, labels_file }))
-    } else {
-        Ok(Action::ShowGui)
+        let answer = answer.trim();
+        let class = match (0..self.network.output_size()).find(|class| self.network.class_name(*class) == answer) {
+            Some(class) => class,
+            None => return Ok(tr_args("gui-unknown-class", &[("answer", answer.to_string())]))
+        };
+
+        let image = match self.last_drawing.take() {
+            Some(image) => image,
+            None => return Ok(tr("gui-no-drawing"))
+        };
+
+        let label = Label::new(class as u8);
+        self.network.train_incremental(&image, label, PROFILE_LEARNING_RATE)?;
+        self.store.add_sample(profile, image, label)?;
+        self.store.save_model(profile, &self.network)?;
+
+        Ok(tr_args("gui-taught", &[("profile", profile.to_string()), ("answer", answer.to_string())]))
+    }
+}
+
+/// Adds the drawings whose answer the user confirms in the GUI to a dataset.
+struct DrawingCollector {
+    sink: Box<dyn SampleSink>,
+    class_names: Vec<String>,
+    input_size: ImageSize,
+    last_drawing: Option<Image>,
+    collected: usize
+}
+
+impl DrawingCollector {
+    fn open((images_file, labels_file): &(String, String), classifier: &dyn Classifier) -> Rc<RefCell<DrawingCollector>> {
+        Rc::new(RefCell::new(DrawingCollector {
+            sink: open_sink(images_file, labels_file),
+            class_names: (0..classifier.output_size()).map(|class| classifier.class_name(class)).collect(),
+            input_size: classifier.input_size(),
+            last_drawing: None,
+            collected: 0
+        }))
+    }
+
+    fn controls(collector: &Rc<RefCell<DrawingCollector>>) -> CollectControls {
+        let collector = collector.clone();
+        CollectControls {
+            on_collect: Box::new(move |answer| {
+                collector.borrow_mut().collect(answer).unwrap_or_else(|err| err.to_string())
+            })
+        }
+    }
+
+    fn remember_drawing(&mut self, img_loader: &ImageLoader) {
+        self.last_drawing = canvas_sample(img_loader, self.input_size);
+    }
+
+    fn collect(&mut self, answer: &str) -> Result<String> {
+        let class = match self.class_names.iter().position(|name| name == answer) {
+            Some(class) => class,
+            None => return Ok(tr_args("gui-unknown-class", &[("answer", answer.to_string())]))
+        };
+
+        let image = match self.last_drawing.take() {
+            Some(image) => image,
+            None => return Ok(tr("gui-no-drawing"))
+        };
+
+        self.sink.append(&LabeledTrainingData::new(image, Label::new(class as u8)))?;
+        self.collected += 1;
+        Ok(tr_args("gui-collected", &[("answer", answer.to_string()), ("count", self.collected.to_string())]))
+    }
+}
+
+/// Classifies the drawing on the GUI canvas, as a number with `grid_cells`
+/// cells, otherwise as a single digit.
+fn classify_canvas(classifier: &dyn Classifier,
+                   img_loader: &ImageLoader,
+                   grid_cells: u32,
+                   multiscale: bool) -> Answer {
+    if grid_cells > 1 {
+        let cells = img_loader.load_cells(classifier.input_size(), grid_cells).unwrap();
+
+        // cells left blank are skipped, so shorter numbers can be entered too
+        let inputs: Vec<DVector<Scalar>> = cells
+            .iter()
+            .filter(|cell| cell.pixels().iter().any(|x| *x < 128))
+            .map(|cell| canvas_input(cell, classifier.preprocessing()))
+            .collect();
+
+        let predictions = classifier.predict_batch(inputs).unwrap();
+        let number: String = predictions
+            .iter()
+            .map(|prediction| classifier.class_name(prediction.class))
+            .collect();
+
+        // the number is only as certain as its least certain digit
+        let confidence = predictions
+            .iter()
+            .map(|prediction| prediction.confidence)
+            .fold(1.0, f64::min);
+
+        return Answer::new(number, confidence)
+    }
+
+    if multiscale {
+        let input_size = classifier.input_size();
+        let mut image = img_loader
+            .load_image(ImageSize {
+                width: input_size.width * MULTISCALE_CANVAS_FACTOR,
+                height: input_size.height * MULTISCALE_CANVAS_FACTOR
+            })
+            .unwrap();
+
+        // the canvas is drawn black on white, the datasets white on black
+        Invert.apply(&mut image, &mut rand::thread_rng());
+
+        let best = predict_multiscale(classifier, &image, DEFAULT_SCALES)
+            .unwrap()
+            .unwrap();
+        info!("best prediction on a {:.0}% crop at ({}, {})", best.scale*100.0, best.x, best.y);
+
+        return Answer::new(classifier.class_name(best.prediction.class), best.prediction.confidence)
+    }
+
+    let image = img_loader.load_image(classifier.input_size()).unwrap();
+    let prediction = classifier.predict(canvas_input(&image, classifier.preprocessing())).unwrap();
+
+    Answer::new(classifier.class_name(prediction.class), prediction.confidence)
+}
+
+/// Like [`classify_canvas`], but also returns the uncertainty estimated by
+/// Monte Carlo dropout; of several cells, the least certain one counts.
+fn classify_canvas_mc_dropout(network: &NeuralNetwork,
+                              img_loader: &ImageLoader,
+                              grid_cells: u32,
+                              passes: usize,
+                              rate: f64,
+                              rng: &mut StdRng) -> Answer {
+    let images = match grid_cells {
+        0 | 1 => vec![img_loader.load_image(network.input_size()).unwrap()],
+        _ => img_loader
+            .load_cells(network.input_size(), grid_cells)
+            .unwrap()
+            .into_iter()
+            .filter(|cell| cell.pixels().iter().any(|x| *x < 128))
+            .collect()
+    };
+
+    let predictions: Vec<_> = images
+        .iter()
+        .map(|image| network.predict_mc_dropout(canvas_input(image, network.preprocessing()), passes, rate, rng).unwrap())
+        .collect();
+
+    let answer: String = predictions.iter().map(|prediction| network.class_name(prediction.class)).collect();
+    let confidence = predictions.iter().map(|prediction| prediction.confidence).fold(1.0, f64::min);
+    let uncertainty = predictions.iter().map(|prediction| prediction.uncertainty).fold(0.0, f64::max);
+
+    Answer::new(answer, confidence).with_uncertainty(uncertainty)
+}
+
+/// Network input for an image of the GUI canvas, which is drawn black on
+/// white, prepared like the samples the network was trained on.
+fn canvas_input(image: &Image, preprocessing: Preprocessing) -> DVector<Scalar> {
+    let mut image = image.clone();
+    Invert.then(preprocessing).apply(&mut image, &mut rand::thread_rng());
+    input_vector(&image)
+}
+
+/// The GUI canvas at `size` as a training sample, inverted to white on black
+/// like the datasets it can be added to.
+fn canvas_sample(img_loader: &ImageLoader, size: ImageSize) -> Option<Image> {
+    let mut image = img_loader.load_image(size).ok()?;
+    Invert.apply(&mut image, &mut rand::thread_rng());
+    Some(image)
+}
+
+fn report_non_finite_values(event: &LayerEvent) {
+    if event.values.iter().any(|x| !x.is_finite()) {
+        warn!("non-finite values in layer {} on the {:?} pass: {:.4}",
+              event.layer, event.pass, event.values.transpose());
+    }
+}
+
+/// Peak resident set size of the process in bytes, where the OS reports it.
+fn peak_resident_memory() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+        .map(|kilobytes| kilobytes * 1024)
+}
+
+/// The `subset` of the training samples, weighted by the lines of
+/// `weights_file` if given, optionally without the ones that cannot be read.
+fn load_training_samples(images_file: &str,
+                         labels_file: &str,
+                         alphabet: Alphabet,
+                         weights_file: Option<&str>,
+                         subset: Subset,
+                         skip_invalid: bool) -> Result<Vec<LabeledTrainingData>> {
+    let samples = stream_samples(images_file, labels_file, alphabet)?;
+    // the weights are matched with the samples before any are left out
+    let samples: Box<dyn Iterator<Item = _> + Send> = match weights_file {
+        Some(weights_file) => Box::new(samples.with_weights(load_sample_weights(weights_file)?)),
+        None => samples
+    };
+
+    Ok(skipping_invalid(samples, skip_invalid).subset(subset).collect::<training_data::Result<Vec<_>>>()?)
+}
+
+/// The network saved in `model_file`, or `untrained()` if there is no such
+/// file yet. Any other error, such as a model saved by a newer version, is
+/// returned rather than replaced, since training would then overwrite it.
+fn load_or_untrained<P: AsRef<Path>>(model_file: P, untrained: impl FnOnce() -> NeuralNetwork) -> Result<NeuralNetwork> {
+    return match NeuralNetwork::load(model_file) {
+        Err(network::ErrorKind::CannotLoadNeuralNetwork(err)) if err.kind() == io::ErrorKind::NotFound => Ok(untrained()),
+        result => Ok(result?)
+    }
+}
+
+/// The labels file given with `labels_option` for a dataset read from or
+/// written to `images_file`, which only IDX datasets need.
+fn labels_file_for(args: &Args, images_file: &str, labels_option: &str) -> Result<String> {
+    // CSV files and image directories have the labels in them
+    return if write::has_labels_file(images_file) {
+        Ok(args.value_of(labels_option)?)
+    } else {
+        Ok(args.optional_value_of(labels_option)?.unwrap_or_default())
+    }
+}
+
+/// `samples` without the ones that cannot be read if `skip_invalid` is set.
+fn skipping_invalid(samples: Box<dyn Iterator<Item = training_data::Result<LabeledTrainingData>> + Send>,
+                    skip_invalid: bool) -> Box<dyn Iterator<Item = training_data::Result<LabeledTrainingData>> + Send> {
+    return if skip_invalid {
+        Box::new(samples.skip_invalid())
+    } else {
+        samples
+    }
+}
+
+/// `--augment` and the options of the augmentations, if it is given.
+fn parse_augmentation(args: &Args) -> Result<Option<Augmentation>> {
+    if !args.value_of::<bool>("augment")? {
+        return Ok(None)
+    }
+
+    let elastic = match args.optional_value_of::<f64>("augment-elastic")? {
+        Some(alpha) => Some(ElasticDistortion { alpha, sigma: args.value_of("augment-elastic-sigma")? }),
+        None => None
+    };
+    Ok(Some(Augmentation {
+        max_rotation: args.value_of("augment-rotation")?,
+        max_shift: args.value_of("augment-shift")?,
+        elastic,
+        blur: args.optional_value_of("augment-blur")?.map(|max_sigma| Blur { max_sigma }),
+        gaussian_noise: args.optional_value_of("augment-noise")?.map(|std_dev| GaussianNoise { std_dev }),
+        salt_and_pepper: args.optional_value_of("augment-salt-and-pepper")?
+            .map(|probability| SaltAndPepper { probability })
+    }))
+}
+
+/// `--skip`, `--limit` and `--random-subset`, which is seeded by `--seed`.
+fn dataset_subset(args: &Args, seed: Option<u64>) -> Result<Subset> {
+    let random: bool = args.value_of("random-subset")?;
+    Ok(Subset {
+        skip: args.value_of("skip")?,
+        limit: args.optional_value_of("limit")?,
+        random_seed: random.then(|| seed.unwrap_or_default())
+    })
+}
+
+/// Samples prepared for a network with `preprocessing`, in parallel.
+fn prepare_samples(samples: Vec<LabeledTrainingData>,
+                   preprocessing: Preprocessing,
+                   input_size: ImageSize) -> Vec<LabeledTrainingData> {
+    return if preprocessing.is_identity() && samples.iter().all(|sample| sample.image().size() == input_size) {
+        samples
+    } else {
+        samples.into_par_iter().map(|sample| preprocessing.prepare_sample(sample.resized(input_size))).collect()
+    }
+}
+
+/// Samples with their labels mapped by `--label-map`, if given.
+fn remap_samples(samples: Vec<LabeledTrainingData>, label_map: Option<&LabelMap>) -> Vec<LabeledTrainingData> {
+    return match label_map {
+        Some(label_map) => samples.into_iter().filter_map(|sample| label_map.apply(sample)).collect(),
+        None => samples
+    }
+}
+
+/// Reproducible randomness with `--seed`, otherwise seeded from the OS.
+fn seeded_rng(seed: Option<u64>) -> StdRng {
+    seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64)
+}
+
+fn parse_list<T: FromStr>(option: &'static str, value: &str) -> Result<Vec<T>> {
+    value
+        .split(',')
+        .map(|item| item.trim().parse::<T>())
+        .collect::<std::result::Result<Vec<T>, _>>()
+        .map_err(|_| ErrorKind::InvalidOptionValue { option, value: value.to_string() })
+}
+
+/// Parses durations like `90s`, `10m` or `1.5h`; plain numbers are seconds.
+fn parse_duration(option: &'static str, value: &str) -> Result<Duration> {
+    let invalid = || ErrorKind::InvalidOptionValue { option, value: value.to_string() };
+
+    let value = value.trim();
+    let (number, unit_seconds) = match value.char_indices().last() {
+        Some((i, 's')) => (&value[..i], 1.0),
+        Some((i, 'm')) => (&value[..i], 60.0),
+        Some((i, 'h')) => (&value[..i], 3600.0),
+        _ => (value, 1.0)
+    };
+
+    let seconds = number.trim().parse::<f64>().map_err(|_| invalid())? * unit_seconds;
+    Duration::try_from_secs_f64(seconds).map_err(|_| invalid())
+}
+
+/// `--images` and `--labels`, or the `split` of MNIST if `--mnist` is given.
+/// `--labels` may be left out for an image directory.
+fn dataset_files(args: &Args, split: MnistSplit) -> Result<(String, String)> {
+    return match args.optional_value_of::<String>("mnist")? {
+        Some(dir) => {
+            let (images_file, labels_file) = mnist::files(dir, split);
+            Ok((images_file.display().to_string(), labels_file.display().to_string()))
+        },
+        None => {
+            let images_file: String = args.value_of("images")?;
+            let labels_file = labels_file_for(args, &images_file, "labels")?;
+            Ok((images_file, labels_file))
+        }
+    }
+}
+
+fn print_duplicate(duplicate: &Duplicate,
+                   (first_file, first): (&str, &[LabeledTrainingData]),
+                   (second_file, second): (&str, &[LabeledTrainingData])) {
+    let (first_label, second_label) = (first[duplicate.first].label().class(), second[duplicate.second].label().class());
+    println!("{} #{} ({}) and {} #{} ({}): {}{}",
+             first_file, duplicate.first, first_label,
+             second_file, duplicate.second, second_label,
+             if duplicate.exact { String::from("identical") } else { format!("{} bits apart", duplicate.distance) },
+             if first_label != second_label { ", labeled differently" } else { "" });
+}
+
+/// Fails if `option` is given together with any of `others`, which it would
+/// ignore; each comes with whether it is given.
+fn reject_conflicts((option, given): (&'static str, bool), others: &[(&'static str, bool)]) -> Result<()> {
+    return match others.iter().find(|(_, other_given)| given && *other_given) {
+        Some((other, _)) => Err(ErrorKind::ConflictingOptions { option: other, other: option }),
+        None => Ok(())
+    }
+}
+
+fn single_model_file(model_files: &[String]) -> Result<String> {
+    return match model_files {
+        [model_file] => Ok(model_file.clone()),
+        _ => Err(ErrorKind::MultipleModels)
+    }
+}
+
+fn parse_args() -> Result<(Action, LevelFilter)> {
+    let mut args = Args::new(PROGRAM_NAME, &tr("program-description"));
+
+    args.flag("t", "train", &tr("help-train"));
+
+    args.flag("", "evaluate", &tr("help-evaluate"));
+
+    args.flag("", "info", &tr("help-info"));
+
+    args.option("",
+                "benchmark-suite",
+                &tr("help-benchmark-suite"),
+                "DIR",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "quantize",
+                &tr("help-quantize"),
+                "OUTPUT",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "convert-model",
+                &tr("help-convert-model"),
+                "OUTPUT",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "export-bundle",
+                &tr("help-export-bundle"),
+                "BUNDLE",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "import-bundle",
+                &tr("help-import-bundle"),
+                "BUNDLE",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "fetch-mnist",
+                &tr("help-fetch-mnist"),
+                "DIR",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "mnist-mirror",
+                &tr("help-mnist-mirror"),
+                "URL",
+                Occur::Optional,
+                Some(String::from(mnist::DEFAULT_MIRROR)));
+
+    args.option("",
+                "synthesize-mnist",
+                &tr("help-synthesize-mnist"),
+                "DIR",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "fonts",
+                &tr("help-fonts"),
+                "DIRS",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "synthetic-samples",
+                &tr("help-synthetic-samples"),
+                "N",
+                Occur::Optional,
+                Some(String::from("60000")));
+
+    args.option("",
+                "mnist",
+                &tr("help-mnist"),
+                "DIR",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "detect",
+                &tr("help-detect"),
+                "IMAGE",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "min-confidence",
+                &tr("help-min-confidence"),
+                "CONFIDENCE",
+                Occur::Optional,
+                Some(String::from("0.9")));
+
+    args.option("",
+                "export-tensors",
+                &tr("help-export-tensors"),
+                "FILE",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "export-grid",
+                &tr("help-export-grid"),
+                "PNG",
+                Occur::Optional,
+                None);
+
+    args.flag("", "find-duplicates", &tr("help-find-duplicates"));
+
+    args.option("",
+                "convert-dataset",
+                &tr("help-convert-dataset"),
+                "IMAGES",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "convert-labels",
+                &tr("help-convert-labels"),
+                "LABELS",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "duplicate-distance",
+                &tr("help-duplicate-distance"),
+                "BITS",
+                Occur::Optional,
+                Some(String::from("2")));
+
+    args.option("",
+                "grid-columns",
+                &tr("help-grid-columns"),
+                "N",
+                Occur::Optional,
+                Some(String::from("10")));
+
+    args.option("",
+                "tensor-format",
+                &tr("help-tensor-format"),
+                "FORMAT",
+                Occur::Optional,
+                Some(String::from("npz")));
+
+    args.option("",
+                "fgsm",
+                &tr("help-fgsm"),
+                "DIR",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "epsilon",
+                &tr("help-epsilon"),
+                "EPSILON",
+                Occur::Optional,
+                Some(String::from("0.1")));
+
+    args.option("",
+                "sample-index",
+                &tr("help-sample-index"),
+                "N",
+                Occur::Optional,
+                Some(String::from("0")));
+
+    args.option("",
+                "prune",
+                &tr("help-prune"),
+                "THRESHOLD",
+                Occur::Optional,
+                None);
+
+    args.flag("", "quantized", &tr("help-quantized"));
+    args.option("",
+                "grid-cells",
+                &tr("help-grid-cells"),
+                "N",
+                Occur::Optional,
+                Some(String::from("1")));
+
+    args.option("",
+                "profiles",
+                &tr("help-profiles"),
+                "DIR",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "profile",
+                &tr("help-profile"),
+                "NAME",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "collect",
+                &tr("help-collect"),
+                "IMAGES",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "collect-labels",
+                &tr("help-collect-labels"),
+                "LABELS",
+                Occur::Optional,
+                None);
+
+    args.flag("",
+              "multiscale",
+              &tr("help-multiscale"));
+
+    args.option("",
+                "mc-dropout",
+                &tr("help-mc-dropout"),
+                "N",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "mc-dropout-rate",
+                &tr("help-mc-dropout-rate"),
+                "RATE",
+                Occur::Optional,
+                Some(String::from("0.1")));
+
+    args.option("",
+                "bench-history",
+                &tr("help-bench-history"),
+                "FILE",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "compare-predictions",
+                &tr("help-compare-predictions"),
+                "CSV",
+                Occur::Optional,
+                None);
+
+    args.flag("", "from-scratch", &tr("help-from-scratch"));
+
+    args.option("m",
+                "model",
+                &tr("help-model"),
+                "MODEL",
+                Occur::Multi,
+                None);
+
+    args.option("",
+                "runs-dir",
+                &tr("help-runs-dir"),
+                "DIR",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "ensemble-voting",
+                &tr("help-ensemble-voting"),
+                "VOTING",
+                Occur::Optional,
+                Some(String::from("average")));
+
+    args.option("i",
+                "images",
+                &tr("help-images"),
+                "IMAGES",
+                Occur::Optional,
+                env::var(KEY_IMAGES_FILE).ok());
+
+    args.option("l",
+                "labels",
+                &tr("help-labels"),
+                "LABELS",
+                Occur::Optional,
+                env::var(KEY_LABELS_FILE).ok());
+
+    args.option("",
+                "skip",
+                &tr("help-skip"),
+                "N",
+                Occur::Optional,
+                Some(String::from("0")));
+
+    args.option("",
+                "limit",
+                &tr("help-limit"),
+                "N",
+                Occur::Optional,
+                None);
+
+    args.flag("", "random-subset", &tr("help-random-subset"));
+
+    args.flag("", "skip-invalid", &tr("help-skip-invalid"));
+
+    args.option("e",
+                "epochs",
+                &tr("help-epochs"),
+                "EPOCHS",
+                Occur::Optional,
+                Some(String::from("1")));
+
+    args.option("",
+                "learning-rate",
+                &tr("help-learning-rate"),
+                "RATE",
+                Occur::Optional,
+                Some(DEFAULT_LEARNING_RATE.to_string()));
+
+    args.option("",
+                "layer-lr-multipliers",
+                &tr("help-layer-lr-multipliers"),
+                "MULTIPLIERS",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "reduce-lr-on-plateau",
+                &tr("help-reduce-lr-on-plateau"),
+                "PATIENCE",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "lr-reduction-factor",
+                &tr("help-lr-reduction-factor"),
+                "FACTOR",
+                Occur::Optional,
+                Some(String::from("0.1")));
+
+    args.option("",
+                "min-learning-rate",
+                &tr("help-min-learning-rate"),
+                "RATE",
+                Occur::Optional,
+                Some(String::from("0")));
+
+    args.option("",
+                "freeze-layers",
+                &tr("help-freeze-layers"),
+                "LAYERS",
+                Occur::Optional,
+                None);
+
+    args.option("b",
+                "batch-size",
+                &tr("help-batch-size"),
+                "N",
+                Occur::Optional,
+                Some(String::from("1")));
+
+    args.option("",
+                "seed",
+                &tr("help-seed"),
+                "N",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "threads",
+                &tr("help-threads"),
+                "N",
+                Occur::Optional,
+                Some(String::from("0")));
+
+    args.option("c",
+                "checkpoint-every",
+                &tr("help-checkpoint-every"),
+                "N",
+                Occur::Optional,
+                Some(String::from("0")));
+
+    args.option("",
+                "warmup-batches",
+                &tr("help-warmup-batches"),
+                "N",
+                Occur::Optional,
+                Some(String::from("0")));
+
+    args.option("",
+                "lr-cycle",
+                &tr("help-lr-cycle"),
+                "POLICY",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "lr-cycle-length",
+                &tr("help-lr-cycle-length"),
+                "N",
+                Occur::Optional,
+                Some(String::from("1000")));
+
+    args.flag("", "shuffle", &tr("help-shuffle"));
+
+    args.flag("", "preprocess", &tr("help-preprocess"));
+
+    args.flag("", "normalize", &tr("help-normalize"));
+
+    args.flag("", "augment", &tr("help-augment"));
+
+    args.option("",
+                "augment-rotation",
+                &tr("help-augment-rotation"),
+                "DEGREES",
+                Occur::Optional,
+                Some(Augmentation::default().max_rotation.to_string()));
+
+    args.option("",
+                "augment-shift",
+                &tr("help-augment-shift"),
+                "PIXELS",
+                Occur::Optional,
+                Some(Augmentation::default().max_shift.to_string()));
+
+    args.option("",
+                "augment-elastic",
+                &tr("help-augment-elastic"),
+                "ALPHA",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "augment-elastic-sigma",
+                &tr("help-augment-elastic-sigma"),
+                "SIGMA",
+                Occur::Optional,
+                Some(ElasticDistortion::default().sigma.to_string()));
+
+    args.option("",
+                "augment-blur",
+                &tr("help-augment-blur"),
+                "SIGMA",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "augment-noise",
+                &tr("help-augment-noise"),
+                "STD_DEV",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "augment-salt-and-pepper",
+                &tr("help-augment-salt-and-pepper"),
+                "PROBABILITY",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "robust-training",
+                &tr("help-robust-training"),
+                "PERTURBATION",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "robust-strength",
+                &tr("help-robust-strength"),
+                "STRENGTH",
+                Occur::Optional,
+                Some(String::from("0.1")));
+
+    args.option("",
+                "teacher",
+                &tr("help-teacher"),
+                "MODEL",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "distillation-temperature",
+                &tr("help-distillation-temperature"),
+                "T",
+                Occur::Optional,
+                Some(String::from("2.0")));
+
+    args.option("",
+                "distillation-weight",
+                &tr("help-distillation-weight"),
+                "WEIGHT",
+                Occur::Optional,
+                Some(String::from("0.5")));
+
+    args.option("",
+                "lr-find",
+                &tr("help-lr-find"),
+                "CSV",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "lr-find-range",
+                &tr("help-lr-find-range"),
+                "MIN,MAX",
+                Occur::Optional,
+                Some(String::from("1e-5,10")));
+
+    args.option("",
+                "lr-find-batches",
+                &tr("help-lr-find-batches"),
+                "N",
+                Occur::Optional,
+                Some(String::from("300")));
+
+    args.option("",
+                "max-duration",
+                &tr("help-max-duration"),
+                "DURATION",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "class-weights",
+                &tr("help-class-weights"),
+                "WEIGHTS",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "sampling",
+                &tr("help-sampling"),
+                "MODE",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "sample-weights",
+                &tr("help-sample-weights"),
+                "FILE",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "extra-images",
+                &tr("help-extra-images"),
+                "IMAGES",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "extra-labels",
+                &tr("help-extra-labels"),
+                "LABELS",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "extra-weight",
+                &tr("help-extra-weight"),
+                "WEIGHT",
+                Occur::Optional,
+                Some(String::from("1.0")));
+
+    args.option("",
+                "ema-decay",
+                &tr("help-ema-decay"),
+                "DECAY",
+                Occur::Optional,
+                None);
+
+    args.option("r",
+                "resume",
+                &tr("help-resume"),
+                "CHECKPOINT",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "input-features",
+                &tr("help-input-features"),
+                "FEATURES",
+                Occur::Optional,
+                Some(String::from("pixels")));
+
+    args.option("",
+                "init-from",
+                &tr("help-init-from"),
+                "MODEL",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "architecture",
+                &tr("help-architecture"),
+                "CONFIG",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "search",
+                &tr("help-search"),
+                "CONFIG",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "search-candidates",
+                &tr("help-search-candidates"),
+                "N",
+                Occur::Optional,
+                Some(String::from("16")));
+
+    args.option("",
+                "search-epochs",
+                &tr("help-search-epochs"),
+                "EPOCHS",
+                Occur::Optional,
+                Some(String::from("1")));
+
+    args.option("",
+                "sweep",
+                &tr("help-sweep"),
+                "CONFIG",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "sweep-dir",
+                &tr("help-sweep-dir"),
+                "DIR",
+                Occur::Optional,
+                Some(String::from("sweep")));
+
+    args.option("",
+                "sweep-jobs",
+                &tr("help-sweep-jobs"),
+                "N",
+                Occur::Optional,
+                Some(String::from("1")));
+
+    args.option("",
+                "validation-images",
+                &tr("help-validation-images"),
+                "IMAGES",
+                Occur::Optional,
+                env::var(KEY_VALIDATION_IMAGES_FILE).ok());
+
+    args.option("",
+                "validation-labels",
+                &tr("help-validation-labels"),
+                "LABELS",
+                Occur::Optional,
+                env::var(KEY_VALIDATION_LABELS_FILE).ok());
+
+    args.option("",
+                "validation-split",
+                &tr("help-validation-split"),
+                "FRACTION",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "verify-sha256",
+                &tr("help-verify-sha256"),
+                "MANIFEST",
+                Occur::Optional,
+                None);
+
+    args.option("a",
+                "alphabet",
+                &tr("help-alphabet"),
+                "ALPHABET",
+                Occur::Optional,
+                Some(String::from("digits")));
+
+    args.option("",
+                "label-map",
+                &tr("help-label-map"),
+                "FILE",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "classes",
+                &tr("help-classes"),
+                "N",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "class-names",
+                &tr("help-class-names"),
+                "NAMES",
+                Occur::Optional,
+                None);
+
+    args.flag("", "detect-nan", &tr("help-detect-nan"));
+
+    args.flag("", "rollback-on-divergence", &tr("help-rollback-on-divergence"));
+
+    args.flag("", "calibrate", &tr("help-calibrate"));
+
+    args.option("",
+                "rejection-threshold",
+                &tr("help-rejection-threshold"),
+                "THRESHOLD",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "max-latency-ms",
+                &tr("help-max-latency-ms"),
+                "MS",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "compute-backend",
+                &tr("help-compute-backend"),
+                "BACKEND",
+                Occur::Optional,
+                Some(String::from("nalgebra")));
+
+    args.option("s",
+                "script",
+                &tr("help-script"),
+                "SCRIPT",
+                Occur::Optional,
+                Some(String::from("latin")));
+
+    // read by i18n::Locale::detect before the help text is built
+    args.option("",
+                "lang",
+                &tr("help-lang"),
+                "LANG",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "explain",
+                &tr("help-explain"),
+                "CODE",
+                Occur::Optional,
+                None);
+
+    args.option("",
+                "log-level",
+                &tr("help-log-level"),
+                "LEVEL",
+                Occur::Optional,
+                Some(String::from("warn")));
+
+    args.parse_from_cli()?;
+
+    let log_level_value: String = args.value_of("log-level")?;
+    let log_level = log_level_value
+        .parse::<LevelFilter>()
+        .map_err(|_| ErrorKind::InvalidOptionValue { option: "log-level", value: log_level_value })?;
+
+    Ok((parse_action(&args)?, log_level))
+}
+
+fn parse_action(args: &Args) -> Result<Action> {
+    if let Some(code) = args.optional_value_of::<String>("explain")? {
+        return Ok(Action::Explain(code))
+    }
+
+    let mut model_files: Vec<String> = args.values_of("model")?;
+    let runs_dir: Option<String> = args.optional_value_of("runs-dir")?;
+    // without an explicit --model the GUI shows the best model of the latest run
+    let latest_run_of = runs_dir.clone().filter(|_| model_files.is_empty());
+    if model_files.is_empty() {
+        model_files.push(String::from(DEFAULT_MODEL_FILE));
+    }
+
+    let backend = args.value_of::<String>("compute-backend")?.parse::<ComputeBackend>()?;
+    let seed: Option<u64> = args.optional_value_of("seed")?;
+    let label_map_file: Option<String> = args.optional_value_of("label-map")?;
+    let label_map = match &label_map_file {
+        Some(label_map_file) => Some(LabelMap::load(label_map_file)?),
+        None => None
+    };
+
+    if let Some(bundle_file) = args.optional_value_of::<String>("export-bundle")? {
+        return Ok(Action::ExportBundle(BundleOption {
+            model_file: single_model_file(&model_files)?,
+            bundle_file
+        }))
+    }
+
+    if let Some(bundle_file) = args.optional_value_of::<String>("import-bundle")? {
+        return Ok(Action::ImportBundle(BundleOption {
+            model_file: single_model_file(&model_files)?,
+            bundle_file
+        }))
+    }
+
+    if let Some(threshold) = args.optional_value_of::<f64>("prune")? {
+        return Ok(Action::Prune(PruneOption {
+            model_file: single_model_file(&model_files)?,
+            threshold
+        }))
+    }
+
+    if let Some(output_file) = args.optional_value_of::<String>("quantize")? {
+        return Ok(Action::Quantize(QuantizeOption {
+            model_file: single_model_file(&model_files)?,
+            output_file
+        }))
+    }
+
+    if let Some(output_file) = args.optional_value_of::<String>("convert-model")? {
+        return Ok(Action::ConvertModel(ConvertOption {
+            model_file: single_model_file(&model_files)?,
+            output_file
+        }))
+    }
+
+    if let Some(image_file) = args.optional_value_of::<String>("detect")? {
+        return Ok(Action::Detect(DetectOption {
+            model_file: single_model_file(&model_files)?,
+            image_file,
+            min_confidence: args.value_of("min-confidence")?,
+            backend
+        }))
+    }
+
+    if let Some(dir) = args.optional_value_of::<String>("fetch-mnist")? {
+        return Ok(Action::FetchMnist(FetchMnistOption {
+            dir,
+            mirror: args.value_of("mnist-mirror")?
+        }))
+    }
+
+    if let Some(dir) = args.optional_value_of::<String>("synthesize-mnist")? {
+        let font_dirs = match args.optional_value_of::<String>("fonts")? {
+            Some(value) => parse_list("fonts", &value)?,
+            None => SYSTEM_FONT_DIRS.iter().map(|dir| dir.to_string()).collect()
+        };
+        return Ok(Action::SynthesizeMnist(SynthesizeMnistOption {
+            dir,
+            font_dirs,
+            samples: args.value_of("synthetic-samples")?,
+            seed
+        }))
+    }
+
+    if let Some(output_file) = args.optional_value_of::<String>("export-tensors")? {
+        let (images_file, labels_file) = dataset_files(args, MnistSplit::Train)?;
+        return Ok(Action::ExportTensors(ExportTensorsOption {
+            output_file,
+            format: args.value_of::<String>("tensor-format")?.parse::<TensorFormat>()?,
+            images_file,
+            labels_file,
+            alphabet: args.value_of::<String>("alphabet")?.parse::<Alphabet>()?,
+            label_map,
+            sample_weights_file: args.optional_value_of("sample-weights")?,
+            subset: dataset_subset(args, seed)?,
+            skip_invalid: args.value_of("skip-invalid")?,
+            input_features: args.value_of::<String>("input-features")?.parse::<InputFeatures>()?
+        }))
+    }
+
+    if let Some(output_file) = args.optional_value_of::<String>("export-grid")? {
+        let (images_file, labels_file) = dataset_files(args, MnistSplit::Train)?;
+        let subset = dataset_subset(args, seed)?;
+        return Ok(Action::ExportGrid(ExportGridOption {
+            output_file,
+            images_file,
+            labels_file,
+            alphabet: args.value_of::<String>("alphabet")?.parse::<Alphabet>()?,
+            subset: Subset { limit: subset.limit.or(Some(DEFAULT_GRID_IMAGES)), ..subset },
+            columns: args.value_of("grid-columns")?,
+            augmentation: parse_augmentation(args)?,
+            preprocess: args.value_of("preprocess")?,
+            seed
+        }))
+    }
+
+    if args.value_of("find-duplicates")? {
+        let (images_file, labels_file) = dataset_files(args, MnistSplit::Train)?;
+        let validation_images: Option<String> = args.optional_value_of("validation-images")?;
+        let validation_labels: Option<String> = args.optional_value_of("validation-labels")?;
+        // with --mnist the test set is checked against the training set
+        let other_files = match validation_images.zip(validation_labels) {
+            Some(files) => Some(files),
+            None if args.optional_value_of::<String>("mnist")?.is_some() => {
+                Some(dataset_files(args, MnistSplit::Test)?)
+            },
+            None => None
+        };
+
+        return Ok(Action::FindDuplicates(FindDuplicatesOption {
+            images_file,
+            labels_file,
+            other_files,
+            alphabet: args.value_of::<String>("alphabet")?.parse::<Alphabet>()?,
+            max_distance: args.value_of("duplicate-distance")?
+        }))
+    }
+
+    if let Some(output_images) = args.optional_value_of::<String>("convert-dataset")? {
+        let (images_file, labels_file) = dataset_files(args, MnistSplit::Train)?;
+        let output_labels = labels_file_for(args, &output_images, "convert-labels")?;
+        return Ok(Action::ConvertDataset(ConvertDatasetOption {
+            images_file,
+            labels_file,
+            alphabet: args.value_of::<String>("alphabet")?.parse::<Alphabet>()?,
+            subset: dataset_subset(args, seed)?,
+            skip_invalid: args.value_of("skip-invalid")?,
+            output_files: (output_images, output_labels)
+        }))
+    }
+
+    if let Some(output_dir) = args.optional_value_of::<String>("fgsm")? {
+        let (images_file, labels_file) = dataset_files(args, MnistSplit::Test)?;
+        return Ok(Action::Adversarial(AdversarialOption {
+            model_file: single_model_file(&model_files)?,
+            images_file,
+            labels_file,
+            alphabet: args.value_of::<String>("alphabet")?.parse::<Alphabet>()?,
+            output_dir,
+            epsilon: args.value_of("epsilon")?,
+            start: args.value_of("sample-index")?,
+            backend
+        }))
+    }
+
+    if let Some(data_dir) = args.optional_value_of::<String>("benchmark-suite")? {
+        return Ok(Action::BenchmarkSuite(BenchmarkOption {
+            model_file: single_model_file(&model_files)?,
+            data_dir,
+            from_scratch: args.value_of("from-scratch")?,
+            epochs: args.value_of("epochs")?,
+            manifest_file: args.optional_value_of("verify-sha256")?,
+            history_file: args.optional_value_of("bench-history")?,
+            seed,
+            backend
+        }))
+    }
+
+    if let Some(output_file) = args.optional_value_of::<String>("search")? {
+        let validation_images: Option<String> = args.optional_value_of("validation-images")?;
+        let validation_labels: Option<String> = args.optional_value_of("validation-labels")?;
+        let (images_file, labels_file) = dataset_files(args, MnistSplit::Train)?;
+
+        return Ok(Action::SearchArchitecture(SearchOption {
+            output_file,
+            images_file,
+            labels_file,
+            validation_files: validation_images.zip(validation_labels),
+            alphabet: args.value_of::<String>("alphabet")?.parse::<Alphabet>()?,
+            label_map,
+            sample_weights_file: args.optional_value_of("sample-weights")?,
+            subset: dataset_subset(args, seed)?,
+            skip_invalid: args.value_of("skip-invalid")?,
+            input_features: args.value_of::<String>("input-features")?.parse::<InputFeatures>()?,
+            search: ArchitectureSearch {
+                space: SearchSpace::default(),
+                candidates: args.value_of("search-candidates")?,
+                epochs: args.value_of("search-epochs")?,
+                batch_size: args.value_of("batch-size")?,
+                threads: args.value_of("threads")?
+            },
+            seed
+        }))
+    }
+
+    if let Some(config_file) = args.optional_value_of::<String>("sweep")? {
+        let validation_images: Option<String> = args.optional_value_of("validation-images")?;
+        let validation_labels: Option<String> = args.optional_value_of("validation-labels")?;
+        let validation_files = validation_images
+            .zip(validation_labels)
+            .ok_or(ErrorKind::SweepRequiresValidationSet)?;
+
+        // the children are given the files --mnist resolves to
+        let (images_file, labels_file) = dataset_files(args, MnistSplit::Train)?;
+        let mut training_args = vec![String::from("--images"), images_file];
+        if !labels_file.is_empty() {
+            training_args.extend([String::from("--labels"), labels_file]);
+        }
+        for option in ["alphabet", "script", "input-features", "threads", "compute-backend"] {
+            training_args.push(format!("--{}", option));
+            training_args.push(args.value_of::<String>(option)?);
+        }
+        if let Some(weights_file) = args.optional_value_of::<String>("sample-weights")? {
+            training_args.extend([String::from("--sample-weights"), weights_file]);
+        }
+        training_args.extend([String::from("--skip"), args.value_of::<String>("skip")?]);
+        if let Some(limit) = args.optional_value_of::<String>("limit")? {
+            training_args.extend([String::from("--limit"), limit]);
+        }
+        for flag in ["random-subset", "skip-invalid"] {
+            if args.value_of::<bool>(flag)? {
+                training_args.push(format!("--{}", flag));
+            }
+        }
+        if let Some(label_map_file) = label_map_file {
+            training_args.extend([String::from("--label-map"), label_map_file]);
+        }
+        // the same seed gives every point with the same hidden size the same initial weights
+        if let Some(seed) = seed {
+            training_args.extend([String::from("--seed"), seed.to_string()]);
+        }
+
+        return Ok(Action::Sweep(SweepOption {
+            model_file: single_model_file(&model_files)?,
+            validation_files,
+            alphabet: args.value_of::<String>("alphabet")?.parse::<Alphabet>()?,
+            label_map,
+            training_args,
+            sweep: Sweep {
+                config: SweepConfig::load(config_file)?,
+                dir: PathBuf::from(args.value_of::<String>("sweep-dir")?),
+                jobs: args.value_of("sweep-jobs")?
+            },
+            seed
+        }))
+    }
+
+    if args.value_of::<bool>("info")? {
+        return Ok(Action::Info(single_model_file(&model_files)?))
+    }
+
+    if args.value_of::<bool>("evaluate")? {
+        let (images_file, labels_file) = dataset_files(args, MnistSplit::Test)?;
+        return Ok(Action::Evaluate(EvaluationOption {
+            model_file: single_model_file(&model_files)?,
+            images_file,
+            labels_file,
+            alphabet: args.value_of::<String>("alphabet")?.parse::<Alphabet>()?,
+            label_map,
+            subset: dataset_subset(args, seed)?,
+            skip_invalid: args.value_of("skip-invalid")?,
+            batch_size: args.value_of("batch-size")?,
+            threads: args.value_of("threads")?,
+            backend
+        }))
+    }
+
+    if let Some(predictions_file) = args.optional_value_of::<String>("compare-predictions")? {
+        let (images_file, labels_file) = dataset_files(args, MnistSplit::Test)?;
+        return Ok(Action::ComparePredictions(ComparisonOption {
+            model_file: single_model_file(&model_files)?,
+            images_file,
+            labels_file,
+            predictions_file,
+            alphabet: args.value_of::<String>("alphabet")?.parse::<Alphabet>()?,
+            backend
+        }))
+    }
+
+    return if args.value_of::<bool>("train")? {
+        let (images_file, labels_file) = dataset_files(args, MnistSplit::Train)?;
+        let epochs: u32 = args.value_of("epochs")?;
+        let learning_rate: f64 = args.value_of("learning-rate")?;
+        let layer_multipliers = match args.optional_value_of::<String>("layer-lr-multipliers")? {
+            Some(value) => parse_list("layer-lr-multipliers", &value)?,
+            None => Vec::new()
+        };
+        let frozen_layers = match args.optional_value_of::<String>("freeze-layers")? {
+            Some(value) => parse_list("freeze-layers", &value)?,
+            None => Vec::new()
+        };
+        let plateau_reduction = match args.optional_value_of::<u32>("reduce-lr-on-plateau")? {
+            Some(patience) => Some(PlateauReduction {
+                patience,
+                factor: args.value_of("lr-reduction-factor")?,
+                min_learning_rate: args.value_of("min-learning-rate")?
+            }),
+            None => None
+        };
+        let cyclical_schedule = match args.optional_value_of::<String>("lr-cycle")? {
+            Some(policy) => Some(CyclicalSchedule {
+                policy: policy.parse::<CyclePolicy>()?,
+                period: args.value_of("lr-cycle-length")?,
+                min_learning_rate: args.value_of("min-learning-rate")?
+            }),
+            None => None
+        };
+        let augmentation = parse_augmentation(args)?;
+        let perturbation = match args.optional_value_of::<String>("robust-training")? {
+            Some(kind) => Some(Perturbation {
+                kind: kind.parse::<PerturbationKind>()?,
+                strength: args.value_of("robust-strength")?
+            }),
+            None => None
+        };
+        let distillation = match args.optional_value_of::<String>("teacher")? {
+            Some(teacher_file) => Some((teacher_file,
+                                        args.value_of("distillation-temperature")?,
+                                        args.value_of("distillation-weight")?)),
+            None => None
+        };
+        let learning_rate_sweep = match args.optional_value_of::<String>("lr-find")? {
+            Some(csv_file) => {
+                let range_value: String = args.value_of("lr-find-range")?;
+                let (min_learning_rate, max_learning_rate) = match parse_list::<f64>("lr-find-range", &range_value)?[..] {
+                    [min, max] if 0.0 < min && min < max => (min, max),
+                    _ => return Err(ErrorKind::InvalidOptionValue { option: "lr-find-range", value: range_value })
+                };
+
+                Some((csv_file, LearningRateSweep {
+                    min_learning_rate,
+                    max_learning_rate,
+                    batches: args.value_of("lr-find-batches")?,
+                    batch_size: args.value_of("batch-size")?,
+                    threads: args.value_of("threads")?
+                }))
+            },
+            None => None
+        };
+        let max_duration = match args.optional_value_of::<String>("max-duration")? {
+            Some(value) => Some(parse_duration("max-duration", &value)?),
+            None => None
+        };
+        let ema_decay: Option<f64> = args.optional_value_of("ema-decay")?;
+        if let Some(decay) = ema_decay.filter(|decay| !(0.0..1.0).contains(decay)) {
+            return Err(ErrorKind::InvalidOptionValue { option: "ema-decay", value: decay.to_string() })
+        }
+        let class_weights = match args.optional_value_of::<String>("class-weights")? {
+            Some(value) if value.eq_ignore_ascii_case("balanced") => Some(ClassWeights::Balanced),
+            Some(value) => match parse_list::<f64>("class-weights", &value)? {
+                weights if weights.iter().any(|weight| *weight < 0.0) =>
+                    return Err(ErrorKind::InvalidOptionValue { option: "class-weights", value }),
+                weights => Some(ClassWeights::Explicit(weights))
+            },
+            None => None
+        };
+        let sampling = match args.optional_value_of::<String>("sampling")? {
+            Some(value) if value.eq_ignore_ascii_case("balanced") => Some(Sampling::Balanced),
+            Some(value) => match value.parse::<usize>() {
+                Ok(max_per_class) if max_per_class > 0 => Some(Sampling::Capped(max_per_class)),
+                _ => return Err(ErrorKind::InvalidOptionValue { option: "sampling", value })
+            },
+            None => None
+        };
+        let checkpoint_interval: usize = args.value_of("checkpoint-every")?;
+        let resume_file: Option<String> = args.optional_value_of("resume")?;
+        let init_file: Option<String> = args.optional_value_of("init-from")?;
+
+        let validation_images: Option<String> = args.optional_value_of("validation-images")?;
+        let validation_labels: Option<String> = args.optional_value_of("validation-labels")?;
+        let validation_files = validation_images.zip(validation_labels);
+
+        let extra_source = match args.optional_value_of::<String>("extra-images")? {
+            Some(images_file) => {
+                // like --labels, left out for CSV files and image directories
+                let labels_file = labels_file_for(args, &images_file, "extra-labels")?;
+                Some((images_file, labels_file, args.value_of("extra-weight")?))
+            },
+            None => None
+        };
+
+        let class_count: Option<usize> = args.optional_value_of("classes")?;
+        let class_names_value: Option<String> = args.optional_value_of("class-names")?;
+        let class_names = match &class_names_value {
+            Some(value) => Some(parse_list::<String>("class-names", value)?),
+            None => None
+        };
+
+        let alphabet = match (class_count, &class_names) {
+            (Some(count), Some(names)) if count != names.len() =>
+                return Err(ErrorKind::InvalidOptionValue {
+                    option: "class-names",
+                    value: class_names_value.unwrap_or_default()
+                }),
+            (Some(count), _) => Alphabet::Custom(count),
+            (None, Some(names)) => Alphabet::Custom(names.len()),
+            (None, None) => match args.value_of::<String>("alphabet")?.parse::<Alphabet>()? {
+                Alphabet::Digits(_) =>
+                    Alphabet::Digits(args.value_of::<String>("script")?.parse::<DigitScript>()?),
+                alphabet => alphabet
+            }
+        };
+
+        // the classes of a label map are named by the map unless --class-names renames them
+        if let (Some(label_map), Some(names)) = (&label_map, &class_names) {
+            if label_map.class_count() != names.len() {
+                return Err(ErrorKind::InvalidOptionValue {
+                    option: "class-names",
+                    value: class_names_value.unwrap_or_default()
+                })
+            }
+        }
+
+        Ok(Action::Train(Box::new(TrainingOption {
+            model_file: single_model_file(&model_files)?,
+            runs_dir,
+            images_file,
+            labels_file,
+            epochs,
+            learning_rate,
+            layer_multipliers,
+            frozen_layers,
+            plateau_reduction,
+            warmup_batches: args.value_of("warmup-batches")?,
+            cyclical_schedule,
+            augmentation,
+            perturbation,
+            shuffle: args.value_of("shuffle")?,
+            preprocess: args.value_of("preprocess")?,
+            normalize: args.value_of("normalize")?,
+            distillation,
+            learning_rate_sweep,
+            max_duration,
+            ema_decay,
+            class_weights,
+            sampling,
+            sample_weights_file: args.optional_value_of("sample-weights")?,
+            subset: dataset_subset(args, seed)?,
+            skip_invalid: args.value_of("skip-invalid")?,
+            extra_source,
+            batch_size: args.value_of("batch-size")?,
+            threads: args.value_of("threads")?,
+            checkpoint_interval,
+            resume_file,
+            init_file,
+            architecture_file: args.optional_value_of("architecture")?,
+            validation_files,
+            validation_split: args.optional_value_of("validation-split")?,
+            alphabet,
+            label_map,
+            input_features: args.value_of::<String>("input-features")?.parse::<InputFeatures>()?,
+            class_names,
+            rejection_threshold: args.optional_value_of("rejection-threshold")?,
+            detect_nan: args.value_of("detect-nan")?,
+            rollback_on_divergence: args.value_of("rollback-on-divergence")?,
+            calibrate: args.value_of("calibrate")?,
+            manifest_file: args.optional_value_of("verify-sha256")?,
+            seed,
+            backend
+        })))
+    } else {
+        let mc_dropout = match args.optional_value_of::<usize>("mc-dropout")? {
+            Some(0) => return Err(ErrorKind::InvalidOptionValue { option: "mc-dropout", value: String::from("0") }),
+            Some(passes) => {
+                let rate: f64 = args.value_of("mc-dropout-rate")?;
+                if !(0.0..1.0).contains(&rate) {
+                    return Err(ErrorKind::InvalidOptionValue { option: "mc-dropout-rate", value: rate.to_string() })
+                }
+                Some((passes, rate))
+            },
+            None => None
+        };
+
+        let collect_files = match args.optional_value_of::<String>("collect")? {
+            Some(images_file) => {
+                let labels_file = labels_file_for(args, &images_file, "collect-labels")?;
+                Some((images_file, labels_file))
+            },
+            None => None
+        };
+
+        let quantized: bool = args.value_of("quantized")?;
+        let multiscale: bool = args.value_of("multiscale")?;
+        let max_latency = args.optional_value_of("max-latency-ms")?.map(Duration::from_millis);
+        let profiles_dir: Option<String> = args.optional_value_of("profiles")?;
+        // MC dropout samples the full network itself, with none of the other ways to classify
+        reject_conflicts(("mc-dropout", mc_dropout.is_some()), &[("multiscale", multiscale),
+                                                                ("quantized", quantized),
+                                                                ("max-latency-ms", max_latency.is_some()),
+                                                                ("profiles", profiles_dir.is_some())])?;
+        // profiles are full networks, tuned as the drawings are taught
+        reject_conflicts(("profiles", profiles_dir.is_some()), &[("quantized", quantized),
+                                                                ("max-latency-ms", max_latency.is_some())])?;
+
+        Ok(Action::ShowGui(GuiOption {
+            model_files,
+            latest_run_of,
+            voting: args.value_of::<String>("ensemble-voting")?.parse::<Voting>()?,
+            backend,
+            quantized,
+            multiscale,
+            grid_cells: args.value_of("grid-cells")?,
+            max_latency,
+            profiles_dir,
+            profile: args.optional_value_of("profile")?,
+            collect_files,
+            mc_dropout
+        }))
     }
 }
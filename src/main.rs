@@ -1,11 +1,18 @@
 use digit_recognition::{ErrorKind, launch};
+use digit_recognition::i18n::{tr, tr_args};
 
 fn main() {
     if let Some(err) = launch().err() {
         if let ErrorKind::CliError(err) = err {
             eprintln!("{}", err)
         } else {
-            eprintln!("Error: {}", err);
+            let code = err.code();
+            eprintln!("{}", tr_args("error-message", &[
+                ("code", code.to_string()),
+                ("summary", tr(code)),
+                ("details", err.to_string())
+            ]));
+            eprintln!("{}", tr_args("explain-hint", &[("code", code.to_string())]));
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,151 @@
+use std::fs::{File, OpenOptions, remove_file, rename};
+use std::io;
+use std::path::{Path, PathBuf};
+use log::info;
+use thiserror::Error;
+use crate::checksum::{self, sha256};
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error(transparent)]
+    ChecksumError(#[from] checksum::ErrorKind),
+
+    #[error("cannot download {url} ({source})")]
+    CannotDownload {
+        url: String,
+        source: Box<ureq::Error>
+    },
+
+    #[error("cannot write {file} ({source})")]
+    CannotWriteFile {
+        file: String,
+        source: io::Error
+    },
+
+    #[error("downloaded {file} has SHA-256 {actual}, but MNIST's is {expected}; the mirror may serve a different file")]
+    ChecksumMismatch {
+        file: String,
+        expected: &'static str,
+        actual: String
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::ChecksumError(err) => err.code(),
+            ErrorKind::CannotDownload { .. } => "E1601",
+            ErrorKind::CannotWriteFile { .. } => "E1602",
+            ErrorKind::ChecksumMismatch { .. } => "E1603"
+        }
+    }
+}
+
+/// Where the MNIST files are downloaded from; yann.lecun.com, where they were
+/// first published, often refuses downloads.
+pub const DEFAULT_MIRROR: &str = "https://ossci-datasets.s3.amazonaws.com/mnist/";
+
+const PARTIAL_FILE_EXTENSION: &str = "part";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MnistSplit {
+    /// 60000 samples for training.
+    Train,
+    /// 10000 samples for evaluation.
+    Test
+}
+
+struct MnistFile {
+    name: &'static str,
+    sha256: &'static str
+}
+
+const FILES: [MnistFile; 4] = [
+    MnistFile {
+        name: "train-images-idx3-ubyte.gz",
+        sha256: "440fcabf73cc546fa21475e81ea370265605f56be210a4024d2ca8f203523609"
+    },
+    MnistFile {
+        name: "train-labels-idx1-ubyte.gz",
+        sha256: "3552534a0a558bbed6aed32b30c495cca23d567ec52cac8be1a0730e8010255c"
+    },
+    MnistFile {
+        name: "t10k-images-idx3-ubyte.gz",
+        sha256: "8d422c7b0a1c1c79245a5bcf07fe86e33eeafee792b84584aec276f5a2dbc4e6"
+    },
+    MnistFile {
+        name: "t10k-labels-idx1-ubyte.gz",
+        sha256: "f7ae60f92e00ec6debd23a6088c31dbd2371eca3ffa0defaefb259924204aec6"
+    }
+];
+
+/// Images and labels file of `split` in a directory MNIST was fetched into.
+pub fn files<P: AsRef<Path>>(dir: P, split: MnistSplit) -> (PathBuf, PathBuf) {
+    let (images, labels) = match split {
+        MnistSplit::Train => (&FILES[0], &FILES[1]),
+        MnistSplit::Test => (&FILES[2], &FILES[3])
+    };
+
+    (dir.as_ref().join(images.name), dir.as_ref().join(labels.name))
+}
+
+/// Downloads the four MNIST files into `dir` from `mirror`, a URL the file
+/// names are appended to. Files that are already there are kept, and an
+/// interrupted download continues where it stopped.
+pub fn fetch<P: AsRef<Path>>(dir: P, mirror: &str) -> Result<()> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)
+        .map_err(|source| ErrorKind::CannotWriteFile { file: dir.display().to_string(), source })?;
+
+    for file in &FILES {
+        let url = format!("{}/{}", mirror.trim_end_matches('/'), file.name);
+        fetch_file(&url, &dir.join(file.name), file.sha256)?;
+    }
+
+    Ok(())
+}
+
+fn fetch_file(url: &str, path: &Path, expected: &'static str) -> Result<()> {
+    if path.exists() && sha256(path)? == expected {
+        info!("{} is already downloaded", path.display());
+        return Ok(())
+    }
+
+    let partial_path = path.with_extension(format!("gz.{}", PARTIAL_FILE_EXTENSION));
+    let cannot_write = |source| ErrorKind::CannotWriteFile { file: partial_path.display().to_string(), source };
+
+    let offset = partial_path.metadata().map_or(0, |metadata| metadata.len());
+    let mut request = ureq::get(url);
+    if offset > 0 {
+        request = request.set("Range", &format!("bytes={}-", offset));
+    }
+
+    match request.call() {
+        Ok(response) => {
+            // servers that ignore the range send the whole file again
+            let resumed = response.status() == 206;
+            info!("downloading {}{}", url, if resumed { format!(" from byte {}", offset) } else { String::new() });
+
+            let file = if resumed {
+                OpenOptions::new().append(true).open(&partial_path)
+            } else {
+                File::create(&partial_path)
+            };
+            io::copy(&mut response.into_reader(), &mut file.map_err(cannot_write)?).map_err(cannot_write)?;
+        },
+        // the previous download got the whole file but stopped before it was checked
+        Err(ureq::Error::Status(416, _)) if offset > 0 => {},
+        Err(err) => return Err(ErrorKind::CannotDownload { url: url.to_string(), source: Box::new(err) })
+    }
+
+    let actual = sha256(&partial_path)?;
+    if actual != expected {
+        // resuming a corrupted file would never succeed, so start over next time
+        remove_file(&partial_path).map_err(cannot_write)?;
+        return Err(ErrorKind::ChecksumMismatch { file: path.display().to_string(), expected, actual })
+    }
+
+    rename(&partial_path, path).map_err(cannot_write)
+}
@@ -0,0 +1,98 @@
+use nalgebra::DVector;
+use crate::data::{Image, ImageSize};
+use crate::network::{Classifier, Prediction, Result, Scalar};
+use crate::training::input_vector;
+use crate::transform::Preprocessing;
+
+/// Sides of the crops tried by default, as fractions of the image's shorter side.
+pub const DEFAULT_SCALES: &[f64] = &[1.0, 0.75, 0.5, 0.35];
+
+/// The most confident prediction of a multi-scale search and the crop it was made on.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ScaledPrediction {
+    pub prediction: Prediction,
+    pub scale: f64,
+    pub x: u32,
+    pub y: u32
+}
+
+/// Classifies square crops of `image` at each of `scales`, sliding every crop
+/// by half its side, and returns the most confident prediction. Meant for
+/// images where the digit may be much smaller than the picture.
+pub fn predict_multiscale(classifier: &dyn Classifier,
+                          image: &Image,
+                          scales: &[f64]) -> Result<Option<ScaledPrediction>> {
+    let size = image.size();
+    let shorter_side = size.width.min(size.height);
+
+    let mut crops = Vec::new();
+    for &scale in scales {
+        let side = ((shorter_side as f64) * scale).round() as u32;
+        if side == 0 || side > shorter_side {
+            continue
+        }
+
+        let stride = (side / 2).max(1);
+        for y in crop_offsets(size.height, side, stride) {
+            for x in crop_offsets(size.width, side, stride) {
+                crops.push((scale, x, y, side));
+            }
+        }
+    }
+
+    let (input_size, preprocessing) = (classifier.input_size(), classifier.preprocessing());
+    let inputs = crops
+        .iter()
+        .map(|&(_, x, y, side)| crop_input(image, x, y, side, input_size, preprocessing))
+        .collect();
+
+    let best = classifier
+        .predict_batch(inputs)?
+        .into_iter()
+        .zip(crops)
+        // `max_by` keeps the last of equal elements; prefer the larger crops on ties
+        .rev()
+        .max_by(|(a, _), (b, _)| a.confidence.total_cmp(&b.confidence))
+        .map(|(prediction, (scale, x, y, _))| ScaledPrediction { prediction, scale, x, y });
+
+    Ok(best)
+}
+
+/// Offsets of the crops along a dimension, the last one flush with the edge.
+pub(crate) fn crop_offsets(length: u32, side: u32, stride: u32) -> Vec<u32> {
+    let last = length - side;
+    let mut offsets: Vec<u32> = (0..=last).step_by(stride as usize).collect();
+    if offsets.last() != Some(&last) {
+        offsets.push(last);
+    }
+
+    offsets
+}
+
+/// Network input for the `side x side` crop at `(x, y)`, resized to
+/// `input_size` and prepared with `preprocessing`.
+pub(crate) fn crop_input(image: &Image,
+                         x: u32,
+                         y: u32,
+                         side: u32,
+                         input_size: ImageSize,
+                         preprocessing: Preprocessing) -> DVector<Scalar> {
+    let width = image.size().width as usize;
+    let pixels: Vec<u8> = image
+        .pixels()
+        .chunks(width)
+        .skip(y as usize)
+        .take(side as usize)
+        .flat_map(|row| &row[x as usize..(x + side) as usize])
+        .copied()
+        .collect();
+
+    let mut crop = Image::builder()
+        .with_size(ImageSize::square(side))
+        .with_pixels_row_major(pixels)
+        .build()
+        .resize(input_size);
+    preprocessing.prepare(&mut crop);
+
+    input_vector(&crop)
+}
@@ -1,22 +1,27 @@
-use std::alloc::LayoutError;
-use std::fs::{File, OpenOptions, write};
+use std::fmt::{Display, Formatter};
+use std::fs::{File, OpenOptions};
 use std::{io, mem};
-use std::io::{BufReader, BufWriter};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
 use std::path::Path;
-use nalgebra::{ArrayStorage, Const, DMatrix, DVector, Dynamic, max, OMatrix, OVector, U10, Unit, VecStorage, Vector, Vector3};
-use rand_distr::{Binomial, Normal, StandardNormal};
-use rand::{Rng, thread_rng};
-use rand::rngs::ThreadRng;
+use std::str::FromStr;
+use nalgebra::{DMatrix, DVector};
+use rand_distr::Normal;
+use rand::{Rng, RngCore, thread_rng};
 use rand::distributions::{Bernoulli, Distribution};
-use serde::{Serialize, Deserialize};
-use std::f64::consts::E;
+use rayon::prelude::*;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde_json::Value;
 use std::iter::zip;
-use std::ops::{AddAssign, Mul, MulAssign, SubAssign};
-use druid::kurbo::Shape;
-use druid::piet::util::resolve_range;
+use log::trace;
 use thiserror::Error;
-use typed_io::Endianness::LE;
-use crate::launch;
+use crate::data::{Image, ImageSize};
+use crate::features::{InputFeatures, Normalization};
+use crate::autograd::{Gradients, NodeId, Tape};
+use crate::backend::ComputeBackend;
+use crate::sparse::CsrMatrix;
+use crate::training::input_vector;
+use crate::training_data::Label;
+use crate::transform::Preprocessing;
 
 #[derive(Error, Debug)]
 pub enum ErrorKind {
@@ -27,91 +32,357 @@ pub enum ErrorKind {
     CannotSaveNeuralNetwork(#[source] io::Error),
 
     #[error("cannot parse neural network save file ({0})")]
-    CannotParseNeuralNetworkFile(#[from] serde_json::Error)
+    CannotParseNeuralNetworkFile(#[from] serde_json::Error),
+
+    #[error("cannot decode neural network save file ({0})")]
+    CannotDecodeNeuralNetworkFile(#[from] ciborium::de::Error<io::Error>),
+
+    #[error("cannot encode neural network ({0})")]
+    CannotEncodeNeuralNetwork(#[from] ciborium::ser::Error<io::Error>),
+
+    #[error("neural network save file has schema version {found}, but this program only reads versions up to {supported}; \
+             it was saved by a newer version of the program")]
+    ModelSchemaTooNew {
+        found: u64,
+        supported: u64
+    },
+
+    #[error("neural network save file does not describe a network")]
+    InvalidModelSchema,
+
+    #[error("neural network save file has format version {found}, but only versions up to {supported} are supported")]
+    UnsupportedModelFormatVersion {
+        found: u8,
+        supported: u8
+    },
+
+    #[error("this network requires input to be a {expected}-dimensional column vector, found {found}")]
+    InputSizeMismatch {
+        expected: usize,
+        found: usize
+    },
+
+    #[error("label {class} is out of range for a network with {class_count} classes")]
+    LabelOutOfRange {
+        class: u8,
+        class_count: usize
+    },
+
+    #[error("unknown activation `{0}` (expected one of: sigmoid, tanh, relu)")]
+    UnknownActivation(String),
+
+    #[error("dropout rate {0} is not in [0, 1)")]
+    InvalidDropoutRate(f64)
 }
 
 pub type Result<T> = std::result::Result<T, ErrorKind>;
 
-pub const INPUT_LAYER_SIZE: usize = 28*28;
-pub const OUTPUT_LAYER_SIZE: usize = 10;
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::CannotLoadNeuralNetwork(_) => "E0102",
+            ErrorKind::CannotSaveNeuralNetwork(_) => "E0103",
+            ErrorKind::CannotParseNeuralNetworkFile(_) => "E0101",
+            ErrorKind::CannotDecodeNeuralNetworkFile(_) => "E0104",
+            ErrorKind::CannotEncodeNeuralNetwork(_) => "E0105",
+            ErrorKind::ModelSchemaTooNew { .. } => "E0106",
+            ErrorKind::InvalidModelSchema => "E0107",
+            ErrorKind::UnsupportedModelFormatVersion { .. } => "E0108",
+            ErrorKind::InputSizeMismatch { .. } => "E0109",
+            ErrorKind::LabelOutOfRange { .. } => "E0110",
+            ErrorKind::UnknownActivation(_) => "E0111",
+            ErrorKind::InvalidDropoutRate(_) => "E0112"
+        }
+    }
+}
+
+/// Element type of weights and activations; single precision halves memory
+/// and speeds up matrix math at no noticeable accuracy cost for this network.
+#[cfg(not(feature = "f32"))]
+pub type Scalar = f64;
+#[cfg(feature = "f32")]
+pub type Scalar = f32;
+
+/// Widens a [`Scalar`] to `f64`; the cast only does something with the
+/// `f32` feature.
+#[allow(clippy::unnecessary_cast)]
+pub fn to_f64(x: Scalar) -> f64 {
+    x as f64
+}
+
+pub const DEFAULT_INPUT_SIZE: ImageSize = ImageSize { width: 28, height: 28 };
 const HIDDEN_LAYER_SIZE: usize = 20;
 
-const PRECISION: f64 = 1e-8;
+const PRECISION: Scalar = 1e-8;
+
+/// Leads a model saved in the binary format, followed by [`MODEL_FORMAT_VERSION`].
+const MODEL_MAGIC: &[u8; 4] = b"DRNN";
+const MODEL_FORMAT_VERSION: u8 = 1;
 
-const MIN_WEIGHT_OR_BIAS: f64 = -1.0 + PRECISION;
-const MAX_WEIGHT_OR_BIAS: f64 = 1.0 - PRECISION;
+/// Layout of the saved network, independent of the [`ModelFormat`]. Bump it
+/// and add an upgrade step to [`migrate`] whenever the layout changes.
+const SCHEMA_VERSION: u64 = 5;
 
-const LEARNING_RATE: f64 = 0.05;
-const ACCURACY: f64 = 0.01;
+/// Serialized as the current [`SCHEMA_VERSION`]; files with older versions
+/// are upgraded by [`migrate`] before they are deserialized.
+#[derive(Default)]
+struct SchemaVersion;
+
+impl Serialize for SchemaVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_u64(SCHEMA_VERSION)
+    }
+}
+
+impl<'de> Deserialize<'de> for SchemaVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        u64::deserialize(deserializer).map(|_| SchemaVersion)
+    }
+}
+
+/// Upgrades a saved network to the current [`SCHEMA_VERSION`] one version at a time.
+fn migrate(mut network: Value) -> Result<Value> {
+    let fields = network.as_object_mut().ok_or(ErrorKind::InvalidModelSchema)?;
+    let version = fields.get("schema_version").and_then(Value::as_u64).unwrap_or(0);
+    if version > SCHEMA_VERSION {
+        return Err(ErrorKind::ModelSchemaTooNew { found: version, supported: SCHEMA_VERSION })
+    }
+
+    for from in version..SCHEMA_VERSION {
+        match from {
+            // unversioned files: every field added since the first release has a default
+            0 => {},
+            // `input_features` was added, and defaults to raw pixels
+            1 => {},
+            // `activation` was added, and defaults to the sigmoid every earlier network used
+            2 => {},
+            // `preprocessing` was added, and defaults to none
+            3 => {},
+            // `normalization` was added, and defaults to none
+            4 => {},
+            _ => unreachable!("no upgrade from schema version {}", from)
+        }
+    }
+
+    fields.insert(String::from("schema_version"), Value::from(SCHEMA_VERSION));
+
+    Ok(network)
+}
+
+/// How a model is stored on disk.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ModelFormat {
+    /// [`MODEL_MAGIC`], a version byte and the network encoded as CBOR.
+    Binary,
+    /// Human-readable, but several times larger and slower to parse.
+    Json
+}
+
+impl ModelFormat {
+    /// JSON for files with the `.json` extension, binary otherwise.
+    pub fn for_path<P: AsRef<Path>>(file: P) -> ModelFormat {
+        return match file.as_ref().extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => ModelFormat::Json,
+            _ => ModelFormat::Binary
+        }
+    }
+}
 
 #[inline(always)]
-fn relu(x: f64) -> f64 {
+pub fn sigmoid(x: Scalar) -> Scalar {
     /*return if x >= 0.0 {
         x
     } else {
         0.0
     }*/
 
-    1.0 / (1.0 + E.powf(-x))
+    1.0 / (1.0 + (-x).exp())
 
     //x.tanh()
 }
 
 #[inline(always)]
-fn relu_prime(x: f64) -> f64 {
+pub fn sigmoid_prime(x: Scalar) -> Scalar {
     /*return if x >= 0.0 {
         1.0
     } else {
         0.0
     }*/
-    let val = relu(x);
+    let val = sigmoid(x);
     val*(1.0-val)
 
     /*let val = x.tanh();
     1.0 - val*val*/
 }
 
-fn cross_entropy_loss(out: &DVector<f64>, expected: &DVector<f64>) -> f64 {
-    //eprintln!("out: {:.10}", out);
-    //eprintln!("expected: {:.10}", expected);
+pub fn tanh(x: Scalar) -> Scalar {
+    x.tanh()
+}
+
+pub fn tanh_prime(x: Scalar) -> Scalar {
+    let val = x.tanh();
+    1.0 - val*val
+}
+
+pub fn relu(x: Scalar) -> Scalar {
+    x.max(0.0)
+}
+
+pub fn relu_prime(x: Scalar) -> Scalar {
+    return if x > 0.0 {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Nonlinearity applied after every hidden layer.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Activation {
+    #[default]
+    Sigmoid,
+    Tanh,
+    Relu
+}
+
+impl Activation {
+    pub fn function(&self) -> fn(Scalar) -> Scalar {
+        return match self {
+            Activation::Sigmoid => sigmoid,
+            Activation::Tanh => tanh,
+            Activation::Relu => relu
+        }
+    }
+
+    pub fn derivative(&self) -> fn(Scalar) -> Scalar {
+        return match self {
+            Activation::Sigmoid => sigmoid_prime,
+            Activation::Tanh => tanh_prime,
+            Activation::Relu => relu_prime
+        }
+    }
 
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Activation::Sigmoid => "sigmoid",
+            Activation::Tanh => "tanh",
+            Activation::Relu => "relu"
+        }
+    }
+}
+
+impl FromStr for Activation {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self> {
+        return match s.to_lowercase().as_str() {
+            "sigmoid" => Ok(Activation::Sigmoid),
+            "tanh" => Ok(Activation::Tanh),
+            "relu" => Ok(Activation::Relu),
+            _ => Err(ErrorKind::UnknownActivation(s.to_string()))
+        }
+    }
+}
+
+/// Sizes of the hidden layers, input side first, and their activation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Architecture {
+    pub hidden_layers: Vec<usize>,
+    #[serde(default)]
+    pub activation: Activation
+}
+
+impl Default for Architecture {
+    fn default() -> Self {
+        Architecture {
+            hidden_layers: vec![HIDDEN_LAYER_SIZE],
+            activation: Activation::default()
+        }
+    }
+}
+
+pub fn cross_entropy_loss(out: &DVector<Scalar>, expected: &DVector<Scalar>) -> Scalar {
     let mut result = 0.0;
     for (i, out_i) in zip(expected.iter(), out.iter()) {
         let (i, out_i) = (*i, *out_i);
-        result += i*out_i.log2();
+        result += i*out_i.max(PRECISION).ln();
     }
 
     -result
 }
 
-fn softmax(vec: &mut DVector<f64>) {
-    let mx = vec.max();
-    vec.apply(|x| *x = E.powf(*x-mx));
+/// Gradient of [`cross_entropy_loss`] w.r.t. `out`.
+pub fn cross_entropy_loss_gradient(out: &DVector<Scalar>, expected: &DVector<Scalar>) -> DVector<Scalar> {
+    expected.zip_map(out, |i, out_i| -i / out_i.max(PRECISION))
+}
+
+/// Zeroes each of `len` units with probability `rate` and scales the kept
+/// ones up, so the expected output of a layer stays the same.
+fn dropout_mask(len: usize, rate: f64, rng: &mut dyn RngCore) -> DVector<Scalar> {
+    let keep = Bernoulli::new(1.0 - rate).expect("dropout rate is checked by the caller");
+    let scale = (1.0 / (1.0 - rate)) as Scalar;
+    DVector::from_fn(len, |_, _| if keep.sample(rng) { scale } else { 0.0 })
+}
+
+/// Layers whose weights are mostly zeros (after pruning) are stored and
+/// computed as sparse matrices.
+const MAX_SPARSE_DENSITY: f64 = 0.5;
 
-    let exp_sum = vec.sum();
-    vec.apply(|x| {
-        *x /= exp_sum
-    });
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredWeights {
+    Dense(DMatrix<Scalar>),
+    Sparse(CsrMatrix)
 }
 
-fn softmax_prime(vec: &DVector<f64>) -> DVector<f64> {
-    let mut result = vec.clone_owned();
-    softmax(&mut result);
+#[derive(Deserialize)]
+struct StoredLayer {
+    weights: StoredWeights,
+    biases: DVector<Scalar>
+}
 
-    result.apply(|x| *x = *x*(1.0-*x));
-    result
+fn serialize_weights<S: Serializer>(weights: &DMatrix<Scalar>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    let sparse = CsrMatrix::from_dense(weights);
+    return if sparse.density() <= MAX_SPARSE_DENSITY {
+        sparse.serialize(serializer)
+    } else {
+        weights.serialize(serializer)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(from = "StoredLayer")]
 struct Layer {
-    weights: DMatrix<f64>,
-    biases: DVector<f64>
+    #[serde(serialize_with = "serialize_weights")]
+    weights: DMatrix<Scalar>,
+    biases: DVector<Scalar>,
+
+    #[serde(skip)]
+    sparse_weights: Option<CsrMatrix>
+}
+
+impl From<StoredLayer> for Layer {
+    fn from(layer: StoredLayer) -> Self {
+        let weights = match layer.weights {
+            StoredWeights::Dense(weights) => weights,
+            StoredWeights::Sparse(weights) => weights.to_dense()
+        };
+
+        let mut layer = Layer {
+            weights,
+            biases: layer.biases,
+            sparse_weights: None
+        };
+        layer.update_sparse_weights();
+        layer
+    }
 }
 
 impl Layer {
-    fn new_untrained(rng: &mut impl Rng,
-                     weight_distr: &impl Distribution<f64>,
-                     bias_distr: &impl Distribution<f64>,
+    fn new_untrained(rng: &mut dyn RngCore,
+                     weight_distr: &impl Distribution<Scalar>,
+                     bias_distr: &impl Distribution<Scalar>,
                      prev_dim: usize,
                      dim: usize) -> Layer {
         let b_distr = Bernoulli::new(0.5).unwrap();
@@ -122,7 +393,7 @@ impl Layer {
         let biases =
             DVector::from_fn(dim, |_, _| {
                 let x = rng.sample(bias_distr);
-                return if rng.sample(&b_distr) {
+                return if rng.sample(b_distr) {
                     -x
                 } else {
                     x
@@ -131,155 +402,799 @@ impl Layer {
 
         Layer {
             weights,
-            biases
+            biases,
+            sparse_weights: None
         }
     }
 
+    fn update_sparse_weights(&mut self) {
+        let sparse = CsrMatrix::from_dense(&self.weights);
+        self.sparse_weights = if sparse.density() <= MAX_SPARSE_DENSITY {
+            Some(sparse)
+        } else {
+            None
+        };
+    }
+
+    fn zero_weights(&self) -> usize {
+        self.weights.iter().filter(|w| **w == 0.0).count()
+    }
+
     fn dim(&self) -> usize {
         self.biases.nrows()
     }
 }
 
+fn default_input_size() -> ImageSize {
+    DEFAULT_INPUT_SIZE
+}
+
+fn default_temperature() -> Scalar {
+    1.0
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct NeuralNetwork {
-    layers: Vec<Layer>
+    #[serde(default)]
+    schema_version: SchemaVersion,
+
+    layers: Vec<Layer>,
+
+    #[serde(default = "default_input_size")]
+    input_size: ImageSize,
+
+    #[serde(default)]
+    class_names: Vec<String>,
+
+    #[serde(default)]
+    rejection_threshold: Option<f64>,
+
+    #[serde(default = "default_temperature")]
+    temperature: Scalar,
+
+    #[serde(default)]
+    input_features: InputFeatures,
+
+    #[serde(default)]
+    activation: Activation,
+
+    #[serde(default)]
+    preprocessing: Preprocessing,
+
+    #[serde(default)]
+    normalization: Option<Normalization>,
+
+    #[serde(skip)]
+    hooks: Vec<LayerHook>,
+
+    #[serde(skip)]
+    backend: ComputeBackend
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Pass {
+    Forward,
+    Backward
+}
+
+/// Values passing through a layer: its activations on the forward pass,
+/// and the gradient of the loss w.r.t. its pre-activations on the backward pass.
+pub struct LayerEvent<'a> {
+    pub pass: Pass,
+    pub layer: usize,
+    pub values: &'a DVector<Scalar>
 }
 
-struct NetworkResult {
-    result: DVector<f64>,
-    activations: Vec<DVector<f64>>,
-    derivatives: Vec<DVector<f64>>
+pub type LayerHook = Box<dyn Fn(&LayerEvent) + Send + Sync>;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Prediction {
+    pub class: usize,
+    pub confidence: f64
+}
+
+/// Mean prediction of several forward passes with dropout, see
+/// [`NeuralNetwork::predict_mc_dropout`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct UncertainPrediction {
+    pub class: usize,
+    /// Mean probability of `class` over the passes.
+    pub confidence: f64,
+    /// Standard deviation of the probability of `class` over the passes.
+    pub uncertainty: f64
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sparsity {
+    pub zero_weights: usize,
+    pub total_weights: usize
+}
+
+impl Sparsity {
+    pub fn ratio(&self) -> f64 {
+        (self.zero_weights as f64) / (self.total_weights.max(1) as f64)
+    }
+}
+
+/// One row of a [`NetworkSummary`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerSummary {
+    /// `dense`, or `sparse` for pruned layers computed as sparse matrices.
+    pub kind: &'static str,
+    pub inputs: usize,
+    pub outputs: usize,
+    pub parameters: usize,
+    pub activation: &'static str
+}
+
+/// Layer-by-layer description of a network, like Keras' `model.summary()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkSummary {
+    pub input_size: ImageSize,
+    pub input_features: InputFeatures,
+    pub layers: Vec<LayerSummary>,
+    pub class_names: Vec<String>,
+    pub temperature: Scalar,
+    pub rejection_threshold: Option<f64>
+}
+
+impl NetworkSummary {
+    pub fn parameter_count(&self) -> usize {
+        self.layers.iter().map(|layer| layer.parameters).sum()
+    }
+}
+
+impl Display for NetworkSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "input: {}x{} {} ({} values)",
+                 self.input_size.width, self.input_size.height, self.input_features.name(),
+                 self.input_features.input_len(self.input_size))?;
+
+        writeln!(f, "{:<6} {:<7} {:<14} {:>10}  activation", "layer", "type", "shape", "parameters")?;
+        for (index, layer) in self.layers.iter().enumerate() {
+            writeln!(f, "{:<6} {:<7} {:<14} {:>10}  {}",
+                     index, layer.kind, format!("{} -> {}", layer.inputs, layer.outputs),
+                     layer.parameters, layer.activation)?;
+        }
+        writeln!(f, "total parameters: {}", self.parameter_count())?;
+
+        match self.class_names.is_empty() {
+            true => writeln!(f, "classes: {}", self.layers.last().map_or(0, |layer| layer.outputs))?,
+            false => writeln!(f, "classes: {}", self.class_names.join(" "))?
+        }
+
+        write!(f, "softmax temperature: {:.3}", self.temperature)?;
+        if let Some(threshold) = self.rejection_threshold {
+            write!(f, ", rejection threshold: {:.2}", threshold)?;
+        }
+        writeln!(f)
+    }
+}
+
+/// Anything that classifies images the way [`NeuralNetwork`] does.
+pub trait Classifier {
+    fn input_size(&self) -> ImageSize;
+
+    /// Number of classes, which are numbered from 0.
+    fn output_size(&self) -> usize;
+
+    fn class_name(&self, class: usize) -> String;
+
+    fn rejection_threshold(&self) -> Option<f64>;
+
+    /// How images are prepared before they are turned into inputs.
+    fn preprocessing(&self) -> Preprocessing {
+        Preprocessing::default()
+    }
+
+    fn predict(&self, input: DVector<Scalar>) -> Result<Prediction>;
+
+    /// Predictions for several inputs, in the same order.
+    fn predict_batch(&self, inputs: Vec<DVector<Scalar>>) -> Result<Vec<Prediction>> {
+        inputs.into_iter().map(|input| self.predict(input)).collect()
+    }
+}
+
+struct ForwardPass {
+    tape: Tape,
+    input: NodeId,
+    output: NodeId,
+    pre_activations: Vec<NodeId>,
+    activations: Vec<NodeId>
+}
+
+/// Values of one layer on a forward pass.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerTrace {
+    pub pre_activations: DVector<Scalar>,
+    /// Outputs of the layer's activation, the softmax for the last layer.
+    pub activations: DVector<Scalar>
+}
+
+/// Everything a forward pass computed, for visualizing what the network
+/// responds to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForwardTrace {
+    /// The input vector with the [`InputFeatures`] appended.
+    pub input: DVector<Scalar>,
+    pub layers: Vec<LayerTrace>
+}
+
+impl ForwardTrace {
+    pub fn output(&self) -> &DVector<Scalar> {
+        self.layers.last().map_or(&self.input, |layer| &layer.activations)
+    }
 }
 
 impl NeuralNetwork {
+    /// Loads a network saved in either [`ModelFormat`], telling them apart by the
+    /// magic header, and upgrades it from older schema versions.
     pub fn load<P: AsRef<Path>>(file: P) -> Result<NeuralNetwork> {
         let file = File::open(file)
-            .map_err(|err| ErrorKind::CannotLoadNeuralNetwork(err))?;
+            .map_err(ErrorKind::CannotLoadNeuralNetwork)?;
+
+        let mut reader = BufReader::new(file);
+        let is_binary = reader
+            .fill_buf()
+            .map_err(ErrorKind::CannotLoadNeuralNetwork)?
+            .starts_with(MODEL_MAGIC);
+
+        if !is_binary {
+            return NeuralNetwork::from_saved(serde_json::from_reader(reader)?)
+        }
+
+        reader.consume(MODEL_MAGIC.len());
+        let mut version = [0u8; 1];
+        reader.read_exact(&mut version)
+            .map_err(ErrorKind::CannotLoadNeuralNetwork)?;
 
-        let reader = BufReader::new(file);
-        let network: NeuralNetwork = serde_json::from_reader(reader)?;
+        if version[0] > MODEL_FORMAT_VERSION {
+            return Err(ErrorKind::UnsupportedModelFormatVersion {
+                found: version[0],
+                supported: MODEL_FORMAT_VERSION
+            })
+        }
+
+        NeuralNetwork::from_saved(ciborium::de::from_reader(reader)?)
+    }
+
+    /// A network saved in any schema version, e.g. as part of a checkpoint.
+    pub(crate) fn from_saved(network: Value) -> Result<NeuralNetwork> {
+        Ok(serde_json::from_value(migrate(network)?)?)
+    }
+
+    /// Copies the parameters of every layer of the network saved in `file`
+    /// whose shape matches the corresponding layer of this one, keeping the
+    /// current (usually freshly initialized) parameters of the rest.
+    /// Returns the number of copied layers.
+    pub fn load_partial<P: AsRef<Path>>(&mut self, file: P) -> Result<usize> {
+        let source = NeuralNetwork::load(file)?;
+
+        let mut copied = 0;
+        for (layer, source_layer) in zip(self.layers.iter_mut(), source.layers) {
+            if layer.weights.shape() == source_layer.weights.shape()
+                && layer.biases.shape() == source_layer.biases.shape() {
+                *layer = source_layer;
+                copied += 1;
+            }
+        }
 
-        Ok(network)
+        Ok(copied)
     }
 
+    /// Saves the network in the format [`ModelFormat::for_path`] picks for `file`.
     pub fn save<P: AsRef<Path>>(&self, file: P) -> Result<()> {
+        let format = ModelFormat::for_path(&file);
+        self.save_as(file, format)
+    }
+
+    pub fn save_as<P: AsRef<Path>>(&self, file: P, format: ModelFormat) -> Result<()> {
         let file = OpenOptions::new()
             .write(true)
             .create(true)
+            .truncate(true)
             .open(file)
-            .map_err(|err| ErrorKind::CannotSaveNeuralNetwork(err))?;
+            .map_err(ErrorKind::CannotSaveNeuralNetwork)?;
 
-        let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, self)?;
+        let mut writer = BufWriter::new(file);
+        match format {
+            ModelFormat::Binary => {
+                writer.write_all(MODEL_MAGIC)
+                    .and_then(|_| writer.write_all(&[MODEL_FORMAT_VERSION]))
+                    .map_err(ErrorKind::CannotSaveNeuralNetwork)?;
+                ciborium::ser::into_writer(self, &mut writer)?;
+            },
+            ModelFormat::Json => serde_json::to_writer_pretty(&mut writer, self)?
+        }
+
+        writer.flush().map_err(ErrorKind::CannotSaveNeuralNetwork)?;
 
         Ok(())
     }
 
-    pub fn new_untrained() -> NeuralNetwork {
-        let mut rng = thread_rng();
+    pub fn new_untrained(input_size: ImageSize, output_size: usize) -> NeuralNetwork {
+        NeuralNetwork::new_untrained_with_features(input_size, output_size, InputFeatures::default())
+    }
+
+    /// An untrained network whose first layer takes `input_features` of images of `input_size`.
+    pub fn new_untrained_with_features(input_size: ImageSize,
+                                       output_size: usize,
+                                       input_features: InputFeatures) -> NeuralNetwork {
+        NeuralNetwork::new_untrained_with_architecture(input_size, output_size, input_features, &Architecture::default())
+    }
+
+    pub fn new_untrained_with_architecture(input_size: ImageSize,
+                                           output_size: usize,
+                                           input_features: InputFeatures,
+                                           architecture: &Architecture) -> NeuralNetwork {
+        NeuralNetwork::new_untrained_with_rng(&mut thread_rng(), input_size, output_size, input_features, architecture)
+    }
+
+    /// Draws the initial parameters from `rng`, e.g. a seeded one to make
+    /// training reproducible.
+    pub fn new_untrained_with_rng(rng: &mut dyn RngCore,
+                                  input_size: ImageSize,
+                                  output_size: usize,
+                                  input_features: InputFeatures,
+                                  architecture: &Architecture) -> NeuralNetwork {
         let weight_distr = Normal::new(0.0, 0.01).unwrap();
         let bias_distr = Normal::new(0.0, 0.01).unwrap();
 
-        let layers = vec![
-            Layer::new_untrained(&mut rng, &weight_distr, &bias_distr, INPUT_LAYER_SIZE, HIDDEN_LAYER_SIZE),
-            //Layer::new_untrained(&mut rng, &weight_distr, &bias_distr, HIDDEN_LAYER_SIZE, HIDDEN_LAYER_SIZE),
-            Layer::new_untrained(&mut rng, &weight_distr, &bias_distr, HIDDEN_LAYER_SIZE, OUTPUT_LAYER_SIZE)
-        ];
+        let mut dims = vec![input_features.input_len(input_size)];
+        dims.extend(&architecture.hidden_layers);
+        dims.push(output_size);
+
+        let layers = dims
+            .windows(2)
+            .map(|dims| Layer::new_untrained(rng, &weight_distr, &bias_distr, dims[0], dims[1]))
+            .collect();
 
         NeuralNetwork {
-            layers
+            schema_version: SchemaVersion,
+            layers,
+            input_size,
+            class_names: Vec::new(),
+            rejection_threshold: None,
+            temperature: default_temperature(),
+            input_features,
+            activation: architecture.activation,
+            preprocessing: Preprocessing::default(),
+            normalization: None,
+            hooks: Vec::new(),
+            backend: ComputeBackend::default()
         }
     }
 
-    pub fn compute(&self, input: DVector<f64>) -> DVector<f64> {
-        self.compute_ex(input).result
+    /// Registers a hook called for every layer on each forward and backward pass.
+    pub fn add_hook(&mut self, hook: impl Fn(&LayerEvent) + Send + Sync + 'static) {
+        self.hooks.push(Box::new(hook));
     }
 
-    fn compute_ex(&self, input: DVector<f64>) -> NetworkResult {
-        if input.len() != INPUT_LAYER_SIZE {
-            panic!("this network requires input to be a {}-dimensional column vector", INPUT_LAYER_SIZE)
+    fn run_hooks(&self, pass: Pass, layer: usize, values: &DVector<Scalar>) {
+        let event = LayerEvent { pass, layer, values };
+        for hook in &self.hooks {
+            hook(&event);
         }
+    }
 
-        let mut result = NetworkResult {
-            result: input,
-            activations: Vec::new(),
-            derivatives: Vec::new()
-        };
+    pub fn set_compute_backend(&mut self, backend: ComputeBackend) {
+        self.backend = backend;
+    }
 
-        result.activations.push(result.result.clone_owned());
+    /// Resolution of the images this network classifies.
+    pub fn input_size(&self) -> ImageSize {
+        self.input_size
+    }
 
-        for (i, layer) in self.layers.iter().enumerate() {
-            let mut tmp = DVector::zeros(layer.dim());
+    pub fn input_features(&self) -> InputFeatures {
+        self.input_features
+    }
 
-            layer.weights.mul_to(&result.result, &mut tmp);
-            tmp += &layer.biases;
+    pub fn output_size(&self) -> usize {
+        self.layers.last().map_or(0, |layer| layer.dim())
+    }
 
-            if i != 1 {
-                result.derivatives.push(tmp.map(|x| relu_prime(x)));
-                tmp.apply(|x| *x = relu(*x));
-                result.activations.push(tmp.clone_owned());
-            }
+    pub fn activation(&self) -> Activation {
+        self.activation
+    }
 
-            //println!("tmp{}: {:.5}", i, tmp);
-            result.result = tmp;
+    pub fn architecture(&self) -> Architecture {
+        Architecture {
+            hidden_layers: self.layers[..self.layers.len().saturating_sub(1)].iter().map(Layer::dim).collect(),
+            activation: self.activation
         }
+    }
+
+    /// Number of weights and biases.
+    pub fn parameter_count(&self) -> usize {
+        self.layers.iter().map(|layer| layer.weights.len() + layer.biases.len()).sum()
+    }
 
-        result.derivatives.push(softmax_prime(&result.result));
-        softmax(&mut result.result);
+    pub fn class_names(&self) -> &[String] {
+        &self.class_names
+    }
 
-        //println!("result: {:.2}", &result.result);
+    pub fn summary(&self) -> NetworkSummary {
+        let layer_count = self.layers.len();
+        let layers = self.layers
+            .iter()
+            .enumerate()
+            .map(|(index, layer)| LayerSummary {
+                kind: if layer.sparse_weights.is_some() { "sparse" } else { "dense" },
+                inputs: layer.weights.ncols(),
+                outputs: layer.dim(),
+                parameters: layer.weights.len() + layer.biases.len(),
+                activation: if index + 1 < layer_count { self.activation.name() } else { "softmax" }
+            })
+            .collect();
 
-        result
+        NetworkSummary {
+            input_size: self.input_size,
+            input_features: self.input_features,
+            layers,
+            class_names: self.class_names.clone(),
+            temperature: self.temperature,
+            rejection_threshold: self.rejection_threshold
+        }
     }
 
-    pub fn train(&mut self,
-                 input: DVector<f64>,
-                 target: &DVector<f64>) {
-        let result = self.compute_ex(input.clone_owned());
+    pub fn set_class_names(&mut self, class_names: Vec<String>) {
+        self.class_names = class_names;
+    }
 
-        let error = cross_entropy_loss(&result.result, target);
-        println!("error: {}", error);
+    /// Human-readable name of an output class, falling back to its index
+    /// for models saved without class names.
+    pub fn class_name(&self, class: usize) -> String {
+        self.class_names
+            .get(class)
+            .cloned()
+            .unwrap_or_else(|| class.to_string())
+    }
 
-        let mut local_gradients = vec![
-            result.result
-        ];
+    /// Minimal confidence below which [`NeuralNetwork::classify`] reports
+    /// an input as unrecognized instead of guessing a class.
+    pub fn rejection_threshold(&self) -> Option<f64> {
+        self.rejection_threshold
+    }
 
-        local_gradients[0] -= target;
-        local_gradients[0].component_mul_assign(&result.derivatives[1]);
+    pub fn set_rejection_threshold(&mut self, threshold: Option<f64>) {
+        self.rejection_threshold = threshold;
+    }
 
-        for i in (0..1).rev() {
-            let layer_size = self.layers[i+1].weights.ncols();
-            let prev_gradient = local_gradients.last().unwrap();
+    /// How images are prepared for this network, both its training samples
+    /// and the images it classifies.
+    pub fn preprocessing(&self) -> Preprocessing {
+        self.preprocessing
+    }
 
-            let mut local_gradient = DVector::zeros(layer_size);
+    pub fn set_preprocessing(&mut self, preprocessing: Preprocessing) {
+        self.preprocessing = preprocessing;
+    }
 
-            // weights
-            self.layers[i+1].weights.tr_mul_to(prev_gradient, &mut local_gradient);
+    /// Statistics of the training set the pixel inputs are standardized
+    /// with, if any.
+    pub fn normalization(&self) -> Option<Normalization> {
+        self.normalization
+    }
 
-            // biases
-            for (j, bias) in self.layers[i+1].biases.column(0).iter().enumerate() {
-                local_gradient[j] += bias;
-            }
+    pub fn set_normalization(&mut self, normalization: Option<Normalization>) {
+        self.normalization = normalization;
+    }
 
-            local_gradient.component_mul_assign(&result.derivatives[i]);
+    /// Softmax temperature the output logits are divided by, fitted after
+    /// training so that confidences match the empirical accuracy.
+    pub fn temperature(&self) -> Scalar {
+        self.temperature
+    }
 
-            local_gradients.push(local_gradient);
+    pub fn set_temperature(&mut self, temperature: Scalar) {
+        self.temperature = temperature;
+    }
+
+    /// Output of the last layer before the temperature and softmax are applied.
+    pub fn logits(&self, input: DVector<Scalar>) -> Result<DVector<Scalar>> {
+        let forward = self.compute_ex(input)?;
+        let logits = *forward.pre_activations.last().unwrap_or(&forward.output);
+        Ok(forward.tape.into_value(logits))
+    }
+
+    pub fn compute(&self, input: DVector<Scalar>) -> Result<DVector<Scalar>> {
+        let forward = self.compute_ex(input)?;
+        Ok(forward.tape.into_value(forward.output))
+    }
+
+    /// Like [`NeuralNetwork::compute`], but also returns the input features
+    /// and the values of every layer.
+    pub fn compute_traced(&self, input: DVector<Scalar>) -> Result<ForwardTrace> {
+        let forward = self.compute_ex(input)?;
+        let layers = zip(&forward.pre_activations, &forward.activations)
+            .map(|(pre_activations, activations)| LayerTrace {
+                pre_activations: forward.tape.value(*pre_activations).clone_owned(),
+                activations: forward.tape.value(*activations).clone_owned()
+            })
+            .collect();
+
+        Ok(ForwardTrace {
+            input: forward.tape.value(forward.input).clone_owned(),
+            layers
+        })
+    }
+
+    /// Weights and biases of `layer`, counted from the input side; row `i` of
+    /// the weights holds the incoming weights of unit `i`.
+    pub fn layer_weights(&self, layer: usize) -> Option<(&DMatrix<Scalar>, &DVector<Scalar>)> {
+        self.layers.get(layer).map(|layer| (&layer.weights, &layer.biases))
+    }
+
+    /// The most probable class for `input` and its softmax probability.
+    pub fn predict(&self, input: DVector<Scalar>) -> Result<Prediction> {
+        let output = self.compute(input)?;
+        let (class, confidence) = output.argmax();
+
+        Ok(Prediction { class, confidence: to_f64(confidence) })
+    }
+
+    /// Like [`NeuralNetwork::predict`], but returns `None` when the prediction
+    /// is less confident than the rejection threshold.
+    pub fn classify(&self, input: DVector<Scalar>) -> Result<Option<Prediction>> {
+        let prediction = self.predict(input)?;
+
+        return match self.rejection_threshold {
+            Some(threshold) if prediction.confidence < threshold => Ok(None),
+            _ => Ok(Some(prediction))
         }
+    }
 
-        local_gradients.reverse();
+    /// Monte Carlo dropout: runs `passes` forward passes that each drop the
+    /// units of the hidden layers with probability `rate`. The spread of the
+    /// outputs estimates how uncertain the network is about `input`.
+    pub fn predict_mc_dropout(&self,
+                              input: DVector<Scalar>,
+                              passes: usize,
+                              rate: f64,
+                              rng: &mut dyn RngCore) -> Result<UncertainPrediction> {
+        if !(0.0..1.0).contains(&rate) {
+            return Err(ErrorKind::InvalidDropoutRate(rate))
+        }
 
-        for (i, layer) in self.layers.iter_mut().enumerate() {
-            // update weights
-            let prev_activation = &result.activations[i];
-            for (k, mut column) in layer.weights.column_iter_mut().enumerate()  {
-                for (j, mut element) in column.iter_mut().enumerate() {
-                    *element -=
-                        LEARNING_RATE * prev_activation[k] * local_gradients[i][j];
+        let outputs = (0..passes.max(1))
+            .map(|_| {
+                let forward = self.forward(input.clone(), Some((rate, &mut *rng)))?;
+                Ok(forward.tape.into_value(forward.output))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let count = outputs.len() as f64;
+        let mean = outputs.iter().fold(DVector::zeros(self.output_size()), |sum, output| sum + output) / count as Scalar;
+        let (class, confidence) = mean.argmax();
+        let variance = outputs
+            .iter()
+            .map(|output| (to_f64(output[class]) - to_f64(confidence)).powi(2))
+            .sum::<f64>() / count;
+
+        Ok(UncertainPrediction { class, confidence: to_f64(confidence), uncertainty: variance.sqrt() })
+    }
+
+    fn compute_ex(&self, input: DVector<Scalar>) -> Result<ForwardPass> {
+        self.forward(input, None)
+    }
+
+    /// Forward pass, optionally dropping hidden units out at the given rate.
+    fn forward(&self, input: DVector<Scalar>, mut dropout: Option<(f64, &mut dyn RngCore)>) -> Result<ForwardPass> {
+        if input.len() != self.input_size.area() {
+            return Err(ErrorKind::InputSizeMismatch {
+                expected: self.input_size.area(),
+                found: input.len()
+            })
+        }
+
+        trace!("forward pass through {} layers with the {} backend", self.layers.len(), self.backend.name());
+
+        let mut tape = Tape::with_backend(self.backend.backend());
+        let mut pre_activations = Vec::new();
+        let mut activations = Vec::new();
+        let input = match self.normalization {
+            Some(normalization) => normalization.apply(input),
+            None => input
+        };
+        let input = tape.input(self.input_features.extend(input, self.input_size));
+        let mut output = input;
+
+        for (i, layer) in self.layers.iter().enumerate() {
+            output = match &layer.sparse_weights {
+                Some(sparse_weights) => tape.sparse_mat_mul(i, sparse_weights, output),
+                None => tape.mat_mul(i, &layer.weights, output)
+            };
+            output = tape.add_bias(i, &layer.biases, output);
+            pre_activations.push(output);
+
+            output = if i + 1 < self.layers.len() {
+                let activation = tape.activation(self.activation.function(), self.activation.derivative(), output);
+                match &mut dropout {
+                    Some((rate, rng)) => {
+                        let mask = dropout_mask(tape.value(activation).len(), *rate, &mut **rng);
+                        tape.mask(mask, activation)
+                    },
+                    None => activation
                 }
+            } else if self.temperature != 1.0 {
+                let scaled = tape.scale(1.0 / self.temperature, output);
+                tape.softmax(scaled)
+            } else {
+                tape.softmax(output)
+            };
+
+            activations.push(output);
+
+            self.run_hooks(Pass::Forward, i, tape.value(output));
+        }
+
+        Ok(ForwardPass { tape, input, output, pre_activations, activations })
+    }
+
+    pub(crate) fn layer_parameters(&self) -> impl Iterator<Item = (&DMatrix<Scalar>, &DVector<Scalar>)> {
+        self.layers.iter().map(|layer| (&layer.weights, &layer.biases))
+    }
+
+    /// Exchanges the weights and biases of every layer with `parameters`.
+    pub(crate) fn swap_layer_parameters(&mut self, parameters: &mut [(DMatrix<Scalar>, DVector<Scalar>)]) {
+        for (layer, (weights, biases)) in zip(self.layers.iter_mut(), parameters) {
+            mem::swap(&mut layer.weights, weights);
+            mem::swap(&mut layer.biases, biases);
+            layer.sparse_weights = None;
+        }
+    }
+
+    /// Zeroes every weight smaller than `threshold` in magnitude. Mostly-zero
+    /// layers are then computed and saved as sparse matrices.
+    pub fn prune(&mut self, threshold: f64) -> Sparsity {
+        for layer in &mut self.layers {
+            layer.weights.apply(|w| if to_f64(w.abs()) < threshold {
+                *w = 0.0
+            });
+            layer.update_sparse_weights();
+        }
+
+        self.sparsity()
+    }
+
+    pub fn sparsity(&self) -> Sparsity {
+        Sparsity {
+            zero_weights: self.layers.iter().map(|layer| layer.zero_weights()).sum(),
+            total_weights: self.layers.iter().map(|layer| layer.weights.len()).sum()
+        }
+    }
+
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Gradients of the loss for a single example w.r.t. all parameters,
+    /// together with the output the network produced for `input`.
+    pub fn gradients(&self,
+                     input: DVector<Scalar>,
+                     target: &DVector<Scalar>) -> Result<(DVector<Scalar>, Gradients)> {
+        let forward = self.compute_ex(input)?;
+        let output = forward.tape.value(forward.output).clone_owned();
+
+        let weights: Vec<_> = self.layers.iter().map(|layer| &layer.weights).collect();
+        let gradients = forward.tape.backward(forward.output,
+                                              cross_entropy_loss_gradient(&output, target),
+                                              &weights);
+
+        for (i, node) in forward.pre_activations.iter().enumerate() {
+            if let Some(gradient) = gradients.node(*node) {
+                self.run_hooks(Pass::Backward, i, gradient);
             }
+        }
 
-            // update biases
-            layer.biases.sub_assign(local_gradients[i].clone_owned() * LEARNING_RATE);
+        Ok((output, gradients))
+    }
+
+    /// Gradient of the loss for `target` w.r.t. `input`, e.g. to perturb an
+    /// image towards a wrong answer. The [`InputFeatures`] are treated as
+    /// constants.
+    pub fn input_gradient(&self, input: DVector<Scalar>, target: &DVector<Scalar>) -> Result<DVector<Scalar>> {
+        let input_len = input.len();
+        let forward = self.compute_ex(input)?;
+        let output = forward.tape.value(forward.output);
+
+        let weights: Vec<_> = self.layers.iter().map(|layer| &layer.weights).collect();
+        let gradients = forward.tape.backward(forward.output,
+                                              cross_entropy_loss_gradient(output, target),
+                                              &weights);
+
+        Ok(gradients
+            .node(forward.input)
+            .map_or_else(|| DVector::zeros(input_len), |gradient| gradient.rows(0, input_len).clone_owned()))
+    }
+
+    /// Performs a gradient descent step; `learning_rates` holds one step size per layer.
+    pub fn apply_gradients(&mut self, gradients: &Gradients, learning_rates: &[f64]) {
+        let backend = self.backend.backend();
+        for (i, (layer, learning_rate)) in zip(self.layers.iter_mut(), learning_rates).enumerate() {
+            // frozen layer, keep it untouched along with its sparse weights
+            if *learning_rate == 0.0 {
+                continue
+            }
+
+            if let Some(gradient) = gradients.weights(i) {
+                backend.matrix_axpy(-(*learning_rate as Scalar), gradient, &mut layer.weights);
+            }
+
+            if let Some(gradient) = gradients.biases(i) {
+                backend.axpy(-(*learning_rate as Scalar), gradient, &mut layer.biases);
+            }
+
+            // updates bring pruned weights back, so the sparse copy is stale
+            layer.sparse_weights = None;
         }
     }
-}
\ No newline at end of file
+
+    /// Performs a single gradient descent step towards `target` and returns
+    /// the output the network produced for `input` before the update.
+    /// `learning_rates` holds one step size per layer.
+    pub fn train(&mut self,
+                 input: DVector<Scalar>,
+                 target: &DVector<Scalar>,
+                 learning_rates: &[f64]) -> Result<DVector<Scalar>> {
+        let (output, gradients) = self.gradients(input, target)?;
+        self.apply_gradients(&gradients, learning_rates);
+
+        Ok(output)
+    }
+
+    /// Nudges every layer towards classifying `sample` as `label`, e.g. after
+    /// a user corrected a prediction. Meant for occasional single examples at
+    /// a learning rate well below the one used for training; returns what the
+    /// network predicted before the update. `sample` is white on black like
+    /// the training datasets and goes through the network's preprocessing,
+    /// as it does for inference.
+    pub fn train_incremental(&mut self, sample: &Image, label: Label, learning_rate: f64) -> Result<Prediction> {
+        let class_count = self.output_size();
+        let target = label
+            .to_one_hot(class_count)
+            .ok_or(ErrorKind::LabelOutOfRange { class: label.class(), class_count })?;
+
+        let mut image = sample.clone();
+        self.preprocessing.prepare(&mut image);
+        let learning_rates = vec![learning_rate; self.layer_count()];
+        let output = self.train(input_vector(&image), &target, &learning_rates)?;
+        let (class, confidence) = output.argmax();
+
+        Ok(Prediction { class, confidence: to_f64(confidence) })
+    }
+}
+
+impl Classifier for NeuralNetwork {
+    fn input_size(&self) -> ImageSize {
+        self.input_size
+    }
+
+    fn output_size(&self) -> usize {
+        NeuralNetwork::output_size(self)
+    }
+
+    fn class_name(&self, class: usize) -> String {
+        NeuralNetwork::class_name(self, class)
+    }
+
+    fn rejection_threshold(&self) -> Option<f64> {
+        self.rejection_threshold
+    }
+
+    fn preprocessing(&self) -> Preprocessing {
+        self.preprocessing
+    }
+
+    fn predict(&self, input: DVector<Scalar>) -> Result<Prediction> {
+        NeuralNetwork::predict(self, input)
+    }
+
+    fn predict_batch(&self, inputs: Vec<DVector<Scalar>>) -> Result<Vec<Prediction>> {
+        inputs
+            .into_par_iter()
+            .map(|input| NeuralNetwork::predict(self, input))
+            .collect()
+    }
+}
@@ -0,0 +1,138 @@
+use std::fs::{create_dir_all, read_dir};
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use crate::data::Image;
+use crate::network;
+use crate::network::NeuralNetwork;
+use crate::dataset::write::{self, append_sample};
+use crate::training_data::{Label, LabeledTrainingData};
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error("cannot access user profiles in {dir} ({source})")]
+    CannotAccessProfiles {
+        dir: String,
+        source: io::Error
+    },
+
+    #[error("invalid profile name `{0}` (use letters, digits, `-` and `_`)")]
+    InvalidProfileName(String),
+
+    #[error("cannot save a sample to the dataset of profile {profile} ({source})")]
+    CannotSaveSample {
+        profile: String,
+        source: write::ErrorKind
+    },
+
+    #[error(transparent)]
+    NeuralNetworkError(#[from] network::ErrorKind)
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::CannotAccessProfiles { .. } => "E0911",
+            ErrorKind::InvalidProfileName(_) => "E0912",
+            ErrorKind::CannotSaveSample { .. } => "E0913",
+            ErrorKind::NeuralNetworkError(err) => err.code()
+        }
+    }
+}
+
+/// Profile used when none has been created yet.
+pub const DEFAULT_PROFILE: &str = "default";
+
+const PROFILE_MODEL_FILE: &str = "model.json";
+const PROFILE_IMAGES_FILE: &str = "images.idx";
+const PROFILE_LABELS_FILE: &str = "labels.idx";
+
+/// Named users sharing a base model, each in a directory of `dir` holding
+/// their fine-tuned copy of the model and the samples it was tuned on, as
+/// IDX files that `--images` and `--labels` can train on.
+pub struct ProfileStore {
+    dir: PathBuf,
+    base_model_file: PathBuf
+}
+
+impl ProfileStore {
+    pub fn open<P: AsRef<Path>, Q: AsRef<Path>>(dir: P, base_model_file: Q) -> Result<ProfileStore> {
+        let dir = dir.as_ref();
+        create_dir_all(dir)
+            .map_err(|source| ErrorKind::CannotAccessProfiles { dir: dir.display().to_string(), source })?;
+
+        Ok(ProfileStore {
+            dir: dir.to_path_buf(),
+            base_model_file: base_model_file.as_ref().to_path_buf()
+        })
+    }
+
+    /// Names of the existing profiles in alphabetical order, or just
+    /// [`DEFAULT_PROFILE`] if there are none.
+    pub fn names(&self) -> Result<Vec<String>> {
+        let entries = read_dir(&self.dir)
+            .map_err(|source| ErrorKind::CannotAccessProfiles { dir: self.dir.display().to_string(), source })?;
+
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().to_str().map(String::from))
+            .filter(|name| is_valid_name(name))
+            .collect();
+        names.sort();
+
+        if names.is_empty() {
+            names.push(String::from(DEFAULT_PROFILE));
+        }
+
+        Ok(names)
+    }
+
+    /// The profile's fine-tuned model, or the base model if it has none yet.
+    pub fn load_model(&self, profile: &str) -> Result<NeuralNetwork> {
+        let model_file = self.profile_dir(profile)?.join(PROFILE_MODEL_FILE);
+        return if model_file.is_file() {
+            Ok(NeuralNetwork::load(model_file)?)
+        } else {
+            Ok(NeuralNetwork::load(&self.base_model_file)?)
+        }
+    }
+
+    pub fn save_model(&self, profile: &str, network: &NeuralNetwork) -> Result<()> {
+        let dir = self.create_profile_dir(profile)?;
+        Ok(network.save(dir.join(PROFILE_MODEL_FILE))?)
+    }
+
+    /// Adds a sample, in the orientation and colors of the training datasets,
+    /// to the profile's personal dataset.
+    pub fn add_sample(&self, profile: &str, image: Image, label: Label) -> Result<()> {
+        let dir = self.create_profile_dir(profile)?;
+        append_sample(dir.join(PROFILE_IMAGES_FILE),
+                      dir.join(PROFILE_LABELS_FILE),
+                      &LabeledTrainingData::new(image, label))
+            .map_err(|source| ErrorKind::CannotSaveSample { profile: profile.to_string(), source })
+    }
+
+    fn profile_dir(&self, profile: &str) -> Result<PathBuf> {
+        return if is_valid_name(profile) {
+            Ok(self.dir.join(profile))
+        } else {
+            Err(ErrorKind::InvalidProfileName(profile.to_string()))
+        }
+    }
+
+    fn create_profile_dir(&self, profile: &str) -> Result<PathBuf> {
+        let dir = self.profile_dir(profile)?;
+        create_dir_all(&dir)
+            .map_err(|source| ErrorKind::CannotAccessProfiles { dir: dir.display().to_string(), source })?;
+
+        Ok(dir)
+    }
+}
+
+// names become directory names, so they must not escape the profiles directory
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
@@ -0,0 +1,110 @@
+use std::fs::{create_dir, create_dir_all, read_dir};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use crate::training::best_model_path;
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error("cannot create run directory in {dir} ({source})")]
+    CannotCreateRunDir {
+        dir: String,
+        source: io::Error
+    },
+
+    #[error("cannot list training runs in {dir} ({source})")]
+    CannotListRuns {
+        dir: String,
+        source: io::Error
+    },
+
+    #[error("no training run in {dir} has saved a model yet")]
+    NoTrainedRun {
+        dir: String
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::CannotCreateRunDir { .. } => "E0701",
+            ErrorKind::CannotListRuns { .. } => "E0702",
+            ErrorKind::NoTrainedRun { .. } => "E0703"
+        }
+    }
+}
+
+const RUN_DIR_PREFIX: &str = "run-";
+const RUN_MODEL_FILE: &str = "model.json";
+
+/// Creates a directory for a new training run in `runs_dir`, named after
+/// the current time so that runs sort chronologically.
+pub fn create_run_dir<P: AsRef<Path>>(runs_dir: P) -> Result<PathBuf> {
+    let runs_dir = runs_dir.as_ref();
+    let cannot_create = |source| ErrorKind::CannotCreateRunDir { dir: runs_dir.display().to_string(), source };
+
+    create_dir_all(runs_dir).map_err(cannot_create)?;
+
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+
+    let mut attempt = 1;
+    loop {
+        let name = match attempt {
+            1 => format!("{}{}", RUN_DIR_PREFIX, started),
+            _ => format!("{}{}-{}", RUN_DIR_PREFIX, started, attempt)
+        };
+
+        let run_dir = runs_dir.join(name);
+        match create_dir(&run_dir) {
+            Ok(()) => return Ok(run_dir),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+            Err(err) => return Err(cannot_create(err))
+        }
+    }
+}
+
+/// File the network of a run is saved to; its history, checkpoint and
+/// best model are kept next to it.
+pub fn run_model_path<P: AsRef<Path>>(run_dir: P) -> PathBuf {
+    run_dir.as_ref().join(RUN_MODEL_FILE)
+}
+
+/// Best model of the most recent run in `runs_dir` that saved a model, or
+/// the last saved model of that run if it has no best model.
+pub fn latest_best_model<P: AsRef<Path>>(runs_dir: P) -> Result<PathBuf> {
+    let runs_dir = runs_dir.as_ref();
+    let entries = read_dir(runs_dir)
+        .map_err(|source| ErrorKind::CannotListRuns { dir: runs_dir.display().to_string(), source })?;
+
+    let mut runs: Vec<(u64, u32, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            run_order(&name).map(|(started, attempt)| (started, attempt, entry.path()))
+        })
+        .collect();
+    runs.sort();
+
+    runs.into_iter()
+        .rev()
+        .flat_map(|(_, _, run_dir)| {
+            let model_file = run_model_path(&run_dir);
+            [best_model_path(&model_file), model_file]
+        })
+        .find(|file| file.is_file())
+        .ok_or_else(|| ErrorKind::NoTrainedRun { dir: runs_dir.display().to_string() })
+}
+
+/// Start time and attempt number of a run directory named by [`create_run_dir`].
+fn run_order(name: &str) -> Option<(u64, u32)> {
+    let suffix = name.strip_prefix(RUN_DIR_PREFIX)?;
+    return match suffix.split_once('-') {
+        Some((started, attempt)) => Some((started.parse().ok()?, attempt.parse().ok()?)),
+        None => Some((suffix.parse().ok()?, 1))
+    }
+}
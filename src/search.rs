@@ -0,0 +1,451 @@
+use std::fmt::{Display, Formatter};
+use std::ffi::OsStr;
+use std::fs::{create_dir_all, File, read_to_string, write};
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use log::info;
+use rand::{Rng, RngCore};
+use rand::seq::SliceRandom;
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+use crate::data::ImageSize;
+use crate::features::InputFeatures;
+use crate::network;
+use crate::network::{Activation, Architecture, NeuralNetwork};
+use crate::training;
+use crate::training::{evaluate, Trainer};
+use crate::training_data::LabeledTrainingData;
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error("cannot read training config {file} ({source})")]
+    CannotReadConfig {
+        file: String,
+        source: io::Error
+    },
+
+    #[error("cannot write training config {file} ({source})")]
+    CannotWriteConfig {
+        file: String,
+        source: io::Error
+    },
+
+    #[error("cannot parse training config ({0})")]
+    InvalidConfig(#[from] toml::de::Error),
+
+    #[error("cannot serialize training config ({0})")]
+    CannotSerializeConfig(#[from] toml::ser::Error),
+
+    #[error("no architecture in the search space has at most {0} parameters")]
+    EmptySearchSpace(usize),
+
+    #[error("cannot write sweep output to {dir} ({source})")]
+    CannotWriteSweep {
+        dir: String,
+        source: io::Error
+    },
+
+    #[error("cannot start training process ({0})")]
+    CannotStartTraining(#[source] io::Error),
+
+    #[error("training of sweep point {point} failed, see {log}")]
+    TrainingProcessFailed {
+        point: usize,
+        log: String
+    },
+
+    #[error(transparent)]
+    TrainingError(#[from] training::ErrorKind),
+
+    #[error(transparent)]
+    NeuralNetworkError(#[from] network::ErrorKind)
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::CannotReadConfig { .. } => "E1201",
+            ErrorKind::CannotWriteConfig { .. } => "E1202",
+            ErrorKind::InvalidConfig(_) => "E1203",
+            ErrorKind::CannotSerializeConfig(_) => "E1204",
+            ErrorKind::EmptySearchSpace(_) => "E1205",
+            ErrorKind::CannotWriteSweep { .. } => "E1206",
+            ErrorKind::CannotStartTraining(_) => "E1207",
+            ErrorKind::TrainingProcessFailed { .. } => "E1208",
+            ErrorKind::TrainingError(err) => err.code(),
+            ErrorKind::NeuralNetworkError(err) => err.code()
+        }
+    }
+}
+
+/// Random architectures tried before giving up on finding one within
+/// [`SearchSpace::max_parameters`].
+const MAX_SAMPLING_ATTEMPTS: usize = 1000;
+
+/// Architecture and learning rate of a training run, stored as TOML so that
+/// `--architecture` can start a full training run from it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrainingConfig {
+    // TOML requires plain values before tables
+    pub learning_rate: f64,
+    pub architecture: Architecture
+}
+
+impl TrainingConfig {
+    pub fn load<P: AsRef<Path>>(file: P) -> Result<TrainingConfig> {
+        let file = file.as_ref();
+        let content = read_to_string(file)
+            .map_err(|source| ErrorKind::CannotReadConfig { file: file.display().to_string(), source })?;
+
+        Ok(toml::from_str(&content)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, file: P) -> Result<()> {
+        let file = file.as_ref();
+        write(file, toml::to_string(self)?)
+            .map_err(|source| ErrorKind::CannotWriteConfig { file: file.display().to_string(), source })
+    }
+}
+
+impl Display for TrainingConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let hidden_layers: Vec<String> = self.architecture.hidden_layers.iter().map(usize::to_string).collect();
+        write!(f, "hidden {} {:?}, learning rate {:.3e}",
+               hidden_layers.join("x"), self.architecture.activation, self.learning_rate)
+    }
+}
+
+/// Architectures and learning rates the search samples from.
+pub struct SearchSpace {
+    /// Choices for the size of each hidden layer.
+    pub hidden_sizes: Vec<usize>,
+    pub max_hidden_layers: usize,
+    pub activations: Vec<Activation>,
+    /// Bounds of the log-uniformly sampled learning rate.
+    pub min_learning_rate: f64,
+    pub max_learning_rate: f64,
+    /// Larger networks are not sampled, which keeps every candidate cheap to train.
+    pub max_parameters: usize
+}
+
+impl Default for SearchSpace {
+    fn default() -> Self {
+        SearchSpace {
+            hidden_sizes: vec![16, 32, 64, 128],
+            max_hidden_layers: 2,
+            activations: vec![Activation::Sigmoid, Activation::Tanh, Activation::Relu],
+            min_learning_rate: 0.01,
+            max_learning_rate: 1.0,
+            max_parameters: 200_000
+        }
+    }
+}
+
+impl SearchSpace {
+    fn sample(&self, rng: &mut dyn RngCore, input_len: usize, class_count: usize) -> Result<TrainingConfig> {
+        for _ in 0..MAX_SAMPLING_ATTEMPTS {
+            let layer_count = rng.gen_range(1..=self.max_hidden_layers.max(1));
+            let hidden_layers: Vec<usize> = (0..layer_count)
+                .filter_map(|_| self.hidden_sizes.choose(rng).copied())
+                .collect();
+
+            let mut dims = vec![input_len];
+            dims.extend(&hidden_layers);
+            dims.push(class_count);
+            let parameters: usize = dims.windows(2).map(|dims| (dims[0] + 1) * dims[1]).sum();
+            if parameters > self.max_parameters {
+                continue;
+            }
+
+            let (min, max) = (self.min_learning_rate.ln(), self.max_learning_rate.ln());
+            return Ok(TrainingConfig {
+                learning_rate: rng.gen_range(min..=max).exp(),
+                architecture: Architecture {
+                    hidden_layers,
+                    activation: self.activations.choose(rng).copied().unwrap_or_default()
+                }
+            })
+        }
+
+        Err(ErrorKind::EmptySearchSpace(self.max_parameters))
+    }
+}
+
+/// Validation accuracy of a candidate after the last round it took part in.
+pub struct CandidateResult {
+    pub config: TrainingConfig,
+    pub accuracy: f64,
+    pub epochs: u32
+}
+
+/// Random search over a [`SearchSpace`] with successive halving: every round
+/// trains the remaining candidates, keeps the better half and doubles the
+/// epochs of the next round, until one candidate is left.
+pub struct ArchitectureSearch {
+    pub space: SearchSpace,
+    pub candidates: usize,
+    /// Epochs of the first round.
+    pub epochs: u32,
+    pub batch_size: usize,
+    pub threads: usize
+}
+
+impl ArchitectureSearch {
+    /// All candidates, best first; the first one survived every round.
+    pub fn run(&self,
+               rng: &mut dyn RngCore,
+               input_size: ImageSize,
+               class_count: usize,
+               input_features: InputFeatures,
+               samples: &[LabeledTrainingData],
+               validation_samples: &[LabeledTrainingData]) -> Result<Vec<CandidateResult>> {
+        let mut candidates = (0..self.candidates.max(1))
+            .map(|_| {
+                let config = self.space.sample(rng, input_features.input_len(input_size), class_count)?;
+                let network = NeuralNetwork::new_untrained_with_rng(rng,
+                                                                    input_size,
+                                                                    class_count,
+                                                                    input_features,
+                                                                    &config.architecture);
+                Ok((CandidateResult { config, accuracy: 0.0, epochs: 0 }, network))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut eliminated = Vec::new();
+        let mut epochs = self.epochs.max(1);
+        loop {
+            for (candidate, network) in &mut candidates {
+                Trainer::builder()
+                    .with_epochs(epochs)
+                    .with_learning_rate(candidate.config.learning_rate)
+                    .with_batch_size(self.batch_size)
+                    .with_threads(self.threads)
+                    .build()
+                    .train(network, samples)?;
+
+                candidate.epochs += epochs;
+                candidate.accuracy = evaluate(network, validation_samples)?;
+                info!("{}: validation accuracy {:.4} after {} epochs",
+                      candidate.config, candidate.accuracy, candidate.epochs);
+            }
+
+            candidates.sort_by(|(a, _), (b, _)| b.accuracy.total_cmp(&a.accuracy));
+            if candidates.len() == 1 {
+                break;
+            }
+
+            let survivors = candidates.len().div_ceil(2);
+            eliminated.push(candidates.drain(survivors..).map(|(candidate, _)| candidate).collect::<Vec<_>>());
+            epochs *= 2;
+        }
+
+        let mut results: Vec<CandidateResult> = candidates.into_iter().map(|(candidate, _)| candidate).collect();
+        // later rounds eliminated the stronger candidates
+        results.extend(eliminated.into_iter().rev().flatten());
+        Ok(results)
+    }
+}
+
+fn default_sweep_epochs() -> u32 {
+    1
+}
+
+/// Values a sweep combines, read from TOML:
+///
+/// ```toml
+/// hidden_sizes = [32, 64]
+/// learning_rates = [0.01, 0.1]
+/// batch_sizes = [1, 16]
+/// epochs = 3
+/// # optional: train a random subset of the combinations
+/// samples = 4
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SweepConfig {
+    pub hidden_sizes: Vec<usize>,
+    pub learning_rates: Vec<f64>,
+    pub batch_sizes: Vec<usize>,
+    #[serde(default)]
+    pub activation: Activation,
+    #[serde(default = "default_sweep_epochs")]
+    pub epochs: u32,
+    #[serde(default)]
+    pub samples: Option<usize>
+}
+
+impl SweepConfig {
+    pub fn load<P: AsRef<Path>>(file: P) -> Result<SweepConfig> {
+        let file = file.as_ref();
+        let content = read_to_string(file)
+            .map_err(|source| ErrorKind::CannotReadConfig { file: file.display().to_string(), source })?;
+
+        Ok(toml::from_str(&content)?)
+    }
+
+    /// Every combination of the values, or a random subset of `samples` of them.
+    pub fn points(&self, rng: &mut dyn RngCore) -> Vec<SweepPoint> {
+        let mut points: Vec<SweepPoint> = self.hidden_sizes
+            .iter()
+            .flat_map(|hidden_size| self.learning_rates
+                .iter()
+                .flat_map(move |learning_rate| self.batch_sizes
+                    .iter()
+                    .map(move |batch_size| SweepPoint {
+                        hidden_size: *hidden_size,
+                        learning_rate: *learning_rate,
+                        batch_size: *batch_size
+                    })))
+            .collect();
+
+        if let Some(samples) = self.samples {
+            points.shuffle(rng);
+            points.truncate(samples);
+        }
+
+        points
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SweepPoint {
+    pub hidden_size: usize,
+    pub learning_rate: f64,
+    pub batch_size: usize
+}
+
+pub struct SweepResult {
+    pub point: SweepPoint,
+    pub accuracy: f64,
+    pub model_file: PathBuf
+}
+
+/// Trains every point of a [`SweepConfig`] in a training process of its own,
+/// up to `jobs` at a time, keeping the models, configs and logs in `dir`.
+pub struct Sweep {
+    pub config: SweepConfig,
+    pub dir: PathBuf,
+    pub jobs: usize
+}
+
+impl Sweep {
+    /// Runs `program` with `training_args` and the options of each point, then
+    /// evaluates the trained models on `validation_samples`, best first.
+    pub fn run<S: AsRef<OsStr>>(&self,
+                                rng: &mut dyn RngCore,
+                                program: S,
+                                training_args: &[String],
+                                validation_samples: &[LabeledTrainingData]) -> Result<Vec<SweepResult>> {
+        create_dir_all(&self.dir).map_err(|source| self.write_error(source))?;
+
+        let points = self.config.points(rng);
+        let jobs = self.jobs.max(1);
+        for (chunk_index, chunk) in points.chunks(jobs).enumerate() {
+            let first = chunk_index * jobs;
+            let mut children = Vec::with_capacity(chunk.len());
+            for (offset, point) in chunk.iter().enumerate() {
+                match self.spawn(&program, training_args, first + offset, point) {
+                    Ok(child) => children.push((first + offset, child)),
+                    Err(error) => {
+                        stop(children);
+                        return Err(error)
+                    }
+                }
+            }
+
+            let mut children = children.into_iter();
+            while let Some((index, mut child)) = children.next() {
+                let succeeded = match child.wait() {
+                    Ok(status) => status.success(),
+                    Err(error) => {
+                        stop(children);
+                        return Err(ErrorKind::CannotStartTraining(error))
+                    }
+                };
+                if !succeeded {
+                    stop(children);
+                    return Err(ErrorKind::TrainingProcessFailed {
+                        point: index,
+                        log: self.point_file(index, "log").display().to_string()
+                    })
+                }
+            }
+        }
+
+        let mut results = points
+            .into_iter()
+            .enumerate()
+            .map(|(index, point)| {
+                let model_file = self.point_file(index, "json");
+                let accuracy = evaluate(&NeuralNetwork::load(&model_file)?, validation_samples)?;
+                info!("sweep point {} {:?}: validation accuracy {:.4}", index, point, accuracy);
+                Ok(SweepResult { point, accuracy, model_file })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        results.sort_by(|a, b| b.accuracy.total_cmp(&a.accuracy));
+        Ok(results)
+    }
+
+    fn spawn<S: AsRef<OsStr>>(&self, program: S, training_args: &[String], index: usize, point: &SweepPoint) -> Result<Child> {
+        let config_file = self.point_file(index, "toml");
+        TrainingConfig {
+            learning_rate: point.learning_rate,
+            architecture: Architecture {
+                hidden_layers: vec![point.hidden_size],
+                activation: self.config.activation
+            }
+        }.save(&config_file)?;
+
+        let log = File::create(self.point_file(index, "log")).map_err(|source| self.write_error(source))?;
+        let log_errors = log.try_clone().map_err(|source| self.write_error(source))?;
+
+        Command::new(program)
+            .args(training_args)
+            .arg("--train")
+            .arg("--model").arg(self.point_file(index, "json"))
+            .arg("--architecture").arg(config_file)
+            .arg("--batch-size").arg(point.batch_size.to_string())
+            .arg("--epochs").arg(self.config.epochs.to_string())
+            .stdout(Stdio::from(log))
+            .stderr(Stdio::from(log_errors))
+            .spawn()
+            .map_err(ErrorKind::CannotStartTraining)
+    }
+
+    fn point_file(&self, index: usize, extension: &str) -> PathBuf {
+        self.dir.join(format!("point-{}.{}", index, extension))
+    }
+
+    fn write_error(&self, source: io::Error) -> ErrorKind {
+        ErrorKind::CannotWriteSweep { dir: self.dir.display().to_string(), source }
+    }
+
+    /// Ranked results as CSV.
+    pub fn save_results<P: AsRef<Path>>(&self, results: &[SweepResult], file: P) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(file).map_err(|source| self.write_error(source))?);
+        let mut write_results = || -> io::Result<()> {
+            writeln!(writer, "rank,hidden_size,learning_rate,batch_size,accuracy,model")?;
+            for (rank, result) in results.iter().enumerate() {
+                writeln!(writer, "{},{},{},{},{},{}",
+                         rank + 1, result.point.hidden_size, result.point.learning_rate, result.point.batch_size,
+                         result.accuracy, result.model_file.display())?;
+            }
+            writer.flush()
+        };
+
+        write_results().map_err(|source| self.write_error(source))
+    }
+}
+
+/// Kills the training processes still running after another one of their
+/// chunk failed, so that none outlives the sweep.
+fn stop<I: IntoIterator<Item = (usize, Child)>>(children: I) {
+    for (_, mut child) in children {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
@@ -0,0 +1,64 @@
+use nalgebra::{DMatrix, DVector};
+use serde::{Serialize, Deserialize};
+use crate::network::Scalar;
+
+/// Compressed sparse row matrix, used for the weights of pruned layers.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CsrMatrix {
+    rows: usize,
+    columns: usize,
+    row_offsets: Vec<usize>,
+    column_indices: Vec<usize>,
+    values: Vec<Scalar>
+}
+
+impl CsrMatrix {
+    pub fn from_dense(matrix: &DMatrix<Scalar>) -> CsrMatrix {
+        let mut row_offsets = Vec::with_capacity(matrix.nrows() + 1);
+        let mut column_indices = Vec::new();
+        let mut values = Vec::new();
+
+        row_offsets.push(0);
+        for row in matrix.row_iter() {
+            for (column, value) in row.iter().enumerate() {
+                if *value != 0.0 {
+                    column_indices.push(column);
+                    values.push(*value);
+                }
+            }
+            row_offsets.push(values.len());
+        }
+
+        CsrMatrix {
+            rows: matrix.nrows(),
+            columns: matrix.ncols(),
+            row_offsets,
+            column_indices,
+            values
+        }
+    }
+
+    pub fn to_dense(&self) -> DMatrix<Scalar> {
+        let mut matrix = DMatrix::zeros(self.rows, self.columns);
+        for row in 0..self.rows {
+            for i in self.row_offsets[row]..self.row_offsets[row + 1] {
+                matrix[(row, self.column_indices[i])] = self.values[i];
+            }
+        }
+
+        matrix
+    }
+
+    /// Fraction of entries that are stored, i.e. non-zero.
+    pub fn density(&self) -> f64 {
+        (self.values.len() as f64) / ((self.rows * self.columns).max(1) as f64)
+    }
+
+    pub fn mul_vec(&self, vector: &DVector<Scalar>) -> DVector<Scalar> {
+        DVector::from_fn(self.rows, |row, _| {
+            (self.row_offsets[row]..self.row_offsets[row + 1])
+                .map(|i| self.values[i] * vector[self.column_indices[i]])
+                .sum()
+        })
+    }
+}
@@ -0,0 +1,244 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::mem::size_of_val;
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+use zip::ZipWriter;
+use zip::write::FileOptions;
+use crate::features::InputFeatures;
+use crate::network::Scalar;
+use crate::training::input_vector;
+use crate::training_data::LabeledTrainingData;
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error("unknown tensor format `{0}` (expected one of: npz, parquet)")]
+    UnknownTensorFormat(String),
+
+    #[error("cannot write tensors to {file} ({source})")]
+    CannotWriteTensors {
+        file: String,
+        source: io::Error
+    },
+
+    #[error("cannot write tensors to {file} ({source})")]
+    CannotWriteArchive {
+        file: String,
+        source: zip::result::ZipError
+    },
+
+    #[cfg(feature = "parquet")]
+    #[error("cannot write tensors to {file} ({source})")]
+    CannotWriteParquet {
+        file: String,
+        source: parquet::errors::ParquetError
+    },
+
+    #[error("this build cannot write parquet files; rebuild with `--features parquet`")]
+    ParquetNotEnabled
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::UnknownTensorFormat(_) => "E1501",
+            ErrorKind::CannotWriteTensors { .. } => "E1502",
+            ErrorKind::CannotWriteArchive { .. } => "E1502",
+            #[cfg(feature = "parquet")]
+            ErrorKind::CannotWriteParquet { .. } => "E1502",
+            ErrorKind::ParquetNotEnabled => "E1503"
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TensorFormat {
+    /// NumPy archive of `inputs.npy`, `labels.npy` and `weights.npy`.
+    Npz,
+    /// One row per sample: `label`, `weight` and a column per input value.
+    Parquet
+}
+
+impl FromStr for TensorFormat {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self> {
+        return match s {
+            "npz" => Ok(TensorFormat::Npz),
+            "parquet" => Ok(TensorFormat::Parquet),
+            _ => Err(ErrorKind::UnknownTensorFormat(s.to_string()))
+        }
+    }
+}
+
+/// Network inputs of a dataset exactly as training computes them, for
+/// reproducing the training data in other frameworks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tensors {
+    /// Row-major, one row of `input_len` values per sample.
+    inputs: Vec<Scalar>,
+    input_len: usize,
+    labels: Vec<u8>,
+    weights: Vec<f64>
+}
+
+impl Tensors {
+    pub fn from_samples(samples: &[LabeledTrainingData], input_features: InputFeatures) -> Tensors {
+        let input_len = samples
+            .first()
+            .map_or(0, |sample| input_features.input_len(sample.image().size()));
+
+        let mut inputs = Vec::with_capacity(samples.len() * input_len);
+        for sample in samples {
+            let input = input_features.extend(input_vector(sample.image()), sample.image().size());
+            inputs.extend(input.iter());
+        }
+
+        Tensors {
+            inputs,
+            input_len,
+            labels: samples.iter().map(|sample| sample.label().class()).collect(),
+            weights: samples.iter().map(|sample| sample.weight()).collect()
+        }
+    }
+
+    pub fn sample_count(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn input_len(&self) -> usize {
+        self.input_len
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, file: P, format: TensorFormat) -> Result<()> {
+        return match format {
+            TensorFormat::Npz => self.save_npz(file),
+            TensorFormat::Parquet => self.save_parquet(file)
+        }
+    }
+
+    fn save_npz<P: AsRef<Path>>(&self, file: P) -> Result<()> {
+        let path = file.as_ref();
+        let cannot_write = |source| ErrorKind::CannotWriteTensors { file: path.display().to_string(), source };
+        let cannot_archive = |source| ErrorKind::CannotWriteArchive { file: path.display().to_string(), source };
+
+        let mut archive = ZipWriter::new(BufWriter::new(File::create(path).map_err(cannot_write)?));
+        let arrays = [
+            ("inputs.npy", npy(SCALAR_DESCR, &[self.sample_count(), self.input_len], &self.inputs)),
+            ("labels.npy", npy("|u1", &[self.sample_count()], &self.labels)),
+            ("weights.npy", npy("<f8", &[self.sample_count()], &self.weights))
+        ];
+
+        for (name, array) in arrays {
+            archive.start_file(name, FileOptions::default()).map_err(cannot_archive)?;
+            archive.write_all(&array).map_err(cannot_write)?;
+        }
+        archive.finish().map_err(cannot_archive)?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "parquet")]
+    fn save_parquet<P: AsRef<Path>>(&self, file: P) -> Result<()> {
+        use std::sync::Arc;
+        use parquet::data_type::{DoubleType, Int32Type};
+        use parquet::file::properties::WriterProperties;
+        use parquet::file::writer::SerializedFileWriter;
+        use parquet::schema::parser::parse_message_type;
+
+        let path = file.as_ref();
+        let cannot_write = |source| ErrorKind::CannotWriteTensors { file: path.display().to_string(), source };
+        let cannot_encode = |source| ErrorKind::CannotWriteParquet { file: path.display().to_string(), source };
+
+        let columns: String = (0..self.input_len).map(|i| format!("required double x{};", i)).collect();
+        let schema = parse_message_type(&format!("message tensors {{ required int32 label; required double weight; {} }}",
+                                                 columns))
+            .map_err(cannot_encode)?;
+
+        let file = File::create(path).map_err(cannot_write)?;
+        let mut writer = SerializedFileWriter::new(file, Arc::new(schema), Arc::new(WriterProperties::builder().build()))
+            .map_err(cannot_encode)?;
+        let mut row_group = writer.next_row_group().map_err(cannot_encode)?;
+
+        let labels: Vec<i32> = self.labels.iter().map(|label| *label as i32).collect();
+        let mut column = row_group.next_column().map_err(cannot_encode)?.expect("schema has a label column");
+        column.typed::<Int32Type>().write_batch(&labels, None, None).map_err(cannot_encode)?;
+        column.close().map_err(cannot_encode)?;
+
+        let mut column = row_group.next_column().map_err(cannot_encode)?.expect("schema has a weight column");
+        column.typed::<DoubleType>().write_batch(&self.weights, None, None).map_err(cannot_encode)?;
+        column.close().map_err(cannot_encode)?;
+
+        for i in 0..self.input_len {
+            let values: Vec<f64> = self.inputs.iter().skip(i).step_by(self.input_len).map(|x| *x as f64).collect();
+            let mut column = row_group.next_column().map_err(cannot_encode)?.expect("schema has a column per input");
+            column.typed::<DoubleType>().write_batch(&values, None, None).map_err(cannot_encode)?;
+            column.close().map_err(cannot_encode)?;
+        }
+
+        row_group.close().map_err(cannot_encode)?;
+        writer.close().map_err(cannot_encode)?;
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "parquet"))]
+    fn save_parquet<P: AsRef<Path>>(&self, _file: P) -> Result<()> {
+        Err(ErrorKind::ParquetNotEnabled)
+    }
+}
+
+#[cfg(not(feature = "f32"))]
+const SCALAR_DESCR: &str = "<f8";
+#[cfg(feature = "f32")]
+const SCALAR_DESCR: &str = "<f4";
+
+/// Little-endian values, as stored in `.npy` files.
+trait NpyValue: Copy {
+    fn write_le(self, out: &mut Vec<u8>);
+}
+
+impl NpyValue for u8 {
+    fn write_le(self, out: &mut Vec<u8>) {
+        out.push(self);
+    }
+}
+
+impl NpyValue for f32 {
+    fn write_le(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl NpyValue for f64 {
+    fn write_le(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+/// An array in the `.npy` format, version 1.0.
+fn npy<T: NpyValue>(descr: &str, shape: &[usize], values: &[T]) -> Vec<u8> {
+    let shape = match shape {
+        [len] => format!("({},)", len),
+        _ => format!("({})", shape.iter().map(|dim| dim.to_string()).collect::<Vec<_>>().join(", "))
+    };
+    let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", descr, shape);
+
+    // the magic string, version and header length take 10 bytes, and the
+    // data has to start at a multiple of 64
+    let padding = 63 - (10 + header.len()) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    let mut array = Vec::with_capacity(10 + header.len() + size_of_val(values));
+    array.extend_from_slice(b"\x93NUMPY\x01\x00");
+    array.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    array.extend_from_slice(header.as_bytes());
+    for value in values {
+        value.write_le(&mut array);
+    }
+    array
+}
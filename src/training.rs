@@ -0,0 +1,1642 @@
+use std::fmt::{Display, Formatter};
+use std::fs::{File, OpenOptions, rename};
+use std::io;
+use std::io::{BufReader, BufWriter, Write};
+use std::f64::consts::PI;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use log::{debug, info};
+use nalgebra::{DMatrix, DVector};
+use rand::{Rng, SeedableRng};
+use rand::seq::SliceRandom;
+use rand::rngs::StdRng;
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+use rayon::prelude::*;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use thiserror::Error;
+use crate::adversarial;
+use crate::augmentation::{Augmentation, GaussianNoise};
+use crate::autograd::Gradients;
+use crate::data::Image;
+use crate::network;
+use crate::network::{cross_entropy_loss, to_f64, NeuralNetwork, Scalar};
+use crate::training_data;
+use crate::training_data::{permutation, Dataset, InMemoryDataset, Label, LabeledTrainingData};
+use crate::transform::{Preprocessing, Transform};
+
+#[derive(Error, Debug)]
+pub enum ErrorKind {
+    #[error("cannot save training history ({0})")]
+    CannotSaveTrainingHistory(#[source] io::Error),
+
+    #[error("cannot save learning rate curve ({0})")]
+    CannotSaveLearningRateCurve(#[source] io::Error),
+
+    #[error("cannot read training checkpoint ({0})")]
+    CannotLoadCheckpoint(#[source] io::Error),
+
+    #[error("cannot save training checkpoint ({0})")]
+    CannotSaveCheckpoint(#[source] io::Error),
+
+    #[error(transparent)]
+    NeuralNetworkError(#[from] network::ErrorKind),
+
+    #[error(transparent)]
+    DatasetError(#[from] training_data::ErrorKind),
+
+    #[error(transparent)]
+    AdversarialError(#[from] adversarial::ErrorKind),
+
+    #[error("cannot start training threads ({0})")]
+    CannotStartThreads(#[from] ThreadPoolBuildError),
+
+    #[error("cannot freeze layer {layer}, the network has {layer_count} layers")]
+    NoSuchLayer {
+        layer: usize,
+        layer_count: usize
+    },
+
+    #[error("label {class} is out of range for a network with {class_count} output classes")]
+    LabelOutOfRange {
+        class: u8,
+        class_count: usize
+    },
+
+    #[error("{weights} class weights were given for a network with {class_count} output classes")]
+    ClassWeightCountMismatch {
+        weights: usize,
+        class_count: usize
+    },
+
+    #[error("unknown learning rate cycle `{0}` (expected triangular or cosine)")]
+    UnknownCyclePolicy(String),
+
+    #[error("cannot parse or serialize training state ({0})")]
+    InvalidTrainingState(#[from] serde_json::Error),
+
+    #[error("training diverged, the loss or gradients became NaN or infinite after {position} examples of epoch {epoch}")]
+    TrainingDiverged {
+        epoch: u32,
+        position: usize
+    },
+
+    #[error("unknown perturbation `{0}` (expected fgsm or noise)")]
+    UnknownPerturbation(String),
+
+    #[error("the teacher network takes {teacher_pixels}-pixel images and has {teacher_classes} classes, \
+             but the trained network takes {student_pixels}-pixel images and has {student_classes}")]
+    TeacherMismatch {
+        teacher_pixels: usize,
+        teacher_classes: usize,
+        student_pixels: usize,
+        student_classes: usize
+    },
+
+    #[error("distillation temperature {0} is not positive")]
+    InvalidDistillationTemperature(f64),
+
+    #[error("the teacher network prepares images with {teacher:?}, but the trained network with {student:?}")]
+    TeacherPreprocessingMismatch {
+        teacher: Preprocessing,
+        student: Preprocessing
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ErrorKind>;
+
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::CannotSaveTrainingHistory(_) => "E0201",
+            ErrorKind::CannotSaveLearningRateCurve(_) => "E0202",
+            ErrorKind::CannotLoadCheckpoint(_) => "E0203",
+            ErrorKind::CannotSaveCheckpoint(_) => "E0204",
+            ErrorKind::NeuralNetworkError(err) => err.code(),
+            ErrorKind::DatasetError(err) => err.code(),
+            ErrorKind::AdversarialError(err) => err.code(),
+            ErrorKind::CannotStartThreads(_) => "E0205",
+            ErrorKind::NoSuchLayer { .. } => "E0206",
+            ErrorKind::LabelOutOfRange { .. } => "E0207",
+            ErrorKind::ClassWeightCountMismatch { .. } => "E0208",
+            ErrorKind::UnknownCyclePolicy(_) => "E0209",
+            ErrorKind::InvalidTrainingState(_) => "E0210",
+            ErrorKind::TrainingDiverged { .. } => "E0211",
+            ErrorKind::UnknownPerturbation(_) => "E0212",
+            ErrorKind::TeacherMismatch { .. } => "E0213",
+            ErrorKind::InvalidDistillationTemperature(_) => "E0214",
+            ErrorKind::TeacherPreprocessingMismatch { .. } => "E0215"
+        }
+    }
+}
+
+pub const DEFAULT_LEARNING_RATE: f64 = 0.05;
+
+const HISTORY_FILE_EXTENSION: &str = "history.json";
+const CHECKPOINT_FILE_EXTENSION: &str = "checkpoint.json";
+const BEST_MODEL_FILE_EXTENSION: &str = "best";
+const TEMPORARY_FILE_EXTENSION: &str = "tmp";
+
+/// Mixed into the seed of every batch's augmentation, so that it doesn't draw
+/// the same numbers as the batch's perturbation.
+const AUGMENTATION_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Relative decrease of the monitored loss that counts as an improvement.
+const PLATEAU_THRESHOLD: f64 = 1e-4;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct EpochMetrics {
+    pub epoch: u32,
+    pub loss: f64,
+    pub accuracy: f64,
+    pub validation_accuracy: Option<f64>,
+    #[serde(default)]
+    pub validation_loss: Option<f64>
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct TrainingHistory {
+    epochs: Vec<EpochMetrics>
+}
+
+impl TrainingHistory {
+    pub fn new() -> TrainingHistory {
+        TrainingHistory::default()
+    }
+
+    pub fn push(&mut self, metrics: EpochMetrics) {
+        self.epochs.push(metrics);
+    }
+
+    pub fn epochs(&self) -> &[EpochMetrics] {
+        &self.epochs
+    }
+
+    /// Path of the history file kept next to `model_file`,
+    /// e.g. `neural_network.json` -> `neural_network.history.json`.
+    pub fn path_for_model<P: AsRef<Path>>(model_file: P) -> PathBuf {
+        model_file.as_ref().with_extension(HISTORY_FILE_EXTENSION)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, file: P) -> Result<()> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(file)
+            .map_err(ErrorKind::CannotSaveTrainingHistory)?;
+
+        let writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(writer, self)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct TrainingProgress {
+    epoch: u32,
+    position: usize,
+    total_loss: f64,
+    correct_answers: usize,
+    best_accuracy: Option<f64>,
+    history: TrainingHistory,
+    #[serde(default)]
+    best_loss: Option<f64>,
+    #[serde(default)]
+    epochs_without_improvement: u32,
+    #[serde(default)]
+    learning_rate_reductions: i32,
+    #[serde(default)]
+    weight_average: Option<WeightAverage>,
+    #[serde(default)]
+    step: usize,
+    /// Seeds the epochs are ordered by, kept so that a resumed run orders
+    /// them the same even if they were drawn at random.
+    #[serde(default)]
+    shuffle_seed: Option<u64>,
+    #[serde(default)]
+    sampling_seed: Option<u64>
+}
+
+impl TrainingProgress {
+    fn finish_epoch(&mut self, validation: Option<(f64, f64)>) -> EpochMetrics {
+        let sample_count = self.position.max(1) as f64;
+        let metrics = EpochMetrics {
+            epoch: self.epoch + 1,
+            loss: self.total_loss / sample_count,
+            accuracy: (self.correct_answers as f64) / sample_count,
+            validation_accuracy: validation.map(|(accuracy, _)| accuracy),
+            validation_loss: validation.map(|(_, loss)| loss)
+        };
+        self.history.push(metrics);
+
+        self.epoch += 1;
+        self.position = 0;
+        self.total_loss = 0.0;
+        self.correct_answers = 0;
+
+        metrics
+    }
+
+    fn is_best(&mut self, accuracy: f64) -> bool {
+        return match self.best_accuracy {
+            Some(best) if best >= accuracy => false,
+            _ => {
+                self.best_accuracy = Some(accuracy);
+                true
+            }
+        }
+    }
+
+    /// Records the loss of an epoch and returns for how many epochs in a row
+    /// it has not improved on the best one.
+    fn track_plateau(&mut self, loss: f64) -> u32 {
+        match self.best_loss {
+            Some(best) if loss >= best * (1.0 - PLATEAU_THRESHOLD) =>
+                self.epochs_without_improvement += 1,
+            _ => {
+                self.best_loss = Some(loss);
+                self.epochs_without_improvement = 0;
+            }
+        }
+
+        self.epochs_without_improvement
+    }
+}
+
+/// Trains on `batches` batches with a learning rate rising exponentially from
+/// `min_learning_rate` to `max_learning_rate`, recording the loss, to see
+/// which learning rates make progress before training diverges.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LearningRateSweep {
+    pub min_learning_rate: f64,
+    pub max_learning_rate: f64,
+    pub batches: usize,
+    pub batch_size: usize,
+    pub threads: usize
+}
+
+/// Sweeps stop early once the smoothed loss exceeds the best one this many times.
+const SWEEP_DIVERGENCE_FACTOR: f64 = 4.0;
+
+/// Weight of the previous losses in the smoothed loss of a sweep.
+const SWEEP_SMOOTHING: f64 = 0.98;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LearningRatePoint {
+    pub learning_rate: f64,
+    pub loss: f64,
+    pub smoothed_loss: f64
+}
+
+impl LearningRateSweep {
+    /// Runs the sweep on `network` and restores its parameters afterwards.
+    pub fn run(&self, network: &mut NeuralNetwork, samples: &[LabeledTrainingData]) -> Result<LearningRateCurve> {
+        let thread_pool = ThreadPoolBuilder::new()
+            .num_threads(self.threads)
+            .build()?;
+
+        let mut initial_parameters: Vec<_> = network
+            .layer_parameters()
+            .map(|(weights, biases)| (weights.clone_owned(), biases.clone_owned()))
+            .collect();
+
+        let steps = self.batches.max(2);
+        let growth = (self.max_learning_rate / self.min_learning_rate).powf(1.0 / ((steps - 1) as f64));
+
+        let mut points = Vec::with_capacity(steps);
+        let mut average_loss = 0.0;
+        let mut best_loss = f64::INFINITY;
+        for (step, batch) in samples.chunks(self.batch_size.max(1)).cycle().take(steps).enumerate() {
+            let learning_rate = self.min_learning_rate * growth.powi(step as i32);
+
+            let result = batch_gradients(network, batch, &input_vectors(batch), None, None, &thread_pool)?;
+            if let Some(gradients) = &result.gradients {
+                network.apply_gradients(gradients, &vec![learning_rate; network.layer_count()]);
+            }
+
+            let loss = result.loss / (batch.len() as f64);
+            average_loss = SWEEP_SMOOTHING * average_loss + (1.0 - SWEEP_SMOOTHING) * loss;
+            let smoothed_loss = average_loss / (1.0 - SWEEP_SMOOTHING.powi(step as i32 + 1));
+
+            debug!("learning rate {:.3e}: loss {:.4}, smoothed {:.4}", learning_rate, loss, smoothed_loss);
+            points.push(LearningRatePoint { learning_rate, loss, smoothed_loss });
+
+            if !smoothed_loss.is_finite() || smoothed_loss > SWEEP_DIVERGENCE_FACTOR * best_loss {
+                info!("loss diverged at learning rate {:.3e}, stopping the sweep", learning_rate);
+                break
+            }
+            best_loss = best_loss.min(smoothed_loss);
+        }
+
+        network.swap_layer_parameters(&mut initial_parameters);
+
+        Ok(LearningRateCurve { points })
+    }
+}
+
+pub struct LearningRateCurve {
+    points: Vec<LearningRatePoint>
+}
+
+impl LearningRateCurve {
+    pub fn points(&self) -> &[LearningRatePoint] {
+        &self.points
+    }
+
+    /// A tenth of the learning rate with the lowest smoothed loss, a common
+    /// rule of thumb for a rate that is still safely below divergence.
+    pub fn suggested_learning_rate(&self) -> Option<f64> {
+        self.points
+            .iter()
+            .filter(|point| point.smoothed_loss.is_finite())
+            .min_by(|a, b| a.smoothed_loss.total_cmp(&b.smoothed_loss))
+            .map(|point| point.learning_rate / 10.0)
+    }
+
+    /// Writes `learning_rate,loss,smoothed_loss` rows with a header line.
+    pub fn save_csv<P: AsRef<Path>>(&self, file: P) -> Result<()> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(file)
+            .map_err(ErrorKind::CannotSaveLearningRateCurve)?;
+
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "learning_rate,loss,smoothed_loss")
+            .map_err(ErrorKind::CannotSaveLearningRateCurve)?;
+        for point in &self.points {
+            writeln!(writer, "{},{},{}", point.learning_rate, point.loss, point.smoothed_loss)
+                .map_err(ErrorKind::CannotSaveLearningRateCurve)?;
+        }
+
+        writer.flush().map_err(ErrorKind::CannotSaveLearningRateCurve)
+    }
+}
+
+/// Multiplies the learning rate by `factor` whenever the validation loss
+/// (training loss without a validation set) has not improved for `patience`
+/// epochs, down to `min_learning_rate`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PlateauReduction {
+    pub patience: u32,
+    pub factor: f64,
+    pub min_learning_rate: f64
+}
+
+/// Per-class multipliers of the loss, so that rare classes of an imbalanced
+/// dataset are not drowned out by frequent ones.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClassWeights {
+    /// Inversely proportional to the class frequencies in the training set,
+    /// averaging to one over the samples.
+    Balanced,
+    /// One weight per class.
+    Explicit(Vec<f64>)
+}
+
+impl ClassWeights {
+    pub fn resolve(&self, samples: &[LabeledTrainingData], class_count: usize) -> Result<Vec<f64>> {
+        self.resolve_counts(&class_counts(samples, class_count), samples.len())
+    }
+
+    /// Same as [`ClassWeights::resolve`] for a training set of `sample_count`
+    /// samples with `counts` of each class.
+    fn resolve_counts(&self, counts: &[usize], sample_count: usize) -> Result<Vec<f64>> {
+        let class_count = counts.len();
+        return match self {
+            ClassWeights::Explicit(weights) if weights.len() != class_count =>
+                Err(ErrorKind::ClassWeightCountMismatch { weights: weights.len(), class_count }),
+            ClassWeights::Explicit(weights) => Ok(weights.clone()),
+            ClassWeights::Balanced => {
+                let present = counts.iter().filter(|count| **count > 0).count().max(1);
+
+                // classes missing from the training set never contribute to the loss anyway
+                Ok(counts
+                    .iter()
+                    .map(|count| match count {
+                        0 => 1.0,
+                        count => (sample_count as f64) / ((present * count) as f64)
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+/// How the samples of every epoch are drawn from the training set, instead
+/// of taking each once, so that frequent classes of a skewed dataset don't
+/// dominate training.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Sampling {
+    /// Every sample is of a class picked with equal probability among the
+    /// classes of the training set, drawn with replacement; an epoch has as
+    /// many samples as the training set.
+    Balanced,
+    /// Each class contributes at most this many of its samples, picked at
+    /// random, to an epoch.
+    Capped(usize)
+}
+
+impl Sampling {
+    /// Positions in `dataset` of the samples of an epoch, in random order.
+    pub fn epoch_indices<D: Dataset>(&self, dataset: &mut D, rng: &mut impl Rng) -> Result<Vec<usize>> {
+        let class_index = class_index(dataset)?;
+        let classes: Vec<&Vec<usize>> = class_index.iter().filter(|indices| !indices.is_empty()).collect();
+
+        return Ok(match self {
+            Sampling::Balanced if classes.is_empty() => Vec::new(),
+            Sampling::Balanced => (0..dataset.len())
+                .map(|_| {
+                    let class = classes[rng.gen_range(0..classes.len())];
+                    class[rng.gen_range(0..class.len())]
+                })
+                .collect(),
+            Sampling::Capped(max_per_class) => {
+                let mut indices: Vec<usize> = classes
+                    .iter()
+                    .flat_map(|class| class.choose_multiple(rng, *max_per_class).copied().collect::<Vec<_>>())
+                    .collect();
+                indices.shuffle(rng);
+                indices
+            }
+        })
+    }
+}
+
+/// Positions of the samples of each class in `dataset`, indexed by class;
+/// classes above the highest label are left out.
+fn class_index<D: Dataset>(dataset: &mut D) -> Result<Vec<Vec<usize>>> {
+    let mut class_index: Vec<Vec<usize>> = Vec::new();
+    for index in 0..dataset.len() {
+        let class = dataset.get(index)?.label().class() as usize;
+        if class >= class_index.len() {
+            class_index.resize(class + 1, Vec::new());
+        }
+        class_index[class].push(index);
+    }
+
+    Ok(class_index)
+}
+
+/// Number of samples of each class; labels out of range are not counted.
+pub fn class_counts(samples: &[LabeledTrainingData], class_count: usize) -> Vec<usize> {
+    let mut counts = vec![0; class_count];
+    for sample in samples {
+        if let Some(count) = counts.get_mut(sample.label().class() as usize) {
+            *count += 1;
+        }
+    }
+
+    counts
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CyclePolicy {
+    /// Rises linearly from the minimum to the base learning rate and back.
+    Triangular,
+    /// Falls from the base learning rate to the minimum along a half cosine,
+    /// then restarts.
+    Cosine
+}
+
+impl FromStr for CyclePolicy {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self> {
+        return match s.to_lowercase().as_str() {
+            "triangular" => Ok(CyclePolicy::Triangular),
+            "cosine" => Ok(CyclePolicy::Cosine),
+            _ => Err(ErrorKind::UnknownCyclePolicy(s.to_string()))
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PerturbationKind {
+    /// Adversarial copies made by [`adversarial::fgsm`] against the network
+    /// being trained.
+    GradientSign,
+    /// Copies with Gaussian noise added to every pixel.
+    Noise
+}
+
+impl FromStr for PerturbationKind {
+    type Err = ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self> {
+        return match s.to_lowercase().as_str() {
+            "fgsm" => Ok(PerturbationKind::GradientSign),
+            "noise" => Ok(PerturbationKind::Noise),
+            _ => Err(ErrorKind::UnknownPerturbation(s.to_string()))
+        }
+    }
+}
+
+/// Perturbed copies of every batch trained on together with the batch, which
+/// makes the network robust to drawings unlike the clean dataset.
+/// `strength` is the FGSM epsilon or the standard deviation of the noise, as
+/// a fraction of the brightness range.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Perturbation {
+    pub kind: PerturbationKind,
+    pub strength: f64
+}
+
+impl Perturbation {
+    /// Copies of `batch`; the noise of each copy is seeded by `seed` and its
+    /// position in the batch, so runs don't depend on the thread count.
+    fn perturb(&self,
+               network: &NeuralNetwork,
+               batch: &[LabeledTrainingData],
+               seed: u64,
+               thread_pool: &ThreadPool) -> Result<Vec<LabeledTrainingData>> {
+        thread_pool.install(|| batch
+            .par_iter()
+            .enumerate()
+            .map(|(index, example)| {
+                let image = match self.kind {
+                    PerturbationKind::GradientSign =>
+                        adversarial::fgsm(network, example.image(), *example.label(), self.strength)?,
+                    PerturbationKind::Noise => {
+                        let mut image = example.image().clone();
+                        GaussianNoise { std_dev: self.strength }
+                            .apply(&mut image, &mut StdRng::seed_from_u64(seed + index as u64));
+                        image
+                    }
+                };
+
+                Ok(LabeledTrainingData::new(image, *example.label()).with_weight(example.weight()))
+            })
+            .collect())
+    }
+}
+
+/// A trained "teacher" network whose softened outputs the trained network
+/// learns to reproduce, compressing a large model into a smaller one.
+/// The target of every example is `hard_target_weight` of its one-hot label
+/// plus the rest of the teacher's softmax at `temperature`; temperatures
+/// above 1 reveal which wrong classes the teacher finds similar.
+pub struct Distillation {
+    teacher: NeuralNetwork,
+    temperature: f64,
+    hard_target_weight: f64
+}
+
+impl Distillation {
+    pub fn new(teacher: NeuralNetwork, temperature: f64) -> Result<Distillation> {
+        if !(temperature > 0.0 && temperature.is_finite()) {
+            return Err(ErrorKind::InvalidDistillationTemperature(temperature))
+        }
+
+        Ok(Distillation { teacher, temperature, hard_target_weight: 0.0 })
+    }
+
+    pub fn with_hard_target_weight(mut self, weight: f64) -> Self {
+        self.hard_target_weight = weight.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn teacher(&self) -> &NeuralNetwork {
+        &self.teacher
+    }
+
+    fn check_student(&self, student: &NeuralNetwork) -> Result<()> {
+        let teacher = &self.teacher;
+        if teacher.input_size() != student.input_size() || teacher.output_size() != student.output_size() {
+            return Err(ErrorKind::TeacherMismatch {
+                teacher_pixels: teacher.input_size().area(),
+                teacher_classes: teacher.output_size(),
+                student_pixels: student.input_size().area(),
+                student_classes: student.output_size()
+            })
+        }
+        // the teacher sees the samples as prepared for the trained network
+        if teacher.preprocessing() != student.preprocessing() {
+            return Err(ErrorKind::TeacherPreprocessingMismatch {
+                teacher: teacher.preprocessing(),
+                student: student.preprocessing()
+            })
+        }
+
+        Ok(())
+    }
+
+    fn target(&self, example: &LabeledTrainingData) -> Result<Target> {
+        let logits = self.teacher.logits(input_vector(example.image()))? / (self.temperature as Scalar);
+        let max = logits.max();
+        let exp = logits.map(|logit| (logit - max).exp());
+        let soft_target = &exp / exp.sum();
+
+        let hard_target = Target::Class(*example.label()).to_vector(self.teacher.output_size())?;
+        let hard_target_weight = self.hard_target_weight as Scalar;
+        Ok(Target::Distribution(hard_target * hard_target_weight + soft_target * (1.0 - hard_target_weight)))
+    }
+}
+
+/// Cycles the learning rate between `min_learning_rate` and the base learning
+/// rate every `period` batches.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CyclicalSchedule {
+    pub policy: CyclePolicy,
+    pub period: usize,
+    pub min_learning_rate: f64
+}
+
+impl CyclicalSchedule {
+    fn learning_rate(&self, base_learning_rate: f64, step: usize) -> f64 {
+        let period = self.period.max(1);
+        let phase = ((step % period) as f64) / (period as f64);
+        let min_learning_rate = self.min_learning_rate.min(base_learning_rate);
+
+        let position = match self.policy {
+            CyclePolicy::Triangular => 1.0 - (2.0 * phase - 1.0).abs(),
+            CyclePolicy::Cosine => 0.5 * (1.0 + (PI * phase).cos())
+        };
+
+        min_learning_rate + (base_learning_rate - min_learning_rate) * position
+    }
+}
+
+/// Exponential moving average of the network parameters, updated after every batch.
+#[derive(Serialize, Deserialize, Debug)]
+struct WeightAverage {
+    layers: Vec<(DMatrix<Scalar>, DVector<Scalar>)>
+}
+
+impl WeightAverage {
+    fn of(network: &NeuralNetwork) -> WeightAverage {
+        WeightAverage {
+            layers: network
+                .layer_parameters()
+                .map(|(weights, biases)| (weights.clone_owned(), biases.clone_owned()))
+                .collect()
+        }
+    }
+
+    /// `average = decay * average + (1 - decay) * parameters`
+    fn update(&mut self, network: &NeuralNetwork, decay: f64) {
+        let decay = decay as Scalar;
+        for ((weights, biases), (new_weights, new_biases)) in self.layers.iter_mut().zip(network.layer_parameters()) {
+            weights.zip_apply(new_weights, |average, x| *average = decay * *average + (1.0 - decay) * x);
+            biases.zip_apply(new_biases, |average, x| *average = decay * *average + (1.0 - decay) * x);
+        }
+    }
+
+    /// Calls `f` with the averaged parameters swapped into `network`.
+    fn with_averaged<T>(&mut self, network: &mut NeuralNetwork, f: impl FnOnce(&NeuralNetwork) -> T) -> T {
+        network.swap_layer_parameters(&mut self.layers);
+        let result = f(network);
+        network.swap_layer_parameters(&mut self.layers);
+
+        result
+    }
+}
+
+#[derive(Serialize)]
+struct CheckpointRef<'a> {
+    network: &'a NeuralNetwork,
+    progress: &'a TrainingProgress
+}
+
+pub struct Checkpoint {
+    network: NeuralNetwork,
+    progress: TrainingProgress
+}
+
+/// How far the run of a [`Checkpoint`] got, to go on from with
+/// [`Trainer::resume_from`].
+pub struct ResumePoint(TrainingProgress);
+
+impl Checkpoint {
+    pub fn path_for_model<P: AsRef<Path>>(model_file: P) -> PathBuf {
+        model_file.as_ref().with_extension(CHECKPOINT_FILE_EXTENSION)
+    }
+
+    pub fn load<P: AsRef<Path>>(file: P) -> Result<Checkpoint> {
+        let file = File::open(file)
+            .map_err(ErrorKind::CannotLoadCheckpoint)?;
+
+        // the network is upgraded like a saved model, in case the checkpoint
+        // was written by an older version
+        let reader = BufReader::new(file);
+        let mut checkpoint: Value = serde_json::from_reader(reader)?;
+        let mut part = |name: &str| checkpoint.get_mut(name).map(Value::take).unwrap_or_default();
+        let (network, progress) = (part("network"), part("progress"));
+
+        Ok(Checkpoint {
+            network: NeuralNetwork::from_saved(network)?,
+            progress: serde_json::from_value(progress)?
+        })
+    }
+
+    pub fn network_mut(&mut self) -> &mut NeuralNetwork {
+        &mut self.network
+    }
+
+    /// The network of the checkpoint, to configure for the resumed run, and
+    /// where its training stopped.
+    pub fn into_parts(self) -> (NeuralNetwork, ResumePoint) {
+        (self.network, ResumePoint(self.progress))
+    }
+
+    // Written to a temporary file first, so interrupting training while
+    // a checkpoint is being saved never leaves a truncated one behind.
+    fn save<P: AsRef<Path>>(file: P,
+                            network: &NeuralNetwork,
+                            progress: &TrainingProgress) -> Result<()> {
+        let temporary_file = file.as_ref().with_extension(TEMPORARY_FILE_EXTENSION);
+
+        let output = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&temporary_file)
+            .map_err(ErrorKind::CannotSaveCheckpoint)?;
+
+        let writer = BufWriter::new(output);
+        serde_json::to_writer(writer, &CheckpointRef { network, progress })?;
+
+        rename(&temporary_file, file)
+            .map_err(ErrorKind::CannotSaveCheckpoint)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CallbackAction {
+    Continue,
+    Stop
+}
+
+/// Hooks invoked by the [`Trainer`] while it runs. `on_batch_end` receives
+/// the number of examples processed so far and the mean loss of the batch.
+pub trait TrainingCallback {
+    fn on_epoch_start(&mut self, _epoch: u32) {}
+
+    fn on_batch_end(&mut self, _epoch: u32, _position: usize, _loss: f64) {}
+
+    fn on_learning_rate_reduced(&mut self, _epoch: u32, _learning_rate: f64) {}
+
+    /// Training stopped because the time limit ran out after `completed_epochs`
+    /// full epochs and `position` examples of the next one.
+    fn on_time_limit_reached(&mut self, _completed_epochs: u32, _position: usize) {}
+
+    /// Returning [`CallbackAction::Stop`] ends training after this epoch.
+    fn on_epoch_end(&mut self, _metrics: &EpochMetrics) -> CallbackAction {
+        CallbackAction::Continue
+    }
+}
+
+/// Path of the best-so-far network kept next to `model_file`, with the same
+/// extension so that it is saved in the same [`ModelFormat`](crate::network::ModelFormat).
+pub fn best_model_path<P: AsRef<Path>>(model_file: P) -> PathBuf {
+    let model_file = model_file.as_ref();
+    return match model_file.extension() {
+        Some(extension) => model_file.with_extension(format!("{}.{}", BEST_MODEL_FILE_EXTENSION, extension.to_string_lossy())),
+        None => model_file.with_extension(BEST_MODEL_FILE_EXTENSION)
+    }
+}
+
+pub fn input_vector(image: &Image) -> DVector<Scalar> {
+    DVector::from_iterator(image.pixels().len(), image
+        .pixels()
+        .iter()
+        .map(|px| ((*px as Scalar) / 255.0) - 0.5))
+}
+
+fn input_vectors(samples: &[LabeledTrainingData]) -> Vec<DVector<Scalar>> {
+    samples.iter().map(|sample| input_vector(sample.image())).collect()
+}
+
+pub fn evaluate(network: &NeuralNetwork, samples: &[LabeledTrainingData]) -> network::Result<f64> {
+    let mut correct_answers = 0;
+    for example in samples {
+        let output = network.compute(input_vector(example.image()))?;
+        if output.argmax().0 == example.label().class() as usize {
+            correct_answers += 1;
+        }
+    }
+
+    Ok((correct_answers as f64) / (samples.len().max(1) as f64))
+}
+
+/// Accuracy and mean cross-entropy loss of `network` on `samples`.
+fn evaluate_with_loss(network: &NeuralNetwork, dataset: &InMemoryDataset) -> Result<(f64, f64)> {
+    let mut evaluation = Evaluation::default();
+    for (example, input) in dataset.samples().iter().zip(dataset.inputs()) {
+        evaluation.add_input(network, input.clone(), example.label().class())?;
+    }
+
+    Ok((evaluation.accuracy(), evaluation.loss()))
+}
+
+/// Accuracy and loss accumulated one sample at a time, so that test sets of
+/// any size can be evaluated without holding them in memory.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Evaluation {
+    pub samples: usize,
+    pub correct_answers: usize,
+    pub total_loss: f64
+}
+
+impl Evaluation {
+    pub fn add(&mut self, network: &NeuralNetwork, example: &LabeledTrainingData) -> Result<()> {
+        self.add_input(network, input_vector(example.image()), example.label().class())
+    }
+
+    /// Adds a sample whose image was already converted to a network input.
+    pub fn add_input(&mut self, network: &NeuralNetwork, input: DVector<Scalar>, class: u8) -> Result<()> {
+        let output = network.compute(input)?;
+
+        self.samples += 1;
+        let target = Target::Class(Label::new(class)).to_vector(network.output_size())?;
+        self.total_loss += cross_entropy_loss(&output, &target) as f64;
+        if output.argmax().0 == class as usize {
+            self.correct_answers += 1;
+        }
+
+        Ok(())
+    }
+
+    pub fn merge(&mut self, other: &Evaluation) {
+        self.samples += other.samples;
+        self.correct_answers += other.correct_answers;
+        self.total_loss += other.total_loss;
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        (self.correct_answers as f64) / (self.samples.max(1) as f64)
+    }
+
+    pub fn loss(&self) -> f64 {
+        self.total_loss / (self.samples.max(1) as f64)
+    }
+}
+
+impl Display for Evaluation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "accuracy {:.2}%, loss {:.4} on {} samples", self.accuracy()*100.0, self.loss(), self.samples)
+    }
+}
+
+/// Evaluates `samples` in batches of `batch_size`, each computed in parallel,
+/// keeping only one batch in memory at a time.
+pub fn evaluate_stream<I>(network: &NeuralNetwork,
+                          samples: I,
+                          batch_size: usize,
+                          threads: usize) -> Result<Evaluation>
+    where I: Iterator<Item = training_data::Result<LabeledTrainingData>>
+{
+    let thread_pool = ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()?;
+
+    let mut samples = samples.peekable();
+    let mut evaluation = Evaluation::default();
+    while samples.peek().is_some() {
+        let batch = samples
+            .by_ref()
+            .take(batch_size.max(1))
+            .collect::<training_data::Result<Vec<LabeledTrainingData>>>()?;
+
+        let batch_evaluation = thread_pool.install(|| batch
+            .par_iter()
+            .try_fold(Evaluation::default, |mut evaluation, example| {
+                evaluation.add(network, example)?;
+                Ok::<Evaluation, ErrorKind>(evaluation)
+            })
+            .try_reduce(Evaluation::default, |mut evaluation, other| {
+                evaluation.merge(&other);
+                Ok(evaluation)
+            }))?;
+        evaluation.merge(&batch_evaluation);
+
+        debug!("evaluated {} samples: {}", evaluation.samples, evaluation);
+    }
+
+    Ok(evaluation)
+}
+
+const MIN_TEMPERATURE: f64 = 0.05;
+const MAX_TEMPERATURE: f64 = 20.0;
+const CALIBRATION_STEPS: usize = 50;
+
+fn negative_log_likelihood(logits: &[(DVector<f64>, usize)], temperature: f64) -> f64 {
+    let total: f64 = logits
+        .iter()
+        .map(|(logits, class)| {
+            let scaled = logits / temperature;
+            let max = scaled.max();
+            let log_sum_exp = max + scaled.map(|x| (x - max).exp()).sum().ln();
+            log_sum_exp - scaled[*class]
+        })
+        .sum();
+
+    total / (logits.len().max(1) as f64)
+}
+
+/// Fits the softmax temperature of `network` that minimizes the negative
+/// log-likelihood of `samples` (usually the validation set), stores it in
+/// the network and returns it. Predicted classes are not affected.
+pub fn calibrate_temperature(network: &mut NeuralNetwork,
+                             samples: &[LabeledTrainingData]) -> network::Result<f64> {
+    network.set_temperature(1.0);
+
+    let mut logits = Vec::with_capacity(samples.len());
+    for example in samples {
+        logits.push((network.logits(input_vector(example.image()))?.map(to_f64),
+                     example.label().class() as usize));
+    }
+
+    // golden-section search over log(temperature), the likelihood is unimodal in it
+    let ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+    let (mut low, mut high) = (MIN_TEMPERATURE.ln(), MAX_TEMPERATURE.ln());
+    for _ in 0..CALIBRATION_STEPS {
+        let left = high - ratio * (high - low);
+        let right = low + ratio * (high - low);
+
+        if negative_log_likelihood(&logits, left.exp()) < negative_log_likelihood(&logits, right.exp()) {
+            high = right;
+        } else {
+            low = left;
+        }
+    }
+
+    let temperature = ((low + high) / 2.0).exp();
+    network.set_temperature(temperature as Scalar);
+
+    Ok(temperature)
+}
+
+struct ExampleResult {
+    loss: f64,
+    correct: bool,
+    gradients: Gradients
+}
+
+fn example_gradients(network: &NeuralNetwork,
+                     example: &LabeledTrainingData,
+                     input: &DVector<Scalar>,
+                     class_weights: Option<&[f64]>,
+                     distillation: Option<&Distillation>) -> Result<ExampleResult> {
+    let class = example.label().class();
+    // the loss and its gradient are linear in the target, so scaling the
+    // target weighs both
+    let class_weight = class_weights.and_then(|weights| weights.get(class as usize)).copied().unwrap_or(1.0);
+    let weight = class_weight * example.weight();
+    let target = match distillation {
+        Some(distillation) => distillation.target(example)?,
+        None => Target::Class(*example.label())
+    };
+    let target = target.to_vector(network.output_size())? * (weight as Scalar);
+    let (output, gradients) = network.gradients(input.clone(), &target)?;
+
+    Ok(ExampleResult {
+        loss: cross_entropy_loss(&output, &target) as f64,
+        correct: output.argmax().0 == class as usize,
+        gradients
+    })
+}
+
+struct BatchResult {
+    loss: f64,
+    correct_answers: usize,
+    gradients: Option<Gradients>
+}
+
+/// Summed loss, correct answers and mean gradients of `batch`, whose
+/// examples are computed in parallel from their `inputs`.
+fn batch_gradients(network: &NeuralNetwork,
+                   batch: &[LabeledTrainingData],
+                   inputs: &[DVector<Scalar>],
+                   class_weights: Option<&[f64]>,
+                   distillation: Option<&Distillation>,
+                   thread_pool: &ThreadPool) -> Result<BatchResult> {
+    let results = thread_pool.install(|| batch
+        .par_iter()
+        .zip(inputs)
+        .map(|(example, input)| example_gradients(network, example, input, class_weights, distillation))
+        .collect::<Result<Vec<_>>>())?;
+
+    let mut batch = BatchResult { loss: 0.0, correct_answers: 0, gradients: None };
+    let example_count = results.len();
+    for result in results {
+        batch.loss += result.loss;
+        if result.correct {
+            batch.correct_answers += 1;
+        }
+
+        match &mut batch.gradients {
+            Some(gradients) => gradients.merge(&result.gradients),
+            None => batch.gradients = Some(result.gradients)
+        }
+    }
+
+    if let Some(gradients) = &mut batch.gradients {
+        gradients.scale(1.0 / (example_count as Scalar));
+    }
+
+    Ok(batch)
+}
+
+/// What a network is trained to output for an example.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    /// All of the probability on the class of a label.
+    Class(Label),
+    /// Probabilities of every class, e.g. blended from a teacher's outputs.
+    Distribution(DVector<Scalar>)
+}
+
+impl Target {
+    /// The probabilities of `class_count` classes, failing if a label is
+    /// not one of them.
+    pub fn to_vector(&self, class_count: usize) -> Result<DVector<Scalar>> {
+        return match self {
+            Target::Class(label) => label
+                .to_one_hot(class_count)
+                .ok_or(ErrorKind::LabelOutOfRange { class: label.class(), class_count }),
+            Target::Distribution(probabilities) => Ok(probabilities.clone())
+        }
+    }
+}
+
+pub struct TrainerBuilder {
+    epochs: u32,
+    learning_rate: f64,
+    layer_multipliers: Vec<f64>,
+    frozen_layers: Vec<usize>,
+    checkpoint_file: Option<PathBuf>,
+    checkpoint_interval: usize,
+    best_model_file: Option<PathBuf>,
+    validation_samples: InMemoryDataset,
+    batch_size: usize,
+    threads: usize,
+    plateau_reduction: Option<PlateauReduction>,
+    max_duration: Option<Duration>,
+    ema_decay: Option<f64>,
+    warmup_batches: usize,
+    cyclical_schedule: Option<CyclicalSchedule>,
+    class_weights: Option<ClassWeights>,
+    augmentation: Option<Augmentation>,
+    perturbation: Option<Perturbation>,
+    distillation: Option<Distillation>,
+    shuffle_seed: Option<u64>,
+    sampling: Option<(Sampling, u64)>,
+    callbacks: Vec<Box<dyn TrainingCallback>>
+}
+
+impl TrainerBuilder {
+    fn new() -> TrainerBuilder {
+        TrainerBuilder {
+            epochs: 1,
+            learning_rate: DEFAULT_LEARNING_RATE,
+            layer_multipliers: Vec::new(),
+            frozen_layers: Vec::new(),
+            checkpoint_file: None,
+            checkpoint_interval: 0,
+            best_model_file: None,
+            validation_samples: InMemoryDataset::from(Vec::new()),
+            batch_size: 1,
+            threads: 0,
+            plateau_reduction: None,
+            max_duration: None,
+            ema_decay: None,
+            warmup_batches: 0,
+            cyclical_schedule: None,
+            class_weights: None,
+            augmentation: None,
+            perturbation: None,
+            distillation: None,
+            shuffle_seed: None,
+            sampling: None,
+            callbacks: Vec::new()
+        }
+    }
+
+    pub fn with_epochs(mut self, epochs: u32) -> Self {
+        self.epochs = epochs;
+        self
+    }
+
+    pub fn with_learning_rate(mut self, learning_rate: f64) -> Self {
+        self.learning_rate = learning_rate;
+        self
+    }
+
+    /// Scales the learning rate of individual layers, input side first;
+    /// layers without a multiplier use the base learning rate.
+    pub fn with_layer_learning_rate_multipliers(mut self, multipliers: Vec<f64>) -> Self {
+        self.layer_multipliers = multipliers;
+        self
+    }
+
+    /// Keeps the parameters of the given layers, input side first, fixed,
+    /// e.g. to fine-tune only the output layer of a pretrained network.
+    pub fn with_frozen_layers(mut self, layers: Vec<usize>) -> Self {
+        self.frozen_layers = layers;
+        self
+    }
+
+    /// Saves a checkpoint to `file` after every `interval` training examples.
+    pub fn with_checkpoints(mut self, file: impl Into<PathBuf>, interval: usize) -> Self {
+        self.checkpoint_file = Some(file.into());
+        self.checkpoint_interval = interval;
+        self
+    }
+
+    /// Samples evaluated after every epoch to report validation accuracy.
+    pub fn with_validation_set(mut self, samples: Vec<LabeledTrainingData>) -> Self {
+        self.validation_samples = InMemoryDataset::from(samples);
+        self
+    }
+
+    /// Saves the network to `file` whenever an epoch ends with the best
+    /// validation accuracy so far (training accuracy without a validation set).
+    pub fn with_best_model(mut self, file: impl Into<PathBuf>) -> Self {
+        self.best_model_file = Some(file.into());
+        self
+    }
+
+    /// Number of examples whose gradients are averaged into one update.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Threads computing the gradients of a batch in parallel (0 uses all cores).
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    pub fn with_plateau_reduction(mut self, plateau_reduction: PlateauReduction) -> Self {
+        self.plateau_reduction = Some(plateau_reduction);
+        self
+    }
+
+    /// Raises the learning rate linearly from almost zero over the first
+    /// `batches` batches, which keeps large batches stable early on.
+    pub fn with_warmup(mut self, batches: usize) -> Self {
+        self.warmup_batches = batches;
+        self
+    }
+
+    /// Cycles the learning rate after the warmup; plateau reductions lower
+    /// the top of the cycle.
+    pub fn with_cyclical_schedule(mut self, schedule: CyclicalSchedule) -> Self {
+        self.cyclical_schedule = Some(schedule);
+        self
+    }
+
+    /// Stops training once it has run for `max_duration`, after saving a
+    /// checkpoint that training can be resumed from.
+    pub fn with_max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = Some(max_duration);
+        self
+    }
+
+    /// Keeps an exponential moving average of the weights, updated with
+    /// `decay` after every batch. It is used for validation, saved as the best
+    /// model and replaces the trained weights when training ends.
+    pub fn with_ema(mut self, decay: f64) -> Self {
+        self.ema_decay = Some(decay);
+        self
+    }
+
+    /// Multiplies the loss of every training example by the weight of its class.
+    pub fn with_class_weights(mut self, class_weights: ClassWeights) -> Self {
+        self.class_weights = Some(class_weights);
+        self
+    }
+
+    /// Trains on the samples in a new random order every epoch, derived from
+    /// `seed`, instead of in the order they are given.
+    pub fn with_shuffling(mut self, seed: u64) -> Self {
+        self.shuffle_seed = Some(seed);
+        self
+    }
+
+    /// Draws the samples of every epoch by `sampling`, randomly but only
+    /// depending on `seed`; the samples are in random order, with or without
+    /// [`TrainerBuilder::with_shuffling`].
+    pub fn with_sampling(mut self, sampling: Sampling, seed: u64) -> Self {
+        self.sampling = Some((sampling, seed));
+        self
+    }
+
+    pub fn with_augmentation(mut self, augmentation: Augmentation) -> Self {
+        self.augmentation = Some(augmentation);
+        self
+    }
+
+    /// Also trains on a perturbed copy of every batch.
+    pub fn with_perturbation(mut self, perturbation: Perturbation) -> Self {
+        self.perturbation = Some(perturbation);
+        self
+    }
+
+    pub fn with_distillation(mut self, distillation: Distillation) -> Self {
+        self.distillation = Some(distillation);
+        self
+    }
+
+    pub fn with_callback(mut self, callback: impl TrainingCallback + 'static) -> Self {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    pub fn build(self) -> Trainer {
+        Trainer {
+            epochs: self.epochs,
+            learning_rate: self.learning_rate,
+            layer_multipliers: self.layer_multipliers,
+            frozen_layers: self.frozen_layers,
+            checkpoint_file: self.checkpoint_file,
+            checkpoint_interval: self.checkpoint_interval,
+            best_model_file: self.best_model_file,
+            validation_samples: self.validation_samples,
+            batch_size: self.batch_size,
+            threads: self.threads,
+            plateau_reduction: self.plateau_reduction,
+            max_duration: self.max_duration,
+            ema_decay: self.ema_decay,
+            warmup_batches: self.warmup_batches,
+            cyclical_schedule: self.cyclical_schedule,
+            class_weights: self.class_weights,
+            augmentation: self.augmentation,
+            perturbation: self.perturbation,
+            distillation: self.distillation,
+            shuffle_seed: self.shuffle_seed,
+            sampling: self.sampling,
+            callbacks: self.callbacks
+        }
+    }
+}
+
+pub struct Trainer {
+    epochs: u32,
+    learning_rate: f64,
+    layer_multipliers: Vec<f64>,
+    frozen_layers: Vec<usize>,
+    checkpoint_file: Option<PathBuf>,
+    checkpoint_interval: usize,
+    best_model_file: Option<PathBuf>,
+    validation_samples: InMemoryDataset,
+    batch_size: usize,
+    threads: usize,
+    plateau_reduction: Option<PlateauReduction>,
+    max_duration: Option<Duration>,
+    ema_decay: Option<f64>,
+    warmup_batches: usize,
+    cyclical_schedule: Option<CyclicalSchedule>,
+    class_weights: Option<ClassWeights>,
+    augmentation: Option<Augmentation>,
+    perturbation: Option<Perturbation>,
+    distillation: Option<Distillation>,
+    shuffle_seed: Option<u64>,
+    sampling: Option<(Sampling, u64)>,
+    callbacks: Vec<Box<dyn TrainingCallback>>
+}
+
+impl Trainer {
+    pub fn builder() -> TrainerBuilder {
+        TrainerBuilder::new()
+    }
+
+    pub fn validation_set(&self) -> &[LabeledTrainingData] {
+        self.validation_samples.samples()
+    }
+
+    /// Trains on a copy of `samples`; [`Trainer::train_dataset`] avoids
+    /// the copy.
+    pub fn train(&mut self,
+                 network: &mut NeuralNetwork,
+                 samples: &[LabeledTrainingData]) -> Result<TrainingHistory> {
+        self.train_dataset(network, &mut InMemoryDataset::from(samples.to_vec()))
+    }
+
+    /// Trains on the samples of `dataset`, read by position every epoch.
+    pub fn train_dataset<D: Dataset>(&mut self,
+                                     network: &mut NeuralNetwork,
+                                     dataset: &mut D) -> Result<TrainingHistory> {
+        self.run(network, TrainingProgress::default(), dataset)
+    }
+
+    /// Continues training from `checkpoint`, returning the trained network.
+    pub fn resume(&mut self,
+                  checkpoint: Checkpoint,
+                  samples: &[LabeledTrainingData]) -> Result<(NeuralNetwork, TrainingHistory)> {
+        self.resume_dataset(checkpoint, &mut InMemoryDataset::from(samples.to_vec()))
+    }
+
+    pub fn resume_dataset<D: Dataset>(&mut self,
+                                      checkpoint: Checkpoint,
+                                      dataset: &mut D) -> Result<(NeuralNetwork, TrainingHistory)> {
+        let (mut network, resume_point) = checkpoint.into_parts();
+        let history = self.resume_from(&mut network, resume_point, dataset)?;
+
+        Ok((network, history))
+    }
+
+    /// Continues training `network`, the one of a checkpoint split by
+    /// [`Checkpoint::into_parts`], from where the checkpoint was saved.
+    pub fn resume_from<D: Dataset>(&mut self,
+                                   network: &mut NeuralNetwork,
+                                   resume_point: ResumePoint,
+                                   dataset: &mut D) -> Result<TrainingHistory> {
+        self.run(network, resume_point.0, dataset)
+    }
+
+    fn run<D: Dataset>(&mut self,
+                       network: &mut NeuralNetwork,
+                       mut progress: TrainingProgress,
+                       dataset: &mut D) -> Result<TrainingHistory> {
+        if let Some(&layer) = self.frozen_layers.iter().find(|layer| **layer >= network.layer_count()) {
+            return Err(ErrorKind::NoSuchLayer { layer, layer_count: network.layer_count() })
+        }
+
+        if let Some(distillation) = &self.distillation {
+            distillation.check_student(network)?;
+        }
+
+        let class_weights = match &self.class_weights {
+            Some(class_weights) => {
+                let mut counts: Vec<usize> = class_index(dataset)?.iter().map(Vec::len).collect();
+                counts.resize(network.output_size(), 0);
+                Some(class_weights.resolve_counts(&counts, dataset.len())?)
+            },
+            None => None
+        };
+
+        let thread_pool = ThreadPoolBuilder::new()
+            .num_threads(self.threads)
+            .build()?;
+
+        let deadline = self.max_duration.map(|max_duration| Instant::now() + max_duration);
+
+        if self.ema_decay.is_some() && progress.weight_average.is_none() {
+            progress.weight_average = Some(WeightAverage::of(network));
+        }
+
+        // the seeds of a checkpoint take precedence over the ones given now
+        let shuffle_seed = self.shuffle_seed.map(|seed| *progress.shuffle_seed.get_or_insert(seed));
+        let sampling = self.sampling.map(|(sampling, seed)| (sampling, *progress.sampling_seed.get_or_insert(seed)));
+
+        while progress.epoch < self.epochs {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                self.stop_at_time_limit(network, &progress)?;
+                break
+            }
+
+            // seeded by the epoch too, so a resumed run sees the same samples
+            let epoch = progress.epoch as u64;
+            let order = match (sampling, shuffle_seed) {
+                (Some((sampling, seed)), _) => sampling.epoch_indices(dataset, &mut StdRng::seed_from_u64(seed ^ epoch))?,
+                (None, Some(seed)) => permutation(dataset.len(), seed ^ epoch),
+                (None, None) => (0..dataset.len()).collect()
+            };
+            dataset.reset()?;
+
+            if self.train_epoch(network, &mut progress, dataset, &order, class_weights.as_deref(), &thread_pool, deadline)? == CallbackAction::Stop {
+                break
+            }
+        }
+
+        // checkpoints keep both, but the trained model is the average
+        if let Some(mut average) = progress.weight_average.take() {
+            network.swap_layer_parameters(&mut average.layers);
+        }
+
+        Ok(progress.history)
+    }
+
+    /// Trains on the samples of `dataset` at the positions in `order`,
+    /// starting from where `progress` left off.
+    #[allow(clippy::too_many_arguments)]
+    fn train_epoch<D: Dataset>(&mut self,
+                               network: &mut NeuralNetwork,
+                               progress: &mut TrainingProgress,
+                               dataset: &mut D,
+                               order: &[usize],
+                               class_weights: Option<&[f64]>,
+                               thread_pool: &ThreadPool,
+                               deadline: Option<Instant>) -> Result<CallbackAction> {
+        for callback in self.callbacks.iter_mut() {
+            callback.on_epoch_start(progress.epoch + 1);
+        }
+
+        let remaining = order.get(progress.position..).unwrap_or_default();
+        for indices in remaining.chunks(self.batch_size) {
+            let batch = indices.iter().map(|index| dataset.get(*index)).collect::<training_data::Result<Vec<_>>>()?;
+            let inputs = indices.iter().map(|index| dataset.input(*index)).collect::<training_data::Result<Vec<_>>>()?;
+            let (batch, inputs) = (&batch[..], &inputs[..]);
+
+            let learning_rates = self.layer_learning_rates(network, progress);
+            let seed = ((progress.epoch as u64) << 32) + progress.position as u64;
+
+            // augmented images are new, so only their inputs are computed again
+            let (augmented, augmented_inputs);
+            let (batch, inputs) = match &self.augmentation {
+                Some(augmentation) => {
+                    augmented = augmentation.augment_batch(batch, seed ^ AUGMENTATION_SEED, thread_pool);
+                    augmented_inputs = input_vectors(&augmented);
+                    (&augmented[..], &augmented_inputs[..])
+                },
+                None => (batch, inputs)
+            };
+
+            let BatchResult { loss: batch_loss, correct_answers, mut gradients } =
+                batch_gradients(network, batch, inputs, class_weights, self.distillation.as_ref(), thread_pool)?;
+
+            // the perturbed copies only add to the gradients, the reported
+            // loss and accuracy stay those of the clean batch
+            if let Some(perturbation) = &self.perturbation {
+                let copies = perturbation.perturb(network, batch, seed, thread_pool)?;
+                let perturbed = batch_gradients(network,
+                                                &copies,
+                                                &input_vectors(&copies),
+                                                class_weights,
+                                                self.distillation.as_ref(),
+                                                thread_pool)?;
+
+                if let (Some(gradients), Some(perturbed)) = (&mut gradients, &perturbed.gradients) {
+                    gradients.merge(perturbed);
+                    gradients.scale(0.5);
+                }
+            }
+
+            // once applied, NaN spreads to every weight and later batches
+            // cannot recover, so stop while the last checkpoint is still good
+            if !batch_loss.is_finite() || gradients.as_ref().is_some_and(|gradients| !gradients.is_finite()) {
+                return Err(ErrorKind::TrainingDiverged { epoch: progress.epoch + 1, position: progress.position })
+            }
+            progress.correct_answers += correct_answers;
+
+            if let Some(gradients) = gradients {
+                network.apply_gradients(&gradients, &learning_rates);
+            }
+
+            if let (Some(average), Some(decay)) = (&mut progress.weight_average, self.ema_decay) {
+                average.update(network, decay);
+            }
+
+            let previous_position = progress.position;
+            progress.step += 1;
+            progress.total_loss += batch_loss;
+            progress.position += batch.len();
+            self.checkpoint_if_due(network, progress, previous_position)?;
+
+            let loss = batch_loss / (batch.len() as f64);
+            debug!("epoch {}, example {}: batch loss {:.4}", progress.epoch + 1, progress.position, loss);
+            for callback in self.callbacks.iter_mut() {
+                callback.on_batch_end(progress.epoch + 1, progress.position, loss);
+            }
+
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) && progress.position < order.len() {
+                return self.stop_at_time_limit(network, progress)
+            }
+        }
+
+        let validation = match (&mut progress.weight_average, self.validation_samples.is_empty()) {
+            (_, true) => None,
+            (Some(average), false) =>
+                Some(average.with_averaged(network, |network| evaluate_with_loss(network, &self.validation_samples))?),
+            (None, false) => Some(evaluate_with_loss(network, &self.validation_samples)?)
+        };
+
+        let metrics = progress.finish_epoch(validation);
+        info!("epoch {} finished: loss {:.4}, accuracy {:.4}, validation accuracy {:?}",
+              metrics.epoch, metrics.loss, metrics.accuracy, metrics.validation_accuracy);
+        self.save_if_best(network, progress, &metrics)?;
+
+        let mut action = CallbackAction::Continue;
+        for callback in self.callbacks.iter_mut() {
+            if callback.on_epoch_end(&metrics) == CallbackAction::Stop {
+                action = CallbackAction::Stop;
+            }
+        }
+
+        self.reduce_learning_rate_on_plateau(progress, &metrics);
+
+        Ok(action)
+    }
+
+    fn stop_at_time_limit(&mut self,
+                          network: &NeuralNetwork,
+                          progress: &TrainingProgress) -> Result<CallbackAction> {
+        if let Some(file) = &self.checkpoint_file {
+            Checkpoint::save(file, network, progress)?;
+        }
+
+        info!("time limit reached after {} epochs and {} examples", progress.epoch, progress.position);
+        for callback in self.callbacks.iter_mut() {
+            callback.on_time_limit_reached(progress.epoch, progress.position);
+        }
+
+        Ok(CallbackAction::Stop)
+    }
+
+    fn save_if_best(&self,
+                    network: &mut NeuralNetwork,
+                    progress: &mut TrainingProgress,
+                    metrics: &EpochMetrics) -> Result<()> {
+        let file = match &self.best_model_file {
+            Some(file) => file,
+            None => return Ok(())
+        };
+
+        let accuracy = metrics.validation_accuracy.unwrap_or(metrics.accuracy);
+        if progress.is_best(accuracy) {
+            info!("epoch {} has the best accuracy so far ({:.4}), saving it to {}",
+                  metrics.epoch, accuracy, file.display());
+            match &mut progress.weight_average {
+                Some(average) => average.with_averaged(network, |network| network.save(file))?,
+                None => network.save(file)?
+            }
+        }
+
+        Ok(())
+    }
+
+    fn reduce_learning_rate_on_plateau(&mut self, progress: &mut TrainingProgress, metrics: &EpochMetrics) {
+        let plateau_reduction = match self.plateau_reduction {
+            Some(plateau_reduction) => plateau_reduction,
+            None => return
+        };
+
+        let loss = metrics.validation_loss.unwrap_or(metrics.loss);
+        if progress.track_plateau(loss) < plateau_reduction.patience.max(1) {
+            return
+        }
+
+        progress.epochs_without_improvement = 0;
+        if self.base_learning_rate(progress) <= plateau_reduction.min_learning_rate {
+            return
+        }
+
+        progress.learning_rate_reductions += 1;
+        let learning_rate = self.base_learning_rate(progress);
+        info!("loss has not improved for {} epochs, reducing the learning rate to {}",
+              plateau_reduction.patience, learning_rate);
+        for callback in self.callbacks.iter_mut() {
+            callback.on_learning_rate_reduced(metrics.epoch, learning_rate);
+        }
+    }
+
+    /// Learning rate after the reductions made on loss plateaus so far.
+    fn base_learning_rate(&self, progress: &TrainingProgress) -> f64 {
+        return match self.plateau_reduction {
+            Some(plateau_reduction) => (self.learning_rate
+                * plateau_reduction.factor.powi(progress.learning_rate_reductions))
+                .max(plateau_reduction.min_learning_rate),
+            None => self.learning_rate
+        }
+    }
+
+    /// Learning rate of the next batch after warmup and cycling.
+    fn scheduled_learning_rate(&self, progress: &TrainingProgress) -> f64 {
+        let learning_rate = self.base_learning_rate(progress);
+
+        if progress.step < self.warmup_batches {
+            return learning_rate * ((progress.step + 1) as f64) / (self.warmup_batches as f64)
+        }
+
+        return match &self.cyclical_schedule {
+            Some(schedule) => schedule.learning_rate(learning_rate, progress.step - self.warmup_batches),
+            None => learning_rate
+        }
+    }
+
+    fn layer_learning_rates(&self, network: &NeuralNetwork, progress: &TrainingProgress) -> Vec<f64> {
+        let learning_rate = self.scheduled_learning_rate(progress);
+
+        (0..network.layer_count())
+            .map(|layer| if self.frozen_layers.contains(&layer) {
+                0.0
+            } else {
+                learning_rate * self.layer_multipliers.get(layer).unwrap_or(&1.0)
+            })
+            .collect()
+    }
+
+    /// Saves a checkpoint if the last batch, which started at `previous_position`,
+    /// crossed a multiple of the checkpoint interval.
+    fn checkpoint_if_due(&self,
+                         network: &NeuralNetwork,
+                         progress: &TrainingProgress,
+                         previous_position: usize) -> Result<()> {
+        match &self.checkpoint_file {
+            Some(file) if self.checkpoint_interval > 0
+                && progress.position / self.checkpoint_interval
+                    != previous_position / self.checkpoint_interval => {
+                debug!("saving a checkpoint after {} examples to {}", progress.position, file.display());
+                Checkpoint::save(file, network, progress)
+            },
+            _ => Ok(())
+        }
+    }
+}
@@ -1,13 +1,34 @@
 use std::fmt::{Debug, Display, Formatter};
+use std::fs::{File, read_dir, read_to_string};
 use std::io;
-use std::io::Read;
+use std::io::{BufRead, BufReader, Lines, Read, Seek, SeekFrom};
+use std::iter::{repeat, Repeat};
+use std::panic::resume_unwind;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread::{self, JoinHandle};
+use flate2::read::MultiGzDecoder;
+use log::warn;
+use nalgebra::DVector;
+use rand::{Rng, SeedableRng};
+use rand::seq::SliceRandom;
+use rand::rngs::StdRng;
 use typed_io::TypedRead;
 use thiserror::Error;
+use crate::classes::Alphabet;
 use crate::data::{Image, ImageSize};
 use crate::io_ext::{IntoDataIter, ReadData, ReadFromBytes, SimpleDataIter};
+use crate::network::Scalar;
+use crate::training::input_vector;
+use crate::transform::{Invert, Transform};
 
-const IMAGES_MAGIC: u32 = 0x00000803;
-const LABELS_MAGIC: u32 = 0x00000801;
+pub(crate) const IMAGES_MAGIC: u32 = 0x00000803;
+pub(crate) const LABELS_MAGIC: u32 = 0x00000801;
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Bytes before the first image: the magic number, the count and the size.
+const IMAGES_HEADER_LEN: u64 = 16;
+/// Bytes before the first label: the magic number and the count.
+const LABELS_HEADER_LEN: u64 = 8;
 
 #[derive(Debug, Copy, Clone)]
 pub enum DataKind {
@@ -36,15 +57,100 @@ pub enum ErrorKind {
     #[error("cannot read dataset due to an I/O error")]
     IO(#[from] io::Error),
 
+    #[error("images and labels cannot both be read from the standard input")]
+    StdinUsedTwice,
+
     #[error("the number of labels ({label_count}) must match the number of training samples ({sample_count})")]
     InvalidLabelCount {
         sample_count: u32,
         label_count: u32
+    },
+
+    #[error("invalid sample weight `{value}` on line {line}")]
+    InvalidSampleWeight {
+        line: usize,
+        value: String
+    },
+
+    #[error("the dataset has more samples than weights ({weight_count})")]
+    MissingSampleWeights {
+        weight_count: usize
+    },
+
+    #[error("the dataset has fewer samples ({sample_count}) than weights")]
+    UnusedSampleWeights {
+        sample_count: usize
+    },
+
+    #[error("invalid sample on line {line} of the CSV dataset ({reason})")]
+    InvalidCsvSample {
+        line: usize,
+        reason: String
+    },
+
+    #[error("cannot decode image {file} ({reason})")]
+    CannotDecodeImage {
+        file: String,
+        reason: String
+    },
+
+    #[error("directory {dir} of the image dataset is not named after a label")]
+    InvalidLabelDirectory {
+        dir: String
+    },
+
+    #[error("the fraction of samples to hold out must be between 0 and 1, not {0}")]
+    InvalidSplitFraction(f64),
+
+    #[error("cannot download dataset {url} ({source})")]
+    CannotDownloadDataset {
+        url: String,
+        source: Box<ureq::Error>
+    },
+
+    #[error("class names file {file} is not a JSON array of names ({reason})")]
+    InvalidClassNames {
+        file: String,
+        reason: String
+    },
+
+    #[error("there is no sample {index} in a dataset of {len}")]
+    SampleOutOfRange {
+        index: usize,
+        len: usize
+    },
+
+    #[error("the {dataset_kind} has {found} dimensions, but {expected} were expected")]
+    UnsupportedDimensions {
+        found: u8,
+        expected: u8,
+        dataset_kind: DataKind
     }
 }
 
 pub type Result<T> = std::result::Result<T, ErrorKind>;
 
+impl ErrorKind {
+    pub fn code(&self) -> &'static str {
+        return match self {
+            ErrorKind::MagicNotFound { .. } => "E0001",
+            ErrorKind::IO(_) => "E0003",
+            ErrorKind::StdinUsedTwice => "E0004",
+            ErrorKind::InvalidLabelCount { .. } => "E0002",
+            ErrorKind::InvalidSampleWeight { .. } => "E0005",
+            ErrorKind::MissingSampleWeights { .. } | ErrorKind::UnusedSampleWeights { .. } => "E0006",
+            ErrorKind::InvalidCsvSample { .. } => "E0007",
+            ErrorKind::CannotDecodeImage { .. } => "E0008",
+            ErrorKind::InvalidLabelDirectory { .. } => "E0009",
+            ErrorKind::InvalidSplitFraction(_) => "E0010",
+            ErrorKind::CannotDownloadDataset { .. } => "E0011",
+            ErrorKind::InvalidClassNames { .. } => "E0012",
+            ErrorKind::SampleOutOfRange { .. } => "E0013",
+            ErrorKind::UnsupportedDimensions { .. } => "E0014"
+        }
+    }
+}
+
 impl ReadFromBytes for ImageSize {
     type Error = io::Error;
     type Config = ();
@@ -53,8 +159,9 @@ impl ReadFromBytes for ImageSize {
                        _config: &Self::Config) -> std::result::Result<Self, Self::Error>
         where Self: Sized
     {
-        let width: u32 = input.read_be()?;
+        // IDX gives the number of rows first
         let height: u32 = input.read_be()?;
+        let width: u32 = input.read_be()?;
 
         Ok(ImageSize {
             width,
@@ -84,16 +191,28 @@ impl ReadFromBytes for Image {
 #[repr(transparent)]
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Label {
-    digit: u8
+    class: u8
 }
 
 impl Label {
-    pub fn new(digit: u8) -> Self {
-        Label { digit }
+    pub fn new(class: u8) -> Self {
+        Label { class }
+    }
+
+    pub fn class(&self) -> u8 {
+        self.class
     }
 
-    pub fn digit(&self) -> u8 {
-        self.digit
+    /// A vector of `class_count` zeros with a one for this class, or `None`
+    /// if there are too few classes for it.
+    pub fn to_one_hot(&self, class_count: usize) -> Option<DVector<Scalar>> {
+        if self.class as usize >= class_count {
+            return None
+        }
+
+        let mut one_hot = DVector::zeros(class_count);
+        one_hot[self.class as usize] = 1.0;
+        Some(one_hot)
     }
 }
 
@@ -105,24 +224,35 @@ impl ReadFromBytes for Label {
                        _config: &Self::Config) -> std::result::Result<Self, Self::Error>
         where Self: Sized
     {
-        let digit: u8 = input.read_ne()?;
-        Ok(Label::new(digit))
+        let class: u8 = input.read_ne()?;
+        Ok(Label::new(class))
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// Weight of samples that haven't been given one.
+pub const DEFAULT_SAMPLE_WEIGHT: f64 = 1.0;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct LabeledTrainingData {
     image: Image,
-    label: Label
+    label: Label,
+    weight: f64
 }
 
 impl LabeledTrainingData {
     pub fn new(image: Image, label: Label) -> LabeledTrainingData {
         LabeledTrainingData {
-            image, label
+            image, label, weight: DEFAULT_SAMPLE_WEIGHT
         }
     }
 
+    /// Scales the sample's contribution to the loss and the gradients, e.g.
+    /// to emphasize recently collected GUI corrections.
+    pub fn with_weight(mut self, weight: f64) -> LabeledTrainingData {
+        self.weight = weight;
+        self
+    }
+
     pub fn image(&self) -> &Image {
         &self.image
     }
@@ -130,6 +260,23 @@ impl LabeledTrainingData {
     pub fn label(&self) -> &Label {
         &self.label
     }
+
+    pub fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    /// The sample with its image resized to `size`, if it has another size.
+    pub fn resized(self, size: ImageSize) -> Self {
+        return if self.image.size() == size {
+            self
+        } else {
+            LabeledTrainingData::new(self.image.resize(size), self.label).with_weight(self.weight)
+        }
+    }
+
+    pub fn into_parts(self) -> (Image, Label) {
+        (self.image, self.label)
+    }
 }
 
 const fn magic(data_kind: DataKind) -> u32 {
@@ -139,14 +286,27 @@ const fn magic(data_kind: DataKind) -> u32 {
     }
 }
 
+/// Checks the magic number of an IDX file: two zero bytes, the type of the
+/// values, unsigned bytes for datasets, and the number of dimensions, which
+/// is the count of items followed by their own dimensions, rows and columns
+/// for images.
 fn verify_magic<R: Read>(input: &mut R, data_kind: DataKind) -> Result<()> {
     let found = input.read_be::<u32>()?;
-    return if found == magic(data_kind) {
+    let expected = magic(data_kind);
+    if found >> 8 != expected >> 8 {
+        return Err(ErrorKind::MagicNotFound {
+            found,
+            magic: expected,
+            dataset_kind: data_kind
+        })
+    }
+
+    return if found == expected {
         Ok(())
     } else {
-        Err(ErrorKind::MagicNotFound {
-            found,
-            magic: magic(data_kind),
+        Err(ErrorKind::UnsupportedDimensions {
+            found: found as u8,
+            expected: expected as u8,
             dataset_kind: data_kind
         })
     }
@@ -220,6 +380,325 @@ impl<I: Read, L: Read> TrainingDataset<I, L> {
     pub fn size(&self) -> u32 {
         self.images.image_count
     }
+
+    pub fn image_size(&self) -> ImageSize {
+        *self.images.images.config()
+    }
+}
+
+impl TrainingDataset<BufReader<File>, BufReader<File>> {
+    /// Opens uncompressed IDX files for reading in any order as a
+    /// [`Dataset`], as well as in order.
+    pub fn open_seekable<P: AsRef<Path>>(images: P, labels: P) -> Result<Self> {
+        Self::from_readers(BufReader::new(File::open(images)?), BufReader::new(File::open(labels)?))
+    }
+}
+
+impl<I: Read + Seek, L: Read + Seek> Dataset for TrainingDataset<I, L> {
+    fn len(&self) -> usize {
+        self.size() as usize
+    }
+
+    /// Seeks to the sample, after which reading in order continues with the
+    /// next one.
+    fn get(&mut self, index: usize) -> Result<LabeledTrainingData> {
+        if index >= self.len() {
+            return Err(ErrorKind::SampleOutOfRange { index, len: self.len() })
+        }
+
+        let image_len = self.images.images.config().area() as u64;
+        self.images.images.input_mut().seek(SeekFrom::Start(IMAGES_HEADER_LEN + index as u64 * image_len))?;
+        self.labels.labels.input_mut().seek(SeekFrom::Start(LABELS_HEADER_LEN + index as u64))?;
+        self.read = index as u32;
+        self.next().unwrap_or_else(|| Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()))
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.images.images.input_mut().seek(SeekFrom::Start(IMAGES_HEADER_LEN))?;
+        self.labels.labels.input_mut().seek(SeekFrom::Start(LABELS_HEADER_LEN))?;
+        self.read = 0;
+        Ok(())
+    }
+}
+
+/// Path that makes a dataset read from the standard input instead of a file.
+pub const STDIN_PATH: &str = "-";
+
+pub(crate) fn is_stdin<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref() == Path::new(STDIN_PATH)
+}
+
+/// The URL of a dataset on a web server, read while it is downloaded.
+fn as_url<P: AsRef<Path>>(path: &P) -> Option<&str> {
+    path.as_ref()
+        .to_str()
+        .filter(|path| path.starts_with("http://") || path.starts_with("https://"))
+}
+
+/// Opens the standard input for [`STDIN_PATH`], a download for a URL and a
+/// file otherwise.
+fn open_input<P: AsRef<Path>>(path: P) -> Result<Box<dyn BufRead + Send>> {
+    if is_stdin(&path) {
+        return Ok(Box::new(BufReader::new(io::stdin())))
+    }
+
+    return match as_url(&path) {
+        Some(url) => {
+            let response = ureq::get(url)
+                .call()
+                .map_err(|err| ErrorKind::CannotDownloadDataset { url: url.to_string(), source: Box::new(err) })?;
+            Ok(Box::new(BufReader::new(response.into_reader())))
+        },
+        None => Ok(Box::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+fn open_dataset_file<P: AsRef<Path>>(path: P) -> Result<Box<dyn Read + Send>> {
+    decompressed(open_input(path)?)
+}
+
+/// `input`, decompressed if it is gzipped like the files MNIST is
+/// distributed as. Gzip streams are told apart from IDX files by their
+/// first bytes rather than the file name, so piped data is detected too.
+fn decompressed<R: BufRead + Send + 'static>(mut input: R) -> Result<Box<dyn Read + Send>> {
+    return if input.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(input))))
+    } else {
+        Ok(Box::new(input))
+    }
+}
+
+impl TrainingDataset<Box<dyn Read + Send>, Box<dyn Read + Send>> {
+    /// Opens IDX image and label files, which may be gzipped; either of them
+    /// (but not both) may be [`STDIN_PATH`] to read a dataset piped into the
+    /// program. Files given as `http://` or `https://` URLs are streamed from
+    /// the server as they are read.
+    pub fn from_paths<P: AsRef<Path>>(images: P, labels: P) -> Result<Self> {
+        if is_stdin(&images) && is_stdin(&labels) {
+            return Err(ErrorKind::StdinUsedTwice)
+        }
+
+        Self::from_readers(open_dataset_file(images)?, open_dataset_file(labels)?)
+    }
+}
+
+/// Samples of a CSV file with one row per image, the label in the first
+/// column followed by the pixels row by row, as in the CSV versions of MNIST.
+/// Images are square and a header row is skipped. Rows are read one at a
+/// time, so files of any size can be streamed.
+pub struct CsvDataset<R: BufRead> {
+    lines: Lines<R>,
+    line: usize,
+    size: Option<ImageSize>
+}
+
+impl<R: BufRead> CsvDataset<R> {
+    pub fn from_reader(input: R) -> Self {
+        Self {
+            lines: input.lines(),
+            line: 0,
+            size: None
+        }
+    }
+
+    fn parse_row(&mut self, row: &str) -> Result<LabeledTrainingData> {
+        let line = self.line;
+        let invalid = |reason: String| ErrorKind::InvalidCsvSample { line, reason };
+
+        let values = row
+            .split(',')
+            .map(|field| field.trim().parse::<u8>().map_err(|_| invalid(format!("`{}` is not a byte", field.trim()))))
+            .collect::<Result<Vec<u8>>>()?;
+
+        let (label, pixels) = values.split_first().ok_or_else(|| invalid(String::from("empty row")))?;
+        let side = (pixels.len() as f64).sqrt().round() as u32;
+        let size = ImageSize { width: side, height: side };
+        if size.area() != pixels.len() || pixels.is_empty() {
+            return Err(invalid(format!("{} pixels do not make a square image", pixels.len())))
+        }
+
+        match self.size {
+            Some(expected) if expected != size =>
+                return Err(invalid(format!("{}x{} image after {}x{} ones", side, side, expected.width, expected.height))),
+            _ => self.size = Some(size)
+        }
+
+        Ok(LabeledTrainingData::new(Image::builder().with_size(size).with_pixels_row_major(pixels.to_vec()).build(),
+                                    Label::new(*label)))
+    }
+}
+
+impl CsvDataset<Box<dyn BufRead + Send>> {
+    /// Opens a CSV dataset, which may be [`STDIN_PATH`] or a URL.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::from_reader(open_input(path)?))
+    }
+}
+
+impl<R: BufRead> Iterator for CsvDataset<R> {
+    type Item = Result<LabeledTrainingData>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let row = match self.lines.next()? {
+                Ok(row) => row,
+                Err(err) => return Some(Err(err.into()))
+            };
+            self.line += 1;
+
+            let first_field = row.split(',').next().unwrap_or_default().trim();
+            if row.trim().is_empty() || (self.line == 1 && first_field.parse::<u8>().is_err()) {
+                continue;
+            }
+
+            return Some(self.parse_row(&row))
+        }
+    }
+}
+
+/// Image size samples of an [`ImageDirDataset`] are scaled to, that of MNIST.
+pub const IMAGE_DIR_SAMPLE_SIZE: ImageSize = ImageSize { width: 28, height: 28 };
+
+const IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// Samples of a directory with a subdirectory of PNG or JPEG images per
+/// label, e.g. `data/3/*.png`, so that one's own scanned or drawn digits can
+/// be trained on. Subdirectories of a label directory hold images of that
+/// label too. Images are converted to grayscale, scaled to the sample size
+/// and, if the background is light, inverted into light strokes on a dark
+/// background like MNIST's. Files are decoded one at a time, in path order.
+pub struct ImageDirDataset {
+    files: std::vec::IntoIter<(PathBuf, Label)>,
+    size: ImageSize
+}
+
+impl ImageDirDataset {
+    pub fn from_path<P: AsRef<Path>>(dir: P, size: ImageSize) -> Result<Self> {
+        let mut files = Vec::new();
+        for label_dir in sorted_entries(dir.as_ref())? {
+            if !label_dir.is_dir() || is_hidden(&label_dir) {
+                continue
+            }
+
+            let label = label_dir
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.parse::<u8>().ok())
+                .ok_or_else(|| ErrorKind::InvalidLabelDirectory { dir: label_dir.display().to_string() })?;
+            collect_images(&label_dir, Label::new(label), &mut files)?;
+        }
+
+        Ok(Self { files: files.into_iter(), size })
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.len() == 0
+    }
+}
+
+fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with('.'))
+}
+
+fn collect_images(dir: &Path, label: Label, files: &mut Vec<(PathBuf, Label)>) -> Result<()> {
+    for path in sorted_entries(dir)? {
+        if is_hidden(&path) {
+            continue
+        }
+
+        if path.is_dir() {
+            collect_images(&path, label, files)?;
+        } else if path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| IMAGE_EXTENSIONS.iter().any(|known| extension.eq_ignore_ascii_case(known))) {
+            files.push((path, label));
+        }
+    }
+
+    Ok(())
+}
+
+fn load_image(path: &Path, size: ImageSize) -> Result<Image> {
+    let image = image::open(path)
+        .map_err(|err| ErrorKind::CannotDecodeImage { file: path.display().to_string(), reason: err.to_string() })?
+        .into_luma8();
+    let (width, height) = image.dimensions();
+    let mut image = Image::builder()
+        .with_size(ImageSize { width, height })
+        .with_pixels_row_major(image.into_raw())
+        .build()
+        .resize(size);
+
+    // scans and drawings are usually dark strokes on white paper
+    let (width, height) = (size.width as usize, size.height as usize);
+    let border: Vec<u8> = image
+        .pixels()
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| {
+            let (x, y) = (i % width, i / width);
+            x == 0 || y == 0 || x + 1 == width || y + 1 == height
+        })
+        .map(|(_, pixel)| *pixel)
+        .collect();
+    let border_brightness = border.iter().map(|pixel| *pixel as f64).sum::<f64>() / (border.len().max(1) as f64);
+    if border_brightness > 127.5 {
+        Invert.apply(&mut image, &mut rand::thread_rng());
+    }
+
+    Ok(image)
+}
+
+impl Iterator for ImageDirDataset {
+    type Item = Result<LabeledTrainingData>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, label) = self.files.next()?;
+        Some(load_image(&path, self.size).map(|image| LabeledTrainingData::new(image, label)))
+    }
+}
+
+/// Samples of an IDX pair, of a CSV dataset that has the labels in it if
+/// `images` ends with `.csv`, or of an [`ImageDirDataset`] if `images` is a
+/// directory, read lazily and normalized for `alphabet`. `labels` is only
+/// read for IDX datasets. Images of a directory are upright and labeled by
+/// class already, so they are not normalized.
+pub fn stream_samples<P: AsRef<Path>>(images: P,
+                                      labels: P,
+                                      alphabet: Alphabet) -> Result<Box<dyn Iterator<Item = Result<LabeledTrainingData>> + Send>> {
+    if images.as_ref().is_dir() {
+        return Ok(Box::new(ImageDirDataset::from_path(images, IMAGE_DIR_SAMPLE_SIZE)?))
+    }
+
+    let samples: Box<dyn Iterator<Item = Result<LabeledTrainingData>> + Send> =
+        if images.as_ref().extension().is_some_and(|extension| extension.eq_ignore_ascii_case("csv")) {
+            Box::new(CsvDataset::from_path(images)?)
+        } else {
+            Box::new(TrainingDataset::from_paths(images, labels)?)
+        };
+
+    Ok(Box::new(samples.map(move |sample| sample.map(|sample| alphabet.normalize_sample(sample)))))
+}
+
+/// Reads a whole dataset, in any format [`stream_samples`] reads, into
+/// memory, normalizing samples for `alphabet`.
+pub fn load_samples<P: AsRef<Path>>(images: P,
+                                    labels: P,
+                                    alphabet: Alphabet) -> Result<Vec<LabeledTrainingData>> {
+    stream_samples(images, labels, alphabet)?.collect()
 }
 
 impl<I: Read, L: Read> Iterator for TrainingDataset<I, L> {
@@ -245,4 +724,543 @@ impl<I: Read, L: Read> Iterator for TrainingDataset<I, L> {
             None
         }
     }
-}
\ No newline at end of file
+}
+/// Adapters of iterators over dataset samples, such as [`TrainingDataset`].
+pub trait SampleIterExt: Iterator<Item = Result<LabeledTrainingData>> + Sized {
+    /// Gives every sample the same `weight`.
+    fn weighted(self, weight: f64) -> Weighted<Self, Repeat<f64>> {
+        self.with_weights(repeat(weight))
+    }
+
+    /// Gives the samples the corresponding `weights`, failing if there are
+    /// more samples than weights or, for a finite list, fewer.
+    fn with_weights<W: IntoIterator<Item = f64>>(self, weights: W) -> Weighted<Self, W::IntoIter> {
+        Weighted {
+            samples: self,
+            weights: weights.into_iter(),
+            read: 0,
+            finished: false
+        }
+    }
+
+    /// Yields the samples in a random order without reading them all into
+    /// memory: each sample is drawn from a buffer of the next `buffer_size`
+    /// ones, so a sample moves forward by at most that many places. The
+    /// order only depends on `seed`.
+    fn shuffled(self, buffer_size: usize, seed: u64) -> Shuffled<Self> {
+        Shuffled {
+            samples: self,
+            buffer: Vec::with_capacity(buffer_size),
+            buffer_size: buffer_size.max(1),
+            rng: StdRng::seed_from_u64(seed)
+        }
+    }
+
+    /// Leaves out samples that cannot be read, such as undecodable images or
+    /// malformed CSV rows, logging the position and the reason of each and a
+    /// summary once the samples run out. An I/O error ends the samples, since
+    /// a truncated or unreadable file cannot be read any further.
+    fn skip_invalid(self) -> SkipInvalid<Self> {
+        SkipInvalid {
+            samples: self,
+            position: 0,
+            skipped: 0,
+            finished: false
+        }
+    }
+
+    /// The samples of `subset`. A random subset is picked once all samples
+    /// have been read, holding no more than the subset in memory.
+    fn subset(self, subset: Subset) -> Subsampled<Self> {
+        Subsampled {
+            samples: self,
+            subset,
+            skipped: 0,
+            kept: 0,
+            picked: None
+        }
+    }
+
+    /// Reads up to `count` samples ahead on a background thread, so that
+    /// decoding the next samples overlaps with processing the current ones.
+    fn prefetch(self, count: usize) -> Prefetch
+        where Self: Send + 'static
+    {
+        let (sender, receiver) = sync_channel(count);
+        let worker = thread::spawn(move || {
+            for sample in self {
+                // the receiving end is gone once the samples aren't wanted anymore
+                if sender.send(sample).is_err() {
+                    break
+                }
+            }
+        });
+
+        Prefetch {
+            samples: receiver,
+            worker: Some(worker)
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<LabeledTrainingData>>> SampleIterExt for I {}
+
+pub struct SkipInvalid<I> {
+    samples: I,
+    position: usize,
+    skipped: usize,
+    finished: bool
+}
+
+impl<I: Iterator<Item = Result<LabeledTrainingData>>> Iterator for SkipInvalid<I> {
+    type Item = Result<LabeledTrainingData>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            let position = self.position;
+            match self.samples.next() {
+                Some(Ok(sample)) => {
+                    self.position += 1;
+                    return Some(Ok(sample))
+                },
+                Some(Err(err @ ErrorKind::IO(_))) => {
+                    warn!("stopped reading at sample {} ({})", position, err);
+                    self.finished = true;
+                },
+                Some(Err(err)) => {
+                    warn!("skipped sample {} ({})", position, err);
+                    self.position += 1;
+                    self.skipped += 1;
+                },
+                None => self.finished = true
+            }
+
+            if self.finished && self.skipped > 0 {
+                warn!("left out {} of {} samples that could not be read", self.skipped, self.position);
+            }
+        }
+
+        None
+    }
+}
+
+/// Which samples of a dataset are used, e.g. a few thousand for a quick
+/// experiment: the first `skip` samples are left out, and at most `limit` of
+/// the rest are kept, the first ones or, with `random_seed`, ones picked at
+/// random, in random order. Samples that cannot be read don't count towards
+/// the limit.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Subset {
+    pub skip: usize,
+    pub limit: Option<usize>,
+    pub random_seed: Option<u64>
+}
+
+pub struct Subsampled<I> {
+    samples: I,
+    subset: Subset,
+    skipped: usize,
+    kept: usize,
+    /// The random subset, once it has been picked.
+    picked: Option<std::vec::IntoIter<LabeledTrainingData>>
+}
+
+impl<I: Iterator<Item = Result<LabeledTrainingData>>> Subsampled<I> {
+    /// Reservoir sampling: the `i`th sample takes the place of one of the
+    /// `limit` picked so far with probability `limit / (i + 1)`. Without a
+    /// limit every sample is picked. The picked samples are then shuffled.
+    fn pick(&mut self, limit: Option<usize>, seed: u64) -> Result<Vec<LabeledTrainingData>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let limit = limit.unwrap_or(usize::MAX);
+        let mut picked = Vec::new();
+        for (i, sample) in (&mut self.samples).enumerate() {
+            let sample = sample?;
+            if picked.len() < limit {
+                picked.push(sample);
+            } else {
+                let j = rng.gen_range(0..=i);
+                if j < limit {
+                    picked[j] = sample;
+                }
+            }
+        }
+
+        picked.shuffle(&mut rng);
+        Ok(picked)
+    }
+}
+
+impl<I: Iterator<Item = Result<LabeledTrainingData>>> Iterator for Subsampled<I> {
+    type Item = Result<LabeledTrainingData>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.skipped < self.subset.skip {
+            self.skipped += 1;
+            if let Err(err) = self.samples.next()? {
+                return Some(Err(err))
+            }
+        }
+
+        if let Some(picked) = &mut self.picked {
+            return picked.next().map(Ok)
+        }
+
+        return match (self.subset.limit, self.subset.random_seed) {
+            (limit, Some(seed)) => match self.pick(limit, seed) {
+                Ok(picked) => {
+                    self.picked = Some(picked.into_iter());
+                    self.next()
+                },
+                Err(err) => Some(Err(err))
+            },
+            (Some(limit), None) if self.kept >= limit => None,
+            _ => {
+                let sample = self.samples.next()?;
+                if sample.is_ok() {
+                    self.kept += 1;
+                }
+                Some(sample)
+            }
+        }
+    }
+}
+
+pub struct Shuffled<I> {
+    samples: I,
+    buffer: Vec<LabeledTrainingData>,
+    buffer_size: usize,
+    rng: StdRng
+}
+
+impl<I: Iterator<Item = Result<LabeledTrainingData>>> Iterator for Shuffled<I> {
+    type Item = Result<LabeledTrainingData>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.buffer.len() < self.buffer_size {
+            match self.samples.next() {
+                Some(Ok(sample)) => self.buffer.push(sample),
+                Some(Err(err)) => return Some(Err(err)),
+                None => break
+            }
+        }
+
+        return if self.buffer.is_empty() {
+            None
+        } else {
+            let index = self.rng.gen_range(0..self.buffer.len());
+            Some(Ok(self.buffer.swap_remove(index)))
+        }
+    }
+}
+
+/// Splits `samples` into a training and a validation set, the latter with
+/// `fraction` of the samples picked at random. The split only depends on
+/// `seed`, and both sets keep the order of `samples`.
+pub fn split_samples(samples: Vec<LabeledTrainingData>,
+                     fraction: f64,
+                     seed: u64) -> Result<(Vec<LabeledTrainingData>, Vec<LabeledTrainingData>)> {
+    let held_out = held_out(samples.len(), fraction, seed)?;
+    Ok(partition(samples, &held_out))
+}
+
+pub struct Prefetch {
+    samples: Receiver<Result<LabeledTrainingData>>,
+    worker: Option<JoinHandle<()>>
+}
+
+impl Iterator for Prefetch {
+    type Item = Result<LabeledTrainingData>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        return match self.samples.recv() {
+            Ok(sample) => Some(sample),
+            Err(_) => {
+                // the reading thread has finished, or panicked, which is passed on
+                if let Some(Err(panic)) = self.worker.take().map(JoinHandle::join) {
+                    resume_unwind(panic);
+                }
+                None
+            }
+        }
+    }
+}
+
+/// `samples` in a random order that only depends on `seed`.
+pub fn shuffle_samples(samples: &[LabeledTrainingData], seed: u64) -> Vec<LabeledTrainingData> {
+    permutation(samples.len(), seed).into_iter().map(|index| samples[index].clone()).collect()
+}
+
+/// The samples of all `sources` in one set, each source spread evenly over
+/// it in its own order, so that a small source such as drawings collected
+/// with the GUI isn't bunched at the start or the end of an epoch.
+pub fn merge_samples(sources: Vec<Vec<LabeledTrainingData>>) -> Vec<LabeledTrainingData> {
+    let mut positioned: Vec<(f64, LabeledTrainingData)> = sources
+        .into_iter()
+        .flat_map(|source| {
+            let len = source.len() as f64;
+            source.into_iter().enumerate().map(move |(i, sample)| ((i as f64 + 0.5) / len, sample))
+        })
+        .collect();
+
+    // the sort is stable, so samples at the same position keep the order of their sources
+    positioned.sort_by(|a, b| a.0.total_cmp(&b.0));
+    positioned.into_iter().map(|(_, sample)| sample).collect()
+}
+
+/// Positions of `len` items in a random order that only depends on `seed`.
+pub(crate) fn permutation(len: usize, seed: u64) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..len).collect();
+    order.shuffle(&mut StdRng::seed_from_u64(seed));
+    order
+}
+
+/// Which of `len` items go to the validation set of a split.
+fn held_out(len: usize, fraction: f64, seed: u64) -> Result<Vec<bool>> {
+    if !(0.0..=1.0).contains(&fraction) {
+        return Err(ErrorKind::InvalidSplitFraction(fraction))
+    }
+
+    let mut held_out = vec![false; len];
+    let validation_count = (len as f64 * fraction).round() as usize;
+    for index in rand::seq::index::sample(&mut StdRng::seed_from_u64(seed), len, validation_count) {
+        held_out[index] = true;
+    }
+
+    Ok(held_out)
+}
+
+fn partition<T>(items: Vec<T>, held_out: &[bool]) -> (Vec<T>, Vec<T>) {
+    let (validation, training): (Vec<_>, Vec<_>) = items
+        .into_iter()
+        .zip(held_out)
+        .partition(|(_, held_out)| **held_out);
+
+    (training.into_iter().map(|(item, _)| item).collect(),
+     validation.into_iter().map(|(item, _)| item).collect())
+}
+
+/// Samples that can be read in any order and as often as needed, so that
+/// training for several epochs or sampling at random doesn't open a dataset
+/// again; the iterators over samples read a dataset once, in order.
+pub trait Dataset {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&mut self, index: usize) -> Result<LabeledTrainingData>;
+
+    /// The network input of the sample at `index`, as [`input_vector`]
+    /// computes it.
+    fn input(&mut self, index: usize) -> Result<DVector<Scalar>> {
+        Ok(input_vector(self.get(index)?.image()))
+    }
+
+    /// Goes back to the first sample, for a dataset that is also read in
+    /// order, e.g. at the start of an epoch.
+    fn reset(&mut self) -> Result<()>;
+}
+
+/// A dataset held in memory together with the network input of every
+/// sample, so that each image is converted once rather than every epoch,
+/// and an index of the samples of each class.
+pub struct InMemoryDataset {
+    samples: Vec<LabeledTrainingData>,
+    inputs: Vec<DVector<Scalar>>,
+    class_index: Vec<Vec<usize>>
+}
+
+impl InMemoryDataset {
+    fn new(samples: Vec<LabeledTrainingData>, inputs: Vec<DVector<Scalar>>) -> Self {
+        let mut class_index: Vec<Vec<usize>> = Vec::new();
+        for (index, sample) in samples.iter().enumerate() {
+            let class = sample.label().class() as usize;
+            if class >= class_index.len() {
+                class_index.resize(class + 1, Vec::new());
+            }
+            class_index[class].push(index);
+        }
+
+        InMemoryDataset { samples, inputs, class_index }
+    }
+
+    /// Reads a whole dataset like [`load_samples`].
+    pub fn load<P: AsRef<Path>>(images: P, labels: P, alphabet: Alphabet) -> Result<Self> {
+        Ok(Self::from(load_samples(images, labels, alphabet)?))
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn samples(&self) -> &[LabeledTrainingData] {
+        &self.samples
+    }
+
+    /// The inputs of the samples, as [`input_vector`] computes them.
+    pub fn inputs(&self) -> &[DVector<Scalar>] {
+        &self.inputs
+    }
+
+    /// Positions of the samples of each class, indexed by class; classes
+    /// above the highest label are left out.
+    pub fn class_index(&self) -> &[Vec<usize>] {
+        &self.class_index
+    }
+
+    /// The samples at `indices`, in that order; a sample may be repeated.
+    pub fn select(&self, indices: &[usize]) -> InMemoryDataset {
+        let (samples, inputs) = indices
+            .iter()
+            .map(|index| (self.samples[*index].clone(), self.inputs[*index].clone()))
+            .unzip();
+
+        InMemoryDataset::new(samples, inputs)
+    }
+
+    /// The dataset in a random order that only depends on `seed`, like
+    /// [`shuffle_samples`].
+    pub fn shuffled(&self, seed: u64) -> InMemoryDataset {
+        self.select(&permutation(self.len(), seed))
+    }
+
+    /// Splits the dataset into a training and a validation set, like
+    /// [`split_samples`].
+    pub fn split(self, fraction: f64, seed: u64) -> Result<(InMemoryDataset, InMemoryDataset)> {
+        let held_out = held_out(self.len(), fraction, seed)?;
+        let (training_samples, validation_samples) = partition(self.samples, &held_out);
+        let (training_inputs, validation_inputs) = partition(self.inputs, &held_out);
+
+        Ok((InMemoryDataset::new(training_samples, training_inputs),
+            InMemoryDataset::new(validation_samples, validation_inputs)))
+    }
+}
+
+impl Dataset for InMemoryDataset {
+    fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    fn get(&mut self, index: usize) -> Result<LabeledTrainingData> {
+        self.samples
+            .get(index)
+            .cloned()
+            .ok_or(ErrorKind::SampleOutOfRange { index, len: self.samples.len() })
+    }
+
+    fn input(&mut self, index: usize) -> Result<DVector<Scalar>> {
+        self.inputs
+            .get(index)
+            .cloned()
+            .ok_or(ErrorKind::SampleOutOfRange { index, len: self.inputs.len() })
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl From<Vec<LabeledTrainingData>> for InMemoryDataset {
+    fn from(samples: Vec<LabeledTrainingData>) -> Self {
+        let inputs = samples.iter().map(|sample| input_vector(sample.image())).collect();
+        InMemoryDataset::new(samples, inputs)
+    }
+}
+
+pub struct Weighted<I, W> {
+    samples: I,
+    weights: W,
+    read: usize,
+    finished: bool
+}
+
+impl<I, W> Iterator for Weighted<I, W>
+    where I: Iterator<Item = Result<LabeledTrainingData>>,
+          W: Iterator<Item = f64>
+{
+    type Item = Result<LabeledTrainingData>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None
+        }
+
+        let sample = match self.samples.next() {
+            Some(Ok(sample)) => sample,
+            Some(Err(err)) => {
+                // the weight of a bad sample is used up, so that the following
+                // samples keep theirs if it is skipped
+                self.weights.next();
+                self.read += 1;
+                return Some(Err(err))
+            },
+            None => {
+                self.finished = true;
+                // an unbounded iterator such as `repeat` always has weights left
+                let bounded = self.weights.size_hint().1.is_some();
+                return if bounded && self.weights.next().is_some() {
+                    Some(Err(ErrorKind::UnusedSampleWeights { sample_count: self.read }))
+                } else {
+                    None
+                }
+            }
+        };
+
+        return match self.weights.next() {
+            Some(weight) => {
+                self.read += 1;
+                Some(Ok(sample.with_weight(weight)))
+            },
+            None => {
+                self.finished = true;
+                Some(Err(ErrorKind::MissingSampleWeights { weight_count: self.read }))
+            }
+        }
+    }
+}
+
+/// Reads one non-negative weight per line, in the order of the samples of
+/// a dataset.
+pub fn load_sample_weights<P: AsRef<Path>>(path: P) -> Result<Vec<f64>> {
+    read_to_string(path)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| line
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|weight| weight.is_finite() && *weight >= 0.0)
+            .ok_or_else(|| ErrorKind::InvalidSampleWeight { line: index + 1, value: line.trim().to_string() }))
+        .collect()
+}
+
+/// Sidecar file naming the classes of the datasets in its directory, as a
+/// JSON array of names indexed by label, e.g. `["T-shirt/top", "Trouser",
+/// ...]` for Fashion-MNIST.
+pub const CLASS_NAMES_FILE: &str = "class-names.json";
+
+/// Names in the [`CLASS_NAMES_FILE`] beside the labels of a dataset, or in
+/// the directory of an image dataset; `None` if there is no such file, or
+/// the dataset is piped in or downloaded.
+pub fn load_class_names<P: AsRef<Path>>(images: P, labels: P) -> Result<Option<Vec<String>>> {
+    let dataset = if images.as_ref().is_dir() { images.as_ref() } else { labels.as_ref() };
+    if is_stdin(dataset) || as_url(&dataset).is_some() {
+        return Ok(None)
+    }
+
+    let dir = if dataset.is_dir() { Some(dataset) } else { dataset.parent() };
+    let file = match dir.map(|dir| dir.join(CLASS_NAMES_FILE)).filter(|file| file.is_file()) {
+        Some(file) => file,
+        None => return Ok(None)
+    };
+
+    serde_json::from_str(&read_to_string(&file)?)
+        .map(Some)
+        .map_err(|err| ErrorKind::InvalidClassNames { file: file.display().to_string(), reason: err.to_string() })
+}
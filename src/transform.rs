@@ -0,0 +1,198 @@
+use rand::{Rng, thread_rng};
+use serde::{Serialize, Deserialize};
+use crate::augmentation::{bilinear, rotate_and_shift};
+use crate::data::Image;
+use crate::training_data::LabeledTrainingData;
+
+/// A change to an image, random or not, for augmenting training samples or
+/// preparing drawings for the network. Transforms are chained with
+/// [`Transform::then`]; an `Option` of a transform applies it if it is `Some`.
+pub trait Transform {
+    fn apply(&self, image: &mut Image, rng: &mut impl Rng);
+
+    fn then<T: Transform>(self, next: T) -> Pipeline<Self, T>
+        where Self: Sized
+    {
+        Pipeline {
+            first: self,
+            second: next
+        }
+    }
+}
+
+/// Applies `first`, then `second`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Pipeline<A: Transform, B: Transform> {
+    first: A,
+    second: B
+}
+
+impl<A: Transform, B: Transform> Transform for Pipeline<A, B> {
+    fn apply(&self, image: &mut Image, rng: &mut impl Rng) {
+        self.first.apply(image, rng);
+        self.second.apply(image, rng);
+    }
+}
+
+impl<T: Transform> Transform for Option<T> {
+    fn apply(&self, image: &mut Image, rng: &mut impl Rng) {
+        if let Some(transform) = self {
+            transform.apply(image, rng);
+        }
+    }
+}
+
+impl<T: Transform> Transform for &T {
+    fn apply(&self, image: &mut Image, rng: &mut impl Rng) {
+        (*self).apply(image, rng);
+    }
+}
+
+/// Swaps black and white, e.g. to turn the black on white drawings of the
+/// GUI canvas into the white on black of the datasets.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Invert;
+
+impl Transform for Invert {
+    fn apply(&self, image: &mut Image, _rng: &mut impl Rng) {
+        for pixel in image.pixels_mut() {
+            *pixel = u8::MAX - *pixel;
+        }
+    }
+}
+
+/// Stretches the brightness so the brightest pixel is white, for faint strokes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Normalize;
+
+impl Transform for Normalize {
+    fn apply(&self, image: &mut Image, _rng: &mut impl Rng) {
+        let max = image.pixels().iter().copied().max().unwrap_or(0);
+        if max == 0 {
+            return
+        }
+
+        for pixel in image.pixels_mut() {
+            *pixel = (*pixel as u32 * u8::MAX as u32 / max as u32) as u8;
+        }
+    }
+}
+
+/// Moves the center of mass of a white on black image to its center, as
+/// MNIST digits were placed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Recenter;
+
+impl Transform for Recenter {
+    fn apply(&self, image: &mut Image, _rng: &mut impl Rng) {
+        let moments = match Moments::of(image) {
+            Some(moments) => moments,
+            None => return
+        };
+
+        let size = image.size();
+        let shift = ((size.width as f64 - 1.0) / 2.0 - moments.center.0,
+                     (size.height as f64 - 1.0) / 2.0 - moments.center.1);
+        *image = rotate_and_shift(image, 0.0, shift);
+    }
+}
+
+/// Shears a white on black image horizontally so that the principal axis of
+/// its strokes is vertical, straightening slanted handwriting.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Deskew;
+
+impl Transform for Deskew {
+    fn apply(&self, image: &mut Image, _rng: &mut impl Rng) {
+        let moments = match Moments::of(image) {
+            Some(moments) if moments.variance_y > 0.0 => moments,
+            _ => return
+        };
+
+        let skew = moments.covariance / moments.variance_y;
+        let width = image.size().width as usize;
+        let pixels: Vec<u8> = (0..image.pixels().len())
+            .map(|i| {
+                let (x, y) = ((i % width) as f64, (i / width) as f64);
+                bilinear(image, x + skew * (y - moments.center.1), y)
+            })
+            .collect();
+
+        image.pixels_mut().copy_from_slice(&pixels);
+    }
+}
+
+/// Brightness-weighted moments of an image.
+struct Moments {
+    center: (f64, f64),
+    variance_y: f64,
+    covariance: f64
+}
+
+impl Moments {
+    /// `None` for a black image.
+    fn of(image: &Image) -> Option<Moments> {
+        let width = image.size().width as usize;
+        let weighted = || image
+            .pixels()
+            .iter()
+            .enumerate()
+            .map(move |(i, pixel)| ((i % width) as f64, (i / width) as f64, *pixel as f64));
+
+        let mass: f64 = weighted().map(|(_, _, weight)| weight).sum();
+        if mass == 0.0 {
+            return None
+        }
+
+        let center = (weighted().map(|(x, _, weight)| x * weight).sum::<f64>() / mass,
+                      weighted().map(|(_, y, weight)| y * weight).sum::<f64>() / mass);
+        let variance_y = weighted().map(|(_, y, weight)| (y - center.1).powi(2) * weight).sum::<f64>() / mass;
+        let covariance = weighted().map(|(x, y, weight)| (x - center.0) * (y - center.1) * weight).sum::<f64>() / mass;
+
+        Some(Moments { center, variance_y, covariance })
+    }
+}
+
+/// How every image is prepared before a network sees it, stored with the
+/// model so that training samples and GUI drawings are prepared alike.
+/// Images are white on black, deskewed first and then recentered.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Default, PartialEq)]
+pub struct Preprocessing {
+    #[serde(default)]
+    pub deskew: bool,
+    #[serde(default)]
+    pub recenter: bool
+}
+
+impl Preprocessing {
+    /// Deskewing and recentering, the preprocessing of many MNIST classifiers.
+    pub const MNIST: Preprocessing = Preprocessing { deskew: true, recenter: true };
+
+    pub fn is_identity(&self) -> bool {
+        !self.deskew && !self.recenter
+    }
+
+    /// Applies the preprocessing, which involves no randomness.
+    pub fn prepare(&self, image: &mut Image) {
+        self.apply(image, &mut thread_rng());
+    }
+
+    pub fn prepare_sample(&self, sample: LabeledTrainingData) -> LabeledTrainingData {
+        if self.is_identity() {
+            return sample
+        }
+
+        let mut image = sample.image().clone();
+        self.prepare(&mut image);
+        LabeledTrainingData::new(image, *sample.label()).with_weight(sample.weight())
+    }
+}
+
+impl Transform for Preprocessing {
+    fn apply(&self, image: &mut Image, rng: &mut impl Rng) {
+        self.deskew
+            .then_some(Deskew)
+            .then(self.recenter.then_some(Recenter))
+            .apply(image, rng);
+    }
+}
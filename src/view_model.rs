@@ -0,0 +1,203 @@
+/// Answers kept in [`ViewModel::history`].
+pub const HISTORY_LEN: usize = 10;
+
+/// What the classifier made of a drawing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Answer {
+    pub text: String,
+    pub confidence: f64,
+    /// Spread of `confidence`, when the classifier estimates it.
+    pub uncertainty: Option<f64>
+}
+
+impl Answer {
+    pub fn new(text: impl Into<String>, confidence: f64) -> Answer {
+        Answer { text: text.into(), confidence, uncertainty: None }
+    }
+
+    pub fn with_uncertainty(mut self, uncertainty: f64) -> Answer {
+        self.uncertainty = Some(uncertainty);
+        self
+    }
+}
+
+/// State and behavior of the drawing window, independent of the GUI toolkit
+/// so it can be tested without opening a window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewModel {
+    answer: Option<Answer>,
+    history: Vec<Answer>,
+    rejection_threshold: f64,
+    profile: String,
+    correction: String,
+    /// A correction of the last drawing taken from the box, kept for
+    /// whichever of teaching and saving uses it second.
+    confirmed_correction: Option<String>,
+    correction_taught: bool,
+    teaching_status: String,
+    collecting: bool
+}
+
+impl ViewModel {
+    pub fn new(rejection_threshold: f64, profile: impl Into<String>) -> ViewModel {
+        ViewModel {
+            answer: None,
+            history: Vec::new(),
+            rejection_threshold: rejection_threshold.clamp(0.0, 1.0),
+            profile: profile.into(),
+            correction: String::new(),
+            confirmed_correction: None,
+            correction_taught: false,
+            teaching_status: String::new(),
+            collecting: false
+        }
+    }
+
+    /// Shows the answer for a new drawing; whatever was said about the
+    /// previous drawing no longer applies.
+    pub fn submit(&mut self, answer: Answer) {
+        self.history.insert(0, answer.clone());
+        self.history.truncate(HISTORY_LEN);
+        self.answer = Some(answer);
+        self.forget_correction();
+        self.teaching_status.clear();
+    }
+
+    pub fn answer(&self) -> Option<&Answer> {
+        self.answer.as_ref()
+    }
+
+    /// Earlier answers, most recent first.
+    pub fn history(&self) -> &[Answer] {
+        &self.history
+    }
+
+    /// Whether there is an answer at least as confident as the threshold.
+    pub fn is_recognized(&self) -> bool {
+        self.answer.as_ref().is_some_and(|answer| answer.confidence >= self.rejection_threshold)
+    }
+
+    /// Message key of the caption above the answer.
+    pub fn caption_key(&self) -> &'static str {
+        return match &self.answer {
+            None => "gui-draw",
+            Some(_) if self.is_recognized() => "gui-result",
+            Some(_) => "gui-unrecognized"
+        }
+    }
+
+    pub fn answer_text(&self) -> &str {
+        return match &self.answer {
+            Some(answer) if self.is_recognized() => &answer.text,
+            _ => "?"
+        }
+    }
+
+    /// Confidence of the answer in percent, empty before the first drawing.
+    pub fn confidence_text(&self) -> String {
+        return match &self.answer {
+            Some(Answer { confidence, uncertainty: Some(uncertainty), .. }) =>
+                format!("{:.0}% ± {:.0}%", confidence*100.0, uncertainty*100.0),
+            Some(Answer { confidence, .. }) => format!("{:.0}%", confidence*100.0),
+            None => String::new()
+        }
+    }
+
+    pub fn rejection_threshold(&self) -> f64 {
+        self.rejection_threshold
+    }
+
+    pub fn set_rejection_threshold(&mut self, threshold: f64) {
+        self.rejection_threshold = threshold.clamp(0.0, 1.0);
+    }
+
+    pub fn profile(&self) -> &str {
+        &self.profile
+    }
+
+    /// Switches to another profile, whose model never saw the last drawing.
+    pub fn select_profile(&mut self, profile: impl Into<String>) {
+        let profile = profile.into();
+        if profile != self.profile {
+            self.profile = profile;
+            self.answer = None;
+            self.history.clear();
+            self.forget_correction();
+            self.teaching_status.clear();
+        }
+    }
+
+    pub fn correction(&self) -> &str {
+        &self.correction
+    }
+
+    pub fn set_correction(&mut self, correction: impl Into<String>) {
+        self.correction = correction.into();
+    }
+
+    /// The typed correction, cleared for the next one; `None` if it's blank.
+    pub fn take_correction(&mut self) -> Option<String> {
+        let correction = self.correction.trim().to_string();
+        self.correction.clear();
+
+        return if correction.is_empty() {
+            None
+        } else {
+            Some(correction)
+        }
+    }
+
+    /// The correction to teach the profile for the last drawing: the typed
+    /// one, or else the one the drawing was saved with; `None` once it has
+    /// been taught.
+    pub fn take_correction_to_teach(&mut self) -> Option<String> {
+        self.confirm_typed_correction();
+        if self.correction_taught {
+            return None
+        }
+
+        self.correction_taught = true;
+        self.confirmed_correction.clone()
+    }
+
+    /// The answer the user confirms for the last drawing: the typed
+    /// correction or the one taught already, or else the recognized answer;
+    /// `None` if there is neither.
+    pub fn take_confirmed_answer(&mut self) -> Option<String> {
+        self.confirm_typed_correction();
+        return match &self.confirmed_correction {
+            Some(correction) => Some(correction.clone()),
+            None if self.is_recognized() => self.answer.as_ref().map(|answer| answer.text.clone()),
+            None => None
+        }
+    }
+
+    fn confirm_typed_correction(&mut self) {
+        if let Some(correction) = self.take_correction() {
+            self.confirmed_correction = Some(correction);
+            self.correction_taught = false;
+        }
+    }
+
+    fn forget_correction(&mut self) {
+        self.confirmed_correction = None;
+        self.correction_taught = false;
+    }
+
+    /// Whether confirmed drawings are saved to a dataset.
+    pub fn is_collecting(&self) -> bool {
+        self.collecting
+    }
+
+    pub fn set_collecting(&mut self, collecting: bool) {
+        self.collecting = collecting;
+    }
+
+    pub fn teaching_status(&self) -> &str {
+        &self.teaching_status
+    }
+
+    pub fn set_teaching_status(&mut self, status: impl Into<String>) {
+        self.teaching_status = status.into();
+    }
+}
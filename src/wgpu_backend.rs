@@ -0,0 +1,235 @@
+use std::borrow::Cow;
+use std::sync::OnceLock;
+use nalgebra::{DMatrix, DVector};
+use wgpu::util::DeviceExt;
+use crate::backend::{Backend, NalgebraBackend};
+use crate::network::Scalar;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+/// `c = a * b` with `a` of shape `m x k` and `b` of shape `k x n`, both read
+/// through row and column strides so transposed operands need no copy.
+/// `c` is written column-major, like nalgebra stores matrices.
+const GEMM_SHADER: &str = r#"
+struct Dimensions {
+    m: u32,
+    k: u32,
+    n: u32,
+    a_row_stride: u32,
+    a_column_stride: u32,
+    b_row_stride: u32,
+    b_column_stride: u32,
+}
+
+@group(0) @binding(0) var<uniform> dims: Dimensions;
+@group(0) @binding(1) var<storage, read> a: array<f32>;
+@group(0) @binding(2) var<storage, read> b: array<f32>;
+@group(0) @binding(3) var<storage, read_write> c: array<f32>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= dims.m * dims.n) {
+        return;
+    }
+
+    let row = id.x % dims.m;
+    let column = id.x / dims.m;
+
+    var sum = 0.0;
+    for (var i = 0u; i < dims.k; i = i + 1u) {
+        sum = sum + a[row * dims.a_row_stride + i * dims.a_column_stride]
+                  * b[i * dims.b_row_stride + column * dims.b_column_stride];
+    }
+
+    c[id.x] = sum;
+}
+"#;
+
+/// Operand of a matrix product: its values and the strides to read them with.
+struct Operand<'a> {
+    values: &'a [Scalar],
+    row_stride: usize,
+    column_stride: usize
+}
+
+/// Matrix products computed on the GPU through wgpu, in single precision;
+/// element-wise operations stay on the CPU.
+pub struct WgpuBackend {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline
+}
+
+static SHARED: OnceLock<Option<WgpuBackend>> = OnceLock::new();
+
+impl WgpuBackend {
+    /// The backend on the first available GPU, or `None` if there is none.
+    pub fn shared() -> Option<&'static WgpuBackend> {
+        SHARED.get_or_init(|| pollster::block_on(WgpuBackend::new())).as_ref()
+    }
+
+    async fn new() -> Option<WgpuBackend> {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                force_fallback_adapter: false,
+                compatible_surface: None
+            })
+            .await?;
+
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: None,
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::downlevel_defaults()
+            }, None)
+            .await
+            .ok()?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("gemm"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(GEMM_SHADER))
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("gemm"),
+            layout: None,
+            module: &module,
+            entry_point: "main"
+        });
+
+        Some(WgpuBackend { device, queue, pipeline })
+    }
+
+    fn gemm(&self, m: usize, k: usize, n: usize, a: Operand, b: Operand) -> Vec<Scalar> {
+        // wgpu rejects empty buffers
+        if m * n == 0 || k == 0 {
+            return vec![0.0; m * n]
+        }
+
+        let dimensions = [m, k, n, a.row_stride, a.column_stride, b.row_stride, b.column_stride]
+            .map(|x| x as u32);
+        let output_size = (m * n * std::mem::size_of::<f32>()) as wgpu::BufferAddress;
+
+        let uniform = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&dimensions),
+            usage: wgpu::BufferUsages::UNIFORM
+        });
+        let a = self.input_buffer(a.values);
+        let b = self.input_buffer(b.values);
+        let output = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: output_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false
+        });
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: output_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: uniform.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: a.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: b.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: output.as_entire_binding() }
+            ]
+        });
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(((m * n) as u32 + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE, 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&output, 0, &staging, 0, output_size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = staging.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        self.device.poll(wgpu::Maintain::Wait);
+
+        let result = bytemuck::cast_slice::<u8, f32>(&slice.get_mapped_range())
+            .iter()
+            .map(|x| *x as Scalar)
+            .collect();
+        staging.unmap();
+
+        result
+    }
+
+    fn input_buffer(&self, values: &[Scalar]) -> wgpu::Buffer {
+        let values: Vec<f32> = values.iter().map(|x| *x as f32).collect();
+
+        self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&values),
+            usage: wgpu::BufferUsages::STORAGE
+        })
+    }
+}
+
+impl Backend for WgpuBackend {
+    fn name(&self) -> &'static str {
+        "wgpu"
+    }
+
+    fn mat_vec(&self, matrix: &DMatrix<Scalar>, vector: &DVector<Scalar>) -> DVector<Scalar> {
+        let (rows, columns) = matrix.shape();
+        let result = self.gemm(rows, columns, 1,
+                               Operand { values: matrix.as_slice(), row_stride: 1, column_stride: rows },
+                               Operand { values: vector.as_slice(), row_stride: 1, column_stride: columns });
+
+        DVector::from_vec(result)
+    }
+
+    fn tr_mat_vec(&self, matrix: &DMatrix<Scalar>, vector: &DVector<Scalar>) -> DVector<Scalar> {
+        let (rows, columns) = matrix.shape();
+        let result = self.gemm(columns, rows, 1,
+                               Operand { values: matrix.as_slice(), row_stride: rows, column_stride: 1 },
+                               Operand { values: vector.as_slice(), row_stride: 1, column_stride: rows });
+
+        DVector::from_vec(result)
+    }
+
+    fn outer(&self, a: &DVector<Scalar>, b: &DVector<Scalar>) -> DMatrix<Scalar> {
+        let (rows, columns) = (a.len(), b.len());
+        let result = self.gemm(rows, 1, columns,
+                               Operand { values: a.as_slice(), row_stride: 1, column_stride: rows },
+                               Operand { values: b.as_slice(), row_stride: columns, column_stride: 1 });
+
+        DMatrix::from_vec(rows, columns, result)
+    }
+
+    fn axpy(&self, alpha: Scalar, x: &DVector<Scalar>, y: &mut DVector<Scalar>) {
+        NalgebraBackend.axpy(alpha, x, y)
+    }
+
+    fn matrix_axpy(&self, alpha: Scalar, x: &DMatrix<Scalar>, y: &mut DMatrix<Scalar>) {
+        NalgebraBackend.matrix_axpy(alpha, x, y)
+    }
+
+    fn scale(&self, alpha: Scalar, x: &DVector<Scalar>) -> DVector<Scalar> {
+        NalgebraBackend.scale(alpha, x)
+    }
+
+    fn map(&self, x: &DVector<Scalar>, function: fn(Scalar) -> Scalar) -> DVector<Scalar> {
+        NalgebraBackend.map(x, function)
+    }
+
+    fn component_mul(&self, a: &DVector<Scalar>, b: &DVector<Scalar>) -> DVector<Scalar> {
+        NalgebraBackend.component_mul(a, b)
+    }
+
+    fn dot(&self, a: &DVector<Scalar>, b: &DVector<Scalar>) -> Scalar {
+        NalgebraBackend.dot(a, b)
+    }
+}
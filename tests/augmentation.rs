@@ -0,0 +1,142 @@
+//! Transformations of training images and drawings.
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use digit_recognition::augmentation::{rotate_and_shift, Augmentation, Blur, ElasticDistortion, SaltAndPepper};
+use digit_recognition::data::{Image, ImageSize};
+use digit_recognition::transform::{Deskew, Invert, Normalize, Preprocessing, Recenter, Transform};
+
+const SIZE: ImageSize = ImageSize { width: 5, height: 5 };
+
+/// A single lit pixel at `(x, y)`.
+fn dot(x: usize, y: usize) -> Image {
+    let mut pixels = vec![0u8; SIZE.area()];
+    pixels[y * SIZE.width as usize + x] = 255;
+    Image::builder().with_size(SIZE).with_pixels_row_major(pixels).build()
+}
+
+#[test]
+fn shift_moves_pixels_and_fills_with_background() {
+    assert_eq!(rotate_and_shift(&dot(1, 2), 0.0, (2.0, -1.0)).pixels(), dot(3, 1).pixels());
+    assert!(rotate_and_shift(&dot(4, 4), 0.0, (1.0, 0.0)).pixels().iter().all(|pixel| *pixel == 0));
+}
+
+#[test]
+fn rotation_turns_around_the_center() {
+    let rotated = rotate_and_shift(&dot(4, 2), 90f64.to_radians(), (0.0, 0.0));
+    assert_eq!(rotated.pixels(), dot(2, 4).pixels());
+    assert_eq!(rotate_and_shift(&dot(2, 2), 33f64.to_radians(), (0.0, 0.0)).pixels()[12], 255);
+}
+
+#[test]
+fn no_augmentation_keeps_the_image() {
+    let augmentation = Augmentation { max_rotation: 0.0, max_shift: 0.0, ..Augmentation::default() };
+    let mut image = dot(1, 3);
+
+    augmentation.apply(&mut image, &mut StdRng::seed_from_u64(1));
+    assert_eq!(image.pixels(), dot(1, 3).pixels());
+}
+
+#[test]
+fn elastic_distortion_moves_strokes_smoothly() {
+    let size = ImageSize::square(28);
+    let pixels: Vec<u8> = (0..size.area()).map(|i| if i % 28 == 14 { 255 } else { 0 }).collect();
+    let line = Image::builder().with_size(size).with_pixels_row_major(pixels).build();
+
+    let mut distorted = line.clone();
+    ElasticDistortion::default().apply(&mut distorted, &mut StdRng::seed_from_u64(3));
+    assert_ne!(distorted.pixels(), line.pixels());
+    // the stroke is bent, not scattered: every row still has ink near the line
+    for row in distorted.pixels().chunks(28) {
+        assert!(row[8..21].iter().any(|pixel| *pixel > 0));
+    }
+
+    let mut still = line.clone();
+    ElasticDistortion { alpha: 0.0, sigma: 4.0 }.apply(&mut still, &mut StdRng::seed_from_u64(3));
+    assert_eq!(still.pixels(), line.pixels());
+}
+
+#[test]
+fn blur_spreads_a_dot() {
+    let mut blurred = dot(2, 2);
+    Blur { max_sigma: 1.0 }.apply(&mut blurred, &mut StdRng::seed_from_u64(5));
+
+    assert!(blurred.pixels()[12] < 255);
+    assert!(blurred.pixels()[13] > 0);
+    assert_eq!(blurred.pixels()[13], blurred.pixels()[11]);
+}
+
+#[test]
+fn salt_and_pepper_only_sets_black_or_white() {
+    let gray = Image::builder().with_size(SIZE).with_pixels_row_major(vec![100u8; SIZE.area()]).build();
+
+    let mut noisy = gray.clone();
+    SaltAndPepper { probability: 0.5 }.apply(&mut noisy, &mut StdRng::seed_from_u64(5));
+    assert!(noisy.pixels().iter().all(|pixel| [0, 100, 255].contains(pixel)));
+    assert!(noisy.pixels().iter().any(|pixel| *pixel != 100));
+
+    let mut clean = gray.clone();
+    SaltAndPepper { probability: 0.0 }.apply(&mut clean, &mut StdRng::seed_from_u64(5));
+    assert_eq!(clean.pixels(), gray.pixels());
+}
+
+#[test]
+fn pipelines_apply_transforms_in_order() {
+    let faint: Vec<u8> = dot(0, 0).pixels().iter().map(|pixel| 255 - pixel / 2).collect();
+    let mut image = Image::builder().with_size(SIZE).with_pixels_row_major(faint).build();
+
+    let preprocessing = Invert.then(None::<Blur>).then(Normalize).then(Recenter);
+    preprocessing.apply(&mut image, &mut StdRng::seed_from_u64(1));
+
+    assert_eq!(image.pixels(), dot(2, 2).pixels());
+}
+
+/// Column of the center of mass of each row that has ink.
+fn row_centers(image: &Image) -> Vec<f64> {
+    image
+        .pixels()
+        .chunks(image.size().width as usize)
+        .filter(|row| row.iter().any(|pixel| *pixel > 0))
+        .map(|row| {
+            let mass: f64 = row.iter().map(|pixel| *pixel as f64).sum();
+            row.iter().enumerate().map(|(x, pixel)| x as f64 * *pixel as f64).sum::<f64>() / mass
+        })
+        .collect()
+}
+
+#[test]
+fn deskewing_straightens_a_slanted_stroke() {
+    let size = ImageSize::square(28);
+    // leans right by one pixel every second row
+    let pixels: Vec<u8> = (0..size.area())
+        .map(|i| if (i % 28) as isize == 8 + (27 - (i / 28) as isize) / 2 && (4..24).contains(&(i / 28)) { 255 } else { 0 })
+        .collect();
+    let mut image = Image::builder().with_size(size).with_pixels_row_major(pixels).build();
+
+    let slant = |image: &Image| {
+        let centers = row_centers(image);
+        (centers[0] - centers[centers.len() - 1]).abs()
+    };
+    assert!(slant(&image) > 8.0);
+
+    Deskew.apply(&mut image, &mut StdRng::seed_from_u64(1));
+    assert!(slant(&image) < 1.0, "slant {} after deskewing", slant(&image));
+
+    Preprocessing::MNIST.prepare(&mut image);
+    let centers = row_centers(&image);
+    let middle = centers.iter().sum::<f64>() / centers.len() as f64;
+    assert!((middle - 13.5).abs() < 0.5, "centered at column {}", middle);
+}
+
+#[test]
+fn resizing_averages_down_and_interpolates_up() {
+    let checkerboard: Vec<u8> = (0..16).map(|i| if (i % 4 + i / 4) % 2 == 0 { 255 } else { 0 }).collect();
+    let image = Image::builder().with_size(ImageSize::square(4)).with_pixels_row_major(checkerboard).build();
+    assert_eq!(image.resize(ImageSize::square(2)).pixels(), &[128; 4]);
+    // a shrink by a fraction covers some source pixels in part
+    assert!(image.resize(ImageSize::square(3)).pixels().iter().all(|pixel| (85..=170).contains(pixel)));
+
+    let gradient = Image::builder().with_size(ImageSize { width: 2, height: 1 }).with_pixels_row_major(vec![0, 200]).build();
+    assert_eq!(gradient.resize(ImageSize { width: 4, height: 2 }).pixels(), &[0, 50, 150, 200, 0, 50, 150, 200]);
+    assert_eq!(dot(2, 2).resize(SIZE), dot(2, 2));
+}
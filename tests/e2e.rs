@@ -0,0 +1,105 @@
+//! The whole pipeline on a synthetic dataset: writing it to IDX files,
+//! loading it back, training, saving, reloading, evaluating and predicting.
+
+use std::fs;
+use std::path::PathBuf;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use digit_recognition::classes::Alphabet;
+use digit_recognition::data::{Image, ImageSize};
+use digit_recognition::dataset::write::append_sample;
+use digit_recognition::features::InputFeatures;
+use digit_recognition::network::{Architecture, NeuralNetwork};
+use digit_recognition::training::{evaluate, evaluate_stream, input_vector, Trainer};
+use digit_recognition::training_data::{load_samples, stream_samples, Label, LabeledTrainingData};
+
+const SIZE: ImageSize = ImageSize { width: 8, height: 8 };
+const CLASS_COUNT: usize = 3;
+
+/// A vertical bar, a horizontal bar or a diagonal, at varying positions.
+fn synthetic_sample(i: usize) -> LabeledTrainingData {
+    let class = i % CLASS_COUNT;
+    let offset = 1 + (i / CLASS_COUNT) % 6;
+
+    let pixels: Vec<u8> = (0..SIZE.area())
+        .map(|position| {
+            let (x, y) = (position % SIZE.width as usize, position / SIZE.width as usize);
+            let on_stroke = match class {
+                0 => x == offset,
+                1 => y == offset,
+                _ => x == y
+            };
+            if on_stroke { 255 } else { (i * 7 % 20) as u8 }
+        })
+        .collect();
+
+    LabeledTrainingData::new(Image::builder().with_size(SIZE).with_pixels_row_major(pixels).build(),
+                             Label::new(class as u8))
+}
+
+/// A directory of its own for the test, removed when dropped.
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn new(name: &str) -> ScratchDir {
+        let path = std::env::temp_dir().join(format!("digit_recognition-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        ScratchDir(path)
+    }
+
+    fn file(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn pipeline_smoke_test() {
+    let dir = ScratchDir::new("e2e");
+    let (images_file, labels_file) = (dir.file("images.idx"), dir.file("labels.idx"));
+    let model_file = dir.file("model.json");
+
+    let generated: Vec<LabeledTrainingData> = (0..60).map(synthetic_sample).collect();
+    for sample in &generated {
+        append_sample(&images_file, &labels_file, sample).unwrap();
+    }
+
+    let samples = load_samples(&images_file, &labels_file, Alphabet::default()).unwrap();
+    assert_eq!(samples.len(), generated.len());
+    assert_eq!(samples[5].image().pixels(), generated[5].image().pixels());
+
+    let mut network = NeuralNetwork::new_untrained_with_rng(&mut StdRng::seed_from_u64(1),
+                                                            SIZE,
+                                                            CLASS_COUNT,
+                                                            InputFeatures::default(),
+                                                            &Architecture::default());
+    Trainer::builder()
+        .with_epochs(20)
+        .with_learning_rate(0.5)
+        .with_batch_size(6)
+        .with_threads(1)
+        .build()
+        .train(&mut network, &samples)
+        .unwrap();
+    let accuracy = evaluate(&network, &samples).unwrap();
+    assert!(accuracy > 0.9, "accuracy {} after training", accuracy);
+
+    network.save(&model_file).unwrap();
+    let reloaded = NeuralNetwork::load(&model_file).unwrap();
+    assert_eq!(evaluate(&reloaded, &samples).unwrap(), accuracy);
+
+    let stream = stream_samples(&images_file, &labels_file, Alphabet::default()).unwrap();
+    let evaluation = evaluate_stream(&reloaded, stream, 16, 2).unwrap();
+    assert_eq!(evaluation.samples, samples.len());
+    assert_eq!(evaluation.accuracy(), accuracy);
+
+    let sample = synthetic_sample(7);
+    let prediction = reloaded.predict(input_vector(sample.image())).unwrap();
+    assert_eq!(prediction.class, sample.label().class() as usize);
+}
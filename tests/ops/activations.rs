@@ -0,0 +1,67 @@
+use nalgebra::DVector;
+use digit_recognition::autograd::Tape;
+use digit_recognition::network::{sigmoid, sigmoid_prime, Scalar};
+use crate::common::{assert_all_close, backends, numerical_gradient, probe};
+
+fn input() -> DVector<Scalar> {
+    DVector::from_vec(vec![0.0, 2.0, -1.0])
+}
+
+#[test]
+fn sigmoid_forward() {
+    for backend in backends() {
+        let mut tape = Tape::with_backend(backend);
+        let x = tape.input(input());
+        let output = tape.activation(sigmoid, sigmoid_prime, x);
+
+        assert_all_close(tape.value(output), &[0.5, 0.8807970779778823, 0.2689414213699951]);
+    }
+}
+
+#[test]
+fn sigmoid_backward() {
+    let input = input();
+    let numerical = numerical_gradient(|input| input.map(sigmoid).dot(&probe(3)), &input);
+
+    for backend in backends() {
+        let mut tape = Tape::with_backend(backend);
+        let x = tape.input(input.clone_owned());
+        let output = tape.activation(sigmoid, sigmoid_prime, x);
+        let gradients = tape.backward(output, probe(3), &[]);
+
+        assert_all_close(gradients.node(x).unwrap(), &numerical);
+    }
+}
+
+#[test]
+fn scale_forward_and_backward() {
+    let input = input();
+    let numerical = numerical_gradient(|input| (input * 0.5).dot(&probe(3)), &input);
+
+    for backend in backends() {
+        let mut tape = Tape::with_backend(backend);
+        let x = tape.input(input.clone_owned());
+        let output = tape.scale(0.5, x);
+        let gradients = tape.backward(output, probe(3), &[]);
+
+        assert_all_close(tape.value(output), &[0.0, 1.0, -0.5]);
+        assert_all_close(gradients.node(x).unwrap(), &numerical);
+    }
+}
+
+#[test]
+fn mask_forward_and_backward() {
+    let input = input();
+    let mask = DVector::from_vec(vec![2.0, 0.0, 2.0]);
+    let numerical = numerical_gradient(|input| input.component_mul(&mask).dot(&probe(3)), &input);
+
+    for backend in backends() {
+        let mut tape = Tape::with_backend(backend);
+        let x = tape.input(input.clone_owned());
+        let output = tape.mask(mask.clone_owned(), x);
+        let gradients = tape.backward(output, probe(3), &[]);
+
+        assert_all_close(tape.value(output), &[0.0, 0.0, -2.0]);
+        assert_all_close(gradients.node(x).unwrap(), &numerical);
+    }
+}
@@ -0,0 +1,57 @@
+use nalgebra::{DMatrix, DVector};
+use digit_recognition::network::Scalar;
+use crate::common::{assert_all_close, backends};
+
+fn matrix() -> DMatrix<Scalar> {
+    DMatrix::from_row_slice(2, 3, &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0])
+}
+
+#[test]
+fn mat_vec() {
+    for backend in backends() {
+        let result = backend.mat_vec(&matrix(), &DVector::from_vec(vec![1.0, 0.0, -1.0]));
+        assert_all_close(&result, &[-2.0, -2.0]);
+    }
+}
+
+#[test]
+fn tr_mat_vec() {
+    for backend in backends() {
+        let result = backend.tr_mat_vec(&matrix(), &DVector::from_vec(vec![1.0, -1.0]));
+        assert_all_close(&result, &[-3.0, -3.0, -3.0]);
+    }
+}
+
+#[test]
+fn outer() {
+    for backend in backends() {
+        let result = backend.outer(&DVector::from_vec(vec![1.0, 2.0]), &DVector::from_vec(vec![3.0, 4.0, 5.0]));
+        assert_all_close(&result, &DMatrix::from_row_slice(2, 3, &[3.0, 4.0, 5.0, 6.0, 8.0, 10.0]));
+    }
+}
+
+#[test]
+fn axpy() {
+    for backend in backends() {
+        let mut y = DVector::from_vec(vec![1.0, 1.0]);
+        backend.axpy(-2.0, &DVector::from_vec(vec![0.5, 3.0]), &mut y);
+        assert_all_close(&y, &[0.0, -5.0]);
+
+        let mut y = matrix();
+        backend.matrix_axpy(0.5, &matrix(), &mut y);
+        assert_all_close(&y, &(matrix() * 1.5));
+    }
+}
+
+#[test]
+fn elementwise() {
+    let a = DVector::from_vec(vec![1.0, -2.0, 3.0]);
+    let b = DVector::from_vec(vec![4.0, 5.0, -6.0]);
+
+    for backend in backends() {
+        assert_all_close(&backend.scale(2.0, &a), &[2.0, -4.0, 6.0]);
+        assert_all_close(&backend.map(&a, Scalar::abs), &[1.0, 2.0, 3.0]);
+        assert_all_close(&backend.component_mul(&a, &b), &[4.0, -10.0, -18.0]);
+        assert_all_close(&[backend.dot(&a, &b)], &[-24.0]);
+    }
+}
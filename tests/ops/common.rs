@@ -0,0 +1,54 @@
+use nalgebra::{DMatrix, DVector};
+use digit_recognition::backend::{Backend, ComputeBackend};
+use digit_recognition::network::Scalar;
+
+#[cfg(not(feature = "f32"))]
+const EPSILON: Scalar = 1e-6;
+#[cfg(not(feature = "f32"))]
+const TOLERANCE: Scalar = 1e-6;
+
+#[cfg(feature = "f32")]
+const EPSILON: Scalar = 1e-2;
+#[cfg(feature = "f32")]
+const TOLERANCE: Scalar = 1e-3;
+
+pub fn assert_all_close<'a>(actual: impl IntoIterator<Item = &'a Scalar>,
+                            expected: impl IntoIterator<Item = &'a Scalar>) {
+    let actual: Vec<Scalar> = actual.into_iter().copied().collect();
+    let expected: Vec<Scalar> = expected.into_iter().copied().collect();
+
+    assert_eq!(actual.len(), expected.len(), "{:?} != {:?}", actual, expected);
+    for (a, e) in actual.iter().zip(expected.iter()) {
+        assert!((a - e).abs() <= TOLERANCE * e.abs().max(1.0), "{:?} != {:?}", actual, expected);
+    }
+}
+
+/// Every backend the suite runs against.
+pub fn backends() -> impl Iterator<Item = &'static dyn Backend> {
+    ComputeBackend::ALL.iter().map(|backend| backend.backend())
+}
+
+/// Central-difference gradient of `f` at `x`.
+pub fn numerical_gradient(f: impl Fn(&DVector<Scalar>) -> Scalar, x: &DVector<Scalar>) -> DVector<Scalar> {
+    DVector::from_fn(x.len(), |i, _| {
+        let (mut plus, mut minus) = (x.clone_owned(), x.clone_owned());
+        plus[i] += EPSILON;
+        minus[i] -= EPSILON;
+        (f(&plus) - f(&minus)) / (2.0 * EPSILON)
+    })
+}
+
+pub fn numerical_matrix_gradient(f: impl Fn(&DMatrix<Scalar>) -> Scalar, x: &DMatrix<Scalar>) -> DMatrix<Scalar> {
+    DMatrix::from_fn(x.nrows(), x.ncols(), |i, j| {
+        let (mut plus, mut minus) = (x.clone_owned(), x.clone_owned());
+        plus[(i, j)] += EPSILON;
+        minus[(i, j)] -= EPSILON;
+        (f(&plus) - f(&minus)) / (2.0 * EPSILON)
+    })
+}
+
+/// Weights the outputs of an operation into a scalar, so that its gradient
+/// w.r.t. the outputs is `probe` itself.
+pub fn probe(len: usize) -> DVector<Scalar> {
+    DVector::from_fn(len, |i, _| 0.3 + 0.7 * (i as Scalar) * if i % 2 == 0 { 1.0 } else { -1.0 })
+}
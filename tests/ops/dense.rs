@@ -0,0 +1,124 @@
+use nalgebra::{DMatrix, DVector};
+use digit_recognition::autograd::Tape;
+use digit_recognition::backend::{Backend, NalgebraBackend};
+use digit_recognition::network::Scalar;
+use digit_recognition::sparse::CsrMatrix;
+use crate::common::{assert_all_close, backends, numerical_gradient, numerical_matrix_gradient, probe};
+
+fn weights() -> DMatrix<Scalar> {
+    DMatrix::from_row_slice(3, 2, &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0])
+}
+
+/// [`weights`] with half of the entries pruned away.
+fn pruned_weights() -> DMatrix<Scalar> {
+    DMatrix::from_row_slice(3, 2, &[1.0, 0.0, 0.0, 4.0, 5.0, 0.0])
+}
+
+fn biases() -> DVector<Scalar> {
+    DVector::from_vec(vec![0.5, -0.5, 0.25])
+}
+
+fn input() -> DVector<Scalar> {
+    DVector::from_vec(vec![1.0, -1.0])
+}
+
+fn dense(backend: &'static dyn Backend,
+         weights: &DMatrix<Scalar>,
+         biases: &DVector<Scalar>,
+         input: &DVector<Scalar>) -> Scalar {
+    let mut tape = Tape::with_backend(backend);
+    let x = tape.input(input.clone_owned());
+    let product = tape.mat_mul(0, weights, x);
+    let output = tape.add_bias(0, biases, product);
+
+    tape.value(output).dot(&probe(3))
+}
+
+fn sparse_dense(weights: &DMatrix<Scalar>, biases: &DVector<Scalar>, input: &DVector<Scalar>) -> Scalar {
+    let mut tape = Tape::with_backend(&NalgebraBackend);
+    let x = tape.input(input.clone_owned());
+    let product = tape.sparse_mat_mul(0, &CsrMatrix::from_dense(weights), x);
+    let output = tape.add_bias(0, biases, product);
+
+    tape.value(output).dot(&probe(3))
+}
+
+#[test]
+fn mat_mul_forward() {
+    for backend in backends() {
+        let mut tape = Tape::with_backend(backend);
+        let x = tape.input(input());
+        let output = tape.mat_mul(0, &weights(), x);
+
+        assert_all_close(tape.value(output), &[-1.0, -1.0, -1.0]);
+    }
+}
+
+#[test]
+fn add_bias_forward() {
+    for backend in backends() {
+        let mut tape = Tape::with_backend(backend);
+        let x = tape.input(DVector::from_vec(vec![1.0, 2.0, 3.0]));
+        let output = tape.add_bias(0, &biases(), x);
+
+        assert_all_close(tape.value(output), &[1.5, 1.5, 3.25]);
+    }
+}
+
+#[test]
+fn dense_backward() {
+    let (weights, biases, input) = (weights(), biases(), input());
+
+    // finite differences through a single-precision GPU backend are too noisy to compare against
+    let input_gradient = numerical_gradient(|input| dense(&NalgebraBackend, &weights, &biases, input), &input);
+    let weights_gradient =
+        numerical_matrix_gradient(|weights| dense(&NalgebraBackend, weights, &biases, &input), &weights);
+    let biases_gradient = numerical_gradient(|biases| dense(&NalgebraBackend, &weights, biases, &input), &biases);
+
+    for backend in backends() {
+        let mut tape = Tape::with_backend(backend);
+        let x = tape.input(input.clone_owned());
+        let product = tape.mat_mul(0, &weights, x);
+        let output = tape.add_bias(0, &biases, product);
+        let gradients = tape.backward(output, probe(3), &[&weights]);
+
+        assert_all_close(gradients.node(x).unwrap(), &input_gradient);
+        assert_all_close(gradients.weights(0).unwrap(), &weights_gradient);
+        assert_all_close(gradients.biases(0).unwrap(), &biases_gradient);
+    }
+}
+
+#[test]
+fn sparse_mat_mul_forward_matches_mat_mul() {
+    let weights = pruned_weights();
+    for backend in backends() {
+        let mut tape = Tape::with_backend(backend);
+        let x = tape.input(input());
+        let dense = tape.mat_mul(0, &weights, x);
+        let sparse = tape.sparse_mat_mul(0, &CsrMatrix::from_dense(&weights), x);
+
+        assert_all_close(tape.value(sparse), &[1.0, -4.0, 5.0]);
+        assert_all_close(tape.value(sparse), tape.value(dense));
+    }
+}
+
+#[test]
+fn sparse_dense_backward() {
+    let (weights, biases, input) = (pruned_weights(), biases(), input());
+
+    let input_gradient = numerical_gradient(|input| sparse_dense(&weights, &biases, input), &input);
+    let weights_gradient = numerical_matrix_gradient(|weights| sparse_dense(weights, &biases, &input), &weights);
+    let biases_gradient = numerical_gradient(|biases| sparse_dense(&weights, biases, &input), &biases);
+
+    for backend in backends() {
+        let mut tape = Tape::with_backend(backend);
+        let x = tape.input(input.clone_owned());
+        let product = tape.sparse_mat_mul(0, &CsrMatrix::from_dense(&weights), x);
+        let output = tape.add_bias(0, &biases, product);
+        let gradients = tape.backward(output, probe(3), &[&weights]);
+
+        assert_all_close(gradients.node(x).unwrap(), &input_gradient);
+        assert_all_close(gradients.weights(0).unwrap(), &weights_gradient);
+        assert_all_close(gradients.biases(0).unwrap(), &biases_gradient);
+    }
+}
@@ -0,0 +1,51 @@
+use nalgebra::DVector;
+use digit_recognition::autograd::Tape;
+use digit_recognition::network::{cross_entropy_loss, cross_entropy_loss_gradient, Scalar};
+use crate::common::{assert_all_close, backends, numerical_gradient};
+
+fn target() -> DVector<Scalar> {
+    DVector::from_vec(vec![0.0, 1.0, 0.0])
+}
+
+#[test]
+fn cross_entropy_forward() {
+    let out = DVector::from_vec(vec![0.25, 0.75]);
+    let expected = DVector::from_vec(vec![0.0, 1.0]);
+
+    assert_all_close(&[cross_entropy_loss(&out, &expected)], &[0.2876820724517809]);
+}
+
+#[test]
+fn cross_entropy_of_zero_probability_is_finite() {
+    let out = DVector::from_vec(vec![1.0, 0.0]);
+    let expected = DVector::from_vec(vec![0.0, 1.0]);
+
+    assert!(cross_entropy_loss(&out, &expected).is_finite());
+    assert!(cross_entropy_loss_gradient(&out, &expected).iter().all(|x| x.is_finite()));
+}
+
+#[test]
+fn cross_entropy_backward() {
+    let out = DVector::from_vec(vec![0.2, 0.5, 0.3]);
+
+    assert_all_close(&cross_entropy_loss_gradient(&out, &target()),
+                     &numerical_gradient(|out| cross_entropy_loss(out, &target()), &out));
+}
+
+#[test]
+fn softmax_cross_entropy_backward() {
+    let logits = DVector::from_vec(vec![0.5, -1.0, 2.0]);
+
+    for backend in backends() {
+        let mut tape = Tape::with_backend(backend);
+        let x = tape.input(logits.clone_owned());
+        let output = tape.softmax(x);
+        let probabilities = tape.value(output).clone_owned();
+        let gradients = tape.backward(output,
+                                      cross_entropy_loss_gradient(&probabilities, &target()),
+                                      &[]);
+
+        // softmax followed by cross-entropy has the well-known gradient `y - t`
+        assert_all_close(gradients.node(x).unwrap(), &(probabilities - target()));
+    }
+}
@@ -0,0 +1,10 @@
+//! Forward values against precomputed references and backward passes against
+//! numerical gradients for every operation the network is built from.
+//! Alternative compute backends are expected to pass the same suite.
+
+mod common;
+mod backend;
+mod dense;
+mod activations;
+mod softmax;
+mod loss;
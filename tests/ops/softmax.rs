@@ -0,0 +1,40 @@
+use nalgebra::DVector;
+use digit_recognition::autograd::{softmax, Tape};
+use digit_recognition::network::Scalar;
+use crate::common::{assert_all_close, backends, numerical_gradient, probe};
+
+fn softmax_of(input: &DVector<Scalar>) -> DVector<Scalar> {
+    let mut output = input.clone_owned();
+    softmax(&mut output);
+    output
+}
+
+#[test]
+fn softmax_forward() {
+    let output = softmax_of(&DVector::from_vec(vec![1.0, 2.0, 3.0]));
+
+    assert_all_close(&output, &[0.09003057317038046, 0.24472847105479764, 0.6652409557748219]);
+}
+
+#[test]
+fn softmax_is_shift_invariant_and_stable() {
+    let shifted = softmax_of(&DVector::from_vec(vec![1001.0, 1002.0, 1003.0]));
+
+    assert_all_close(&shifted, &softmax_of(&DVector::from_vec(vec![1.0, 2.0, 3.0])));
+}
+
+#[test]
+fn softmax_backward() {
+    let input = DVector::from_vec(vec![0.5, -1.0, 2.0]);
+    let numerical = numerical_gradient(|input| softmax_of(input).dot(&probe(3)), &input);
+
+    for backend in backends() {
+        let mut tape = Tape::with_backend(backend);
+        let x = tape.input(input.clone_owned());
+        let output = tape.softmax(x);
+        let gradients = tape.backward(output, probe(3), &[]);
+
+        assert_all_close(tape.value(output), &softmax_of(&input));
+        assert_all_close(gradients.node(x).unwrap(), &numerical);
+    }
+}
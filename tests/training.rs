@@ -0,0 +1,754 @@
+//! Training trajectories on a tiny fixture, which are reproducible when the
+//! network is initialized from a seeded random number generator.
+
+use std::cell::RefCell;
+use std::io::Cursor;
+use std::iter::zip;
+use std::rc::Rc;
+use nalgebra::{DMatrix, DVector};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use digit_recognition::augmentation::Augmentation;
+use digit_recognition::data::{Image, ImageSize};
+use digit_recognition::dataset::write::IdxWriter;
+use digit_recognition::ensemble::{self, Ensemble, Voting};
+use digit_recognition::features::{InputFeatures, Normalization};
+use digit_recognition::network::{self, Activation, Architecture, ModelFormat, NeuralNetwork, Pass, Scalar};
+use digit_recognition::quantization::QuantizedNetwork;
+use digit_recognition::training::{best_model_path, class_counts, evaluate, input_vector, Checkpoint, ClassWeights, CyclePolicy, CyclicalSchedule, Distillation, ErrorKind, Perturbation, PerturbationKind,
+                                  Sampling, Target, Trainer, TrainerBuilder, TrainingCallback, TrainingHistory};
+use digit_recognition::training_data::{InMemoryDataset, Label, LabeledTrainingData, TrainingDataset};
+use digit_recognition::transform::Preprocessing;
+
+const SIZE: ImageSize = ImageSize { width: 2, height: 2 };
+
+/// Bright left or right columns, labeled 0 and 1.
+fn fixture() -> Vec<LabeledTrainingData> {
+    (0..12u8)
+        .map(|i| {
+            let (bright, dark) = (200 + i * 4, i * 3);
+            let (pixels, class) = match i % 2 {
+                0 => (vec![bright, dark, bright, dark], 0),
+                _ => (vec![dark, bright, dark, bright], 1)
+            };
+
+            LabeledTrainingData::new(Image::builder().with_size(SIZE).with_pixels_row_major(pixels).build(),
+                                     Label::new(class))
+        })
+        .collect()
+}
+
+struct BatchLosses(Rc<RefCell<Vec<f64>>>);
+
+impl TrainingCallback for BatchLosses {
+    fn on_batch_end(&mut self, _epoch: u32, _position: usize, loss: f64) {
+        self.0.borrow_mut().push(loss);
+    }
+}
+
+fn trajectory(seed: u64, threads: usize) -> Vec<f64> {
+    perturbed_trajectory(seed, threads, None)
+}
+
+fn perturbed_trajectory(seed: u64, threads: usize, perturbation: Option<Perturbation>) -> Vec<f64> {
+    augmented_trajectory(seed, threads, None, perturbation)
+}
+
+fn augmented_trajectory(seed: u64,
+                        threads: usize,
+                        augmentation: Option<Augmentation>,
+                        perturbation: Option<Perturbation>) -> Vec<f64> {
+    configured_trajectory(seed, threads, |mut builder| {
+        if let Some(augmentation) = augmentation {
+            builder = builder.with_augmentation(augmentation);
+        }
+        if let Some(perturbation) = perturbation {
+            builder = builder.with_perturbation(perturbation);
+        }
+        builder
+    })
+}
+
+fn configured_trajectory(seed: u64, threads: usize, configure: impl FnOnce(TrainerBuilder) -> TrainerBuilder) -> Vec<f64> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut network = NeuralNetwork::new_untrained_with_rng(&mut rng,
+                                                            SIZE,
+                                                            2,
+                                                            InputFeatures::default(),
+                                                            &Architecture::default());
+
+    let losses = Rc::new(RefCell::new(Vec::new()));
+    let builder = Trainer::builder()
+        .with_epochs(3)
+        .with_learning_rate(0.5)
+        .with_batch_size(4)
+        .with_threads(threads)
+        .with_callback(BatchLosses(losses.clone()));
+    configure(builder).build().train(&mut network, &fixture()).unwrap();
+
+    let losses = losses.borrow().clone();
+    losses
+}
+
+#[test]
+fn same_seed_gives_same_trajectory() {
+    let trajectory = trajectory(7, 1);
+
+    assert_eq!(trajectory.len(), 9);
+    assert_eq!(trajectory, self::trajectory(7, 1));
+}
+
+#[test]
+fn different_seeds_give_different_trajectories() {
+    assert_ne!(trajectory(7, 1), trajectory(8, 1));
+}
+
+#[test]
+fn trajectory_does_not_depend_on_thread_count() {
+    assert_eq!(trajectory(7, 1), trajectory(7, 4));
+}
+
+#[test]
+fn perturbed_trajectories_do_not_depend_on_thread_count() {
+    for kind in [PerturbationKind::GradientSign, PerturbationKind::Noise] {
+        let perturbation = Some(Perturbation { kind, strength: 0.2 });
+
+        assert_eq!(perturbed_trajectory(7, 1, perturbation), perturbed_trajectory(7, 4, perturbation));
+        assert_ne!(perturbed_trajectory(7, 1, perturbation), trajectory(7, 1));
+    }
+}
+
+#[test]
+fn augmented_trajectories_do_not_depend_on_thread_count() {
+    let augmentation = Some(Augmentation { max_rotation: 30.0, max_shift: 1.0, ..Augmentation::default() });
+
+    assert_eq!(augmented_trajectory(7, 1, augmentation, None), augmented_trajectory(7, 4, augmentation, None));
+    assert_ne!(augmented_trajectory(7, 1, augmentation, None), trajectory(7, 1));
+}
+
+#[test]
+fn shuffled_trajectories_depend_on_the_shuffle_seed() {
+    let shuffled = |shuffle_seed| configured_trajectory(7, 1, |builder| builder.with_shuffling(shuffle_seed));
+
+    assert_eq!(shuffled(3), shuffled(3));
+    assert_ne!(shuffled(3), shuffled(4));
+    assert_ne!(shuffled(3), trajectory(7, 1));
+}
+
+#[test]
+fn history_has_the_metrics_of_every_epoch() {
+    let mut network = untrained_network(1, 2);
+    let history = Trainer::builder()
+        .with_epochs(3)
+        .with_learning_rate(0.5)
+        .with_batch_size(4)
+        .with_validation_set(fixture())
+        .build()
+        .train(&mut network, &fixture())
+        .unwrap();
+
+    assert_eq!(history.epochs().iter().map(|metrics| metrics.epoch).collect::<Vec<_>>(), vec![1, 2, 3]);
+    for metrics in history.epochs() {
+        assert!(metrics.loss.is_finite() && metrics.loss > 0.0);
+        assert!((0.0..=1.0).contains(&metrics.accuracy));
+        assert!(metrics.validation_accuracy.is_some() && metrics.validation_loss.is_some());
+    }
+    assert_eq!(history.epochs()[2].validation_accuracy, Some(evaluate(&network, &fixture()).unwrap()));
+
+    let model_file = std::env::temp_dir().join(format!("digit_recognition-history-{}.bin", std::process::id()));
+    let history_file = TrainingHistory::path_for_model(&model_file);
+    assert_eq!(history_file, model_file.with_file_name(format!("digit_recognition-history-{}.history.json", std::process::id())));
+    history.save(&history_file).unwrap();
+    let saved: TrainingHistory = serde_json::from_slice(&std::fs::read(&history_file).unwrap()).unwrap();
+    std::fs::remove_file(&history_file).unwrap();
+    assert_eq!(saved.epochs(), history.epochs());
+}
+
+#[test]
+fn best_model_is_the_one_of_the_best_validation_accuracy() {
+    // the better the network learns the fixture, the worse it does on these
+    let flipped: Vec<LabeledTrainingData> = fixture()
+        .into_iter()
+        .map(|sample| {
+            let (image, label) = sample.into_parts();
+            LabeledTrainingData::new(image, Label::new(1 - label.class()))
+        })
+        .collect();
+    let trainer = |epochs| Trainer::builder()
+        .with_epochs(epochs)
+        .with_learning_rate(0.5)
+        .with_batch_size(4)
+        .with_threads(1)
+        .with_validation_set(flipped.clone());
+
+    let best_file = best_model_path(std::env::temp_dir().join(format!("digit_recognition-best-{}.json", std::process::id())));
+    let mut network = untrained_network(1, 2);
+    let history = trainer(6).with_best_model(&best_file).build().train(&mut network, &fixture()).unwrap();
+    let best = NeuralNetwork::load(&best_file).unwrap();
+    std::fs::remove_file(&best_file).unwrap();
+
+    let accuracies: Vec<f64> = history.epochs().iter().map(|metrics| metrics.validation_accuracy.unwrap()).collect();
+    let best_accuracy = accuracies.iter().copied().fold(0.0, f64::max);
+    let best_epoch = accuracies.iter().position(|accuracy| *accuracy == best_accuracy).unwrap() + 1;
+    assert!(accuracies[5] < best_accuracy, "validation accuracies {:?}", accuracies);
+
+    let mut expected = untrained_network(1, 2);
+    trainer(best_epoch as u32).build().train(&mut expected, &fixture()).unwrap();
+    assert!(compute_alike(&best, &expected));
+    assert_eq!(evaluate(&best, &flipped).unwrap(), best_accuracy);
+}
+
+/// Whether `configure` trains like one batch of the whole fixture per
+/// learning rate of `schedule`, in order.
+fn follows_schedule(configure: impl FnOnce(TrainerBuilder) -> TrainerBuilder, schedule: &[f64]) -> bool {
+    let whole_fixture = || Trainer::builder().with_learning_rate(0.5).with_batch_size(12).with_threads(1);
+    let mut scheduled = untrained_network(1, 2);
+    configure(whole_fixture().with_epochs(schedule.len() as u32)).build().train(&mut scheduled, &fixture()).unwrap();
+
+    let mut expected = untrained_network(1, 2);
+    for learning_rate in schedule {
+        whole_fixture().with_learning_rate(*learning_rate).build().train(&mut expected, &fixture()).unwrap();
+    }
+
+    compute_alike(&scheduled, &expected)
+}
+
+#[test]
+fn learning_rate_warms_up_then_cycles() {
+    assert!(follows_schedule(|builder| builder.with_warmup(4), &[0.125, 0.25, 0.375, 0.5, 0.5]));
+    assert!(!follows_schedule(|builder| builder, &[0.125, 0.25, 0.375, 0.5, 0.5]));
+
+    let triangular = CyclicalSchedule { policy: CyclePolicy::Triangular, period: 4, min_learning_rate: 0.125 };
+    assert!(follows_schedule(|builder| builder.with_warmup(2).with_cyclical_schedule(triangular),
+                             &[0.25, 0.5, 0.125, 0.3125, 0.5, 0.3125, 0.125]));
+
+    let cosine = CyclicalSchedule { policy: CyclePolicy::Cosine, period: 2, min_learning_rate: 0.125 };
+    assert!(follows_schedule(|builder| builder.with_cyclical_schedule(cosine), &[0.5, 0.3125, 0.5, 0.3125]));
+
+    assert_eq!("Cosine".parse::<CyclePolicy>().unwrap(), CyclePolicy::Cosine);
+    assert!(matches!("linear".parse::<CyclePolicy>(), Err(ErrorKind::UnknownCyclePolicy(_))));
+}
+
+#[test]
+fn diverging_runs_stop_at_the_first_non_finite_batch() {
+    // the first batch makes the weights infinite, the next one's loss is NaN
+    let losses = Rc::new(RefCell::new(Vec::new()));
+    let result = Trainer::builder()
+        .with_epochs(3)
+        .with_learning_rate(f64::INFINITY)
+        .with_batch_size(4)
+        .with_callback(BatchLosses(losses.clone()))
+        .build()
+        .train(&mut untrained_network(1, 2), &fixture());
+
+    assert!(matches!(result, Err(ErrorKind::TrainingDiverged { epoch: 1, position: 4 })), "{:?}", result);
+    assert_eq!(losses.borrow().len(), 1);
+    assert!(losses.borrow()[0].is_finite());
+}
+
+/// The network `resumed` trains from the checkpoint that `interrupted` left
+/// 8 samples into its last epoch, as if it had been stopped there.
+fn resumed_network(name: &str, interrupted: TrainerBuilder, resumed: TrainerBuilder) -> NeuralNetwork {
+    let checkpoint_file = std::env::temp_dir().join(format!("digit_recognition-{}-{}.checkpoint", name, std::process::id()));
+    interrupted.with_checkpoints(&checkpoint_file, 8).build().train(&mut untrained_network(1, 2), &fixture()).unwrap();
+    let checkpoint = Checkpoint::load(&checkpoint_file).unwrap();
+    std::fs::remove_file(&checkpoint_file).unwrap();
+
+    resumed.build().resume(checkpoint, &fixture()).unwrap().0
+}
+
+fn outputs(network: &NeuralNetwork) -> Vec<DVector<Scalar>> {
+    fixture().iter().map(|sample| network.compute(input_vector(sample.image())).unwrap()).collect()
+}
+
+/// Whether both networks compute the same, but for the rounding of weights
+/// saved in a checkpoint.
+fn compute_alike(first: &NeuralNetwork, second: &NeuralNetwork) -> bool {
+    zip(outputs(first), outputs(second)).all(|(first, second)| (first - second).amax() < 1e-12)
+}
+
+fn two_epochs() -> TrainerBuilder {
+    Trainer::builder().with_epochs(2).with_learning_rate(0.5).with_batch_size(4).with_threads(1)
+}
+
+#[test]
+fn resumed_runs_keep_the_shuffle_seed_of_the_checkpoint() {
+    let mut uninterrupted = untrained_network(1, 2);
+    two_epochs().with_shuffling(3).build().train(&mut uninterrupted, &fixture()).unwrap();
+
+    // without --seed, the resumed run draws a seed of its own
+    let resumed = resumed_network("shuffled", two_epochs().with_shuffling(3), two_epochs().with_shuffling(4));
+    assert!(compute_alike(&resumed, &uninterrupted));
+
+    let mut sampled = untrained_network(1, 2);
+    two_epochs().with_sampling(Sampling::Balanced, 3).build().train(&mut sampled, &fixture()).unwrap();
+    let resumed = resumed_network("sampled",
+                                  two_epochs().with_sampling(Sampling::Balanced, 3),
+                                  two_epochs().with_sampling(Sampling::Balanced, 4));
+    assert!(compute_alike(&resumed, &sampled));
+}
+
+#[test]
+fn resumed_runs_end_like_uninterrupted_ones() {
+    let mut uninterrupted = untrained_network(1, 2);
+    two_epochs().build().train(&mut uninterrupted, &fixture()).unwrap();
+
+    let resumed = resumed_network("resumed", two_epochs(), two_epochs());
+    assert!(compute_alike(&resumed, &uninterrupted));
+    assert!(!compute_alike(&untrained_network(1, 2), &uninterrupted));
+}
+
+#[test]
+fn checkpoints_of_older_schemas_are_upgraded() {
+    let checkpoint_file = std::env::temp_dir().join(format!("digit_recognition-old-checkpoint-{}.checkpoint", std::process::id()));
+    two_epochs().with_checkpoints(&checkpoint_file, 8).build().train(&mut untrained_network(1, 2), &fixture()).unwrap();
+
+    let mut saved: serde_json::Value = serde_json::from_slice(&std::fs::read(&checkpoint_file).unwrap()).unwrap();
+    let network = saved["network"].as_object_mut().unwrap();
+    for field in ["schema_version", "input_features", "activation", "preprocessing", "normalization"] {
+        network.remove(field);
+    }
+    std::fs::write(&checkpoint_file, serde_json::to_vec(&saved).unwrap()).unwrap();
+    let checkpoint = Checkpoint::load(&checkpoint_file);
+    std::fs::remove_file(&checkpoint_file).unwrap();
+
+    // the network is configured for the resumed run before it goes on
+    let (mut network, resume_point) = checkpoint.unwrap().into_parts();
+    assert_eq!(network.activation(), Activation::Sigmoid);
+    network.set_class_names(vec![String::from("left"), String::from("right")]);
+    let mut dataset = InMemoryDataset::from(fixture());
+    let history = two_epochs().build().resume_from(&mut network, resume_point, &mut dataset).unwrap();
+    assert_eq!(history.epochs().len(), 2);
+    assert_eq!(network.class_name(1), "right");
+}
+
+#[test]
+fn training_reads_a_dataset_by_position() {
+    let mut writer = IdxWriter::new(Cursor::new(Vec::new()), Cursor::new(Vec::new()));
+    writer.write_all(&fixture()).unwrap();
+    let (_, images, labels) = writer.finish().unwrap();
+    let mut file = TrainingDataset::from_readers(Cursor::new(images.into_inner()), Cursor::new(labels.into_inner())).unwrap();
+
+    let mut from_memory = untrained_network(1, 2);
+    two_epochs().with_shuffling(3).build().train(&mut from_memory, &fixture()).unwrap();
+    let mut from_file = untrained_network(1, 2);
+    two_epochs().with_shuffling(3).build().train_dataset(&mut from_file, &mut file).unwrap();
+
+    assert!(compute_alike(&from_file, &from_memory));
+}
+
+/// Six samples of class 0 and three of class 1.
+fn skewed_fixture() -> InMemoryDataset {
+    let samples: Vec<LabeledTrainingData> = fixture()
+        .into_iter()
+        .filter(|sample| sample.label().class() == 0 || sample.image().pixels()[0] < 20)
+        .collect();
+    InMemoryDataset::from(samples)
+}
+
+#[test]
+fn balanced_sampling_draws_classes_equally() {
+    let mut dataset = skewed_fixture();
+    assert_eq!(dataset.class_index().iter().map(Vec::len).collect::<Vec<_>>(), vec![6, 3]);
+
+    let mut rng = StdRng::seed_from_u64(2);
+    let drawn: Vec<usize> = (0..100).flat_map(|_| Sampling::Balanced.epoch_indices(&mut dataset, &mut rng).unwrap()).collect();
+    assert_eq!(drawn.len(), 100 * dataset.len());
+    let ones = drawn.iter().filter(|index| dataset.samples()[**index].label().class() == 1).count();
+    assert!((0.45..0.55).contains(&(ones as f64 / drawn.len() as f64)), "{} of {} drawn from class 1", ones, drawn.len());
+
+    let capped = Sampling::Capped(2).epoch_indices(&mut dataset, &mut rng).unwrap();
+    let mut classes: Vec<u8> = capped.iter().map(|index| dataset.samples()[*index].label().class()).collect();
+    classes.sort();
+    assert_eq!(classes, vec![0, 0, 1, 1]);
+}
+
+#[test]
+fn class_weights_scale_the_loss_of_their_classes() {
+    let skewed = skewed_fixture().samples().to_vec();
+    assert_eq!(class_counts(&skewed, 3), vec![6, 3, 0]);
+    assert_eq!(ClassWeights::Balanced.resolve(&skewed, 3).unwrap(), vec![0.75, 1.5, 1.0]);
+    assert!(matches!(ClassWeights::Explicit(vec![1.0, 2.0]).resolve(&skewed, 3),
+                     Err(ErrorKind::ClassWeightCountMismatch { weights: 2, class_count: 3 })));
+
+    let trained = |samples: &[LabeledTrainingData], class_weights: Option<ClassWeights>| {
+        let mut builder = two_epochs();
+        if let Some(class_weights) = class_weights {
+            builder = builder.with_class_weights(class_weights);
+        }
+        let mut network = untrained_network(1, 2);
+        builder.build().train(&mut network, samples).unwrap();
+        network
+    };
+
+    // weighing a class is the same as weighing each of its samples
+    let reweighed: Vec<LabeledTrainingData> = skewed
+        .iter()
+        .map(|sample| match sample.label().class() {
+            0 => sample.clone().with_weight(0.75),
+            _ => sample.clone().with_weight(1.5)
+        })
+        .collect();
+    let balanced = trained(&skewed, Some(ClassWeights::Balanced));
+    assert!(compute_alike(&balanced, &trained(&reweighed, None)));
+    assert!(compute_alike(&balanced, &trained(&skewed, Some(ClassWeights::Explicit(vec![0.75, 1.5])))));
+    assert!(!compute_alike(&balanced, &trained(&skewed, None)));
+
+    let mut trainer = Trainer::builder().with_class_weights(ClassWeights::Explicit(vec![1.0; 3])).build();
+    assert!(matches!(trainer.train(&mut untrained_network(1, 2), &skewed),
+                     Err(ErrorKind::ClassWeightCountMismatch { weights: 3, class_count: 2 })));
+}
+
+fn untrained_network(seed: u64, class_count: usize) -> NeuralNetwork {
+    NeuralNetwork::new_untrained_with_rng(&mut StdRng::seed_from_u64(seed),
+                                          SIZE,
+                                          class_count,
+                                          InputFeatures::default(),
+                                          &Architecture::default())
+}
+
+#[test]
+fn student_learns_from_the_teacher_alone() {
+    let mut teacher = untrained_network(1, 2);
+    Trainer::builder().with_epochs(20).with_learning_rate(0.5).with_batch_size(4).build()
+        .train(&mut teacher, &fixture())
+        .unwrap();
+    assert_eq!(evaluate(&teacher, &fixture()).unwrap(), 1.0);
+
+    // with no weight on the labels, only the teacher says what is right
+    let mislabeled: Vec<LabeledTrainingData> = fixture()
+        .into_iter()
+        .map(|sample| {
+            let (image, _) = sample.into_parts();
+            LabeledTrainingData::new(image, Label::new(0))
+        })
+        .collect();
+
+    let mut student = untrained_network(2, 2);
+    Trainer::builder()
+        .with_epochs(20)
+        .with_learning_rate(0.5)
+        .with_batch_size(4)
+        .with_distillation(Distillation::new(teacher, 1.0).unwrap())
+        .build()
+        .train(&mut student, &mislabeled)
+        .unwrap();
+
+    assert_eq!(evaluate(&student, &fixture()).unwrap(), 1.0);
+}
+
+#[test]
+fn teacher_must_have_the_student_classes() {
+    let mut trainer = Trainer::builder()
+        .with_distillation(Distillation::new(untrained_network(1, 3), 2.0).unwrap())
+        .build();
+
+    let result = trainer.train(&mut untrained_network(2, 2), &fixture());
+    assert!(matches!(result, Err(ErrorKind::TeacherMismatch { teacher_classes: 3, student_classes: 2, .. })));
+    assert!(matches!(Distillation::new(untrained_network(1, 2), 0.0), Err(ErrorKind::InvalidDistillationTemperature(_))));
+}
+
+#[test]
+fn teacher_must_prepare_images_like_the_student() {
+    let mut teacher = untrained_network(1, 2);
+    teacher.set_preprocessing(Preprocessing::MNIST);
+    let mut trainer = Trainer::builder()
+        .with_distillation(Distillation::new(teacher, 2.0).unwrap())
+        .build();
+
+    let result = trainer.train(&mut untrained_network(2, 2), &fixture());
+    assert!(matches!(result, Err(ErrorKind::TeacherPreprocessingMismatch { teacher: Preprocessing::MNIST, .. })));
+}
+
+#[test]
+fn normalization_is_applied_to_inputs_and_saved_with_the_model() {
+    let normalization = Normalization::of(&fixture()).unwrap();
+    let values: Vec<f64> = fixture()
+        .iter()
+        .flat_map(|sample| input_vector(sample.image()).iter().map(|value| network::to_f64(*value)).collect::<Vec<_>>())
+        .collect();
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / values.len() as f64;
+    assert!((network::to_f64(normalization.mean) - mean).abs() < 1e-4);
+    assert!((network::to_f64(normalization.std_dev) - variance.sqrt()).abs() < 1e-4);
+    assert_eq!(Normalization::of(&[]), None);
+
+    let mut network = untrained_network(1, 2);
+    network.set_normalization(Some(normalization));
+    let input = input_vector(fixture()[0].image());
+    assert_eq!(network.compute(input.clone()).unwrap(),
+               untrained_network(1, 2).compute(normalization.apply(input.clone())).unwrap());
+
+    let model_file = std::env::temp_dir().join(format!("digit_recognition-normalization-{}.json", std::process::id()));
+    network.save(&model_file).unwrap();
+    let reloaded = NeuralNetwork::load(&model_file).unwrap();
+    std::fs::remove_file(&model_file).unwrap();
+    assert_eq!(reloaded.normalization(), Some(normalization));
+    assert_eq!(reloaded.compute(input.clone()).unwrap(), network.compute(input).unwrap());
+}
+
+/// `network` saved as JSON, passed through `edit` and loaded again.
+fn reloaded_with(network: &NeuralNetwork, name: &str, edit: impl FnOnce(&mut serde_json::Value)) -> network::Result<NeuralNetwork> {
+    let model_file = std::env::temp_dir().join(format!("digit_recognition-{}-{}.json", name, std::process::id()));
+    network.save(&model_file).unwrap();
+    let mut saved: serde_json::Value = serde_json::from_slice(&std::fs::read(&model_file).unwrap()).unwrap();
+    edit(&mut saved);
+    std::fs::write(&model_file, serde_json::to_vec(&saved).unwrap()).unwrap();
+
+    let reloaded = NeuralNetwork::load(&model_file);
+    std::fs::remove_file(&model_file).unwrap();
+    reloaded
+}
+
+#[test]
+fn models_of_older_schemas_are_upgraded() {
+    let network = untrained_network(1, 2);
+    // as saved before versioning, when none of the later fields existed
+    let unversioned = reloaded_with(&network, "schema-0", |saved| {
+        let fields = saved.as_object_mut().unwrap();
+        for field in ["schema_version", "input_features", "activation", "preprocessing", "normalization"] {
+            assert!(fields.remove(field).is_some(), "{} is not saved", field);
+        }
+    }).unwrap();
+
+    assert_eq!(unversioned.input_features(), InputFeatures::default());
+    assert_eq!(unversioned.activation(), Activation::Sigmoid);
+    assert_eq!(unversioned.normalization(), None);
+    let input = input_vector(fixture()[0].image());
+    assert_eq!(unversioned.compute(input.clone()).unwrap(), network.compute(input).unwrap());
+
+    // saving it again writes the current version
+    let version = |network: &NeuralNetwork| {
+        let mut version = serde_json::Value::Null;
+        reloaded_with(network, "schema-version", |saved| version = saved["schema_version"].clone()).unwrap();
+        version
+    };
+    assert!(version(&network).as_u64().unwrap() > 0);
+    assert_eq!(version(&unversioned), version(&network));
+}
+
+#[test]
+fn best_models_are_saved_in_the_format_of_the_model() {
+    for (model_file, best_file, format) in [("model.bin", "model.best.bin", ModelFormat::Binary),
+                                            ("model.json", "model.best.json", ModelFormat::Json)] {
+        assert_eq!(best_model_path(model_file), std::path::PathBuf::from(best_file));
+        assert_eq!(ModelFormat::for_path(best_model_path(model_file)), format);
+    }
+}
+
+/// Forward values of every hidden layer of `network` for the fixture.
+fn hidden_values(network: &mut NeuralNetwork) -> Vec<DVector<Scalar>> {
+    let hidden_layers = network.architecture().hidden_layers.len();
+    let values = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = values.clone();
+    network.add_hook(move |event| if event.pass == Pass::Forward && event.layer < hidden_layers {
+        recorded.lock().unwrap().push(event.values.clone());
+    });
+    outputs(network);
+
+    let values = values.lock().unwrap().clone();
+    values
+}
+
+#[test]
+fn partial_loads_keep_the_layers_of_another_head() {
+    let mut source = untrained_network(1, 2);
+    two_epochs().build().train(&mut source, &fixture()).unwrap();
+    let model_file = std::env::temp_dir().join(format!("digit_recognition-partial-{}.bin", std::process::id()));
+    source.save(&model_file).unwrap();
+
+    let mut head_swapped = untrained_network(2, 3);
+    let copied = head_swapped.load_partial(&model_file);
+    std::fs::remove_file(&model_file).unwrap();
+
+    assert_eq!(copied.unwrap(), source.architecture().hidden_layers.len());
+    assert_eq!(head_swapped.output_size(), 3);
+    assert_eq!(hidden_values(&mut head_swapped), hidden_values(&mut source));
+    assert_ne!(hidden_values(&mut untrained_network(2, 3)), hidden_values(&mut source));
+}
+
+#[test]
+fn models_of_newer_schemas_are_rejected() {
+    let result = reloaded_with(&untrained_network(1, 2), "schema-new", |saved| saved["schema_version"] = 1000.into());
+
+    assert!(matches!(result, Err(network::ErrorKind::ModelSchemaTooNew { found: 1000, .. })));
+}
+
+#[test]
+fn incremental_updates_preprocess_like_inference() {
+    let size = ImageSize::square(8);
+    let network = || {
+        let mut network = NeuralNetwork::new_untrained_with_rng(&mut StdRng::seed_from_u64(5),
+                                                                size,
+                                                                2,
+                                                                InputFeatures::default(),
+                                                                &Architecture::default());
+        network.set_preprocessing(Preprocessing::MNIST);
+        network
+    };
+    // a stroke in a corner, which recentering moves to the middle
+    let pixels: Vec<u8> = (0..size.area()).map(|i| if i % 8 < 2 && i / 8 < 3 { 255 } else { 0 }).collect();
+    let drawing = Image::builder().with_size(size).with_pixels_row_major(pixels).build();
+    let mut prepared = drawing.clone();
+    Preprocessing::MNIST.prepare(&mut prepared);
+    assert_ne!(prepared.pixels(), drawing.pixels());
+
+    let mut incremental = network();
+    incremental.train_incremental(&drawing, Label::new(1), 0.5).unwrap();
+    let mut expected = network();
+    expected.train(input_vector(&prepared), &Label::new(1).to_one_hot(2).unwrap(), &[0.5, 0.5]).unwrap();
+
+    let input = input_vector(&prepared);
+    assert_eq!(incremental.compute(input.clone()).unwrap(), expected.compute(input).unwrap());
+}
+
+#[test]
+fn ensembles_average_or_vote_on_the_outputs_of_their_members() {
+    let files: Vec<std::path::PathBuf> = (1..=3u64)
+        .map(|seed| {
+            let file = std::env::temp_dir().join(format!("digit_recognition-member-{}-{}.bin", seed, std::process::id()));
+            untrained_network(seed, 2).save(&file).unwrap();
+            file
+        })
+        .collect();
+    let average = Ensemble::load(&files, Voting::Average);
+    let majority = Ensemble::load(&files, Voting::Majority);
+    for file in &files {
+        std::fs::remove_file(file).unwrap();
+    }
+    let (average, majority) = (average.unwrap(), majority.unwrap());
+    assert_eq!(average.members().len(), 3);
+
+    for sample in fixture() {
+        let input = input_vector(sample.image());
+        let member_outputs: Vec<DVector<Scalar>> = average.members()
+            .iter()
+            .map(|member| member.compute(input.clone()).unwrap())
+            .collect();
+
+        let mean = member_outputs.iter().sum::<DVector<Scalar>>() / 3.0;
+        assert!((average.compute(input.clone()).unwrap() - mean).amax() < 1e-12);
+
+        let votes = member_outputs.iter().filter(|output| output.argmax().0 == 1).count();
+        let shares = majority.compute(input).unwrap();
+        assert_eq!(shares.as_slice(), &[(3 - votes) as Scalar / 3.0, votes as Scalar / 3.0]);
+    }
+}
+
+#[test]
+fn ensemble_members_must_classify_alike() {
+    let result = Ensemble::new(vec![untrained_network(1, 2), untrained_network(2, 2), untrained_network(3, 3)], Voting::Average);
+    assert!(matches!(result, Err(ensemble::ErrorKind::MemberMismatch { member: 2 })));
+    assert!(matches!(Ensemble::new(Vec::new(), Voting::Majority), Err(ensemble::ErrorKind::NoMembers)));
+}
+
+#[test]
+fn quantized_networks_compute_like_the_original() {
+    let mut network = untrained_network(1, 2);
+    network.set_normalization(Normalization::of(&fixture()));
+    Trainer::builder().with_epochs(20).with_learning_rate(0.5).with_batch_size(4).build()
+        .train(&mut network, &fixture())
+        .unwrap();
+
+    let quantized = QuantizedNetwork::from_network(&network);
+    let model_file = std::env::temp_dir().join(format!("digit_recognition-quantized-{}.json", std::process::id()));
+    quantized.save(&model_file).unwrap();
+    let reloaded = QuantizedNetwork::load(&model_file);
+    std::fs::remove_file(&model_file).unwrap();
+    let reloaded = reloaded.unwrap();
+
+    assert_eq!(reloaded.output_size(), 2);
+    for (sample, expected) in zip(fixture(), outputs(&network)) {
+        let output = quantized.compute(input_vector(sample.image())).unwrap();
+        assert!((&output - &expected).amax() < 0.05, "{} quantized to {}", expected, output);
+        assert_eq!(output.argmax().0, sample.label().class() as usize);
+        assert_eq!(reloaded.compute(input_vector(sample.image())).unwrap(), output);
+    }
+
+    let result = quantized.compute(DVector::zeros(SIZE.area() + 1));
+    assert!(matches!(result, Err(network::ErrorKind::InputSizeMismatch { .. })));
+}
+
+/// Weights and biases of every layer of a network with dense layers, as saved.
+fn layer_parameters(network: &NeuralNetwork) -> Vec<(DMatrix<Scalar>, DVector<Scalar>)> {
+    let mut layers = Vec::new();
+    reloaded_with(network, "parameters", |saved| for layer in saved["layers"].as_array().unwrap() {
+        layers.push((serde_json::from_value(layer["weights"].clone()).unwrap(),
+                     serde_json::from_value(layer["biases"].clone()).unwrap()));
+    }).unwrap();
+
+    layers
+}
+
+#[test]
+fn weight_averages_replace_the_trained_weights() {
+    let one_batch = || Trainer::builder().with_learning_rate(0.5).with_batch_size(12);
+    let initial = layer_parameters(&untrained_network(1, 2));
+    let mut trained = untrained_network(1, 2);
+    one_batch().build().train(&mut trained, &fixture()).unwrap();
+    let trained = layer_parameters(&trained);
+
+    let averaged = |decay| {
+        let mut network = untrained_network(1, 2);
+        one_batch().with_ema(decay).build().train(&mut network, &fixture()).unwrap();
+        layer_parameters(&network)
+    };
+    assert_eq!(averaged(1.0), initial);
+    assert_eq!(averaged(0.0), trained);
+
+    for (((weights, biases), (initial_weights, initial_biases)), (trained_weights, trained_biases))
+        in zip(zip(averaged(0.75), &initial), &trained) {
+        assert!((weights - (initial_weights * 0.75 + trained_weights * 0.25)).amax() < 1e-12);
+        assert!((biases - (initial_biases * 0.75 + trained_biases * 0.25)).amax() < 1e-12);
+    }
+    assert_ne!(initial, trained);
+}
+
+#[test]
+fn pruned_networks_compute_with_the_remaining_weights() {
+    let mut network = untrained_network(1, 2);
+    let mut layers = layer_parameters(&network);
+
+    // zeroes about two thirds of the weights
+    let mut magnitudes: Vec<Scalar> = layers.iter().flat_map(|(weights, _)| weights.iter().map(|w| w.abs())).collect();
+    magnitudes.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let threshold = network::to_f64(magnitudes[magnitudes.len() * 2 / 3]);
+    for (weights, _) in &mut layers {
+        weights.apply(|w| if network::to_f64(w.abs()) < threshold { *w = 0.0 });
+    }
+
+    let sparsity = network.prune(threshold);
+    assert_eq!(sparsity.zero_weights, magnitudes.len() * 2 / 3);
+    assert_eq!(sparsity.total_weights, magnitudes.len());
+    assert_eq!(network.sparsity(), sparsity);
+    assert!(network.summary().layers.iter().any(|layer| layer.kind == "sparse"));
+
+    let reloaded = reloaded_with(&network, "pruned", |_| {}).unwrap();
+    for sample in fixture() {
+        let input = input_vector(sample.image());
+        let mut expected = input.clone();
+        for (i, (weights, biases)) in layers.iter().enumerate() {
+            expected = weights * expected + biases;
+            if i + 1 < layers.len() {
+                expected.apply(|x| *x = network::sigmoid(*x));
+            }
+        }
+        expected.apply(|x| *x = x.exp());
+        expected /= expected.sum();
+
+        let output = network.compute(input.clone()).unwrap();
+        assert!((&output - &expected).amax() < 1e-6, "{} computed as {}", expected, output);
+        assert_eq!(reloaded.compute(input).unwrap(), output);
+    }
+}
+
+#[test]
+fn targets_are_one_hot_for_known_classes_only() {
+    let one_hot = Label::new(2).to_one_hot(4).unwrap();
+    assert_eq!(one_hot.as_slice(), &[0.0, 0.0, 1.0, 0.0]);
+    assert_eq!(Label::new(4).to_one_hot(4), None);
+
+    assert_eq!(Target::Class(Label::new(2)).to_vector(4).unwrap(), one_hot);
+    assert!(matches!(Target::Class(Label::new(9)).to_vector(4),
+                     Err(ErrorKind::LabelOutOfRange { class: 9, class_count: 4 })));
+}
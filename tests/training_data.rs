@@ -0,0 +1,588 @@
+//! Reading datasets in the formats they are distributed in.
+
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::thread;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use digit_recognition::classes::{Alphabet, EmnistSplit};
+use digit_recognition::data::{Image, ImageSize};
+use digit_recognition::dataset::contact_sheet::contact_sheet;
+use digit_recognition::dataset::duplicates::{Duplicate, find_duplicates, find_duplicates_between};
+use digit_recognition::dataset::synthetic::{self, load_fonts};
+use digit_recognition::dataset::write::{self, append_sample, open_sink, IdxWriter};
+use digit_recognition::training::input_vector;
+use digit_recognition::training_data::{CsvDataset, Dataset, load_class_names, load_samples, merge_samples, shuffle_samples, stream_samples, split_samples, ErrorKind, ImageDirDataset,
+                                       InMemoryDataset, Label, LabeledTrainingData, SampleIterExt, Subset, TrainingDataset,
+                                       IMAGE_DIR_SAMPLE_SIZE};
+
+const SIZE: ImageSize = ImageSize { width: 3, height: 2 };
+
+fn samples() -> Vec<LabeledTrainingData> {
+    (0..5u8)
+        .map(|i| LabeledTrainingData::new(Image::builder()
+                                              .with_size(SIZE)
+                                              .with_pixels_row_major((0..6).map(|j| i * 40 + j).collect::<Vec<u8>>())
+                                              .build(),
+                                          Label::new(i)))
+        .collect()
+}
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("digit_recognition-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    path
+}
+
+fn gzip(file: &PathBuf) -> PathBuf {
+    let compressed = file.with_extension("idx.gz");
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&fs::read(file).unwrap()).unwrap();
+    fs::write(&compressed, encoder.finish().unwrap()).unwrap();
+    compressed
+}
+
+#[test]
+fn gzipped_idx_files_are_decompressed() {
+    let dir = scratch_dir("gzip");
+    let (images_file, labels_file) = (dir.join("images.idx"), dir.join("labels.idx"));
+    for sample in &samples() {
+        append_sample(&images_file, &labels_file, sample).unwrap();
+    }
+
+    let plain = load_samples(&images_file, &labels_file, Alphabet::default()).unwrap();
+    let gzipped = load_samples(&gzip(&images_file), &gzip(&labels_file), Alphabet::default()).unwrap();
+    // a compressed and a plain file can be mixed
+    let mixed = load_samples(&images_file, &gzip(&labels_file), Alphabet::default()).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(plain.len(), 5);
+    for loaded in [&gzipped, &mixed] {
+        assert_eq!(loaded.len(), plain.len());
+        for (loaded, plain) in loaded.iter().zip(&plain) {
+            assert_eq!(loaded.image().pixels(), plain.image().pixels());
+            assert_eq!(loaded.label(), plain.label());
+        }
+    }
+}
+
+/// A dark vertical stroke on white paper, as scanned.
+fn scanned_digit(size: u32, column: u32) -> Image {
+    let pixels: Vec<u8> = (0..size * size).map(|i| if (column..column + 3).contains(&(i % size)) { 0 } else { 255 }).collect();
+    Image::builder().with_size(ImageSize::square(size)).with_pixels_row_major(pixels).build()
+}
+
+#[test]
+fn image_directories_are_labeled_by_directory_name() {
+    let dir = scratch_dir("image-dir");
+    for (label, count) in [("3", 2), ("7", 1)] {
+        fs::create_dir_all(dir.join(label).join("scans")).unwrap();
+        for i in 0..count {
+            scanned_digit(56, 20 + i).save_png(dir.join(label).join(format!("{}.png", i))).unwrap();
+        }
+    }
+    scanned_digit(28, 10).save_png(dir.join("7").join("scans").join("nested.png")).unwrap();
+    fs::write(dir.join("7").join("notes.txt"), "not an image").unwrap();
+
+    let dataset = ImageDirDataset::from_path(&dir, IMAGE_DIR_SAMPLE_SIZE).unwrap();
+    assert_eq!(dataset.len(), 4);
+    let samples = dataset.collect::<Result<Vec<_>, _>>().unwrap();
+
+    let labels: Vec<u8> = samples.iter().map(|sample| sample.label().class()).collect();
+    assert_eq!(labels, vec![3, 3, 7, 7]);
+    for sample in &samples {
+        assert_eq!(sample.image().size(), IMAGE_DIR_SAMPLE_SIZE);
+        // inverted into a light stroke on a dark background
+        assert_eq!(sample.image().pixels()[0], 0);
+        assert!(sample.image().pixels().iter().any(|pixel| *pixel > 128));
+    }
+
+    fs::create_dir_all(dir.join("misc")).unwrap();
+    let result = ImageDirDataset::from_path(&dir, IMAGE_DIR_SAMPLE_SIZE);
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(matches!(result, Err(ErrorKind::InvalidLabelDirectory { .. })));
+}
+
+#[test]
+fn emnist_splits_are_transposed_and_named() {
+    let balanced: Alphabet = "emnist-balanced".parse().unwrap();
+    assert_eq!(balanced, Alphabet::Emnist(EmnistSplit::Balanced));
+    assert_eq!(balanced.class_count(), 47);
+    assert_eq!(balanced.class_names()[36], "a");
+    assert_eq!("emnist-byclass".parse::<Alphabet>().unwrap().class_names()[61], "z");
+
+    let normalized = balanced.normalize_sample(samples().remove(3).with_weight(0.5));
+    assert_eq!(normalized.image().pixels(), samples()[3].image().transposed().pixels());
+    assert_eq!(normalized.label(), samples()[3].label());
+    assert_eq!(normalized.weight(), 0.5);
+}
+
+#[test]
+fn idx_writer_output_reads_back() {
+    let mut writer = IdxWriter::new(Cursor::new(Vec::new()), Cursor::new(Vec::new()));
+    writer.write_all(&samples()).unwrap();
+    let (count, images, labels) = writer.finish().unwrap();
+    assert_eq!(count, 5);
+
+    let dataset = TrainingDataset::from_readers(Cursor::new(images.into_inner()), Cursor::new(labels.into_inner())).unwrap();
+    assert_eq!(dataset.size(), 5);
+    let read = dataset.collect::<Result<Vec<_>, _>>().unwrap();
+    for (read, written) in read.iter().zip(&samples()) {
+        assert_eq!(read.image().pixels(), written.image().pixels());
+        assert_eq!(read.image().size(), SIZE);
+        assert_eq!(read.label(), written.label());
+    }
+}
+
+#[test]
+fn idx_images_have_rows_before_columns() {
+    let header = |magic: u32, dimensions: &[u32]| -> Vec<u8> {
+        [magic].iter().chain(dimensions).flat_map(|value| value.to_be_bytes()).collect()
+    };
+    let mut images = header(0x803, &[1, 2, 3]);
+    images.extend(0..6u8);
+    let labels = [header(0x801, &[1]), vec![7]].concat();
+
+    let dataset = TrainingDataset::from_readers(Cursor::new(images.clone()), Cursor::new(labels.clone())).unwrap();
+    let sample = dataset.collect::<Result<Vec<_>, _>>().unwrap().remove(0);
+    assert_eq!(sample.image().size(), ImageSize { width: 3, height: 2 });
+    assert_eq!(sample.image().pixels(), &[0, 1, 2, 3, 4, 5]);
+
+    let resized = sample.resized(ImageSize::square(4));
+    assert_eq!((resized.image().size(), resized.label()), (ImageSize::square(4), &Label::new(7)));
+
+    // color images have a fourth dimension, the channels
+    let color = [header(0x804, &[1, 2, 3, 3]), vec![0; 18]].concat();
+    assert!(matches!(TrainingDataset::from_readers(Cursor::new(color), Cursor::new(labels)),
+                     Err(ErrorKind::UnsupportedDimensions { found: 4, expected: 3, .. })));
+}
+
+#[test]
+fn idx_writer_rejects_images_of_another_size() {
+    let mut writer = IdxWriter::new(Cursor::new(Vec::new()), Cursor::new(Vec::new()));
+    writer.write(&samples()[0]).unwrap();
+
+    let result = writer.write(&LabeledTrainingData::new(scanned_digit(4, 0), Label::new(1)));
+    assert!(matches!(result, Err(write::ErrorKind::ImageSizeMismatch { position: 1, width: 3, found_width: 4, .. })));
+}
+
+#[test]
+fn gzipped_idx_files_read_back() {
+    let dir = scratch_dir("gzipped-idx");
+    let (images_file, labels_file) = (dir.join("images.gz"), dir.join("labels.gz"));
+    assert_eq!(write::write_gzipped(&images_file, &labels_file, &samples()).unwrap(), 5);
+    assert!(fs::read(&images_file).unwrap().starts_with(&[0x1f, 0x8b]));
+
+    let read = load_samples(&images_file, &labels_file, Alphabet::default()).unwrap();
+    assert_eq!(read.iter().map(|sample| sample.image().pixels().to_vec()).collect::<Vec<_>>(),
+               samples().iter().map(|sample| sample.image().pixels().to_vec()).collect::<Vec<_>>());
+    assert_eq!(classes(&read), classes(&samples()));
+}
+
+#[test]
+fn synthesizing_without_fonts_fails() {
+    let dir = scratch_dir("no-fonts");
+    fs::write(dir.join("notes.txt"), "not a font").unwrap();
+    assert!(matches!(load_fonts(&[&dir, &dir.join("missing")]), Err(synthetic::ErrorKind::NoFonts { .. })));
+}
+
+#[test]
+fn class_names_are_read_from_the_sidecar_file() {
+    let dir = scratch_dir("class-names");
+    let (images_file, labels_file) = (dir.join("images.idx"), dir.join("labels.idx"));
+    assert_eq!(load_class_names(&images_file, &labels_file).unwrap(), None);
+
+    fs::write(dir.join("class-names.json"), r#"["T-shirt/top", "Trouser"]"#).unwrap();
+    assert_eq!(load_class_names(&images_file, &labels_file).unwrap(),
+               Some(vec![String::from("T-shirt/top"), String::from("Trouser")]));
+    assert_eq!(load_class_names(&dir, &PathBuf::new()).unwrap().map(|names| names.len()), Some(2));
+
+    fs::write(dir.join("class-names.json"), r#"{"0": "T-shirt/top"}"#).unwrap();
+    assert!(matches!(load_class_names(&images_file, &labels_file), Err(ErrorKind::InvalidClassNames { .. })));
+}
+
+#[test]
+fn datasets_are_read_in_any_order_and_again() {
+    let dir = scratch_dir("seekable");
+    let (images_file, labels_file) = (dir.join("images.idx"), dir.join("labels.idx"));
+    let mut writer = IdxWriter::create(&images_file, &labels_file).unwrap();
+    writer.write_all(&samples()).unwrap();
+    writer.finish().unwrap();
+
+    let mut file = TrainingDataset::open_seekable(&images_file, &labels_file).unwrap();
+    let mut memory = InMemoryDataset::from(samples());
+    for dataset in [&mut file as &mut dyn Dataset, &mut memory] {
+        assert_eq!(dataset.len(), 5);
+        assert_eq!(dataset.get(3).unwrap().image().pixels(), samples()[3].image().pixels());
+        assert_eq!(dataset.get(1).unwrap().label(), &Label::new(1));
+        assert!(matches!(dataset.get(5), Err(ErrorKind::SampleOutOfRange { index: 5, len: 5 })));
+        dataset.reset().unwrap();
+    }
+
+    // reading in order goes on after the sample read last, or from the start after a reset
+    file.get(2).unwrap();
+    assert_eq!(file.next().unwrap().unwrap().label(), &Label::new(3));
+    file.reset().unwrap();
+    assert_eq!(classes(&file.collect::<Result<Vec<_>, _>>().unwrap()), vec![0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn sinks_grow_datasets_in_every_format() {
+    let dir = scratch_dir("sinks");
+    let square: Vec<LabeledTrainingData> = (0..3u8)
+        .map(|i| LabeledTrainingData::new(Image::builder()
+                                              .with_size(IMAGE_DIR_SAMPLE_SIZE)
+                                              .with_pixels_row_major(vec![i * 40; IMAGE_DIR_SAMPLE_SIZE.area()])
+                                              .build(),
+                                          Label::new(i % 2)))
+        .collect();
+
+    for (images_file, labels_file) in [(dir.join("drawings.csv"), PathBuf::new()),
+                                       (dir.join("drawings/"), PathBuf::new()),
+                                       (dir.join("images.idx"), dir.join("labels.idx"))] {
+        // a sink opened again goes on where the dataset ends
+        open_sink(&images_file, &labels_file).append(&square[0]).unwrap();
+        let mut sink = open_sink(&images_file, &labels_file);
+        for sample in &square[1..] {
+            sink.append(sample).unwrap();
+        }
+
+        let read = load_samples(&images_file, &labels_file, Alphabet::default()).unwrap();
+        let mut read: Vec<(u8, u8)> = read.iter().map(|sample| (sample.label().class(), sample.image().pixels()[0])).collect();
+        // image directories are read label by label; dark images are not inverted
+        read.sort();
+        assert_eq!(read, vec![(0, 0), (0, 80), (1, 40)], "{}", images_file.display());
+    }
+}
+
+#[test]
+fn sinks_append_to_extensionless_idx_files() {
+    let dir = scratch_dir("mnist-sink");
+    let (images_file, labels_file) = (dir.join("train-images-idx3-ubyte"), dir.join("train-labels-idx1-ubyte"));
+    write::convert(samples()[..2].iter().cloned().map(Ok), &images_file, &labels_file).unwrap();
+
+    open_sink(&images_file, &labels_file).append(&samples()[2]).unwrap();
+    assert!(images_file.is_file());
+    assert_eq!(load_samples(&images_file, &labels_file, Alphabet::default()).unwrap(), samples()[..3].to_vec());
+}
+
+#[test]
+fn samples_are_only_appended_to_matching_idx_files() {
+    let dir = scratch_dir("append");
+    let (images_file, labels_file) = (dir.join("images.idx"), dir.join("labels.idx"));
+    append_sample(&images_file, &labels_file, &samples()[0]).unwrap();
+    let appended = || (fs::read(&images_file).unwrap(), fs::read(&labels_file).unwrap());
+    let before = appended();
+
+    let square = LabeledTrainingData::new(Image::builder()
+                                              .with_size(ImageSize::square(2))
+                                              .with_pixels_row_major(vec![0; 4])
+                                              .build(),
+                                          Label::new(0));
+    let result = append_sample(&images_file, &labels_file, &square);
+    assert!(matches!(result, Err(write::ErrorKind::ImageSizeMismatch { width: 3, height: 2, found_width: 2, found_height: 2, .. })));
+    assert_eq!(appended(), before);
+
+    // labels for one sample more than there are images
+    let (other_images, other_labels) = (dir.join("other-images.idx"), dir.join("other-labels.idx"));
+    append_sample(&other_images, &other_labels, &samples()[1]).unwrap();
+    append_sample(&other_images, &other_labels, &samples()[2]).unwrap();
+    let result = append_sample(&images_file, &other_labels, &samples()[3]);
+    assert!(matches!(result, Err(write::ErrorKind::CannotReadDataset(ErrorKind::InvalidLabelCount { sample_count: 1, label_count: 2 }))));
+
+    let truncated = dir.join("truncated.idx");
+    fs::write(&truncated, &before.0[..before.0.len() - 1]).unwrap();
+    let result = append_sample(&truncated, &labels_file, &samples()[3]);
+    assert!(matches!(result, Err(write::ErrorKind::IncompleteDataset { count: 1, .. })));
+
+    let text = dir.join("notes.txt");
+    fs::write(&text, "not an IDX file at all").unwrap();
+    let result = append_sample(&text, &labels_file, &samples()[3]);
+    assert!(matches!(result, Err(write::ErrorKind::CannotReadDataset(ErrorKind::MagicNotFound { .. }))));
+    assert_eq!(fs::read_to_string(&text).unwrap(), "not an IDX file at all");
+
+    append_sample(&images_file, &labels_file, &samples()[3]).unwrap();
+    assert_eq!(load_samples(&images_file, &labels_file, Alphabet::default()).unwrap(), vec![samples()[0].clone(), samples()[3].clone()]);
+}
+
+#[test]
+fn datasets_convert_between_formats() {
+    let dir = scratch_dir("convert");
+    let samples: Vec<LabeledTrainingData> = (0..4u8)
+        .map(|i| LabeledTrainingData::new(Image::builder()
+                                              .with_size(IMAGE_DIR_SAMPLE_SIZE)
+                                              .with_pixels_row_major(vec![i * 40; IMAGE_DIR_SAMPLE_SIZE.area()])
+                                              .build(),
+                                          Label::new(i % 3)))
+        .collect();
+    let key = |samples: &[LabeledTrainingData]| -> Vec<(u8, u8)> {
+        let mut key: Vec<(u8, u8)> = samples.iter().map(|sample| (sample.label().class(), sample.image().pixels()[0])).collect();
+        key.sort();
+        key
+    };
+
+    // each format is converted from the one before it
+    let mut source = (dir.join("source.idx"), dir.join("source-labels.idx"));
+    write::convert(samples.iter().cloned().map(Ok), &source.0, &source.1).unwrap();
+    for target in [(dir.join("digits.csv"), PathBuf::new()),
+                   (dir.join("digits/"), PathBuf::new()),
+                   (dir.join("images.idx.gz"), dir.join("labels.idx.gz")),
+                   (dir.join("images.idx"), dir.join("labels.idx")),
+                   (dir.join("train-images-idx3-ubyte"), dir.join("train-labels-idx1-ubyte"))] {
+        let read = stream_samples(&source.0, &source.1, Alphabet::default()).unwrap();
+        assert_eq!(write::convert(read, &target.0, &target.1).unwrap(), samples.len());
+
+        let converted = load_samples(&target.0, &target.1, Alphabet::default()).unwrap();
+        assert_eq!(key(&converted), key(&samples), "{}", target.0.display());
+        source = target;
+    }
+
+    // extensionless files are IDX like MNIST's, only directories hold images
+    assert!(dir.join("train-images-idx3-ubyte").is_file());
+    assert!(dir.join("digits").is_dir());
+
+    // converting again replaces the output instead of adding to it
+    write::convert(samples[..1].iter().cloned().map(Ok), dir.join("digits.csv"), PathBuf::new()).unwrap();
+    assert_eq!(load_samples(dir.join("digits.csv"), PathBuf::new(), Alphabet::default()).unwrap().len(), 1);
+}
+
+fn classes(samples: &[LabeledTrainingData]) -> Vec<u8> {
+    samples.iter().map(|sample| sample.label().class()).collect()
+}
+
+#[test]
+fn shuffling_reorders_samples_reproducibly() {
+    let shuffled = |buffer_size, seed| -> Vec<LabeledTrainingData> {
+        samples().into_iter().map(Ok).shuffled(buffer_size, seed).collect::<Result<_, _>>().unwrap()
+    };
+
+    let order = classes(&shuffled(3, 1));
+    assert_ne!(order, classes(&samples()));
+    assert_eq!(order, classes(&shuffled(3, 1)));
+    let mut sorted = order.clone();
+    sorted.sort();
+    assert_eq!(sorted, classes(&samples()));
+    // the first sample comes from the first three at the latest
+    assert!(order[0] < 3);
+
+    assert_eq!(classes(&shuffled(1, 1)), classes(&samples()));
+
+    let in_memory = shuffle_samples(&samples(), 1);
+    assert_eq!(classes(&in_memory), classes(&shuffle_samples(&samples(), 1)));
+    assert_ne!(classes(&in_memory), classes(&shuffle_samples(&samples(), 2)));
+}
+
+#[test]
+fn splits_are_reproducible_and_keep_the_order() {
+    let (training, validation) = split_samples(samples(), 0.4, 9).unwrap();
+    assert_eq!((training.len(), validation.len()), (3, 2));
+
+    let mut all = [classes(&training), classes(&validation)].concat();
+    all.sort();
+    assert_eq!(all, classes(&samples()));
+    assert!(classes(&training).windows(2).all(|pair| pair[0] < pair[1]));
+
+    let (_, again) = split_samples(samples(), 0.4, 9).unwrap();
+    assert_eq!(classes(&again), classes(&validation));
+
+    assert!(matches!(split_samples(samples(), 1.5, 9), Err(ErrorKind::InvalidSplitFraction(_))));
+}
+
+#[test]
+fn in_memory_datasets_keep_inputs_with_their_samples() {
+    let dataset = InMemoryDataset::from(samples());
+    assert_eq!(dataset.len(), 5);
+
+    let shuffled = dataset.shuffled(1);
+    let (training, validation) = dataset.split(0.4, 9).unwrap();
+    assert_eq!((training.len(), validation.len()), (3, 2));
+    assert_eq!(classes(validation.samples()), classes(&split_samples(samples(), 0.4, 9).unwrap().1));
+
+    for dataset in [&shuffled, &training, &validation] {
+        for (sample, input) in dataset.samples().iter().zip(dataset.inputs()) {
+            assert_eq!(*input, input_vector(sample.image()));
+        }
+    }
+}
+
+#[test]
+fn prefetching_yields_the_samples_in_order() {
+    let prefetched: Vec<LabeledTrainingData> = samples().into_iter().map(Ok).prefetch(2).collect::<Result<_, _>>().unwrap();
+    assert_eq!(prefetched, samples());
+
+    // stopping early leaves the reading thread blocked on a full buffer,
+    // which dropping the adapter releases
+    let mut partial = samples().into_iter().map(Ok).prefetch(1);
+    assert_eq!(partial.next().unwrap().unwrap(), samples()[0]);
+    drop(partial);
+
+    let failing = vec![Ok(samples().remove(0)), Err(ErrorKind::StdinUsedTwice)].into_iter().prefetch(4);
+    assert!(matches!(failing.collect::<Result<Vec<_>, _>>(), Err(ErrorKind::StdinUsedTwice)));
+}
+
+#[test]
+fn merged_sources_are_spread_evenly() {
+    let drawings: Vec<LabeledTrainingData> = samples().into_iter().take(2).map(|sample| sample.with_weight(2.0)).collect();
+    let merged = merge_samples(vec![samples(), drawings]);
+
+    assert_eq!(classes(&merged), vec![0, 0, 1, 2, 3, 1, 4]);
+    let weights: Vec<f64> = merged.iter().map(|sample| sample.weight()).collect();
+    assert_eq!(weights, vec![1.0, 2.0, 1.0, 1.0, 1.0, 2.0, 1.0]);
+    assert!(merge_samples(vec![Vec::new(), Vec::new()]).is_empty());
+}
+
+/// Answers a single HTTP request with `body`, returning the URL to request.
+fn serve_once(body: Vec<u8>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let _ = stream.read(&mut [0; 1024]);
+        write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len()).unwrap();
+        stream.write_all(&body).unwrap();
+    });
+    format!("http://{}/dataset", address)
+}
+
+#[test]
+fn datasets_are_streamed_from_urls() {
+    let dir = scratch_dir("http");
+    let (images_file, labels_file) = (dir.join("images.idx"), dir.join("labels.idx"));
+    for sample in &samples() {
+        append_sample(&images_file, &labels_file, sample).unwrap();
+    }
+    let images_url = serve_once(fs::read(gzip(&images_file)).unwrap());
+    let labels_url = serve_once(fs::read(&labels_file).unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+
+    let streamed = stream_samples(images_url, labels_url, Alphabet::default())
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(streamed, samples());
+
+    // nothing listens on a port that was just released
+    let unreachable = format!("http://{}/dataset", TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap());
+    let result = stream_samples(unreachable.clone(), unreachable, Alphabet::default());
+    assert!(matches!(result, Err(ErrorKind::CannotDownloadDataset { .. })));
+}
+
+#[test]
+fn subsets_skip_limit_and_pick_reproducibly() {
+    let subset = |subset| -> Vec<u8> {
+        classes(&samples().into_iter().map(Ok).subset(subset).collect::<Result<Vec<_>, _>>().unwrap())
+    };
+
+    assert_eq!(subset(Subset::default()), vec![0, 1, 2, 3, 4]);
+    assert_eq!(subset(Subset { skip: 1, limit: Some(2), random_seed: None }), vec![1, 2]);
+    assert_eq!(subset(Subset { skip: 4, limit: Some(2), random_seed: None }), vec![4]);
+
+    let random = Subset { skip: 1, limit: Some(2), random_seed: Some(3) };
+    let picked = subset(random);
+    assert_eq!(picked.len(), 2);
+    assert!(picked[0] != picked[1] && picked.iter().all(|class| *class >= 1));
+    assert_eq!(picked, subset(random));
+
+    // without a limit, the seed still shuffles the samples
+    let shuffled = |random_seed| subset(Subset { skip: 1, limit: None, random_seed: Some(random_seed) });
+    let mut all = shuffled(3);
+    assert_eq!(all, shuffled(3));
+    assert!((0..10).any(|seed| shuffled(seed) != vec![1, 2, 3, 4]));
+    all.sort();
+    assert_eq!(all, vec![1, 2, 3, 4]);
+    let mut all = subset(Subset { limit: Some(9), ..random });
+    all.sort();
+    assert_eq!(all, vec![1, 2, 3, 4]);
+
+    // unreadable samples don't take the place of readable ones
+    let with_errors = samples()
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, sample)| [Err(ErrorKind::InvalidCsvSample { line: i, reason: String::new() }), Ok(sample)]);
+    let limited: Vec<_> = with_errors.subset(Subset { limit: Some(2), ..Subset::default() }).collect();
+    assert_eq!(limited.iter().filter(|sample| sample.is_ok()).count(), 2);
+}
+
+#[test]
+fn invalid_samples_are_skipped_when_asked() {
+    let csv = "7,0,0,0,255\n3,0,0,999,0\n1,255,0,0,0\n";
+    let read = |skip_invalid: bool| -> Result<Vec<LabeledTrainingData>, ErrorKind> {
+        let samples = CsvDataset::from_reader(Cursor::new(csv)).with_weights([1.0, 2.0, 3.0]);
+        if skip_invalid {
+            samples.skip_invalid().collect()
+        } else {
+            samples.collect()
+        }
+    };
+
+    assert!(matches!(read(false), Err(ErrorKind::InvalidCsvSample { line: 2, .. })));
+    let read = read(true).unwrap();
+    assert_eq!(classes(&read), vec![7, 1]);
+    // the bad sample doesn't shift the weights of the ones after it
+    assert_eq!(read[1].weight(), 3.0);
+
+    // a truncated IDX file ends the samples
+    let mut writer = IdxWriter::new(Cursor::new(Vec::new()), Cursor::new(Vec::new()));
+    writer.write_all(&samples()).unwrap();
+    let (_, images, labels) = writer.finish().unwrap();
+    let mut images = images.into_inner();
+    images.truncate(images.len() - 2);
+    let truncated = TrainingDataset::from_readers(Cursor::new(images), Cursor::new(labels.into_inner())).unwrap();
+    assert_eq!(classes(&truncated.skip_invalid().collect::<Result<Vec<_>, _>>().unwrap()), vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn contact_sheets_lay_out_labeled_images() {
+    // the third label, 12, is wider than the images and cut off
+    let digits: Vec<LabeledTrainingData> = (0..3u8)
+        .map(|class| LabeledTrainingData::new(Image::builder()
+                                                  .with_size(ImageSize::square(8))
+                                                  .with_pixels_row_major(vec![0; 64])
+                                                  .build(),
+                                              Label::new(class * 6)))
+        .collect();
+
+    let sheet = contact_sheet(&digits, 2);
+    assert_eq!(sheet.size(), ImageSize { width: 19, height: 19 });
+    let pixel = |x: usize, y: usize| sheet.pixels()[y * 19 + x];
+    let grid = pixel(0, 0);
+    assert!(grid > 0 && grid < 255);
+    assert_eq!([pixel(9, 5), pixel(5, 9), pixel(18, 18)], [grid; 3]);
+    assert_eq!(pixel(7, 7), 0);
+
+    // the second image starts at (10, 1), its 6 one pixel into the label's box
+    let six: Vec<[bool; 3]> = (2..7).map(|y| [pixel(11, y) == 255, pixel(12, y) == 255, pixel(13, y) == 255]).collect();
+    assert_eq!(six, vec![[true, true, true], [true, false, false], [true, true, true], [true, false, true], [true, true, true]]);
+}
+
+#[test]
+fn duplicates_are_found_within_and_between_datasets() {
+    let bar = |vertical: bool, speck: u8| {
+        let pixels: Vec<u8> = (0..28 * 28)
+            .map(|i| if (if vertical { i % 28 } else { i / 28 }) / 4 == 3 { 255 } else { 0 })
+            .enumerate()
+            .map(|(i, pixel)| if i == 0 { speck } else { pixel })
+            .collect();
+        LabeledTrainingData::new(Image::builder()
+                                     .with_size(ImageSize::square(28))
+                                     .with_pixels_row_major(pixels)
+                                     .build(),
+                                 Label::new(1))
+    };
+    let samples = vec![bar(true, 0), bar(false, 0), bar(true, 0), bar(true, 20)];
+
+    let pairs = |duplicates: Vec<Duplicate>| duplicates
+        .iter()
+        .map(|duplicate| (duplicate.first, duplicate.second, duplicate.exact))
+        .collect::<Vec<_>>();
+    assert_eq!(pairs(find_duplicates(&samples, 0)), vec![(0, 2, true), (0, 3, false), (2, 3, false)]);
+    assert_eq!(find_duplicates(&samples, 2), find_duplicates(&samples, 0));
+    // the bars cross, so their hashes share the bits where they do
+    let crossing = find_duplicates(&samples, 64).into_iter().find(|duplicate| (duplicate.first, duplicate.second) == (0, 1));
+    assert!(matches!(crossing, Some(Duplicate { distance: 3..=63, exact: false, .. })));
+
+    assert_eq!(pairs(find_duplicates_between(&samples[1..2], &samples, 0)), vec![(0, 1, true)]);
+}
@@ -0,0 +1,130 @@
+//! Behavior of the drawing window, checked without opening it.
+
+use digit_recognition::view_model::{Answer, ViewModel, HISTORY_LEN};
+
+#[test]
+fn nothing_is_shown_before_the_first_drawing() {
+    let view_model = ViewModel::new(0.5, "default");
+
+    assert_eq!(view_model.caption_key(), "gui-draw");
+    assert_eq!(view_model.answer_text(), "?");
+    assert_eq!(view_model.confidence_text(), "");
+}
+
+#[test]
+fn answers_below_the_threshold_are_rejected() {
+    let mut view_model = ViewModel::new(0.5, "default");
+
+    view_model.submit(Answer::new("7", 0.4));
+    assert_eq!(view_model.caption_key(), "gui-unrecognized");
+    assert_eq!(view_model.answer_text(), "?");
+    assert_eq!(view_model.confidence_text(), "40%");
+
+    view_model.set_rejection_threshold(0.3);
+    assert_eq!(view_model.caption_key(), "gui-result");
+    assert_eq!(view_model.answer_text(), "7");
+}
+
+#[test]
+fn confidence_shows_the_uncertainty() {
+    let mut view_model = ViewModel::new(0.0, "default");
+    view_model.submit(Answer::new("3", 0.9).with_uncertainty(0.05));
+
+    assert_eq!(view_model.confidence_text(), "90% ± 5%");
+}
+
+#[test]
+fn history_is_bounded_and_newest_first() {
+    let mut view_model = ViewModel::new(0.0, "default");
+    for i in 0..HISTORY_LEN + 3 {
+        view_model.submit(Answer::new(i.to_string(), 1.0));
+    }
+
+    let history: Vec<&str> = view_model.history().iter().map(|answer| answer.text.as_str()).collect();
+    assert_eq!(history.len(), HISTORY_LEN);
+    assert_eq!(history[0], (HISTORY_LEN + 2).to_string());
+    assert_eq!(history[HISTORY_LEN - 1], "3");
+}
+
+#[test]
+fn switching_profiles_forgets_the_answer() {
+    let mut view_model = ViewModel::new(0.0, "default");
+    view_model.submit(Answer::new("1", 1.0));
+
+    view_model.select_profile("default");
+    assert!(view_model.answer().is_some());
+
+    view_model.select_profile("alice");
+    assert_eq!(view_model.profile(), "alice");
+    assert!(view_model.answer().is_none());
+    assert!(view_model.history().is_empty());
+}
+
+#[test]
+fn teaching_status_lasts_until_the_next_drawing() {
+    let mut view_model = ViewModel::new(0.0, "default");
+    view_model.submit(Answer::new("1", 1.0));
+    view_model.set_teaching_status("taught");
+
+    view_model.submit(Answer::new("2", 1.0));
+    assert_eq!(view_model.teaching_status(), "");
+}
+
+#[test]
+fn blank_corrections_are_ignored() {
+    let mut view_model = ViewModel::new(0.0, "default");
+
+    view_model.set_correction("   ");
+    assert_eq!(view_model.take_correction(), None);
+
+    view_model.set_correction(" 4 ");
+    assert_eq!(view_model.take_correction(), Some("4".to_string()));
+    assert_eq!(view_model.correction(), "");
+}
+
+#[test]
+fn a_typed_correction_is_confirmed_over_the_answer() {
+    let mut view_model = ViewModel::new(0.5, "default");
+    assert!(!view_model.is_collecting());
+    view_model.set_collecting(true);
+    assert!(view_model.is_collecting());
+    assert_eq!(view_model.take_confirmed_answer(), None);
+
+    view_model.submit(Answer::new("7", 0.9));
+    view_model.set_correction(" 1 ");
+    assert_eq!(view_model.take_confirmed_answer(), Some(String::from("1")));
+    // the correction stands for the drawing, not just the first save
+    assert_eq!(view_model.take_confirmed_answer(), Some(String::from("1")));
+    view_model.submit(Answer::new("7", 0.9));
+    assert_eq!(view_model.take_confirmed_answer(), Some(String::from("7")));
+
+    // an unrecognized answer is not confirmed by default
+    view_model.submit(Answer::new("4", 0.2));
+    assert_eq!(view_model.take_confirmed_answer(), None);
+}
+
+#[test]
+fn a_taught_correction_is_saved_with_the_drawing() {
+    let mut view_model = ViewModel::new(0.5, "default");
+    view_model.submit(Answer::new("7", 0.9));
+    view_model.set_correction("1");
+
+    assert_eq!(view_model.take_correction_to_teach(), Some(String::from("1")));
+    assert_eq!(view_model.take_confirmed_answer(), Some(String::from("1")));
+    // taught once only, until another correction is typed
+    assert_eq!(view_model.take_correction_to_teach(), None);
+    view_model.set_correction("4");
+    assert_eq!(view_model.take_correction_to_teach(), Some(String::from("4")));
+    assert_eq!(view_model.take_confirmed_answer(), Some(String::from("4")));
+
+    // a correction saved first is taught too
+    view_model.submit(Answer::new("3", 0.9));
+    view_model.set_correction("8");
+    assert_eq!(view_model.take_confirmed_answer(), Some(String::from("8")));
+    assert_eq!(view_model.take_correction_to_teach(), Some(String::from("8")));
+
+    // nor does it outlive the drawing
+    view_model.submit(Answer::new("5", 0.9));
+    assert_eq!(view_model.take_correction_to_teach(), None);
+    assert_eq!(view_model.take_confirmed_answer(), Some(String::from("5")));
+}